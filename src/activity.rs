@@ -0,0 +1,185 @@
+// ----------------------
+// Activity Report
+// ----------------------
+
+// A monthly summary of what the history recorded: versions and pre-downloads
+// that appeared, what the built-in downloader fetched, and the files that
+// failed a check, either right after downloading or in an archive check.
+// It is written as Markdown so it can be pasted into a wiki or a changelog.
+
+#[cfg(feature = "gui")]
+use crate::export;
+use crate::history::{History, VersionKind};
+use crate::{bytes_to_gb, map_biz_code};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+#[cfg(feature = "gui")]
+use log::{error, info};
+#[cfg(feature = "gui")]
+use std::path::PathBuf;
+
+// A calendar month, e.g. (2026, 10)
+pub type Month = (i32, u32);
+
+pub fn month_of(time: DateTime<Utc>) -> Month {
+    (time.year(), time.month())
+}
+
+// "2026-10" as typed on the command line
+pub fn parse_month(text: &str) -> Result<Month, String> {
+    let parsed = text
+        .trim()
+        .split_once('-')
+        .and_then(|(year, month)| Some((year.parse().ok()?, month.parse().ok()?)))
+        .filter(|&(year, month)| NaiveDate::from_ymd_opt(year, month, 1).is_some());
+    parsed.ok_or_else(|| format!("Invalid month \"{}\", expected e.g. 2026-10", text))
+}
+
+pub fn month_label((year, month): Month) -> String {
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .map(|date| date.format("%B %Y").to_string())
+        .unwrap_or_else(|| format!("{}-{:02}", year, month))
+}
+
+// Months with anything recorded, newest first, always including the current one
+#[cfg(feature = "gui")]
+pub fn months(history: &History) -> Vec<Month> {
+    let mut months = vec![month_of(Utc::now())];
+    let times = history
+        .records
+        .iter()
+        .map(|record| record.first_seen)
+        .chain(history.downloads.iter().map(|download| download.finished))
+        .chain(history.archive_checks.iter().map(|run| run.finished));
+    for time in times {
+        let month = month_of(time);
+        if !months.contains(&month) {
+            months.push(month);
+        }
+    }
+    months.sort_by(|a, b| b.cmp(a));
+    months
+}
+
+// The Markdown report of one month
+pub fn monthly_report(history: &History, month: Month) -> String {
+    let in_month = |time: &DateTime<Utc>| month_of(*time) == month;
+    let mut records: Vec<_> = history.records.iter().filter(|record| in_month(&record.first_seen)).collect();
+    records.sort_by_key(|record| record.first_seen);
+    let downloads: Vec<_> = history.downloads.iter().filter(|download| in_month(&download.finished)).collect();
+    let archive_checks: Vec<_> = history.archive_checks.iter().filter(|run| in_month(&run.finished)).collect();
+
+    // Failed checks: downloads that didn't match the API, and archived files
+    // found damaged (once per file, however many runs found it)
+    let mut failures: Vec<(DateTime<Utc>, String, String)> = downloads
+        .iter()
+        .filter_map(|download| {
+            let failure = download.failure.as_ref()?;
+            let file = format!("{} ({} {})", download.file_name, download.game, download.version);
+            Some((download.finished, file, failure.clone()))
+        })
+        .collect();
+    let mut damaged = Vec::new();
+    for run in &archive_checks {
+        for check in &run.problems {
+            if !damaged.contains(&&check.path) {
+                damaged.push(&check.path);
+                failures.push((run.finished, check.path.display().to_string(), format!("Archive check: {}", check.status.label())));
+            }
+        }
+    }
+    failures.sort_by_key(|(time, _, _)| *time);
+
+    let completed: Vec<_> = downloads.iter().filter(|download| download.failure.is_none()).collect();
+    let downloaded: u64 = completed.iter().map(|download| download.size).sum();
+    let count = |kind| records.iter().filter(|record| record.kind == kind).count();
+
+    let mut report = format!("# Activity Report: {}\n\n", month_label(month));
+    report.push_str("| | |\n|---|---|\n");
+    report.push_str(&format!("| New versions | {} |\n", count(VersionKind::Live)));
+    report.push_str(&format!("| Pre-downloads detected | {} |\n", count(VersionKind::PreDownload)));
+    report.push_str(&format!(
+        "| Files downloaded | {} ({:.2} GB) |\n",
+        completed.len(),
+        bytes_to_gb(&downloaded.to_string())
+    ));
+    report.push_str(&format!(
+        "| Archive checks | {} ({} files checked) |\n",
+        archive_checks.len(),
+        archive_checks.iter().map(|run| run.files).sum::<usize>()
+    ));
+    report.push_str(&format!("| Verification failures | {} |\n", failures.len()));
+
+    report.push_str("\n## Versions Seen\n\n");
+    if records.is_empty() {
+        report.push_str("None.\n");
+    } else {
+        report.push_str("| Game | Version | Kind | First Seen |\n|---|---|---|---|\n");
+        for record in &records {
+            let kind = match record.kind {
+                VersionKind::Live => "Live",
+                VersionKind::PreDownload => "Pre-download",
+            };
+            report.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                map_biz_code(&record.biz),
+                record.version,
+                kind,
+                record.first_seen.format("%Y-%m-%d %H:%M UTC")
+            ));
+        }
+    }
+
+    // Downloaded files summed up per game version
+    report.push_str("\n## Downloads\n\n");
+    let mut versions: Vec<(&str, &str, usize, u64)> = Vec::new();
+    for download in &completed {
+        match versions
+            .iter_mut()
+            .find(|(game, version, _, _)| *game == download.game && *version == download.version)
+        {
+            Some((_, _, files, size)) => {
+                *files += 1;
+                *size += download.size;
+            }
+            None => versions.push((&download.game, &download.version, 1, download.size)),
+        }
+    }
+    if versions.is_empty() {
+        report.push_str("None.\n");
+    } else {
+        report.push_str("| Game | Version | Files | Size |\n|---|---|---|---|\n");
+        for (game, version, files, size) in versions {
+            report.push_str(&format!(
+                "| {} | {} | {} | {:.2} GB |\n",
+                game,
+                version,
+                files,
+                bytes_to_gb(&size.to_string())
+            ));
+        }
+    }
+
+    report.push_str("\n## Verification Failures\n\n");
+    if failures.is_empty() {
+        report.push_str("None.\n");
+    } else {
+        report.push_str("| Date | File | Problem |\n|---|---|---|\n");
+        for (time, file, problem) in failures {
+            report.push_str(&format!("| {} | {} | {} |\n", time.format("%Y-%m-%d"), file, problem));
+        }
+    }
+    report
+}
+
+// Write the report of a month to the export folder
+#[cfg(feature = "gui")]
+pub fn export_report(history: &History, month: Month) -> Result<PathBuf, String> {
+    let title = format!("Activity {}-{:02}", month.0, month.1);
+    let path = export::export_dir()?.join(export::export_file_name(&title, "md"));
+    std::fs::write(&path, monthly_report(history, month)).map_err(|e| {
+        error!("Failed to write activity report {}: {}", path.display(), e);
+        format!("Export error: {}", e)
+    })?;
+    info!("Exported activity report to {}", path.display());
+    Ok(path)
+}
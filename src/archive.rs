@@ -0,0 +1,399 @@
+// ----------------------
+// Archive Maintenance
+// ----------------------
+
+// Long-term archives and mirrors are only worth keeping if their files are
+// still intact. The archive check walks the download folder for the
+// `manifest.json` files the downloader and verification write, re-hashes
+// every file they list and compares it with the stored size, MD5 and (when
+// recorded) SHA-256, so bit rot and files that went missing are found before
+// anyone downloads them from the mirror. It runs on a schedule from the
+// window, or from cron with `check-archive`.
+
+#[cfg(feature = "gui")]
+use crate::export;
+use crate::verify::{self, HashAlgorithm};
+use crate::bytes_to_gb;
+#[cfg(feature = "gui")]
+use crate::CancelToken;
+use chrono::{DateTime, Utc};
+#[cfg(feature = "gui")]
+use log::error;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "gui")]
+use std::sync::Arc;
+use std::sync::Mutex;
+
+// How deep below the download folder manifests are looked for;
+// `<game>/<version>/manifest.json` is two levels down
+const MAX_DEPTH: usize = 4;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ArchiveStatus {
+    Ok,
+    Missing,
+    WrongSize(u64),     // Size on disk
+    Corrupt,            // Hash differs from the manifest
+    Unreadable(String), // Could not be read
+}
+
+impl ArchiveStatus {
+    pub fn label(&self) -> String {
+        match self {
+            ArchiveStatus::Ok => "OK".to_string(),
+            ArchiveStatus::Missing => "missing".to_string(),
+            ArchiveStatus::WrongSize(size) => format!("wrong size ({} bytes)", size),
+            ArchiveStatus::Corrupt => "hash mismatch".to_string(),
+            ArchiveStatus::Unreadable(e) => format!("unreadable: {}", e),
+        }
+    }
+}
+
+// Result of re-checking one archived file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArchiveCheck {
+    pub path: PathBuf,
+    pub version: String,
+    pub status: ArchiveStatus,
+}
+
+// Archive check running on a worker thread; the UI reads it every frame
+#[derive(Default)]
+pub struct ArchiveProgress {
+    pub total: usize,
+    pub checks: Vec<ArchiveCheck>,
+    pub errors: Vec<String>, // Manifests that couldn't be read
+    pub finished: bool,
+}
+
+impl ArchiveProgress {
+    // Files that are damaged or gone
+    pub fn problems(&self) -> Vec<&ArchiveCheck> {
+        self.checks.iter().filter(|check| check.status != ArchiveStatus::Ok).collect()
+    }
+}
+
+// An archive check started from the window
+#[cfg(feature = "gui")]
+pub struct ArchiveJob {
+    pub root: PathBuf,
+    pub progress: Arc<Mutex<ArchiveProgress>>,
+    pub cancel: CancelToken,
+}
+
+// Every `manifest.json` below `root`
+pub fn find_manifests(root: &Path) -> Vec<PathBuf> {
+    fn walk(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() && depth < MAX_DEPTH {
+                walk(&path, depth + 1, found);
+            } else if entry.file_name() == "manifest.json" {
+                found.push(path);
+            }
+        }
+    }
+    let mut found = Vec::new();
+    walk(root, 0, &mut found);
+    found.sort();
+    found
+}
+
+// Re-hash every file listed in the manifests below `root`, adding each
+// result to `progress`. Files are read one at a time to spare the disks.
+pub fn check_archive(root: &Path, progress: &Mutex<ArchiveProgress>, is_cancelled: &dyn Fn() -> bool) {
+    let mut entries = Vec::new();
+    for path in find_manifests(root) {
+        match verify::read_manifest(&path) {
+            Ok(manifest) => {
+                let folder = path.parent().unwrap_or(root).to_path_buf();
+                entries.extend(manifest.files.into_iter().map(|entry| (folder.clone(), entry)));
+            }
+            Err(e) => {
+                warn!("{}", e);
+                progress.lock().unwrap_or_else(|e| e.into_inner()).errors.push(e);
+            }
+        }
+    }
+    progress.lock().unwrap_or_else(|e| e.into_inner()).total = entries.len();
+    info!("Checking {} archived files below {}.", entries.len(), root.display());
+
+    for (folder, entry) in entries {
+        if is_cancelled() {
+            break;
+        }
+        let path = folder.join(&entry.file_name);
+        let status = match std::fs::metadata(&path) {
+            Err(_) => ArchiveStatus::Missing,
+            Ok(metadata) if metadata.len() != entry.size => ArchiveStatus::WrongSize(metadata.len()),
+            Ok(_) => {
+                let algorithm = if entry.sha256.is_some() { HashAlgorithm::Md5AndSha256 } else { HashAlgorithm::Md5 };
+                match verify::hash_file(&path, algorithm, is_cancelled) {
+                    Ok((_, md5, sha256)) => {
+                        let md5_ok = md5.eq_ignore_ascii_case(&entry.md5);
+                        let sha256_ok = match (&entry.sha256, sha256) {
+                            (Some(expected), Some(actual)) => expected.eq_ignore_ascii_case(&actual),
+                            _ => true,
+                        };
+                        if md5_ok && sha256_ok {
+                            ArchiveStatus::Ok
+                        } else {
+                            ArchiveStatus::Corrupt
+                        }
+                    }
+                    // Stopping isn't a finding about the file
+                    Err(_) if is_cancelled() => break,
+                    Err(e) => ArchiveStatus::Unreadable(e),
+                }
+            }
+        };
+        if status != ArchiveStatus::Ok {
+            warn!("Archived file {} is {}.", path.display(), status.label());
+        }
+        progress.lock().unwrap_or_else(|e| e.into_inner()).checks.push(ArchiveCheck {
+            path,
+            version: entry.version,
+            status,
+        });
+    }
+}
+
+// Summary of a finished archive check, kept in the history
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArchiveCheckRun {
+    pub finished: DateTime<Utc>,
+    pub files: usize,               // Files checked
+    pub problems: Vec<ArchiveCheck>, // Damaged or missing files
+}
+
+// Text report of an archive check, one line per damaged or missing file
+#[cfg(feature = "gui")]
+pub fn report(root: &Path, progress: &ArchiveProgress, now: DateTime<Utc>) -> String {
+    let problems = progress.problems();
+    let mut report = format!(
+        "Archive check of {}\n{}\n{} files checked, {} damaged or missing\n",
+        root.display(),
+        now.format("%Y-%m-%d %H:%M UTC"),
+        progress.checks.len(),
+        problems.len()
+    );
+    if !problems.is_empty() {
+        report.push('\n');
+    }
+    for check in problems {
+        report.push_str(&format!("{}  {}\n", check.path.display(), check.status.label()));
+    }
+    for e in &progress.errors {
+        report.push_str(&format!("\n{}", e));
+    }
+    report
+}
+
+// Write the report of an archive check to the export folder
+#[cfg(feature = "gui")]
+pub fn write_report(root: &Path, progress: &ArchiveProgress) -> Result<PathBuf, String> {
+    let path = export::export_dir()?.join(export::export_file_name("Archive Check", "txt"));
+    std::fs::write(&path, report(root, progress, Utc::now())).map_err(|e| {
+        error!("Failed to write report {}: {}", path.display(), e);
+        format!("Report export error: {}", e)
+    })?;
+    info!("Exported archive check report to {}", path.display());
+    Ok(path)
+}
+
+// ----------------------
+// Deduplication
+// ----------------------
+
+// Audio packages often carry over between versions unchanged, so the same
+// file ends up in several version folders. Files with the same size and MD5
+// in the manifests are hard-linked to one copy: every folder keeps its file,
+// and the disk stores it once (the filesystem counts the links, so deleting
+// one version's folder leaves the others intact). Both copies are re-hashed
+// before linking, so a rotted copy is never linked over a good one.
+
+// Manifest entries with the same contents, in path order
+pub struct DuplicateGroup {
+    pub md5: String,
+    pub size: u64,
+    pub paths: Vec<PathBuf>,
+    pub linked: usize, // Paths already sharing the first path's data
+}
+
+impl DuplicateGroup {
+    // Space freed by linking the remaining copies
+    pub fn savings(&self) -> u64 {
+        self.size * (self.paths.len() - 1 - self.linked) as u64
+    }
+}
+
+// Outcome of a deduplication, as shown in the Downloads tab
+#[derive(Default)]
+pub struct DedupProgress {
+    pub groups: usize,      // Files found in more than one version
+    pub linked: usize,      // Copies replaced by a link in this run
+    pub saved: u64,         // Bytes freed in this run
+    pub already_saved: u64, // Bytes already shared by earlier runs
+    pub errors: Vec<String>,
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub finished: bool, // Read by the Downloads tab
+}
+
+// A deduplication started from the window
+#[cfg(feature = "gui")]
+pub struct DedupJob {
+    pub dry_run: bool,
+    pub progress: Arc<Mutex<DedupProgress>>,
+    pub cancel: CancelToken,
+}
+
+// Files listed more than once with the same size and MD5 in the manifests below `root`
+pub fn find_duplicates(root: &Path) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for path in find_manifests(root) {
+        let Ok(manifest) = verify::read_manifest(&path) else {
+            continue;
+        };
+        let folder = path.parent().unwrap_or(root);
+        for entry in manifest.files {
+            let file = folder.join(&entry.file_name);
+            if entry.md5.is_empty() || !file.is_file() {
+                continue;
+            }
+            let md5 = entry.md5.to_lowercase();
+            match groups.iter_mut().find(|group| group.md5 == md5 && group.size == entry.size) {
+                Some(group) if !group.paths.contains(&file) => group.paths.push(file),
+                Some(_) => {}
+                None => groups.push(DuplicateGroup {
+                    md5,
+                    size: entry.size,
+                    paths: vec![file],
+                    linked: 0,
+                }),
+            }
+        }
+    }
+
+    groups.retain(|group| group.paths.len() > 1);
+    for group in &mut groups {
+        group.paths.sort();
+        let first = group.paths[0].clone();
+        group.linked = group.paths[1..].iter().filter(|path| same_file(&first, path)).count();
+    }
+    groups
+}
+
+// Whether two paths are links to the same data
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+// Windows doesn't expose file ids on stable Rust; linking again is harmless
+#[cfg(not(unix))]
+fn same_file(_a: &Path, _b: &Path) -> bool {
+    false
+}
+
+// Replace every duplicate below `root` with a hard link to the first copy.
+// With `dry_run`, only count what would be freed.
+pub fn deduplicate(root: &Path, dry_run: bool, progress: &Mutex<DedupProgress>, is_cancelled: &dyn Fn() -> bool) {
+    let groups = find_duplicates(root);
+    let set = |update: &dyn Fn(&mut DedupProgress)| update(&mut progress.lock().unwrap_or_else(|e| e.into_inner()));
+    set(&|progress| {
+        progress.groups = groups.len();
+        progress.already_saved = groups.iter().map(|group| group.size * group.linked as u64).sum();
+    });
+    info!("Found {} files stored in more than one version below {}.", groups.len(), root.display());
+
+    for group in &groups {
+        if is_cancelled() {
+            break;
+        }
+        if dry_run {
+            set(&|progress| {
+                progress.linked += group.paths.len() - 1 - group.linked;
+                progress.saved += group.savings();
+            });
+            continue;
+        }
+        let keep = &group.paths[0];
+        if let Err(e) = check_md5(keep, &group.md5, is_cancelled) {
+            set(&|progress| progress.errors.push(e.clone()));
+            continue;
+        }
+        for path in &group.paths[1..] {
+            if is_cancelled() {
+                break;
+            }
+            if same_file(keep, path) {
+                continue;
+            }
+            match check_md5(path, &group.md5, is_cancelled).and_then(|_| link(keep, path)) {
+                Ok(()) => set(&|progress| {
+                    progress.linked += 1;
+                    progress.saved += group.size;
+                }),
+                Err(e) => {
+                    warn!("{}", e);
+                    set(&|progress| progress.errors.push(e.clone()));
+                }
+            }
+        }
+    }
+}
+
+fn check_md5(path: &Path, md5: &str, is_cancelled: &dyn Fn() -> bool) -> Result<(), String> {
+    let actual = verify::file_md5(path, is_cancelled)?;
+    if actual.eq_ignore_ascii_case(md5) {
+        Ok(())
+    } else {
+        Err(format!("Not linking {}: its MD5 no longer matches the manifest", path.display()))
+    }
+}
+
+// Swap `path` for a hard link to `keep`. The link is made under a temporary
+// name first, so a failure leaves the original copy in place.
+fn link(keep: &Path, path: &Path) -> Result<(), String> {
+    let temporary = path.with_extension("dedup");
+    let _ = std::fs::remove_file(&temporary);
+    std::fs::hard_link(keep, &temporary)
+        .map_err(|e| format!("Link error ({} -> {}): {}", path.display(), keep.display(), e))?;
+    std::fs::rename(&temporary, path).map_err(|e| {
+        let _ = std::fs::remove_file(&temporary);
+        format!("Link error ({}): {}", path.display(), e)
+    })?;
+    info!("Linked {} to {}.", path.display(), keep.display());
+    Ok(())
+}
+
+// Text report of a deduplication
+pub fn dedup_report(progress: &DedupProgress, dry_run: bool) -> String {
+    let mut report = format!(
+        "{} file(s) stored in more than one version; {} {} copies, {:.2} GB {}.\n",
+        progress.groups,
+        if dry_run { "would link" } else { "linked" },
+        progress.linked,
+        bytes_to_gb(&progress.saved.to_string()),
+        if dry_run { "would be freed" } else { "freed" },
+    );
+    if progress.already_saved > 0 {
+        report.push_str(&format!(
+            "{:.2} GB was already shared by earlier runs.\n",
+            bytes_to_gb(&progress.already_saved.to_string())
+        ));
+    }
+    for e in &progress.errors {
+        report.push_str(&format!("{}\n", e));
+    }
+    report
+}
@@ -0,0 +1,233 @@
+// ----------------------
+// aria2 Integration
+// ----------------------
+
+// Hands downloads to a running aria2 daemon (`aria2c --enable-rpc`) over its
+// JSON-RPC interface instead of downloading in-process. Each file is sent
+// with its output name and MD5 so aria2 checks it, and the daemon is polled
+// for progress while any of the sent files is unfinished.
+
+use crate::jobs::{self, JobKind};
+use crate::PackageFile;
+use eframe::egui;
+use log::{error, info};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Where the daemon listens and its `--rpc-secret`, if any
+#[derive(Clone)]
+pub struct Aria2Rpc {
+    pub url: String,
+    pub secret: String,
+}
+
+impl Aria2Rpc {
+    // Call an RPC method, adding the secret token when one is set
+    fn call(&self, client: &reqwest::blocking::Client, method: &str, params: Vec<Value>) -> Result<Value, String> {
+        let mut params = params;
+        if !self.secret.is_empty() {
+            params.insert(0, json!(format!("token:{}", self.secret)));
+        }
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": "genshin_package_scanner",
+            "method": method,
+            "params": params,
+        });
+
+        let response: Value = client
+            .post(self.url.trim())
+            .json(&request)
+            .send()
+            .and_then(|response| response.json())
+            .map_err(|e| format!("aria2 RPC error: {}", e))?;
+        if let Some(error) = response.get("error") {
+            let message = error.get("message").and_then(Value::as_str).unwrap_or("unknown error");
+            return Err(format!("aria2 RPC error: {}", message));
+        }
+        Ok(response["result"].clone())
+    }
+}
+
+// A file handed to aria2 and its last known progress
+#[derive(Clone)]
+pub struct Aria2Job {
+    pub name: String,
+    pub gid: String,    // aria2's id for the download
+    pub status: String, // active, waiting, paused, error, complete or removed
+    pub completed: u64,
+    pub total: u64,
+    pub speed: u64, // Bytes per second
+}
+
+impl Aria2Job {
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "complete" | "error" | "removed")
+    }
+}
+
+#[derive(Default)]
+pub struct Aria2State {
+    pub jobs: Vec<Aria2Job>,
+    pub error: Option<String>, // Last RPC failure
+}
+
+// Sent files and their progress, updated by worker threads
+#[derive(Default)]
+pub struct Aria2 {
+    state: Arc<Mutex<Aria2State>>,
+    busy: Arc<AtomicBool>, // An RPC worker is running
+    last_poll: Option<Instant>,
+    held: Option<Vec<String>>, // While on hold, the gids it paused
+}
+
+impl Aria2 {
+    pub fn state(&self) -> MutexGuard<'_, Aria2State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Add files to aria2, each saved into its folder under its URL's file name
+    pub fn send(&self, ctx: &egui::Context, rpc: Aria2Rpc, files: Vec<(PackageFile, PathBuf)>) {
+        let state = self.state.clone();
+        let ctx = ctx.clone();
+        let label = format!("Send {} file(s) to aria2", files.len());
+        jobs::spawn(JobKind::Aria2, label, None, move || {
+            let client = reqwest::blocking::Client::new();
+            for (file, dir) in files {
+                let mut options = json!({
+                    "dir": dir.display().to_string(),
+                    "out": file.file_name(),
+                });
+                if !file.md5.is_empty() {
+                    options["checksum"] = json!(format!("md5={}", file.md5.to_lowercase()));
+                }
+
+                let result = rpc.call(&client, "aria2.addUri", vec![json!([file.url]), options]);
+                let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                match result {
+                    Ok(gid) => {
+                        info!("Sent {} to aria2 as {}.", file.url, gid);
+                        state.error = None;
+                        state.jobs.push(Aria2Job {
+                            name: file.file_name(),
+                            gid: gid.as_str().unwrap_or_default().to_string(),
+                            status: "waiting".to_string(),
+                            completed: 0,
+                            total: 0,
+                            speed: 0,
+                        });
+                    }
+                    Err(e) => {
+                        error!("{}", e);
+                        state.error = Some(e);
+                        break;
+                    }
+                }
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    // Refresh the progress of unfinished jobs about once a second. Called every frame.
+    pub fn update(&mut self, ctx: &egui::Context, rpc: &Aria2Rpc) {
+        let gids: Vec<String> = self
+            .state()
+            .jobs
+            .iter()
+            .filter(|job| !job.is_finished())
+            .map(|job| job.gid.clone())
+            .collect();
+        if gids.is_empty() || self.busy.load(Ordering::Relaxed) {
+            return;
+        }
+        if self.last_poll.is_some_and(|last_poll| last_poll.elapsed() < POLL_INTERVAL) {
+            ctx.request_repaint_after(POLL_INTERVAL);
+            return;
+        }
+        self.last_poll = Some(Instant::now());
+        self.busy.store(true, Ordering::Relaxed);
+
+        let rpc = rpc.clone();
+        let state = self.state.clone();
+        let busy = self.busy.clone();
+        let ctx = ctx.clone();
+        jobs::spawn(JobKind::Aria2, "Poll aria2 progress", None, move || {
+            let client = reqwest::blocking::Client::new();
+            let keys = json!(["status", "completedLength", "totalLength", "downloadSpeed"]);
+            for gid in gids {
+                let result = rpc.call(&client, "aria2.tellStatus", vec![json!(gid), keys.clone()]);
+                let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                match result {
+                    Ok(status) => {
+                        // aria2 reports numbers as strings
+                        let number = |key: &str| status[key].as_str().and_then(|value| value.parse().ok()).unwrap_or(0);
+                        if let Some(job) = state.jobs.iter_mut().find(|job| job.gid == gid) {
+                            job.status = status["status"].as_str().unwrap_or_default().to_string();
+                            job.completed = number("completedLength");
+                            job.total = number("totalLength");
+                            job.speed = number("downloadSpeed");
+                        }
+                        state.error = None;
+                    }
+                    Err(e) => {
+                        state.error = Some(e);
+                        break;
+                    }
+                }
+            }
+            busy.store(false, Ordering::Relaxed);
+            ctx.request_repaint();
+        });
+    }
+
+    // Pause the unfinished jobs while on hold (e.g. while the game is
+    // running), and unpause them when it is released
+    pub fn hold(&mut self, ctx: &egui::Context, rpc: &Aria2Rpc, hold: bool) {
+        let (method, gids) = match (hold, self.held.take()) {
+            (true, None) => {
+                let gids: Vec<String> = self
+                    .state()
+                    .jobs
+                    .iter()
+                    .filter(|job| matches!(job.status.as_str(), "active" | "waiting"))
+                    .map(|job| job.gid.clone())
+                    .collect();
+                self.held = Some(gids.clone());
+                ("aria2.pause", gids)
+            }
+            (false, Some(gids)) => ("aria2.unpause", gids),
+            (_, held) => {
+                self.held = held;
+                return;
+            }
+        };
+        if gids.is_empty() {
+            return;
+        }
+
+        let rpc = rpc.clone();
+        let state = self.state.clone();
+        let ctx = ctx.clone();
+        jobs::spawn(JobKind::Aria2, format!("Call {}", method), None, move || {
+            let client = reqwest::blocking::Client::new();
+            for gid in gids {
+                if let Err(e) = rpc.call(&client, method, vec![json!(gid)]) {
+                    error!("{}", e);
+                    state.lock().unwrap_or_else(|e| e.into_inner()).error = Some(e);
+                }
+            }
+            info!("Called {} on the aria2 jobs.", method);
+            ctx.request_repaint();
+        });
+    }
+
+    // Forget jobs aria2 has finished with
+    pub fn clear_finished(&self) {
+        self.state().jobs.retain(|job| !job.is_finished());
+    }
+}
@@ -0,0 +1,171 @@
+// ----------------------
+// Audio Package Listing
+// ----------------------
+
+// Every audio package of a game in one table: the live version's, the
+// pre-download's and the patch from the live version to the pre-download.
+// Sorted by language or size, or grouped to one row per language with the
+// three sizes side by side, it answers "how big is the Japanese audio this
+// patch" at a glance.
+
+use crate::{bytes_to_gb, map_language_code, AudioPackage, GamePackage};
+use eframe::egui;
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum AudioSort {
+    #[default]
+    Language,
+    Size, // Largest first
+}
+
+// Which download an audio package belongs to
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Origin {
+    Live,
+    PreDownload,
+    Patch, // From the live version to the pre-download
+}
+
+impl Origin {
+    fn label(self) -> &'static str {
+        match self {
+            Origin::Live => "Live",
+            Origin::PreDownload => "Pre-download",
+            Origin::Patch => "Patch",
+        }
+    }
+}
+
+struct AudioRow {
+    code: String,     // Language code, e.g. "ja-jp"
+    language: String, // Its name
+    origin: Origin,
+    version: String, // The version installed, or "from → to" for the patch
+    size: u64,
+    decompressed_size: u64,
+}
+
+#[derive(Default)]
+pub struct AudioListing {
+    pub sort: AudioSort,
+    pub grouped: bool, // One row per language
+}
+
+impl AudioListing {
+    pub fn show(&mut self, ui: &mut egui::Ui, package: &GamePackage) {
+        let mut rows = audio_rows(package);
+        if rows.is_empty() {
+            ui.label("No audio packages in this response.");
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label("Sort by:");
+            ui.selectable_value(&mut self.sort, AudioSort::Language, "Language");
+            ui.selectable_value(&mut self.sort, AudioSort::Size, "Size");
+            ui.separator();
+            ui.checkbox(&mut self.grouped, "Group by language");
+        });
+
+        if self.grouped {
+            self.show_grouped(ui, &rows);
+            return;
+        }
+        match self.sort {
+            AudioSort::Language => rows.sort_by(|a, b| a.language.cmp(&b.language).then(a.origin.cmp(&b.origin))),
+            AudioSort::Size => rows.sort_by_key(|row| std::cmp::Reverse(row.size)),
+        }
+        egui::Grid::new("audio_packages").striped(true).show(ui, |ui| {
+            ui.strong("Language");
+            ui.strong("Package");
+            ui.strong("Size");
+            ui.strong("Decompressed");
+            ui.end_row();
+            for row in &rows {
+                ui.label(&row.language).on_hover_text(&row.code);
+                ui.label(format!("{} {}", row.origin.label(), row.version));
+                ui.label(gb(row.size));
+                ui.label(gb(row.decompressed_size));
+                ui.end_row();
+            }
+        });
+    }
+
+    // One row per language, with the size of each of its packages
+    fn show_grouped(&self, ui: &mut egui::Ui, rows: &[AudioRow]) {
+        let mut languages: Vec<(&str, &str)> = Vec::new();
+        for row in rows {
+            if !languages.iter().any(|(code, _)| *code == row.code) {
+                languages.push((&row.code, &row.language));
+            }
+        }
+        let size = |code: &str, origin: Origin| {
+            rows.iter().find(|row| row.code == code && row.origin == origin).map(|row| row.size)
+        };
+        let largest = |code: &str| rows.iter().filter(|row| row.code == code).map(|row| row.size).max().unwrap_or(0);
+        match self.sort {
+            AudioSort::Language => languages.sort_by(|a, b| a.1.cmp(b.1)),
+            AudioSort::Size => languages.sort_by_key(|(code, _)| std::cmp::Reverse(largest(code))),
+        }
+
+        // Column titles carry the versions, the same for every language
+        let version = |origin: Origin| rows.iter().find(|row| row.origin == origin).map(|row| row.version.clone());
+        let origins: Vec<(Origin, String)> = [Origin::Live, Origin::PreDownload, Origin::Patch]
+            .into_iter()
+            .filter_map(|origin| version(origin).map(|version| (origin, format!("{} {}", origin.label(), version))))
+            .collect();
+
+        egui::Grid::new("audio_packages_grouped").striped(true).show(ui, |ui| {
+            ui.strong("Language");
+            for (_, title) in &origins {
+                ui.strong(title);
+            }
+            ui.end_row();
+            for (code, language) in &languages {
+                ui.label(*language).on_hover_text(*code);
+                for (origin, _) in &origins {
+                    ui.label(size(code, *origin).map_or_else(|| "-".to_string(), gb));
+                }
+                ui.end_row();
+            }
+        });
+    }
+}
+
+// The audio packages of the live version, the pre-download and the patch
+// between them
+fn audio_rows(package: &GamePackage) -> Vec<AudioRow> {
+    let mut rows = Vec::new();
+    let mut add = |pkgs: &[AudioPackage], origin: Origin, version: &str| {
+        for pkg in pkgs {
+            rows.push(AudioRow {
+                code: pkg.language.clone(),
+                language: map_language_code(&pkg.language),
+                origin,
+                version: version.to_string(),
+                size: pkg.size.parse().unwrap_or(0),
+                decompressed_size: pkg.decompressed_size.parse().unwrap_or(0),
+            });
+        }
+    };
+
+    let live = package.main.major.as_ref();
+    if let Some(major) = live {
+        add(&major.audio_pkgs, Origin::Live, &major.version);
+    }
+    let Some(pre_download) = &package.pre_download else {
+        return rows;
+    };
+    let Some(target) = &pre_download.major else {
+        return rows;
+    };
+    add(&target.audio_pkgs, Origin::PreDownload, &target.version);
+    let patch = live.and_then(|major| pre_download.patches.iter().find(|patch| patch.version == major.version));
+    if let Some(patch) = patch {
+        add(&patch.audio_pkgs, Origin::Patch, &format!("{} → {}", patch.version, target.version));
+    }
+    rows
+}
+
+fn gb(bytes: u64) -> String {
+    format!("{:.2} GB", bytes_to_gb(&bytes.to_string()))
+}
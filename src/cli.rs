@@ -0,0 +1,967 @@
+// ----------------------
+// Command Line
+// ----------------------
+
+// The fetching, report, export, verification and download code behind the
+// window, driven from the command line for scripts, cron jobs and headless
+// machines. Started without a subcommand, the app opens its window as before.
+// Settings (the first game's source, report templates, download folder and
+// connections) and the version history are shared with the window. Exit codes
+// tell scripts what happened (see below), and shell completions can be
+// generated with the `completions` command.
+
+use crate::activity;
+use crate::archive::{self, ArchiveProgress, DedupProgress};
+use crate::doctor::{self, CheckStatus};
+use crate::export::{self, UrlListFormat};
+use crate::github::GithubFiles;
+use crate::history::{History, VersionKind, VersionRecord};
+use crate::hooks;
+use crate::mirror::{self, MirrorStatus};
+use crate::network::{self, IpFamily};
+use crate::polling::{self, Poller};
+use crate::report::{self, ExportFormat, ReportTemplates};
+use crate::service::{self, ServiceOptions};
+use crate::settings::Settings;
+use crate::systemd;
+#[cfg(feature = "tui")]
+use crate::tui;
+use crate::upload;
+use crate::variables::Variables;
+use crate::verify::{self, FileStatus, HashAlgorithm, VerifyProgress};
+use crate::{
+    compare, download, fetch_and_process_data, package_files, CancelToken, FetchResult, GameData, GamePackage, Major,
+    PackageFile, DEFAULT_API_URL,
+};
+use chrono::{DateTime, Utc};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use regex::Regex;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(version, about = "Fetch, export, verify and download game packages from the HoYoPlay API. Run without a command to open the window.")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[arg(long, global = true, value_name = "FILE", conflicts_with = "replay", help = "Save every API request and its full response to FILE, e.g. for a bug report")]
+    pub record: Option<PathBuf>,
+    #[arg(long, global = true, value_name = "FILE", help = "Answer API requests from a file saved with --record instead of the network")]
+    pub replay: Option<PathBuf>,
+    #[arg(long, global = true, value_name = "FILE", help = "Also trust the root certificates in this PEM file, e.g. a TLS-inspecting proxy's")]
+    pub ca_bundle: Option<String>,
+    #[arg(long, global = true, help = "Skip TLS certificate verification. Unsafe: responses and downloads can be tampered with")]
+    pub insecure: bool,
+    #[arg(long, global = true, value_enum, help = "IP version to prefer or limit connections to")]
+    pub ip_family: Option<IpFamily>,
+    #[arg(long, global = true, value_name = "ADDRESS|INTERFACE", help = "Connect from this local IP address, or an address of this interface (e.g. eth1)")]
+    pub bind: Option<String>,
+    #[arg(long, global = true, value_name = "URL", help = "Look up host names with this DNS-over-HTTPS endpoint (JSON API), e.g. https://cloudflare-dns.com/dns-query")]
+    pub doh: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Fetch the API once and print the package report")]
+    Fetch {
+        #[command(flatten)]
+        source: SourceArgs,
+        #[arg(long, help = "Print the parsed packages as JSON instead of the report")]
+        json: bool,
+        #[arg(long, help = "In a GitHub Actions job: set step outputs (new_version, predownload_available, ...), write a job summary and exit with 0 when versions are new")]
+        github: bool,
+    },
+    #[command(about = "Fetch on an interval and report versions as they appear")]
+    Watch {
+        #[command(flatten)]
+        source: SourceArgs,
+        #[arg(long, default_value = "10m", value_parser = parse_interval, help = "Time between fetches, e.g. 5m, 10m or 1h; at least 5m")]
+        interval: Duration,
+        #[arg(long, help = "Upload a JSON snapshot and HTML report of each game after every fetch, to the S3 or WebDAV target in the settings")]
+        upload: bool,
+        #[arg(long, help = "Print one JSON object per event (fetch, new version, upload, error) as JSON Lines")]
+        json: bool,
+    },
+    #[command(about = "Write the packages in one of the export formats")]
+    Export {
+        #[command(flatten)]
+        source: SourceArgs,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        #[arg(long, value_name = "REGEX", help = "Only list the files whose URL matches this regular expression, e.g. Audio_Japanese (md5 and URL list formats)")]
+        url_filter: Option<String>,
+        #[arg(long, short, help = "File to write to instead of standard output")]
+        output: Option<PathBuf>,
+        #[arg(long, value_name = "NAME", help = "Upload the export under this file name to the S3 or WebDAV target in the settings")]
+        upload: Option<String>,
+    },
+    #[command(about = "Check the files in a folder against the API's sizes and MD5 checksums")]
+    Verify {
+        #[arg(help = "Folder of downloaded package files")]
+        dir: PathBuf,
+        #[command(flatten)]
+        source: SourceArgs,
+        #[arg(long, help = "Compute SHA-256 as well and write a manifest.json")]
+        sha256: bool,
+    },
+    #[command(about = "Download the packages of the live version or the pre-download")]
+    Download {
+        #[command(flatten)]
+        source: SourceArgs,
+        #[arg(long = "lang", value_name = "CODE", help = "Audio language to include, e.g. ja-jp; repeat for more (default: all)")]
+        languages: Vec<String>,
+        #[arg(long, help = "Download the pre-download instead of the live version")]
+        pre_download: bool,
+        #[arg(long, help = "Leave out the game packages")]
+        audio_only: bool,
+        #[arg(long, help = "Folder to save into (default: the app's download folder)")]
+        dir: Option<PathBuf>,
+        #[arg(long, help = "Upload each verified file to the SFTP mirror in the settings")]
+        mirror: bool,
+    },
+    #[command(about = "Re-hash the archived downloads against their manifest.json and report damaged or missing files")]
+    CheckArchive {
+        #[arg(long, help = "Folder to check (default: the app's download folder)")]
+        dir: Option<PathBuf>,
+    },
+    #[command(about = "Hard-link archived files that are identical across versions and report the space saved")]
+    Dedup {
+        #[arg(long, help = "Folder to deduplicate (default: the app's download folder)")]
+        dir: Option<PathBuf>,
+        #[arg(long, help = "Only report what would be linked")]
+        dry_run: bool,
+    },
+    #[command(about = "Print a month's activity from the history as Markdown: versions seen, downloads and failed checks")]
+    Activity {
+        #[arg(long, value_parser = activity::parse_month, help = "Month to report, e.g. 2026-10 (default: this month)")]
+        month: Option<activity::Month>,
+        #[arg(long, short, help = "File to write to instead of standard output")]
+        output: Option<PathBuf>,
+    },
+    #[cfg(feature = "tui")]
+    #[command(about = "Browse the packages in the terminal, e.g. over SSH: tables, reports, fetch, copy and export")]
+    Tui {
+        #[command(flatten)]
+        source: SourceArgs,
+    },
+    #[command(about = "Compare two sources, e.g. the global and CN APIs or two saved responses")]
+    Diff {
+        #[arg(help = "API URL or saved JSON response")]
+        a: String,
+        #[arg(help = "API URL or saved JSON response")]
+        b: String,
+        #[arg(long, short, value_name = "BIZ", help = "Game to compare when a response has several, e.g. hk4e_global")]
+        game: Option<String>,
+        #[arg(long, help = "Reject sections with unknown or missing fields")]
+        strict: bool,
+    },
+    #[command(about = "Check the network, clock and folders when fetches or downloads fail")]
+    Doctor {
+        #[arg(long, short, help = "API URL to test (default: the first game in the app's settings)")]
+        source: Option<String>,
+        #[arg(long, help = "Download folder to test (default: the one in the settings)")]
+        dir: Option<PathBuf>,
+    },
+    #[command(about = "Start `watch` with the machine: a systemd unit on Linux, a startup task on Windows")]
+    InstallService {
+        #[arg(long, default_value = "10m", value_parser = parse_interval, help = "Time between fetches, e.g. 5m, 10m or 1h; at least 5m")]
+        interval: Duration,
+        #[arg(long, help = "Upload snapshots after every fetch, like `watch --upload`")]
+        upload: bool,
+        #[arg(long, help = "Linux: install a system unit (needs root) instead of a user unit")]
+        system: bool,
+        #[arg(long, help = "Only print the systemd unit")]
+        print: bool,
+    },
+    #[command(about = "Stop and remove the watcher set up by install-service")]
+    UninstallService {
+        #[arg(long, help = "Linux: remove the system unit instead of the user unit")]
+        system: bool,
+    },
+    #[command(about = "Print a completion script for a shell, e.g. `completions bash > /etc/bash_completion.d/genshin_package_scanner`")]
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+// Where to fetch from and which game of the response to use
+#[derive(Args)]
+pub struct SourceArgs {
+    #[arg(long, short, help = "API URL or saved JSON response (default: the first game in the app's settings)")]
+    source: Option<String>,
+    #[arg(long, short, value_name = "BIZ", help = "Only use this game of the response, e.g. hk4e_global")]
+    game: Option<String>,
+    #[arg(long, help = "Reject sections with unknown or missing fields")]
+    strict: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Text,       // Combined report from the user's templates
+    Bbcode,     // Combined report for forums
+    Discord,    // Combined report in Discord Markdown
+    Html,       // Combined report as HTML with tables and links
+    Json,       // Parsed packages
+    Md5,        // `md5sum -c` checksum list
+    Urls,       // One URL per line
+    Fdm,        // Free Download Manager URL list
+    Referer,    // URL list with a Referer header per line
+    Aria2,      // aria2 input file
+    Bash,       // URLs as a bash array
+    Powershell, // URLs as a PowerShell string array
+}
+
+// Release builds on Windows have no console of their own; use the one of the
+// shell that started the app so its output shows up
+pub fn attach_console() {
+    #[cfg(windows)]
+    unsafe {
+        winapi::um::wincon::AttachConsole(winapi::um::wincon::ATTACH_PARENT_PROCESS);
+    }
+}
+
+// ----------------------
+// Exit Codes
+// ----------------------
+
+// What a command's exit status means, so cron jobs and CI pipelines can branch
+// on it. `fetch` tells whether the response held a version not seen before;
+// every other command exits with 0 when it succeeds.
+pub const EXIT_NO_CHANGE: i32 = 0;
+pub const EXIT_FAILED: i32 = 1; // Anything else went wrong, e.g. a file failed verification
+pub const EXIT_API_ERROR: i32 = 2; // The API couldn't be reached, reported an error or sent an unusable response
+pub const EXIT_NEW_VERSION: i32 = 10; // A live version not seen before
+pub const EXIT_NEW_PRE_DOWNLOAD: i32 = 11; // A pre-download not seen before (and no new live version)
+pub const EXIT_USAGE: i32 = 64; // Invalid arguments
+
+// An error with the exit code it ends the command with
+struct Failure {
+    code: i32,
+    message: String,
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Self {
+            code: EXIT_FAILED,
+            message,
+        }
+    }
+}
+
+// Run a subcommand and return its exit code. Errors are printed to stderr.
+pub fn run(command: Command) -> i32 {
+    match execute(command) {
+        Ok(code) => code,
+        Err(failure) => {
+            eprintln!("{}", failure.message);
+            failure.code
+        }
+    }
+}
+
+fn execute(command: Command) -> Result<i32, Failure> {
+    let settings = Settings::load();
+    network::configure(&settings.network);
+    if network::current().accept_invalid_certs {
+        eprintln!("WARNING: {}", network::INSECURE_WARNING);
+    }
+    match command {
+        Command::Fetch { source, json, github } => {
+            // Fail before fetching when the outputs can't be written
+            let github = if github {
+                Some(GithubFiles::from_env().map_err(|message| Failure { code: EXIT_USAGE, message })?)
+            } else {
+                None
+            };
+            let result = fetch(&settings, &source).inspect_err(|failure| run_error_hook(&settings, &source, failure))?;
+            let text = if json {
+                packages_json(&result.games)?
+            } else {
+                report::game_reports(&result.games, &settings.templates, None)
+            };
+            println!("{}", text.trim_end());
+
+            // Versions are recorded in the shared history; the first fetch into
+            // an empty history finds every version new
+            let mut history = History::load();
+            let new_records = record_fetch(&mut history, &result, Utc::now());
+            for record in &new_records {
+                eprintln!("{}", announcement(&settings, &result, record));
+            }
+            run_version_hooks(&settings, &result, &new_records);
+            // A workflow reads the change from the outputs; exiting with 10 or 11 would fail its step
+            if let Some(github) = github {
+                github.write(&result, &new_records, &settings.templates)?;
+                return Ok(EXIT_NO_CHANGE);
+            }
+            let new_kind = |kind| new_records.iter().any(|record| record.kind == kind);
+            Ok(if new_kind(VersionKind::Live) {
+                EXIT_NEW_VERSION
+            } else if new_kind(VersionKind::PreDownload) {
+                EXIT_NEW_PRE_DOWNLOAD
+            } else {
+                EXIT_NO_CHANGE
+            })
+        }
+        Command::Watch {
+            source,
+            interval,
+            upload,
+            json,
+        } => watch(&settings, &source, interval, upload, json),
+        Command::Export {
+            source,
+            format,
+            url_filter,
+            output,
+            upload,
+        } => {
+            if upload.is_some() {
+                check_upload_target(&settings)?;
+            }
+            let filter = export::url_filter(url_filter.as_deref().unwrap_or_default())?;
+            let result = fetch(&settings, &source)?;
+            let text = export_text(&result.games, &settings.templates, format, filter.as_ref())?;
+            match &output {
+                Some(path) => {
+                    std::fs::write(path, &text).map_err(|e| format!("Export error ({}): {}", path.display(), e))?;
+                    eprintln!("Wrote {}", path.display());
+                }
+                // An upload alone doesn't print the export
+                None if upload.is_some() => {}
+                None => print!("{}", text),
+            }
+            if let Some(name) = upload {
+                let url = settings.upload.upload(&name, text.as_bytes())?;
+                eprintln!("Uploaded {}", url);
+            }
+            Ok(EXIT_NO_CHANGE)
+        }
+        Command::Verify { dir, source, sha256 } => {
+            let result = fetch(&settings, &source)?;
+            let package = single_package(&result.games)?;
+            verify_folder(&dir, package, sha256)?;
+            Ok(EXIT_NO_CHANGE)
+        }
+        Command::Download {
+            source,
+            languages,
+            pre_download,
+            audio_only,
+            dir,
+            mirror,
+        } => {
+            if mirror && !settings.mirror.is_configured() {
+                return Err(Failure {
+                    code: EXIT_USAGE,
+                    message: "No SFTP mirror is set up; fill in Downloads > SFTP Mirror in the window first.".to_string(),
+                });
+            }
+            let result = fetch(&settings, &source)?;
+            let package = single_package(&result.games)?;
+            let major = if pre_download {
+                let major = package.pre_download.as_ref().and_then(|pre_download| pre_download.major.as_ref());
+                major.ok_or_else(|| format!("{} has no pre-download right now.", package.game.display_name()))?
+            } else {
+                let major = package.main.major.as_ref();
+                major.ok_or_else(|| format!("{} has no live version in the response.", package.game.display_name()))?
+            };
+            let files = major_files(major, &languages, audio_only)?;
+            let root = dir.unwrap_or_else(|| download::download_dir(&settings.download_folder));
+            download_files(&settings, &package.game.display_name(), &files, &root, mirror)?;
+            Ok(EXIT_NO_CHANGE)
+        }
+        Command::CheckArchive { dir } => {
+            let root = dir.unwrap_or_else(|| download::download_dir(&settings.download_folder));
+            check_archive(&root)
+        }
+        Command::Dedup { dir, dry_run } => {
+            let root = dir.unwrap_or_else(|| download::download_dir(&settings.download_folder));
+            let progress = Mutex::new(DedupProgress::default());
+            archive::deduplicate(&root, dry_run, &progress, &|| false);
+            let progress = progress.into_inner().unwrap_or_else(|e| e.into_inner());
+            print!("{}", archive::dedup_report(&progress, dry_run));
+            Ok(if progress.errors.is_empty() { EXIT_NO_CHANGE } else { EXIT_FAILED })
+        }
+        Command::Activity { month, output } => {
+            let month = month.unwrap_or_else(|| activity::month_of(Utc::now()));
+            let report = activity::monthly_report(&History::load(), month);
+            match &output {
+                Some(path) => {
+                    std::fs::write(path, &report).map_err(|e| format!("Export error ({}): {}", path.display(), e))?;
+                    eprintln!("Wrote {}", path.display());
+                }
+                None => print!("{}", report),
+            }
+            Ok(EXIT_NO_CHANGE)
+        }
+        #[cfg(feature = "tui")]
+        Command::Tui { source } => {
+            let strict = source.strict || settings.strict_parsing;
+            tui::run(&settings, source.source(&settings), strict, source.game.clone())?;
+            Ok(EXIT_NO_CHANGE)
+        }
+        Command::Diff { a, b, game, strict } => {
+            let fetch_one = |source: &str| {
+                let args = SourceArgs {
+                    source: Some(source.to_string()),
+                    game: game.clone(),
+                    strict,
+                };
+                fetch(&settings, &args)
+            };
+            let (left, right) = (fetch_one(&a)?, fetch_one(&b)?);
+            print_diff(single_package(&left.games)?, single_package(&right.games)?);
+            Ok(EXIT_NO_CHANGE)
+        }
+        Command::Doctor { source, dir } => {
+            // A saved response can't be tested, so fall back to the default API
+            let source = source.unwrap_or_else(|| {
+                Some(default_source(&settings))
+                    .filter(|source| source.starts_with("http"))
+                    .unwrap_or_else(|| DEFAULT_API_URL.to_string())
+            });
+            let dir = dir.unwrap_or_else(|| download::download_dir(&settings.download_folder));
+            let checks = doctor::run_checks(&source, &dir);
+            for check in &checks {
+                println!("{:<5} {:<16} {}", check.status.label(), check.name, check.detail);
+            }
+            Ok(if checks.iter().any(|check| check.status == CheckStatus::Failed) {
+                EXIT_FAILED
+            } else {
+                EXIT_NO_CHANGE
+            })
+        }
+        Command::InstallService {
+            interval,
+            upload,
+            system,
+            print,
+        } => {
+            let options = ServiceOptions { interval, upload, system };
+            if print {
+                let exe = std::env::current_exe().map_err(|e| format!("Service error: {}", e))?;
+                print!("{}", service::systemd_unit(&exe.to_string_lossy(), &options));
+                return Ok(EXIT_NO_CHANGE);
+            }
+            if upload {
+                check_upload_target(&settings)?;
+            }
+            println!("{}", service::install(&options)?);
+            Ok(EXIT_NO_CHANGE)
+        }
+        Command::UninstallService { system } => {
+            println!("{}", service::uninstall(system)?);
+            Ok(EXIT_NO_CHANGE)
+        }
+        Command::Completions { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+            Ok(EXIT_NO_CHANGE)
+        }
+    }
+}
+
+// ----------------------
+// Fetching
+// ----------------------
+
+impl SourceArgs {
+    fn source(&self, settings: &Settings) -> String {
+        match &self.source {
+            Some(source) => source.trim().to_string(),
+            None => default_source(settings),
+        }
+    }
+}
+
+// Fetch a source, keeping only the requested game. Fields that failed to
+// parse are reported on stderr; the rest is returned.
+fn fetch(settings: &Settings, args: &SourceArgs) -> Result<FetchResult, Failure> {
+    let source = args.source(settings);
+    let mut result =
+        fetch_and_process_data(&source, args.strict || settings.strict_parsing, &|| false).map_err(|message| Failure {
+            code: EXIT_API_ERROR,
+            message,
+        })?;
+    for e in &result.parse_errors {
+        eprintln!("warning: {}", e);
+    }
+
+    if let Some(biz) = &args.game {
+        result.games.retain(|game| game.game.biz == *biz);
+        if result.games.is_empty() {
+            return Err(format!("The response has no game {}.", biz).into());
+        }
+    }
+    Ok(result)
+}
+
+// Source of the first game in the settings
+fn default_source(settings: &Settings) -> String {
+    settings
+        .games
+        .first()
+        .map(|game| game.source.trim().to_string())
+        .unwrap_or_else(|| DEFAULT_API_URL.to_string())
+}
+
+// The package of the only (or first) game in a response
+fn single_package(games: &[GameData]) -> Result<&GamePackage, String> {
+    let package = games
+        .iter()
+        .find_map(|game| game.package.as_ref())
+        .ok_or_else(|| "The response has no package data.".to_string())?;
+    if games.len() > 1 {
+        eprintln!("The response has {} games; using {}. Pick another with --game.", games.len(), package.game.biz);
+    }
+    Ok(package)
+}
+
+// Parse intervals such as "90s", "10m", "1h" or a plain number of minutes
+fn parse_interval(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => text.split_at(index),
+        None => (text, "m"),
+    };
+    let number: u64 = number.parse().map_err(|_| format!("Invalid interval: {}", text))?;
+    let seconds = match unit {
+        "s" => number,
+        "m" => number * 60,
+        "h" => number * 3600,
+        _ => return Err(format!("Invalid interval unit in {}; use s, m or h", text)),
+    };
+    if seconds == 0 {
+        return Err("The interval must be longer than zero.".to_string());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+// ----------------------
+// Reports and Exports
+// ----------------------
+
+fn packages_json(games: &[GameData]) -> Result<String, String> {
+    let packages: Vec<&GamePackage> = games.iter().filter_map(|game| game.package.as_ref()).collect();
+    serde_json::to_string_pretty(&packages).map_err(|e| format!("JSON error: {}", e))
+}
+
+fn export_text(games: &[GameData], templates: &ReportTemplates, format: Format, filter: Option<&Regex>) -> Result<String, String> {
+    // The files of every game, in one list
+    let files: Vec<PackageFile> = games
+        .iter()
+        .filter_map(|game| game.package.as_ref())
+        .flat_map(|package| export::filtered_files(package, filter))
+        .collect();
+    let url_list = |format| export::format_url_list(&files, format);
+    let is_report = matches!(format, Format::Text | Format::Bbcode | Format::Discord | Format::Html | Format::Json);
+    if filter.is_some() && is_report {
+        return Err("--url-filter only applies to the md5 and URL list formats.".to_string());
+    }
+    let text = match format {
+        Format::Text => report::game_reports(games, templates, None),
+        Format::Bbcode => report::game_reports(games, templates, Some(ExportFormat::BBCode)),
+        Format::Discord => report::game_reports(games, templates, Some(ExportFormat::Discord)),
+        Format::Html => report::game_reports(games, templates, Some(ExportFormat::Html)),
+        Format::Json => packages_json(games)? + "\n",
+        Format::Md5 => export::checksum_lines(files.iter()),
+        Format::Urls => url_list(UrlListFormat::Plain),
+        Format::Fdm => url_list(UrlListFormat::FreeDownloadManager),
+        Format::Referer => url_list(UrlListFormat::WithReferer),
+        Format::Aria2 => url_list(UrlListFormat::Aria2),
+        Format::Bash => url_list(UrlListFormat::BashArray),
+        Format::Powershell => url_list(UrlListFormat::PowerShellArray),
+    };
+    if text.trim().is_empty() {
+        return Err("Nothing to export.".to_string());
+    }
+    Ok(text)
+}
+
+// ----------------------
+// Watch
+// ----------------------
+
+// Fetch every `interval` until interrupted, printing the versions in each
+// response and announcing the ones seen for the first time. Sightings go into
+// the same history the window keeps. With `upload`, each fetch's snapshots
+// are uploaded as well; a failed upload is retried with the next fetch. The
+// waits follow the polling rules in `polling.rs`: a minimum interval, jitter,
+// and backing off while fetches keep failing.
+fn watch(settings: &Settings, args: &SourceArgs, interval: Duration, upload: bool, json: bool) -> Result<i32, Failure> {
+    if upload {
+        check_upload_target(settings)?;
+    }
+    let mut history = History::load();
+    let watchdog = systemd::watchdog_interval();
+    let mut last_success: Option<DateTime<Utc>> = None;
+    if interval < polling::MIN_INTERVAL {
+        eprintln!("Fetching every {} minutes, the shortest interval allowed.", polling::MIN_INTERVAL.as_secs() / 60);
+    }
+    let mut poller = Poller::new(interval);
+    systemd::notify("READY=1");
+    loop {
+        let now = Utc::now();
+        let stamp = now.format("%Y-%m-%d %H:%M:%S UTC");
+        let result = fetch(settings, args);
+        // Ping right away, as the fetch may have taken a while
+        systemd::notify("WATCHDOG=1");
+        match result {
+            Ok(result) => {
+                last_success = Some(now);
+                poller.succeeded();
+                for package in result.games.iter().filter_map(|game| game.package.as_ref()) {
+                    if json {
+                        print_event(now, "fetch", fetch_event(package));
+                    } else {
+                        println!("[{}] {}: {}", stamp, package.game.label(), version_summary(package));
+                    }
+                }
+                let new_records = record_fetch(&mut history, &result, now);
+                for record in &new_records {
+                    if json {
+                        let message = announcement(settings, &result, record);
+                        let event = serde_json::json!({ "biz": record.biz, "kind": kind_label(record.kind), "version": record.version, "message": message });
+                        print_event(now, "new_version", event);
+                    } else {
+                        println!("[{}] {}", stamp, announcement(settings, &result, record));
+                    }
+                }
+                run_version_hooks(settings, &result, &new_records);
+                if upload {
+                    let files = upload::snapshot_files(&result.games, &settings.templates);
+                    match upload::upload_files(&settings.upload, &files) {
+                        Ok(count) if json => print_event(now, "upload", serde_json::json!({ "files": count })),
+                        Ok(count) => println!("[{}] Uploaded {} files", stamp, count),
+                        Err(e) if json => print_event(now, "error", serde_json::json!({ "message": e })),
+                        Err(e) => eprintln!("[{}] {}", stamp, e),
+                    }
+                }
+            }
+            Err(failure) => {
+                poller.failed();
+                if json {
+                    print_event(now, "error", serde_json::json!({ "message": failure.message }));
+                } else {
+                    eprintln!("[{}] {}", stamp, failure.message);
+                }
+                run_error_hook(settings, args, &failure);
+            }
+        }
+        let status = match last_success {
+            Some(last) if last == now => format!("Last fetch OK at {}", stamp),
+            Some(last) => format!("Last fetch failed at {}; last OK at {}", stamp, last.format("%Y-%m-%d %H:%M:%S UTC")),
+            None => format!("Last fetch failed at {}; none OK yet", stamp),
+        };
+        let delay = poller.next_delay();
+        let status = if poller.is_backing_off() {
+            format!("{}; backing off, next fetch in {}s", status, delay.as_secs())
+        } else {
+            status
+        };
+        systemd::notify(&format!("STATUS={}", status));
+        systemd::sleep(delay, watchdog);
+    }
+}
+
+// One line of `watch --json`: the event's fields plus its name and time
+fn print_event(time: DateTime<Utc>, name: &str, fields: serde_json::Value) {
+    let mut event = serde_json::json!({ "event": name, "time": time.to_rfc3339() });
+    if let (Some(event), serde_json::Value::Object(fields)) = (event.as_object_mut(), fields) {
+        event.extend(fields);
+    }
+    println!("{}", event);
+}
+
+// What a fetch found for one game
+fn fetch_event(package: &GamePackage) -> serde_json::Value {
+    let live = package.main.major.as_ref();
+    let pre_download = package.pre_download.as_ref().and_then(|pre_download| pre_download.major.as_ref());
+    let size = |major: &Major| major.game_pkgs.iter().map(|pkg| pkg.size.parse::<u64>().unwrap_or(0)).sum::<u64>();
+    serde_json::json!({
+        "game": package.game.display_name(),
+        "biz": package.game.biz,
+        "version": live.map(|major| major.version.as_str()),
+        "size": live.map(size),
+        "pre_download": pre_download.map(|major| major.version.as_str()),
+        "pre_download_size": pre_download.map(size),
+    })
+}
+
+// Record the versions, download links and unknown fields of a fetch in the
+// history, saving it when anything changed. Returns the versions not seen before.
+pub fn record_fetch(history: &mut History, result: &FetchResult, now: DateTime<Utc>) -> Vec<VersionRecord> {
+    let mut changed = !history.record_schema(&result.unknown_fields, "command line", now).is_empty();
+    let mut new_records = Vec::new();
+    for package in result.games.iter().filter_map(|game| game.package.as_ref()) {
+        new_records.extend(history.record(package, now));
+        changed |= history.record_links(package);
+    }
+    if changed || !new_records.is_empty() {
+        if let Err(e) = history.save() {
+            eprintln!("{}", e);
+        }
+    }
+    new_records
+}
+
+// The message announcing a version seen for the first time
+fn announcement(settings: &Settings, result: &FetchResult, record: &VersionRecord) -> String {
+    result
+        .games
+        .iter()
+        .filter_map(|game| game.package.as_ref())
+        .find(|package| package.game.biz == record.biz)
+        .and_then(|package| Variables::new_version(package, record, &settings.messages))
+        .map(|vars| vars.message)
+        .unwrap_or_else(|| format!("New {} {} for {}", kind_label(record.kind), record.version, record.biz))
+}
+
+// Run the hooks for the versions a fetch found, waiting for each. Their
+// output goes to stderr, keeping stdout to the report or JSON.
+fn run_version_hooks(settings: &Settings, result: &FetchResult, new_records: &[VersionRecord]) {
+    let packages: Vec<_> = result.games.iter().filter_map(|game| game.package.clone()).collect();
+    hooks::run_all(&hooks::version_hooks(&settings.hooks, &packages, new_records, &settings.messages), true);
+}
+
+fn run_error_hook(settings: &Settings, args: &SourceArgs, failure: &Failure) {
+    if let Some(hook) = hooks::error_hook(&settings.hooks, &args.source(settings), &failure.message, &settings.messages) {
+        hooks::run_all(&[hook], true);
+    }
+}
+
+// Fail early, before fetching, when uploads were asked for but aren't set up
+fn check_upload_target(settings: &Settings) -> Result<(), Failure> {
+    if settings.upload.is_configured() {
+        Ok(())
+    } else {
+        Err(Failure {
+            code: EXIT_USAGE,
+            message: "No upload target is set up; choose S3 or WebDAV under Games > Upload in the window first.".to_string(),
+        })
+    }
+}
+
+fn kind_label(kind: VersionKind) -> &'static str {
+    match kind {
+        VersionKind::Live => "live version",
+        VersionKind::PreDownload => "pre-download",
+    }
+}
+
+// e.g. "live 5.0.0, pre-download 5.1.0"
+fn version_summary(package: &GamePackage) -> String {
+    let live = package.main.major.as_ref().map(|major| major.version.as_str()).unwrap_or("-");
+    match package.pre_download.as_ref().and_then(|pre_download| pre_download.major.as_ref()) {
+        Some(next) => format!("live {}, pre-download {}", live, next.version),
+        None => format!("live {}, no pre-download", live),
+    }
+}
+
+// ----------------------
+// Verify
+// ----------------------
+
+fn verify_folder(dir: &Path, package: &GamePackage, sha256: bool) -> Result<(), String> {
+    let game = package.game.display_name();
+    let (files, unknown) = verify::match_folder(dir, &package_files(package))?;
+    for name in &unknown {
+        eprintln!("Not in the API: {}", name);
+    }
+    if files.is_empty() {
+        return Err(format!("No files of {} found in {}.", game, dir.display()));
+    }
+
+    let algorithm = if sha256 { HashAlgorithm::Md5AndSha256 } else { HashAlgorithm::Md5 };
+    let progress = Mutex::new(VerifyProgress {
+        total: files.len(),
+        unknown,
+        ..Default::default()
+    });
+    let on_checked = || {
+        let checked = progress.lock().map(|progress| progress.checks.len()).unwrap_or(0);
+        eprint!("\rChecked {} of {} files", checked, files.len());
+    };
+    verify::verify_files(dir, &files, algorithm, &progress, &on_checked, &|| false);
+    eprintln!();
+
+    let mut progress = progress.into_inner().unwrap_or_else(|e| e.into_inner());
+    progress.finished = true;
+    progress.checks.sort_by_key(|check| check.file.file_name());
+    for check in &progress.checks {
+        let (status, detail) = match &check.status {
+            FileStatus::Ok => ("OK", String::new()),
+            FileStatus::WrongSize => ("WRONG SIZE", format!(" ({} bytes, expected {})", check.size, check.file.size)),
+            FileStatus::Mismatch => ("MD5 MISMATCH", String::new()),
+            FileStatus::Missing => ("MISSING", String::new()),
+            FileStatus::Failed(e) => ("FAILED", format!(" ({})", e)),
+        };
+        println!("{:<12} {}{}", status, check.file.file_name(), detail);
+    }
+    if sha256 {
+        let path = verify::write_manifest(dir, &game, &progress.checks)?;
+        eprintln!("Wrote {}", path.display());
+    }
+
+    let bad = progress.checks.len() - progress.count(&FileStatus::Ok);
+    if progress.passed() {
+        println!("All {} files passed.", progress.checks.len());
+        Ok(())
+    } else {
+        Err(format!("{} of {} files failed verification.", bad, progress.checks.len()))
+    }
+}
+
+// Re-hash every file listed in the manifests below `root`. The run is kept in
+// the shared history, so the window's schedule counts it too.
+fn check_archive(root: &Path) -> Result<i32, Failure> {
+    let progress = Mutex::new(ArchiveProgress::default());
+    archive::check_archive(root, &progress, &|| false);
+    let mut progress = progress.into_inner().unwrap_or_else(|e| e.into_inner());
+    progress.finished = true;
+    if progress.total == 0 && progress.errors.is_empty() {
+        return Err(format!("No manifest.json with files found below {}.", root.display()).into());
+    }
+
+    for e in &progress.errors {
+        eprintln!("{}", e);
+    }
+    let problems = progress.problems();
+    for check in &problems {
+        println!("{}  {}", check.path.display(), check.status.label());
+    }
+    println!("{} files checked, {} damaged or missing.", progress.checks.len(), problems.len());
+    let failed = !problems.is_empty() || !progress.errors.is_empty();
+
+    let mut history = History::load();
+    history.record_archive_check(&progress, Utc::now());
+    history.save()?;
+    Ok(if failed { EXIT_FAILED } else { EXIT_NO_CHANGE })
+}
+
+// ----------------------
+// Download
+// ----------------------
+
+// The game packages and the chosen audio packs of a version
+fn major_files(major: &Major, languages: &[String], audio_only: bool) -> Result<Vec<PackageFile>, String> {
+    let available: Vec<&str> = major.audio_pkgs.iter().map(|pkg| pkg.language.as_str()).collect();
+    for language in languages {
+        if !available.iter().any(|code| code.eq_ignore_ascii_case(language)) {
+            return Err(format!(
+                "Version {} has no {} audio; available: {}",
+                major.version,
+                language,
+                available.join(", ")
+            ));
+        }
+    }
+
+    let file = |url: &str, md5: &str, size: &str| PackageFile {
+        version: major.version.clone(),
+        url: url.to_string(),
+        md5: md5.to_string(),
+        size: size.parse().unwrap_or(0),
+    };
+    let mut files = Vec::new();
+    if !audio_only {
+        files.extend(major.game_pkgs.iter().map(|pkg| file(&pkg.url, &pkg.md5, &pkg.size)));
+    }
+    let wanted = |code: &str| languages.is_empty() || languages.iter().any(|language| language.eq_ignore_ascii_case(code));
+    files.extend(
+        major
+            .audio_pkgs
+            .iter()
+            .filter(|pkg| wanted(&pkg.language))
+            .map(|pkg| file(&pkg.url, &pkg.md5, &pkg.size)),
+    );
+    Ok(files)
+}
+
+// Download files one after another, uploading each to the SFTP mirror when `mirror` is set
+fn download_files(settings: &Settings, game: &str, files: &[PackageFile], root: &Path, mirror: bool) -> Result<(), String> {
+    let cancel = CancelToken::default();
+    let bytes_per_second = settings.bandwidth_limit_mb as u64 * 1024 * 1024;
+    let mirror_file = |file: &PackageFile, path: &Path| -> Result<(), String> {
+        if !mirror {
+            return Ok(());
+        }
+        let on_status = |status: MirrorStatus| match status {
+            MirrorStatus::Resuming(bytes) => println!("  resuming upload after {} bytes", bytes),
+            MirrorStatus::Uploading => println!("  uploading to {}", settings.mirror.host.trim()),
+            MirrorStatus::Verifying => println!("  checking MD5 on the server"),
+            _ => {}
+        };
+        let remote = mirror::mirror_file(&settings.mirror, game, file, path, &on_status)
+            .map_err(|e| format!("Mirror upload of {} failed: {}", file.file_name(), e))?;
+        println!("  mirrored to {}", remote);
+        Ok(())
+    };
+    for (index, file) in files.iter().enumerate() {
+        let dest = download::version_dir(root, game, &file.version).join(file.file_name());
+        if file.size > 0 && std::fs::metadata(&dest).is_ok_and(|metadata| metadata.len() == file.size) {
+            println!("[{}/{}] {} is already downloaded", index + 1, files.len(), file.file_name());
+            mirror_file(file, &dest)?;
+            continue;
+        }
+
+        println!("[{}/{}] {}", index + 1, files.len(), file.file_name());
+        let on_progress = |downloaded: u64, size: u64| {
+            let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+            eprint!("\r  {:.1} of {:.1} MB", mb(downloaded), mb(size));
+            let _ = std::io::stderr().flush();
+        };
+        let result = download::download_now(
+            game,
+            file,
+            root,
+            settings.download_connections,
+            bytes_per_second,
+            &cancel,
+            &on_progress,
+        );
+        eprintln!();
+        // Finished files and failed checks go into the history for the activity report
+        let failure = match &result {
+            Ok(_) => Some(None),
+            Err(e) if e == download::SIZE_MISMATCH || e == download::MD5_MISMATCH => Some(Some(e.clone())),
+            Err(_) => None,
+        };
+        if let Some(failure) = failure {
+            let mut history = History::load();
+            let size = result.as_ref().ok().and_then(|path| std::fs::metadata(path).ok());
+            history.record_download(game, file, size.map_or(file.size, |metadata| metadata.len()), failure, Utc::now());
+            history.save()?;
+        }
+        let path = result.map_err(|e| format!("Download of {} failed: {}", file.file_name(), e))?;
+        println!("  saved to {}", path.display());
+        mirror_file(file, &path)?;
+    }
+    Ok(())
+}
+
+// ----------------------
+// Diff
+// ----------------------
+
+// The comparison table of the Compare tab, with differing rows marked
+fn print_diff(left: &GamePackage, right: &GamePackage) {
+    let rows = compare::compare(left, right);
+    let width = |column: &dyn Fn(&compare::ComparisonRow) -> usize, title: usize| rows.iter().map(column).max().unwrap_or(0).max(title);
+    let left_title = left.game.label();
+    let item_width = width(&|row| row.item.chars().count(), 0);
+    let left_width = width(&|row| row.left.chars().count(), left_title.chars().count());
+
+    println!("  {:<item_width$}  {:<left_width$}  {}", "", left_title, right.game.label());
+    for row in &rows {
+        let mark = if row.differs { '*' } else { ' ' };
+        println!("{} {:<item_width$}  {:<left_width$}  {}", mark, row.item, row.left, row.right);
+    }
+}
@@ -0,0 +1,32 @@
+// ----------------------
+// Rich Text Clipboard
+// ----------------------
+
+// egui only puts plain text on the clipboard. Rich-text copies go through
+// arboard instead, which places the HTML next to a plain-text fallback, so
+// word processors and mail clients keep the headings, links and tables while
+// plain editors still get the usual report. The clipboard handle is kept for
+// the life of the app: on Linux it serves the contents and they would vanish
+// once it is dropped.
+
+use arboard::Clipboard;
+use log::error;
+use std::sync::{Mutex, OnceLock};
+
+fn clipboard() -> &'static Mutex<Option<Clipboard>> {
+    static CLIPBOARD: OnceLock<Mutex<Option<Clipboard>>> = OnceLock::new();
+    CLIPBOARD.get_or_init(|| Mutex::new(None))
+}
+
+// Place HTML on the clipboard, with `alt_text` for applications that only take plain text
+pub fn copy_html(html: &str, alt_text: &str) -> Result<(), String> {
+    let mut slot = clipboard().lock().unwrap_or_else(|e| e.into_inner());
+    let clipboard = match slot.take() {
+        Some(clipboard) => slot.insert(clipboard),
+        None => slot.insert(Clipboard::new().map_err(|e| format!("Clipboard error: {}", e))?),
+    };
+    clipboard.set_html(html, Some(alt_text)).map_err(|e| {
+        error!("Failed to copy rich text: {}", e);
+        format!("Clipboard error: {}", e)
+    })
+}
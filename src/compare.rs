@@ -0,0 +1,202 @@
+// ----------------------
+// Region Comparison
+// ----------------------
+
+// Compares the data of two games side by side, typically the global and CN
+// releases of the same game. Mirror maintainers use this to spot version
+// skew, size differences and CDN host changes between regions. The same
+// helpers also summarize what the pre-download changes relative to the live
+// version.
+
+use crate::{bytes_to_gb, map_language_code, GamePackage, Major, Package, Patch};
+
+// One line of the comparison table
+pub struct ComparisonRow {
+    pub item: String,
+    pub left: String,
+    pub right: String,
+    pub differs: bool, // Highlighted in the UI
+}
+
+impl ComparisonRow {
+    fn new(item: impl Into<String>, left: String, right: String) -> Self {
+        let differs = left != right;
+        Self {
+            item: item.into(),
+            left,
+            right,
+            differs,
+        }
+    }
+}
+
+// Build the comparison table for two game packages
+pub fn compare(left: &GamePackage, right: &GamePackage) -> Vec<ComparisonRow> {
+    let mut rows = Vec::new();
+
+    let left_pre = left.pre_download.as_ref().and_then(|pre| pre.major.as_ref());
+    let right_pre = right.pre_download.as_ref().and_then(|pre| pre.major.as_ref());
+
+    compare_major(&mut rows, "", left.main.major.as_ref(), right.main.major.as_ref());
+    rows.push(ComparisonRow::new(
+        "Patches",
+        patch_list(&left.main.patches),
+        patch_list(&right.main.patches),
+    ));
+
+    if left_pre.is_some() || right_pre.is_some() {
+        compare_major(&mut rows, "Pre-download ", left_pre, right_pre);
+    }
+
+    rows
+}
+
+// Rows for one major version: version, sizes per package type and CDN hosts
+fn compare_major(rows: &mut Vec<ComparisonRow>, prefix: &str, left: Option<&Major>, right: Option<&Major>) {
+    let version = |major: Option<&Major>| major.map(|m| m.version.clone()).unwrap_or_else(|| "-".to_string());
+    rows.push(ComparisonRow::new(format!("{}Version", prefix), version(left), version(right)));
+
+    let game_size = |major: Option<&Major>| {
+        major
+            .map(|m| format!("{} part(s), {}", m.game_pkgs.len(), total_size(&m.game_pkgs)))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    rows.push(ComparisonRow::new(format!("{}Game Packages", prefix), game_size(left), game_size(right)));
+
+    // Every audio language present in either region
+    let mut languages: Vec<String> = Vec::new();
+    for major in [left, right].into_iter().flatten() {
+        for pkg in &major.audio_pkgs {
+            if !languages.contains(&pkg.language) {
+                languages.push(pkg.language.clone());
+            }
+        }
+    }
+    for language in languages {
+        let audio_size = |major: Option<&Major>| {
+            major
+                .and_then(|m| m.audio_pkgs.iter().find(|pkg| pkg.language == language))
+                .map(|pkg| format!("{:.2}GB", bytes_to_gb(&pkg.size)))
+                .unwrap_or_else(|| "-".to_string())
+        };
+        rows.push(ComparisonRow::new(
+            format!("{}Audio ({})", prefix, map_language_code(&language)),
+            audio_size(left),
+            audio_size(right),
+        ));
+    }
+
+    let hosts = |major: Option<&Major>| {
+        major
+            .map(|m| url_hosts(m.game_pkgs.iter().map(|pkg| pkg.url.as_str())))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    rows.push(ComparisonRow::new(format!("{}Game URL Host", prefix), hosts(left), hosts(right)));
+
+    let audio_hosts = |major: Option<&Major>| {
+        major
+            .map(|m| url_hosts(m.audio_pkgs.iter().map(|pkg| pkg.url.as_str())))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    rows.push(ComparisonRow::new(format!("{}Audio URL Host", prefix), audio_hosts(left), audio_hosts(right)));
+}
+
+// Helper function to sum package sizes as "12.34GB"
+fn total_size(pkgs: &[Package]) -> String {
+    format!("{:.2}GB", sum_gb(pkgs.iter().map(|pkg| pkg.size.as_str())))
+}
+
+// Helper function to list the source versions of the available patches
+fn patch_list(patches: &[Patch]) -> String {
+    if patches.is_empty() {
+        return "-".to_string();
+    }
+    patches.iter().map(|patch| patch.version.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+// Helper function to list the distinct hosts of a set of URLs
+fn url_hosts<'a>(urls: impl Iterator<Item = &'a str>) -> String {
+    let mut hosts: Vec<String> = Vec::new();
+    for url in urls {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| "(invalid URL)".to_string());
+        if !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+    if hosts.is_empty() {
+        return "-".to_string();
+    }
+    hosts.join(", ")
+}
+
+// ----------------------
+// Next Patch Summary
+// ----------------------
+
+// Summarize the difference between the live version and the pre-download:
+// version jump, size change per package type and audio languages added or
+// dropped. Returns None when there is no pre-download.
+#[cfg(any(feature = "gui", test))]
+pub fn next_patch_summary(package: &GamePackage) -> Option<String> {
+    let live = package.main.major.as_ref()?;
+    let pre_download = package.pre_download.as_ref()?;
+    let next = pre_download.major.as_ref()?;
+
+    let mut lines = vec![format!("Version: {} -> {}", live.version, next.version)];
+
+    let live_game = sum_gb(live.game_pkgs.iter().map(|pkg| pkg.size.as_str()));
+    let next_game = sum_gb(next.game_pkgs.iter().map(|pkg| pkg.size.as_str()));
+    lines.push(format!("Game Packages: {}", size_change(live_game, next_game)));
+
+    for pkg in &next.audio_pkgs {
+        if let Some(live_pkg) = live.audio_pkgs.iter().find(|live_pkg| live_pkg.language == pkg.language) {
+            lines.push(format!(
+                "Audio ({}): {}",
+                map_language_code(&pkg.language),
+                size_change(bytes_to_gb(&live_pkg.size), bytes_to_gb(&pkg.size))
+            ));
+        }
+    }
+
+    let added: Vec<String> = next
+        .audio_pkgs
+        .iter()
+        .filter(|pkg| !live.audio_pkgs.iter().any(|live_pkg| live_pkg.language == pkg.language))
+        .map(|pkg| map_language_code(&pkg.language))
+        .collect();
+    if !added.is_empty() {
+        lines.push(format!("New Audio Languages: {}", added.join(", ")));
+    }
+
+    let removed: Vec<String> = live
+        .audio_pkgs
+        .iter()
+        .filter(|live_pkg| !next.audio_pkgs.iter().any(|pkg| pkg.language == live_pkg.language))
+        .map(|live_pkg| map_language_code(&live_pkg.language))
+        .collect();
+    if !removed.is_empty() {
+        lines.push(format!("Removed Audio Languages: {}", removed.join(", ")));
+    }
+
+    // Download needed to update an existing install of the live version
+    if let Some(patch) = pre_download.patches.iter().find(|patch| patch.version == live.version) {
+        let game_patch = sum_gb(patch.game_pkgs.iter().map(|pkg| pkg.size.as_str()));
+        lines.push(format!("Game Patch from {}: {:.2}GB", patch.version, game_patch));
+    }
+
+    Some(lines.join("\n"))
+}
+
+// Helper function to sum byte count strings in gigabytes
+fn sum_gb<'a>(sizes: impl Iterator<Item = &'a str>) -> f64 {
+    sizes.map(bytes_to_gb).sum()
+}
+
+// Helper function to format a size change, e.g. "70.12GB -> 72.30GB (+2.18GB)"
+#[cfg(any(feature = "gui", test))]
+fn size_change(before: f64, after: f64) -> String {
+    format!("{:.2}GB -> {:.2}GB ({:+.2}GB)", before, after, after - before)
+}
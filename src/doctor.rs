@@ -0,0 +1,401 @@
+// ----------------------
+// Diagnostics
+// ----------------------
+
+// Checks behind a bare "Request error": whether the API host resolves,
+// accepts a connection and completes a TLS handshake, which proxy the request
+// goes through, how far the local clock is off the server's, and whether the
+// download and settings folders have room and can be written to. Each layer
+// is its own check so a failure points at the one that broke.
+
+use crate::network;
+use crate::settings;
+use chrono::{DateTime, Utc};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// How long each network step may take
+const TIMEOUT: Duration = Duration::from_secs(10);
+// Beyond a few minutes TLS certificates and signed download URLs start failing
+const MAX_CLOCK_SKEW_SECONDS: i64 = 300;
+// Roughly one full game with a voice pack
+const LOW_DISK_SPACE: u64 = 100 * 1024 * 1024 * 1024;
+// Proxy variables reqwest reads, in the order it prefers them
+const PROXY_VARIABLES: [&str; 8] = [
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+    "NO_PROXY",
+    "no_proxy",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheckStatus {
+    Ok,
+    Warning, // Works, but likely to cause trouble
+    Failed,
+    Skipped, // An earlier check failed or the platform can't tell
+}
+
+impl CheckStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            CheckStatus::Ok => "OK",
+            CheckStatus::Warning => "WARN",
+            CheckStatus::Failed => "FAIL",
+            CheckStatus::Skipped => "SKIP",
+        }
+    }
+}
+
+// Result of one diagnostic step
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+// Run every check against an API URL and a download folder
+pub fn run_checks(api_url: &str, download_dir: &Path) -> Vec<Check> {
+    let mut checks = network_checks(api_url);
+    checks.extend(disk_checks("Download folder", download_dir));
+    if let Some(folder) = settings::config_path("settings.json").and_then(|path| path.parent().map(Path::to_path_buf)) {
+        checks.push(write_check("Settings folder", &folder));
+    }
+    checks
+}
+
+// ----------------------
+// Network
+// ----------------------
+
+fn network_checks(api_url: &str) -> Vec<Check> {
+    let url = match reqwest::Url::parse(api_url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+        _ => {
+            return vec![Check::new(
+                "API URL",
+                CheckStatus::Failed,
+                format!("{} is not an http(s) URL", api_url),
+            )]
+        }
+    };
+    let host = url.host_str().unwrap_or_default().to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let mut checks = vec![Check::new("API URL", CheckStatus::Ok, api_url), proxy_check(), tls_settings_check()];
+    let settings = network::current();
+    if let Some(check) = local_address_check(&settings) {
+        checks.push(check);
+    }
+
+    // DNS and TCP go straight to the host, even when requests use a proxy.
+    // Addresses of an IP version that's not allowed are left out.
+    let addresses: Vec<SocketAddr> = match (host.as_str(), port).to_socket_addrs() {
+        Ok(addresses) => {
+            let ips = settings.ip_family.arrange(addresses.map(|address| address.ip()).collect());
+            if ips.is_empty() {
+                let detail = format!("{} has no address for {}", host, settings.ip_family.label());
+                checks.push(Check::new("DNS resolution", CheckStatus::Failed, detail));
+            }
+            ips.into_iter().map(|ip| SocketAddr::new(ip, port)).collect()
+        }
+        Err(e) => {
+            checks.push(Check::new("DNS resolution", CheckStatus::Failed, format!("{}: {}", host, e)));
+            Vec::new()
+        }
+    };
+    if !addresses.is_empty() {
+        let shown: Vec<String> = addresses.iter().take(3).map(|address| address.ip().to_string()).collect();
+        checks.push(Check::new("DNS resolution", CheckStatus::Ok, format!("{} -> {}", host, shown.join(", "))));
+    }
+    // With DNS-over-HTTPS, requests connect to the addresses it gives instead
+    let addresses = match doh_check(&settings, &host, &addresses) {
+        Some((check, doh_addresses)) => {
+            checks.push(check);
+            if doh_addresses.is_empty() {
+                addresses
+            } else {
+                doh_addresses.into_iter().map(|ip| SocketAddr::new(ip, port)).collect()
+            }
+        }
+        None => addresses,
+    };
+    if !addresses.is_empty() {
+        checks.push(connect_check(&addresses));
+    }
+
+    // The request itself goes through reqwest like every fetch does
+    let request_name = if url.scheme() == "https" { "TLS handshake" } else { "HTTP request" };
+    let client = match network::builder_for(&[api_url]).timeout(TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            checks.push(Check::new(request_name, CheckStatus::Failed, network::describe(&e)));
+            return checks;
+        }
+    };
+    let started = Instant::now();
+    match client.get(url.clone()).send() {
+        Ok(response) => {
+            let elapsed = started.elapsed().as_millis();
+            let status = response.status();
+            checks.push(Check::new(
+                request_name,
+                CheckStatus::Ok,
+                format!("completed, answered HTTP {} in {} ms", status.as_u16(), elapsed),
+            ));
+            checks.push(clock_check(response.headers().get(reqwest::header::DATE).and_then(|date| date.to_str().ok())));
+            checks.push(response_check(status.is_success(), response.text()));
+        }
+        Err(e) => {
+            checks.push(Check::new(request_name, CheckStatus::Failed, network::describe(&e)));
+            checks.push(Check::new("Clock skew", CheckStatus::Skipped, "no response to compare with"));
+        }
+    }
+    checks
+}
+
+// Proxies reqwest picks up from the environment, with credentials hidden
+fn proxy_check() -> Check {
+    let mut set = Vec::new();
+    let mut invalid = false;
+    for name in PROXY_VARIABLES {
+        let Ok(value) = std::env::var(name) else {
+            continue;
+        };
+        if name.eq_ignore_ascii_case("NO_PROXY") {
+            set.push(format!("{}={}", name, value));
+            continue;
+        }
+        match reqwest::Url::parse(&value) {
+            Ok(mut url) => {
+                if url.password().is_some() {
+                    let _ = url.set_password(Some("***"));
+                }
+                set.push(format!("{}={}", name, url));
+            }
+            Err(_) => {
+                invalid = true;
+                set.push(format!("{}={} (not a valid URL)", name, value));
+            }
+        }
+    }
+    match (set.is_empty(), invalid) {
+        (true, _) => Check::new("Proxy", CheckStatus::Ok, "none, connecting directly"),
+        (false, false) => Check::new("Proxy", CheckStatus::Ok, set.join(", ")),
+        (false, true) => Check::new("Proxy", CheckStatus::Warning, set.join(", ")),
+    }
+}
+
+// The CA bundle and verification settings every request uses
+fn tls_settings_check() -> Check {
+    let settings = network::current();
+    let bundle = settings.ca_bundle.trim();
+    if settings.accept_invalid_certs {
+        return Check::new("TLS settings", CheckStatus::Warning, network::INSECURE_WARNING);
+    }
+    if bundle.is_empty() {
+        return Check::new("TLS settings", CheckStatus::Ok, "system root certificates");
+    }
+    match network::load_ca_bundle(bundle) {
+        Ok(certificates) => Check::new(
+            "TLS settings",
+            CheckStatus::Ok,
+            format!("system root certificates plus {} from {}", certificates.len(), bundle),
+        ),
+        Err(e) => Check::new("TLS settings", CheckStatus::Failed, e),
+    }
+}
+
+// The local address connections are made from, when one is set
+fn local_address_check(settings: &network::NetworkSettings) -> Option<Check> {
+    match network::local_address(settings) {
+        Ok(None) => None,
+        Ok(Some(address)) if address.is_unspecified() => {
+            Some(Check::new("Local address", CheckStatus::Ok, settings.ip_family.label()))
+        }
+        Ok(Some(address)) => {
+            let detail = match settings.bind.trim() {
+                bind if bind == address.to_string() => bind.to_string(),
+                bind => format!("{} ({})", address, bind),
+            };
+            Some(Check::new("Local address", CheckStatus::Ok, detail))
+        }
+        Err(e) => Some(Check::new("Local address", CheckStatus::Failed, e)),
+    }
+}
+
+// The DNS-over-HTTPS lookup of `host`, when it's on, with the addresses it
+// gave. Addresses that share none with the system resolver's hint at DNS
+// poisoning, though CDNs also answer differently by resolver location.
+fn doh_check(settings: &network::NetworkSettings, host: &str, system: &[SocketAddr]) -> Option<(Check, Vec<IpAddr>)> {
+    let doh_url = settings.doh_url.trim();
+    if doh_url.is_empty() {
+        return None;
+    }
+    let addresses = match network::doh_lookup(doh_url, host, settings.ip_family) {
+        Ok(addresses) => addresses,
+        Err(e) => {
+            let detail = format!("{} (the system resolver is used instead)", e);
+            return Some((Check::new("DNS over HTTPS", CheckStatus::Failed, detail), Vec::new()));
+        }
+    };
+    let shown: Vec<String> = addresses.iter().take(3).map(|address| address.to_string()).collect();
+    let detail = format!("{} -> {} via {}", host, shown.join(", "), doh_url);
+    let check = if !system.is_empty() && !system.iter().any(|address| addresses.contains(&address.ip())) {
+        let detail = format!("{}; the system resolver gives other addresses, which may be poisoned", detail);
+        Check::new("DNS over HTTPS", CheckStatus::Warning, detail)
+    } else {
+        Check::new("DNS over HTTPS", CheckStatus::Ok, detail)
+    };
+    Some((check, addresses))
+}
+
+fn connect_check(addresses: &[SocketAddr]) -> Check {
+    let mut errors = Vec::new();
+    for address in addresses {
+        let started = Instant::now();
+        match TcpStream::connect_timeout(address, TIMEOUT) {
+            Ok(_) => {
+                return Check::new(
+                    "TCP connection",
+                    CheckStatus::Ok,
+                    format!("{} in {} ms", address, started.elapsed().as_millis()),
+                )
+            }
+            Err(e) => errors.push(format!("{}: {}", address, e)),
+        }
+    }
+    Check::new("TCP connection", CheckStatus::Failed, errors.join("; "))
+}
+
+// Compare the server's Date header with the local clock
+fn clock_check(date: Option<&str>) -> Check {
+    let Some(server) = date.and_then(|date| DateTime::parse_from_rfc2822(date).ok()) else {
+        return Check::new("Clock skew", CheckStatus::Skipped, "the server sent no Date header");
+    };
+    let skew = Utc::now().signed_duration_since(server).num_seconds();
+    let detail = match skew {
+        // The header only has whole seconds
+        -1..=1 => "in sync with the server".to_string(),
+        s if s > 0 => format!("local clock is {}s ahead of the server", s),
+        s => format!("local clock is {}s behind the server", -s),
+    };
+    let status = if skew.abs() > MAX_CLOCK_SKEW_SECONDS { CheckStatus::Warning } else { CheckStatus::Ok };
+    Check::new("Clock skew", status, detail)
+}
+
+// Whether the body is the API's JSON, and what it says
+fn response_check(success: bool, body: reqwest::Result<String>) -> Check {
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => return Check::new("API response", CheckStatus::Failed, network::describe(&e)),
+    };
+    let Ok(root) = serde_json::from_str::<serde_json::Value>(&body) else {
+        return Check::new("API response", CheckStatus::Failed, format!("not JSON ({} bytes)", body.len()));
+    };
+    match root.get("retcode").and_then(|retcode| retcode.as_i64()) {
+        Some(0) if success => Check::new("API response", CheckStatus::Ok, "retcode 0"),
+        Some(retcode) => Check::new(
+            "API response",
+            CheckStatus::Failed,
+            format!("retcode {}: {}", retcode, root.get("message").and_then(|m| m.as_str()).unwrap_or("")),
+        ),
+        None => Check::new("API response", CheckStatus::Warning, "JSON without a retcode"),
+    }
+}
+
+// ----------------------
+// Disk
+// ----------------------
+
+fn disk_checks(name: &'static str, dir: &Path) -> Vec<Check> {
+    let mut checks = vec![write_check(name, dir)];
+    let Some(existing) = existing_ancestor(dir) else {
+        return checks;
+    };
+    checks.push(match free_space(&existing) {
+        Some(free) => {
+            let detail = format!("{:.2}GB free on {}", free as f64 / (1024.0 * 1024.0 * 1024.0), existing.display());
+            let status = if free < LOW_DISK_SPACE { CheckStatus::Warning } else { CheckStatus::Ok };
+            Check::new("Disk space", status, detail)
+        }
+        None => Check::new("Disk space", CheckStatus::Skipped, format!("can't tell for {}", existing.display())),
+    });
+    checks
+}
+
+// A folder that doesn't exist yet only needs its nearest existing parent to
+// be writable, since downloads create it
+fn write_check(name: &'static str, dir: &Path) -> Check {
+    let Some(existing) = existing_ancestor(dir) else {
+        return Check::new(name, CheckStatus::Failed, format!("{}: no part of the path exists", dir.display()));
+    };
+    let probe = existing.join(".genshin_package_scanner_write_test");
+    let result = std::fs::write(&probe, b"").and_then(|_| std::fs::remove_file(&probe));
+    match (result, existing == dir) {
+        (Ok(()), true) => Check::new(name, CheckStatus::Ok, format!("{} is writable", dir.display())),
+        (Ok(()), false) => Check::new(
+            name,
+            CheckStatus::Ok,
+            format!("{} will be created in {}, which is writable", dir.display(), existing.display()),
+        ),
+        (Err(e), _) => Check::new(name, CheckStatus::Failed, format!("can't write to {}: {}", existing.display(), e)),
+    }
+}
+
+fn existing_ancestor(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find(|path| path.is_dir()).map(Path::to_path_buf)
+}
+
+// Bytes available to this user on the volume holding `path`
+#[cfg(unix)]
+fn free_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // The field types differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn free_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut available: winapi::shared::ntdef::ULARGE_INTEGER = unsafe { std::mem::zeroed() };
+    let ok = unsafe {
+        winapi::um::fileapi::GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return None;
+    }
+    Some(unsafe { *available.QuadPart() })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
@@ -0,0 +1,908 @@
+// ----------------------
+// Downloads
+// ----------------------
+
+// Built-in downloader for package files. Files are saved into a
+// `<game>/<version>/` folder under their URL's file name, with a
+// `manifest.json` recording what was fetched and when. Downloads run one file
+// at a time, highest priority first, and can be paused and resumed. Each file
+// is split into byte ranges fetched over several connections at once, and
+// every connection draws from one shared bandwidth limit so a 70 GB overnight
+// download doesn't saturate the household connection. Each file can also
+// have a limit of its own; both can be changed while it downloads. The queue and each
+// file's progress are saved to `downloads.json`, so an unfinished download can
+// be resumed after the app is closed or crashes. Files are written to a
+// `.part` name and only renamed once their size and MD5 check out, so an
+// interrupted transfer never looks like a complete file.
+
+use crate::export::sanitize_file_name;
+#[cfg(feature = "gui")]
+use crate::jobs::{self, JobKind};
+use crate::network;
+#[cfg(feature = "gui")]
+use crate::settings::config_path;
+use crate::verify;
+use crate::{CancelToken, PackageFile};
+#[cfg(feature = "gui")]
+use eframe::egui;
+use log::{error, info, warn};
+use reqwest::blocking::Client;
+use reqwest::header::{ACCEPT_RANGES, CONTENT_LENGTH, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// How often the queue is written to disk when it has changed
+#[cfg(feature = "gui")]
+const SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+// Errors of a finished file that didn't match the API, as opposed to a transfer that broke off
+pub const SIZE_MISMATCH: &str = "Size differs from the API";
+pub const MD5_MISMATCH: &str = "MD5 mismatch";
+
+// A connection got the whole file back for its range request
+const RANGE_IGNORED: &str = "Download error: the server ignored the range request";
+
+// Folder the files of a version are kept in, e.g. `.../Genshin Impact/5.1.0`
+pub fn version_dir(root: &Path, game: &str, version: &str) -> PathBuf {
+    root.join(sanitize_file_name(game)).join(sanitize_file_name(version))
+}
+
+// Name a file is downloaded under until it has been checked, e.g. `game.zip.part`
+pub fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+// Folder downloads are saved to: the configured one, or `Downloads/Genshin Package Scanner`
+pub fn download_dir(configured: &str) -> PathBuf {
+    if !configured.trim().is_empty() {
+        return PathBuf::from(configured.trim());
+    }
+    dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+        .join("Genshin Package Scanner")
+}
+
+// ----------------------
+// Bandwidth Limit
+// ----------------------
+
+// Token bucket shared by every connection. A transfer that exceeds the budget
+// goes into debt and sleeps until the bucket has refilled.
+pub struct RateLimiter {
+    state: Mutex<LimiterState>,
+}
+
+struct LimiterState {
+    bytes_per_second: u64, // 0 = unlimited
+    available: f64,        // Bytes that may be sent right now, negative when in debt
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self {
+            state: Mutex::new(LimiterState {
+                bytes_per_second,
+                available: bytes_per_second as f64,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    // Change the limit; running downloads pick it up with their next chunk
+    #[cfg(feature = "gui")]
+    pub fn set_limit(&self, bytes_per_second: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.bytes_per_second != bytes_per_second {
+                state.bytes_per_second = bytes_per_second;
+                state.available = state.available.min(bytes_per_second as f64);
+            }
+        }
+    }
+
+    // Wait until `bytes` may be transferred
+    fn acquire(&self, bytes: usize) {
+        let wait = {
+            let Ok(mut state) = self.state.lock() else {
+                return;
+            };
+            let limit = state.bytes_per_second as f64;
+            if limit == 0.0 {
+                return;
+            }
+
+            // Refill for the time since the last transfer, at most one second's worth
+            let now = Instant::now();
+            let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+            state.available = (state.available + elapsed * limit).min(limit);
+            state.refilled_at = now;
+
+            state.available -= bytes as f64;
+            (state.available < 0.0).then(|| Duration::from_secs_f64(-state.available / limit))
+        };
+        if let Some(wait) = wait {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+// ----------------------
+// Download Queue
+// ----------------------
+
+#[cfg(feature = "gui")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum DownloadStatus {
+    Queued,
+    Downloading,
+    Paused,
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+// Queued files with a higher priority start first
+#[cfg(feature = "gui")]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+#[cfg(feature = "gui")]
+impl Priority {
+    pub const ALL: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Normal => "Normal",
+            Priority::High => "High",
+        }
+    }
+}
+
+// A byte range of a file fetched over its own connection
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Segment {
+    start: u64,
+    end: u64,        // Exclusive; 0 when the size is unknown
+    downloaded: u64, // Bytes written from `start`
+}
+
+// Progress shared with the worker threads
+#[derive(Default)]
+struct Transfer {
+    size: u64,                          // 0 until known
+    ranged: bool,                       // Server accepts range requests, so segments can be resumed
+    segments: Vec<Segment>,
+    // Read by the window's queue; `download_now` waits for the worker instead
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    running: bool,                      // A worker is still writing to the file
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    result: Option<Result<(), String>>, // Set by the worker when it finishes or fails
+}
+
+#[cfg(feature = "gui")]
+pub struct Download {
+    pub game: String,
+    pub file: PackageFile,
+    pub dest: PathBuf,
+    pub status: DownloadStatus,
+    pub priority: Priority,
+    pub speed_limit_mb: u32, // Speed cap of this file in MB/s, 0 = only the global limit
+    transfer: Arc<Mutex<Transfer>>,
+    limiter: Arc<RateLimiter>,
+    cancel: CancelToken,
+}
+
+#[cfg(feature = "gui")]
+impl Download {
+    // Bytes downloaded so far and the file size (from the API until the server reports it)
+    pub fn progress(&self) -> (u64, u64) {
+        let transfer = self.transfer.lock().unwrap_or_else(|e| e.into_inner());
+        let downloaded = transfer.segments.iter().map(|segment| segment.downloaded).sum();
+        let size = if transfer.size > 0 { transfer.size } else { self.file.size };
+        (downloaded, size)
+    }
+
+    fn is_pending(&self) -> bool {
+        matches!(
+            self.status,
+            DownloadStatus::Queued | DownloadStatus::Downloading | DownloadStatus::Paused
+        )
+    }
+}
+
+#[cfg(feature = "gui")]
+pub struct Downloader {
+    pub downloads: Vec<Download>,
+    pub restored: usize, // Unfinished downloads loaded from the last session, until the user decides
+    limiter: Arc<RateLimiter>,
+    last_saved: Option<(Instant, String)>, // When the queue was last written and what
+    held: Option<Vec<String>>,             // While on hold, the URLs it paused
+}
+
+#[cfg(feature = "gui")]
+impl Default for Downloader {
+    fn default() -> Self {
+        Self {
+            downloads: Vec::new(),
+            restored: 0,
+            limiter: Arc::new(RateLimiter::new(0)),
+            last_saved: None,
+            held: None,
+        }
+    }
+}
+
+// An unfinished download as stored in `downloads.json`
+#[cfg(feature = "gui")]
+#[derive(Serialize, Deserialize)]
+struct SavedDownload {
+    game: String,
+    file: PackageFile,
+    dest: PathBuf,
+    priority: Priority,
+    #[serde(default)]
+    speed_limit_mb: u32,
+    size: u64,
+    ranged: bool,
+    segments: Vec<Segment>,
+}
+
+#[cfg(feature = "gui")]
+impl Downloader {
+    // Load the unfinished downloads of the last session. They come back
+    // paused until the user chooses to resume them.
+    pub fn load() -> Self {
+        let mut downloader = Self::default();
+        let Some(path) = config_path("downloads.json") else {
+            return downloader;
+        };
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return downloader;
+        };
+        let saved: Vec<SavedDownload> = match serde_json::from_str(&contents) {
+            Ok(saved) => saved,
+            Err(e) => {
+                warn!("Invalid download queue {}: {}", path.display(), e);
+                return downloader;
+            }
+        };
+
+        info!("Restored {} unfinished download(s).", saved.len());
+        downloader.restored = saved.len();
+        downloader.downloads = saved
+            .into_iter()
+            .map(|saved| Download {
+                game: saved.game,
+                file: saved.file,
+                dest: saved.dest,
+                status: DownloadStatus::Paused,
+                priority: saved.priority,
+                speed_limit_mb: saved.speed_limit_mb,
+                transfer: Arc::new(Mutex::new(Transfer {
+                    size: saved.size,
+                    ranged: saved.ranged,
+                    segments: saved.segments,
+                    ..Default::default()
+                })),
+                limiter: Arc::new(RateLimiter::new(0)),
+                cancel: CancelToken::default(),
+            })
+            .collect();
+        downloader.last_saved = Some((Instant::now(), contents));
+        downloader
+    }
+
+    // Write the unfinished downloads now, e.g. when the app is closing
+    pub fn save_now(&mut self) {
+        self.last_saved = None;
+        self.save();
+    }
+
+    // Write the unfinished downloads to disk if they changed since the last save
+    fn save(&mut self) {
+        if self.last_saved.as_ref().is_some_and(|(saved_at, _)| saved_at.elapsed() < SAVE_INTERVAL) {
+            return;
+        }
+        let Some(path) = config_path("downloads.json") else {
+            return;
+        };
+
+        let saved: Vec<SavedDownload> = self
+            .downloads
+            .iter()
+            .filter(|download| download.is_pending())
+            .map(|download| {
+                let transfer = download.transfer.lock().unwrap_or_else(|e| e.into_inner());
+                SavedDownload {
+                    game: download.game.clone(),
+                    file: download.file.clone(),
+                    dest: download.dest.clone(),
+                    priority: download.priority,
+                    speed_limit_mb: download.speed_limit_mb,
+                    size: transfer.size,
+                    ranged: transfer.ranged,
+                    segments: transfer.segments.clone(),
+                }
+            })
+            .collect();
+        let contents = match serde_json::to_string_pretty(&saved) {
+            Ok(contents) => contents,
+            Err(e) => {
+                error!("Download queue save error: {}", e);
+                return;
+            }
+        };
+        if self.last_saved.as_ref().is_some_and(|(_, last)| *last == contents) {
+            self.last_saved = Some((Instant::now(), contents));
+            return;
+        }
+
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Err(e) = std::fs::write(&path, &contents) {
+            error!("Failed to write download queue to {}: {}", path.display(), e);
+        }
+        self.last_saved = Some((Instant::now(), contents));
+    }
+
+    // Resume every download restored from the last session
+    pub fn resume_restored(&mut self) {
+        for index in 0..self.downloads.len() {
+            self.resume(index);
+        }
+        self.restored = 0;
+    }
+
+    // Drop the restored downloads, deleting their partial files
+    pub fn discard_restored(&mut self) {
+        for download in self.downloads.iter().filter(|download| download.status == DownloadStatus::Paused) {
+            let part = part_path(&download.dest);
+            if let Err(e) = std::fs::remove_file(&part) {
+                warn!("Could not remove partial download {}: {}", part.display(), e);
+            }
+        }
+        self.downloads.retain(|download| download.status != DownloadStatus::Paused);
+        self.restored = 0;
+    }
+}
+
+#[cfg(feature = "gui")]
+impl Downloader {
+    // Queue a file to be saved into its version's folder under `root`
+    pub fn add(&mut self, game: &str, file: PackageFile, root: &Path) {
+        let existing = self
+            .downloads
+            .iter_mut()
+            .find(|download| download.file.url == file.url && download.status != DownloadStatus::Cancelled);
+        if let Some(download) = existing {
+            // A file that failed, or was finished and has since been deleted, is fetched again
+            let stale = match &download.status {
+                DownloadStatus::Failed(_) => true,
+                DownloadStatus::Done => !download.dest.exists(),
+                _ => false,
+            };
+            if stale {
+                info!("Queueing {} again.", file.url);
+                download.transfer = Arc::default();
+                download.cancel = CancelToken::default();
+                download.status = DownloadStatus::Queued;
+            } else {
+                info!("{} is already queued.", file.url);
+            }
+            return;
+        }
+        self.downloads.push(Download {
+            game: game.to_string(),
+            dest: version_dir(root, game, &file.version).join(file.file_name()),
+            file,
+            status: DownloadStatus::Queued,
+            priority: Priority::Normal,
+            speed_limit_mb: 0,
+            transfer: Arc::default(),
+            limiter: Arc::new(RateLimiter::new(0)),
+            cancel: CancelToken::default(),
+        });
+    }
+
+    // Pause the running download and start no others while on hold (e.g.
+    // while the game is running). Releasing the hold resumes what it paused.
+    pub fn hold(&mut self, hold: bool) {
+        match (hold, self.held.take()) {
+            (true, None) => {
+                let mut paused = Vec::new();
+                for index in 0..self.downloads.len() {
+                    if self.downloads[index].status == DownloadStatus::Downloading {
+                        paused.push(self.downloads[index].file.url.clone());
+                        self.pause(index);
+                    }
+                }
+                self.held = Some(paused);
+            }
+            (false, Some(paused)) => {
+                for index in 0..self.downloads.len() {
+                    let download = &self.downloads[index];
+                    if download.status == DownloadStatus::Paused && paused.contains(&download.file.url) {
+                        self.resume(index);
+                    }
+                }
+            }
+            (_, held) => self.held = held,
+        }
+    }
+
+    // A file is being transferred. Queued files need no attention until the
+    // running one finishes, and its worker wakes the UI for that.
+    pub fn is_active(&self) -> bool {
+        self.downloads.iter().any(|download| download.status == DownloadStatus::Downloading)
+    }
+
+    // Stop a download; it stays in the list as cancelled
+    pub fn cancel(&mut self, index: usize) {
+        if let Some(download) = self.downloads.get_mut(index) {
+            download.cancel.cancel();
+            if download.is_pending() {
+                download.status = DownloadStatus::Cancelled;
+            }
+        }
+    }
+
+    // Stop a download but keep what it has fetched so it can be resumed
+    pub fn pause(&mut self, index: usize) {
+        if let Some(download) = self.downloads.get_mut(index) {
+            if matches!(download.status, DownloadStatus::Queued | DownloadStatus::Downloading) {
+                download.cancel.cancel();
+                download.status = DownloadStatus::Paused;
+            }
+        }
+    }
+
+    // Put a paused or failed download back in the queue
+    pub fn resume(&mut self, index: usize) {
+        if let Some(download) = self.downloads.get_mut(index) {
+            if matches!(download.status, DownloadStatus::Paused | DownloadStatus::Failed(_)) {
+                download.cancel = CancelToken::default();
+                download.status = DownloadStatus::Queued;
+            }
+        }
+    }
+
+    // Move a download one place up (-1) or down (+1) in the list
+    pub fn move_by(&mut self, index: usize, offset: isize) {
+        let target = index as isize + offset;
+        if index < self.downloads.len() && target >= 0 && (target as usize) < self.downloads.len() {
+            self.downloads.swap(index, target as usize);
+        }
+    }
+
+    // Remove finished, failed and cancelled downloads from the list
+    pub fn clear_finished(&mut self) {
+        self.downloads.retain(Download::is_pending);
+    }
+
+    // Collect results from workers, start the next queued file (the first one
+    // with the highest priority) and save the queue. Called every frame.
+    // Returns the downloads that finished or failed since the last call.
+    #[cfg(feature = "gui")]
+    pub fn update(&mut self, ctx: &egui::Context, connections: u32, bytes_per_second: u64) -> Vec<&Download> {
+        self.limiter.set_limit(bytes_per_second);
+        for download in &self.downloads {
+            download.limiter.set_limit(download.speed_limit_mb as u64 * 1024 * 1024);
+        }
+        self.save();
+
+        let mut finished = Vec::new();
+        for (index, download) in self.downloads.iter_mut().enumerate() {
+            if download.status != DownloadStatus::Downloading {
+                continue;
+            }
+            let result = download.transfer.lock().ok().and_then(|mut transfer| transfer.result.take());
+            match result {
+                Some(Ok(())) => {
+                    download.status = DownloadStatus::Done;
+                    finished.push(index);
+                }
+                Some(Err(e)) => {
+                    download.status = DownloadStatus::Failed(e);
+                    finished.push(index);
+                }
+                None => {}
+            }
+        }
+
+        if self.held.is_none() && !self.downloads.iter().any(|download| download.status == DownloadStatus::Downloading) {
+            self.start_next(ctx, connections);
+        }
+        finished.into_iter().map(|index| &self.downloads[index]).collect()
+    }
+
+    // Start the first queued file with the highest priority
+    #[cfg(feature = "gui")]
+    fn start_next(&mut self, ctx: &egui::Context, connections: u32) {
+        let mut next: Option<&mut Download> = None;
+        for download in &mut self.downloads {
+            // A paused worker may still be finishing its last chunk
+            let running = download.transfer.lock().map(|transfer| transfer.running).unwrap_or(false);
+            if download.status != DownloadStatus::Queued || running {
+                continue;
+            }
+            if next.as_ref().is_none_or(|next| download.priority > next.priority) {
+                next = Some(download);
+            }
+        }
+        if let Some(download) = next {
+            download.status = DownloadStatus::Downloading;
+            let limiters = vec![self.limiter.clone(), download.limiter.clone()];
+            start_download(ctx, download, connections.max(1), limiters);
+        }
+    }
+}
+
+// Aggregate progress of every pending or finished download of a version
+#[cfg(feature = "gui")]
+pub struct VersionProgress {
+    pub game: String,
+    pub version: String,
+    pub downloaded: u64,
+    pub size: u64,
+    pub files: usize,
+    pub done: usize,
+}
+
+#[cfg(feature = "gui")]
+impl Downloader {
+    pub fn version_progress(&self) -> Vec<VersionProgress> {
+        let mut versions: Vec<VersionProgress> = Vec::new();
+        for download in self.downloads.iter().filter(|download| download.status != DownloadStatus::Cancelled) {
+            let index = match versions
+                .iter()
+                .position(|version| version.game == download.game && version.version == download.file.version)
+            {
+                Some(index) => index,
+                None => {
+                    versions.push(VersionProgress {
+                        game: download.game.clone(),
+                        version: download.file.version.clone(),
+                        downloaded: 0,
+                        size: 0,
+                        files: 0,
+                        done: 0,
+                    });
+                    versions.len() - 1
+                }
+            };
+            let (downloaded, size) = download.progress();
+            let version = &mut versions[index];
+            version.downloaded += downloaded;
+            version.size += size;
+            version.files += 1;
+            if download.status == DownloadStatus::Done {
+                version.done += 1;
+            }
+        }
+        versions
+    }
+}
+
+// Run a download on a worker thread, drawing from every limiter in
+// `limiters`; the outcome is left in its transfer
+#[cfg(feature = "gui")]
+fn start_download(ctx: &egui::Context, download: &Download, connections: u32, limiters: Vec<Arc<RateLimiter>>) {
+    let game = download.game.clone();
+    let file = download.file.clone();
+    let dest = download.dest.clone();
+    let transfer = download.transfer.clone();
+    let cancel = download.cancel.clone();
+    let ctx = ctx.clone();
+    if let Ok(mut transfer) = transfer.lock() {
+        transfer.running = true;
+        transfer.result = None;
+    }
+
+    // Pausing and cancelling go through the download list, which tracks the status
+    jobs::spawn(JobKind::Download, file.file_name(), None, move || {
+        info!("Downloading {} to {}.", file.url, dest.display());
+        let result = run_download(&file, &dest, connections, &transfer, &limiters, &cancel).map(|size| {
+            let folder = dest.parent().unwrap_or(Path::new("."));
+            if let Err(e) = verify::add_to_manifest(folder, &game, &file, size) {
+                error!("{}", e);
+            }
+        });
+        if let Err(e) = &result {
+            error!("Download of {} stopped: {}", file.url, e);
+        }
+        if let Ok(mut transfer) = transfer.lock() {
+            transfer.running = false;
+            // A paused or cancelled download has already been marked as such
+            if !cancel.is_cancelled() {
+                transfer.result = Some(result);
+            }
+        }
+        ctx.request_repaint();
+    });
+}
+
+// Download one file on the calling thread, for the command line, into its
+// version folder under `root`. `on_progress` is called every half second with
+// the bytes downloaded and the file size. Returns where the file was saved.
+pub fn download_now(
+    game: &str,
+    file: &PackageFile,
+    root: &Path,
+    connections: u32,
+    bytes_per_second: u64,
+    cancel: &CancelToken,
+    on_progress: &(dyn Fn(u64, u64) + Sync),
+) -> Result<PathBuf, String> {
+    let folder = version_dir(root, game, &file.version);
+    let dest = folder.join(file.file_name());
+    let transfer = Mutex::new(Transfer::default());
+    let limiters = [Arc::new(RateLimiter::new(bytes_per_second))];
+    let finished = AtomicBool::new(false);
+
+    let size = std::thread::scope(|scope| {
+        scope.spawn(|| {
+            while !finished.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(500));
+                let transfer = transfer.lock().unwrap_or_else(|e| e.into_inner());
+                let downloaded = transfer.segments.iter().map(|segment| segment.downloaded).sum();
+                on_progress(downloaded, if transfer.size > 0 { transfer.size } else { file.size });
+            }
+        });
+        let result = run_download(file, &dest, connections, &transfer, &limiters, cancel);
+        finished.store(true, Ordering::Relaxed);
+        result
+    })?;
+
+    if let Err(e) = verify::add_to_manifest(&folder, game, file, size) {
+        error!("{}", e);
+    }
+    Ok(dest)
+}
+
+// Download a file to its `.part` name, check it and move it into place. Returns its size.
+fn run_download(
+    file: &PackageFile,
+    dest: &Path,
+    connections: u32,
+    transfer: &Mutex<Transfer>,
+    limiters: &[Arc<RateLimiter>],
+    cancel: &CancelToken,
+) -> Result<u64, String> {
+    let client = network::client_for(&[&file.url]);
+    let part = part_path(dest);
+
+    // Pick up where a paused download left off if the partial file is still there
+    let resumable = {
+        let transfer = transfer.lock().map_err(|_| "Download state poisoned".to_string())?;
+        transfer.ranged && std::fs::metadata(&part).is_ok_and(|metadata| metadata.len() == transfer.size)
+    };
+    if resumable {
+        info!("Resuming {}.", part.display());
+    } else {
+        start_segments(&client, file, &part, connections, transfer)?;
+    }
+
+    let ranged = transfer.lock().map_err(|_| "Download state poisoned".to_string())?.ranged;
+    let mut result = fetch_segments(&client, file, &part, transfer, limiters, cancel);
+    // Some edges and caches answer a range request with the whole file even
+    // though the HEAD said ranges work; start over on a single connection
+    let ranges_ignored = ranged && !transfer.lock().map_err(|_| "Download state poisoned".to_string())?.ranged;
+    if ranges_ignored && !cancel.is_cancelled() {
+        warn!("{} ignored range requests, fetching it over one connection.", file.url);
+        restart_unranged(&part, transfer)?;
+        result = fetch_segments(&client, file, &part, transfer, limiters, cancel);
+    }
+    result?;
+
+    // Check the finished file against the size and MD5 from the API. A bad
+    // file is removed so retrying starts over.
+    let size = std::fs::metadata(&part).map(|metadata| metadata.len()).unwrap_or(0);
+    let problem = if file.size > 0 && size != file.size {
+        Some(SIZE_MISMATCH.to_string())
+    } else if !file.md5.is_empty() && !verify::file_md5(&part, &|| cancel.is_cancelled())?.eq_ignore_ascii_case(&file.md5) {
+        Some(MD5_MISMATCH.to_string())
+    } else {
+        None
+    };
+    if let Some(problem) = problem {
+        if let Err(e) = std::fs::remove_file(&part) {
+            warn!("Could not remove bad download {}: {}", part.display(), e);
+        }
+        return Err(problem);
+    }
+
+    std::fs::rename(&part, dest).map_err(|e| format!("Download error ({}): {}", dest.display(), e))?;
+    Ok(size)
+}
+
+// Create the output file and split the download into one range per connection
+fn start_segments(client: &Client, file: &PackageFile, dest: &Path, connections: u32, transfer: &Mutex<Transfer>) -> Result<(), String> {
+    // Ask for the size and whether the server accepts range requests
+    let head = client
+        .head(&file.url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Download error: {}", e))?;
+    let size: u64 = head
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    let ranged = size > 0 && head.headers().get(ACCEPT_RANGES).is_some_and(|value| value == "bytes");
+    let connections = if ranged { connections as u64 } else { 1 };
+
+    // Split the file into one range per connection
+    let segment_size = size.div_ceil(connections).max(1);
+    let segments: Vec<Segment> = (0..connections)
+        .map(|index| Segment {
+            start: index * segment_size,
+            end: ((index + 1) * segment_size).min(size),
+            downloaded: 0,
+        })
+        .filter(|segment| size == 0 || segment.start < segment.end)
+        .collect();
+    {
+        let mut transfer = transfer.lock().map_err(|_| "Download state poisoned".to_string())?;
+        transfer.size = size;
+        transfer.ranged = ranged;
+        transfer.segments = segments;
+    }
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Download error ({}): {}", parent.display(), e))?;
+    }
+    let output = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(dest)
+        .map_err(|e| format!("Download error ({}): {}", dest.display(), e))?;
+    if size > 0 {
+        output.set_len(size).map_err(|e| format!("Download error ({}): {}", dest.display(), e))?;
+    }
+    Ok(())
+}
+
+// Fetch every segment of a transfer, each over its own connection
+fn fetch_segments(
+    client: &Client,
+    file: &PackageFile,
+    part: &Path,
+    transfer: &Mutex<Transfer>,
+    limiters: &[Arc<RateLimiter>],
+    cancel: &CancelToken,
+) -> Result<(), String> {
+    let (ranged, segment_count) = {
+        let transfer = transfer.lock().map_err(|_| "Download state poisoned".to_string())?;
+        (transfer.ranged, transfer.segments.len())
+    };
+    info!("Fetching {} over {} connection(s).", file.url, segment_count);
+    let results: Vec<Result<(), String>> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..segment_count)
+            .map(|index| scope.spawn(move || fetch_segment(client, file, part, index, ranged, transfer, limiters, cancel)))
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap_or_else(|_| Err("Download worker panicked".to_string())))
+            .collect()
+    });
+    results.into_iter().collect()
+}
+
+// Turn a transfer whose server ignored ranges into one segment from the
+// start, emptying what the ranged connections wrote
+fn restart_unranged(part: &Path, transfer: &Mutex<Transfer>) -> Result<(), String> {
+    let mut transfer = transfer.lock().map_err(|_| "Download state poisoned".to_string())?;
+    transfer.segments = vec![Segment {
+        start: 0,
+        end: transfer.size,
+        downloaded: 0,
+    }];
+    let output = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(part)
+        .map_err(|e| format!("Download error ({}): {}", part.display(), e))?;
+    output.set_len(transfer.size).map_err(|e| format!("Download error ({}): {}", part.display(), e))
+}
+
+// Fetch one range of a file over its own connection and write it in place
+#[allow(clippy::too_many_arguments)]
+fn fetch_segment(
+    client: &Client,
+    file: &PackageFile,
+    dest: &Path,
+    index: usize,
+    ranged: bool,
+    transfer: &Mutex<Transfer>,
+    limiters: &[Arc<RateLimiter>],
+    cancel: &CancelToken,
+) -> Result<(), String> {
+    let segment = transfer
+        .lock()
+        .map_err(|_| "Download state poisoned".to_string())?
+        .segments[index]
+        .clone();
+    if ranged && segment.downloaded >= segment.end - segment.start {
+        return Ok(());
+    }
+
+    let mut request = client.get(&file.url);
+    if ranged {
+        request = request.header(RANGE, format!("bytes={}-{}", segment.start + segment.downloaded, segment.end - 1));
+    }
+    let mut response = request
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Download error: {}", e))?;
+    // A 200 is the whole file, which written at this range's offset would run
+    // past its end. The other connections stop once they see the flag.
+    if ranged && response.status() != StatusCode::PARTIAL_CONTENT {
+        if let Ok(mut transfer) = transfer.lock() {
+            transfer.ranged = false;
+        }
+        return Err(RANGE_IGNORED.to_string());
+    }
+
+    let mut output = OpenOptions::new()
+        .write(true)
+        .open(dest)
+        .map_err(|e| format!("Download error ({}): {}", dest.display(), e))?;
+    output
+        .seek(SeekFrom::Start(segment.start + segment.downloaded))
+        .map_err(|e| format!("Download error ({}): {}", dest.display(), e))?;
+
+    // A range stops at its end even if the server sends more
+    let mut remaining = if ranged { segment.end - segment.start - segment.downloaded } else { u64::MAX };
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    while remaining > 0 {
+        if cancel.is_cancelled() {
+            return Err("Download cancelled.".to_string());
+        }
+        let wanted = remaining.min(CHUNK_SIZE as u64) as usize;
+        let read = response.read(&mut buffer[..wanted]).map_err(|e| format!("Download error: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        remaining -= read as u64;
+        for limiter in limiters {
+            limiter.acquire(read);
+        }
+        output
+            .write_all(&buffer[..read])
+            .map_err(|e| format!("Download error ({}): {}", dest.display(), e))?;
+        if let Ok(mut transfer) = transfer.lock() {
+            if ranged && !transfer.ranged {
+                return Err(RANGE_IGNORED.to_string());
+            }
+            transfer.segments[index].downloaded += read as u64;
+        }
+    }
+
+    // A range that ended early means the connection dropped
+    if ranged {
+        let downloaded = transfer.lock().map(|transfer| transfer.segments[index].downloaded).unwrap_or(0);
+        if downloaded < segment.end - segment.start {
+            return Err(format!("Download error: connection closed after {} bytes", downloaded));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,464 @@
+// ----------------------
+// Report Exports
+// ----------------------
+
+// Writes reports to files in the export folder (`Documents/Genshin Package
+// Scanner` by default). Many communities prefer a screenshot-style card to a
+// wall of text, so reports can also be rendered to PNG using the app's own
+// font and dark theme colours, or to PDF for archiving. Checksum files make
+// downloads verifiable with standard tools, and URL lists hand them to
+// external download managers.
+
+#[cfg(feature = "gui")]
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+#[cfg(feature = "gui")]
+use eframe::egui;
+#[cfg(feature = "gui")]
+use image::{Rgba, RgbaImage};
+use crate::{package_files, GamePackage, PackageFile};
+#[cfg(any(feature = "gui", feature = "tui"))]
+use log::{error, info};
+use log::warn;
+#[cfg(feature = "gui")]
+use printpdf::{BuiltinFont, Mm, PdfDocument};
+use regex::Regex;
+#[cfg(feature = "gui")]
+use std::fs::File;
+#[cfg(feature = "gui")]
+use std::io::BufWriter;
+#[cfg(any(feature = "gui", feature = "tui"))]
+use std::path::PathBuf;
+
+#[cfg(feature = "gui")]
+const PADDING: u32 = 24;
+#[cfg(feature = "gui")]
+const MAX_TEXT_WIDTH: f32 = 1200.0;
+#[cfg(feature = "gui")]
+const TITLE_SIZE: f32 = 24.0;
+#[cfg(feature = "gui")]
+const BODY_SIZE: f32 = 16.0;
+
+// Colours of egui's dark theme
+#[cfg(feature = "gui")]
+const BACKGROUND: Rgba<u8> = Rgba([27, 27, 27, 255]);
+#[cfg(feature = "gui")]
+const ACCENT: Rgba<u8> = Rgba([90, 170, 255, 255]);
+#[cfg(feature = "gui")]
+const TITLE_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+#[cfg(feature = "gui")]
+const TEXT_COLOR: Rgba<u8> = Rgba([180, 180, 180, 255]);
+
+// Folder exports are written to, created if missing
+#[cfg(any(feature = "gui", feature = "tui"))]
+pub fn export_dir() -> Result<PathBuf, String> {
+    let dir = dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .ok_or_else(|| "No documents folder available".to_string())?
+        .join("Genshin Package Scanner");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Export error ({}): {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+// File name for an export, e.g. "Genshin Impact Main Data 2024-08-28 0412.png"
+#[cfg(any(feature = "gui", feature = "tui"))]
+pub fn export_file_name(title: &str, extension: &str) -> String {
+    format!("{} {}.{}", sanitize_file_name(title), chrono::Utc::now().format("%Y-%m-%d %H%M"), extension)
+}
+
+// Helper function to replace characters that aren't safe in file names
+pub fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '.' { c } else { '_' })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+// Render a report to a PNG card in the export folder. Returns the file path.
+#[cfg(feature = "gui")]
+pub fn export_png(title: &str, text: &str) -> Result<PathBuf, String> {
+    let fonts = egui::FontDefinitions::default();
+    let font_data = fonts
+        .font_data
+        .get("Ubuntu-Light")
+        .ok_or_else(|| "Image export error: default font missing".to_string())?;
+    let font = FontRef::try_from_slice(&font_data.font).map_err(|e| format!("Image export error: {}", e))?;
+
+    let title_font = font.as_scaled(PxScale::from(TITLE_SIZE));
+    let body_font = font.as_scaled(PxScale::from(BODY_SIZE));
+
+    let lines: Vec<String> = text.trim_end().lines().flat_map(|line| wrap_line(&body_font, line)).collect();
+
+    // Size the card to the widest line
+    let content_width = lines
+        .iter()
+        .map(|line| line_width(&body_font, line))
+        .chain(std::iter::once(line_width(&title_font, title)))
+        .fold(0.0, f32::max);
+    let title_height = title_font.height() + title_font.line_gap();
+    let line_height = body_font.height() + body_font.line_gap();
+    let width = content_width.ceil() as u32 + PADDING * 2;
+    let height = (title_height + 12.0 + line_height * lines.len() as f32).ceil() as u32 + PADDING * 2 + 4;
+
+    let mut image = RgbaImage::from_pixel(width, height, BACKGROUND);
+
+    // Accent bar across the top
+    for y in 0..4 {
+        for x in 0..width {
+            image.put_pixel(x, y, ACCENT);
+        }
+    }
+
+    let mut y = (PADDING + 4) as f32;
+    draw_text(&mut image, &title_font, title, PADDING as f32, y, TITLE_COLOR);
+    y += title_height + 12.0;
+    for line in &lines {
+        draw_text(&mut image, &body_font, line, PADDING as f32, y, TEXT_COLOR);
+        y += line_height;
+    }
+
+    let path = export_dir()?.join(export_file_name(title, "png"));
+    image.save(&path).map_err(|e| {
+        error!("Failed to write image {}: {}", path.display(), e);
+        format!("Image export error: {}", e)
+    })?;
+    info!("Exported image to {}", path.display());
+    Ok(path)
+}
+
+// Helper function to measure a line of text in pixels
+#[cfg(feature = "gui")]
+fn line_width<F: Font>(font: &ab_glyph::PxScaleFont<&F>, line: &str) -> f32 {
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in line.chars() {
+        let glyph_id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, glyph_id);
+        }
+        width += font.h_advance(glyph_id);
+        previous = Some(glyph_id);
+    }
+    width
+}
+
+// Helper function to break a line that is wider than the card (long URLs) into several
+#[cfg(feature = "gui")]
+fn wrap_line<F: Font>(font: &ab_glyph::PxScaleFont<&F>, line: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for c in line.chars() {
+        current.push(c);
+        if line_width(font, &current) > MAX_TEXT_WIDTH {
+            current.pop();
+            lines.push(std::mem::take(&mut current));
+            current.push(c);
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+// Helper function to draw a line of text with its top-left corner at (x, y)
+#[cfg(feature = "gui")]
+fn draw_text<F: Font>(image: &mut RgbaImage, font: &ab_glyph::PxScaleFont<&F>, text: &str, x: f32, y: f32, color: Rgba<u8>) {
+    let mut caret = x;
+    let baseline = y + font.ascent();
+    let mut previous = None;
+    for c in text.chars() {
+        let glyph_id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += font.kern(previous, glyph_id);
+        }
+        let glyph = glyph_id.with_scale_and_position(font.scale(), point(caret, baseline));
+        caret += font.h_advance(glyph_id);
+        previous = Some(glyph_id);
+
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i32 + gx as i32;
+            let py = bounds.min.y as i32 + gy as i32;
+            if px < 0 || py < 0 || px >= image.width() as i32 || py >= image.height() as i32 {
+                return;
+            }
+            let pixel = image.get_pixel_mut(px as u32, py as u32);
+            for channel in 0..3 {
+                let blended = pixel[channel] as f32 * (1.0 - coverage) + color[channel] as f32 * coverage;
+                pixel[channel] = blended.round() as u8;
+            }
+        });
+    }
+}
+
+// ----------------------
+// PDF Export
+// ----------------------
+
+// A4 page layout in millimetres. Body text is set in Courier so long URLs
+// can be wrapped at a fixed number of characters.
+#[cfg(feature = "gui")]
+const PAGE_WIDTH: f32 = 210.0;
+#[cfg(feature = "gui")]
+const PAGE_HEIGHT: f32 = 297.0;
+#[cfg(feature = "gui")]
+const PAGE_MARGIN: f32 = 20.0;
+#[cfg(feature = "gui")]
+const PDF_LINE_HEIGHT: f32 = 4.2;
+#[cfg(feature = "gui")]
+const PDF_LINE_CHARS: usize = 88;
+#[cfg(feature = "gui")]
+const PDF_LINES_PER_PAGE: usize = ((PAGE_HEIGHT - PAGE_MARGIN * 2.0) / PDF_LINE_HEIGHT) as usize;
+#[cfg(feature = "gui")]
+const PDF_HEADING_LINES: usize = 3;
+
+// Write a report to a PDF in the export folder: a contents page listing each
+// chapter (one per version) with its page number, then the chapters, each
+// starting on a new page with a bookmark. Returns the file path.
+#[cfg(feature = "gui")]
+pub fn export_pdf(title: &str, chapters: &[(String, String)]) -> Result<PathBuf, String> {
+    // Lay out every chapter first so the contents page can list page numbers
+    let mut pages: Vec<(Option<&str>, Vec<String>)> = Vec::new();
+    let mut chapter_pages = Vec::new();
+    for (heading, body) in chapters {
+        chapter_pages.push(pages.len() + 2); // 1-based, after the contents page
+        let mut page = (Some(heading.as_str()), Vec::new());
+        let mut used = PDF_HEADING_LINES;
+        for line in body.trim_end().lines().flat_map(|line| wrap_chars(line, PDF_LINE_CHARS)) {
+            if used >= PDF_LINES_PER_PAGE {
+                pages.push(std::mem::take(&mut page));
+                used = 0;
+            }
+            page.1.push(line);
+            used += 1;
+        }
+        pages.push(page);
+    }
+
+    let pdf_error = |e: printpdf::Error| format!("PDF export error: {}", e);
+    let (doc, contents_page, contents_layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Report");
+    let heading_font = doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(pdf_error)?;
+    let text_font = doc.add_builtin_font(BuiltinFont::Helvetica).map_err(pdf_error)?;
+    let body_font = doc.add_builtin_font(BuiltinFont::Courier).map_err(pdf_error)?;
+
+    // Contents page
+    let layer = doc.get_page(contents_page).get_layer(contents_layer);
+    let mut y = PAGE_HEIGHT - PAGE_MARGIN;
+    layer.use_text(title, 18.0, Mm(PAGE_MARGIN), Mm(y), &heading_font);
+    y -= 8.0;
+    let generated = format!("Generated: {}", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"));
+    layer.use_text(generated, 10.0, Mm(PAGE_MARGIN), Mm(y), &text_font);
+    y -= 14.0;
+    layer.use_text("Contents", 14.0, Mm(PAGE_MARGIN), Mm(y), &heading_font);
+    y -= 8.0;
+    for ((heading, _), page_number) in chapters.iter().zip(&chapter_pages) {
+        // Dot leaders out to the page number, e.g. "Main Data ........ 2"
+        let entry = format!("{} {:.<width$} {}", heading, "", page_number, width = 78usize.saturating_sub(heading.len()));
+        layer.use_text(entry, 9.0, Mm(PAGE_MARGIN), Mm(y), &body_font);
+        y -= PDF_LINE_HEIGHT * 1.5;
+    }
+    doc.add_bookmark("Contents", contents_page);
+
+    let total_pages = pages.len() + 1;
+    for (index, (heading, lines)) in pages.iter().enumerate() {
+        let (page, layer) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Report");
+        let layer = doc.get_page(page).get_layer(layer);
+        let mut y = PAGE_HEIGHT - PAGE_MARGIN;
+
+        if let Some(heading) = heading {
+            doc.add_bookmark(*heading, page);
+            layer.use_text(*heading, 14.0, Mm(PAGE_MARGIN), Mm(y), &heading_font);
+            y -= PDF_LINE_HEIGHT * PDF_HEADING_LINES as f32;
+        }
+        for line in lines {
+            layer.use_text(line.as_str(), 9.0, Mm(PAGE_MARGIN), Mm(y), &body_font);
+            y -= PDF_LINE_HEIGHT;
+        }
+
+        let footer = format!("{} - page {} of {}", title, index + 2, total_pages);
+        layer.use_text(footer, 8.0, Mm(PAGE_MARGIN), Mm(PAGE_MARGIN / 2.0), &text_font);
+    }
+
+    let path = export_dir()?.join(export_file_name(title, "pdf"));
+    let file = File::create(&path).map_err(|e| format!("PDF export error ({}): {}", path.display(), e))?;
+    doc.save(&mut BufWriter::new(file)).map_err(|e| {
+        error!("Failed to write PDF {}: {}", path.display(), e);
+        format!("PDF export error: {}", e)
+    })?;
+    info!("Exported PDF to {}", path.display());
+    Ok(path)
+}
+
+// Helper function to break a line into pieces of at most `width` characters
+#[cfg(feature = "gui")]
+fn wrap_chars(line: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars.chunks(width).map(|chunk| chunk.iter().collect()).collect()
+}
+
+// ----------------------
+// Checksum Files
+// ----------------------
+
+// Write a `checksums.md5` for each version of a game into its own folder
+// (e.g. `Genshin Impact 5.1.0/checksums.md5`), so downloads made with any
+// tool can be checked with `md5sum -c checksums.md5`. Patches are listed
+// under the version they update to. Returns the files written.
+#[cfg(any(feature = "gui", feature = "tui"))]
+pub fn export_md5_checksums(game_name: &str, package: &GamePackage) -> Result<Vec<PathBuf>, String> {
+    let files = package_files(package);
+    let mut versions: Vec<&str> = Vec::new();
+    for file in &files {
+        if !versions.contains(&file.version.as_str()) {
+            versions.push(&file.version);
+        }
+    }
+
+    let mut written = Vec::new();
+    for version in versions {
+        let contents = checksum_lines(files.iter().filter(|file| file.version == version));
+
+        let dir = export_dir()?.join(sanitize_file_name(&format!("{} {}", game_name, version)));
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Export error ({}): {}", dir.display(), e))?;
+        let path = dir.join("checksums.md5");
+        std::fs::write(&path, contents).map_err(|e| {
+            error!("Failed to write checksums {}: {}", path.display(), e);
+            format!("Checksum export error: {}", e)
+        })?;
+        info!("Exported checksums to {}", path.display());
+        written.push(path);
+    }
+    Ok(written)
+}
+
+// `<md5>  <filename>` lines for `md5sum -c`; files without an MD5 are left out
+pub fn checksum_lines<'a>(files: impl Iterator<Item = &'a PackageFile>) -> String {
+    let mut contents = String::new();
+    for file in files {
+        if file.md5.is_empty() {
+            warn!("No MD5 for {}, leaving it out of the checksum file", file.url);
+            continue;
+        }
+        contents.push_str(&format!("{}  {}\n", file.md5.to_lowercase(), file.file_name()));
+    }
+    contents
+}
+
+// ----------------------
+// Download Lists
+// ----------------------
+
+// Plain-text URL lists for external download managers
+#[derive(Clone, Copy, PartialEq)]
+pub enum UrlListFormat {
+    Plain,               // One URL per line, which any downloader takes
+    FreeDownloadManager, // The same, for FDM's "Import list of URLs"
+    WithReferer,         // `<url>\tReferer: <origin>` per line, for tools that need the header
+    Aria2,               // aria2 input file (`aria2c -i`), with output names and MD5 checksums
+    BashArray,           // `urls=(...)`, to paste into a shell script
+    PowerShellArray,     // `$urls = @(...)`, to paste into a PowerShell script
+}
+
+// A regular expression the URLs of a list must match, e.g. `Audio_Japanese`
+// or `\.zip\.00[1-3]`. An empty pattern keeps every URL.
+pub fn url_filter(pattern: &str) -> Result<Option<Regex>, String> {
+    if pattern.trim().is_empty() {
+        return Ok(None);
+    }
+    Regex::new(pattern.trim()).map(Some).map_err(|e| format!("URL filter error: {}", e))
+}
+
+// The files of a package whose URL matches `filter`, or all of them without one
+pub fn filtered_files(package: &GamePackage, filter: Option<&Regex>) -> Vec<PackageFile> {
+    let mut files = package_files(package);
+    if let Some(filter) = filter {
+        files.retain(|file| filter.is_match(&file.url));
+    }
+    files
+}
+
+// The URLs of the files of a package in one of the list formats, only the
+// ones matching `filter` if there is one
+#[cfg(any(feature = "gui", test))]
+pub fn url_list(package: &GamePackage, format: UrlListFormat, filter: Option<&Regex>) -> String {
+    format_url_list(&filtered_files(package, filter), format)
+}
+
+// The URLs of `files` in one of the list formats; empty without files
+pub fn format_url_list(files: &[PackageFile], format: UrlListFormat) -> String {
+    if files.is_empty() {
+        return String::new();
+    }
+    let mut contents = match format {
+        UrlListFormat::BashArray => "urls=(\n".to_string(),
+        UrlListFormat::PowerShellArray => "$urls = @(\n".to_string(),
+        _ => String::new(),
+    };
+    for file in files {
+        match format {
+            UrlListFormat::Plain | UrlListFormat::FreeDownloadManager => contents.push_str(&format!("{}\n", file.url)),
+            UrlListFormat::WithReferer => contents.push_str(&format!("{}\tReferer: {}\n", file.url, url_origin(&file.url))),
+            UrlListFormat::Aria2 => {
+                contents.push_str(&format!("{}\n  out={}\n", file.url, file.file_name()));
+                if !file.md5.is_empty() {
+                    contents.push_str(&format!("  checksum=md5={}\n", file.md5.to_lowercase()));
+                }
+            }
+            // Single quotes keep `$` and spaces literal; a quote inside is
+            // closed, escaped and reopened in bash, and doubled in PowerShell
+            UrlListFormat::BashArray => contents.push_str(&format!("  '{}'\n", file.url.replace('\'', "'\\''"))),
+            UrlListFormat::PowerShellArray => contents.push_str(&format!("    '{}'\n", file.url.replace('\'', "''"))),
+        }
+    }
+    if matches!(format, UrlListFormat::BashArray | UrlListFormat::PowerShellArray) {
+        contents.push_str(")\n");
+    }
+    contents
+}
+
+// Write the URLs of the files of a package to a text file in the export folder
+#[cfg(feature = "gui")]
+pub fn export_url_list(title: &str, package: &GamePackage, format: UrlListFormat, filter: Option<&Regex>) -> Result<PathBuf, String> {
+    let contents = url_list(package, format, filter);
+    if contents.is_empty() {
+        return Err(match filter {
+            Some(filter) => format!("No download URLs match the filter {}.", filter),
+            None => "No download URLs to export.".to_string(),
+        });
+    }
+
+    let suffix = match format {
+        UrlListFormat::Plain => "urls",
+        UrlListFormat::FreeDownloadManager => "FDM List",
+        UrlListFormat::WithReferer => "URL List",
+        UrlListFormat::Aria2 => "aria2 Input",
+        UrlListFormat::BashArray => "URL Array (bash)",
+        UrlListFormat::PowerShellArray => "URL Array (PowerShell)",
+    };
+    let path = export_dir()?.join(export_file_name(&format!("{} {}", title, suffix), "txt"));
+    std::fs::write(&path, contents).map_err(|e| {
+        error!("Failed to write URL list {}: {}", path.display(), e);
+        format!("Export error: {}", e)
+    })?;
+    info!("Exported URL list to {}", path.display());
+    Ok(path)
+}
+
+// Helper function to get the origin of a URL, e.g. "https://autopatchhk.yuanshen.com/"
+fn url_origin(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(url) => format!("{}/", url.origin().ascii_serialization()),
+        Err(_) => String::new(),
+    }
+}
+
+// File name part of a download URL, e.g. "GenshinImpact_5.0.0.zip.001"
+pub fn file_name_from_url(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
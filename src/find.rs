@@ -0,0 +1,135 @@
+// ----------------------
+// Find Bar
+// ----------------------
+
+// A search field over a long text, such as the raw JSON panels, with the
+// number of matches and Previous/Next buttons. Every match is highlighted and
+// the current one is scrolled into view. Case is ignored for ASCII letters,
+// which keeps match positions the same as in the text.
+
+use crate::labelled;
+use eframe::egui;
+use eframe::egui::text::LayoutJob;
+use std::hash::Hash;
+use std::ops::Range;
+
+// Height of the text below the bar
+const TEXT_HEIGHT: f32 = 480.0;
+// Room left above the current match when scrolling to it
+const SCROLL_MARGIN: f32 = 3.0; // Rows
+
+#[derive(Default)]
+pub struct FindBar {
+    query: String,
+    current: usize, // Index of the highlighted match
+    scroll: bool,   // Scroll to the current match this frame
+}
+
+impl FindBar {
+    // The bar with `text` below it, its rows drawn only while visible
+    pub fn show(&mut self, ui: &mut egui::Ui, id: impl Hash, text: &str) {
+        let mut matches = Vec::new();
+        ui.horizontal(|ui| {
+            let response = labelled(ui, "Find:", egui::TextEdit::singleline(&mut self.query).desired_width(240.0));
+            matches = find_all(text, &self.query);
+            if response.changed() {
+                self.current = 0;
+                self.scroll = true;
+            }
+            let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if enter {
+                response.request_focus();
+            }
+
+            let any = !matches.is_empty();
+            if ui.add_enabled(any, egui::Button::new("Previous")).clicked() {
+                self.current = (self.current + matches.len() - 1) % matches.len();
+                self.scroll = true;
+            }
+            if (ui.add_enabled(any, egui::Button::new("Next")).clicked() || enter) && any {
+                self.current = (self.current + 1) % matches.len();
+                self.scroll = true;
+            }
+            self.current = self.current.min(matches.len().saturating_sub(1));
+            if any {
+                ui.label(format!("{} of {}", self.current + 1, matches.len()));
+            } else if !self.query.is_empty() {
+                ui.label("No matches");
+            }
+        });
+
+        // Byte offset of each line's start
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(index, _)| index + 1));
+        let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+        let row_height = ui.fonts(|fonts| fonts.row_height(&font_id));
+
+        let mut scroll_area = egui::ScrollArea::both().id_source(id).max_height(TEXT_HEIGHT).auto_shrink([false, true]);
+        if let Some(&start) = matches.get(self.current).filter(|_| self.scroll) {
+            let line = line_starts.partition_point(|&line_start| line_start <= start) - 1;
+            let row = row_height + ui.spacing().item_spacing.y;
+            scroll_area = scroll_area.vertical_scroll_offset((line as f32 - SCROLL_MARGIN).max(0.0) * row);
+        }
+        self.scroll = false;
+
+        scroll_area.show_rows(ui, row_height, line_starts.len(), |ui, rows| {
+            for row in rows {
+                let end = line_starts.get(row + 1).map_or(text.len(), |next| next - 1);
+                let job = self.highlighted_line(ui, &font_id, text, line_starts[row]..end, &matches);
+                ui.label(job);
+            }
+        });
+    }
+
+    // The line `text[line]` with the matches in it on a highlighted background
+    fn highlighted_line(
+        &self,
+        ui: &egui::Ui,
+        font_id: &egui::FontId,
+        text: &str,
+        line: Range<usize>,
+        matches: &[usize],
+    ) -> LayoutJob {
+        let format = |background| egui::TextFormat {
+            font_id: font_id.clone(),
+            color: ui.visuals().text_color(),
+            background,
+            ..Default::default()
+        };
+        let plain = format(egui::Color32::TRANSPARENT);
+        let mut job = LayoutJob::default();
+        job.wrap.max_width = f32::INFINITY;
+
+        // Matches that overlap the line, cut to it
+        let length = self.query.len();
+        let first = matches.partition_point(|&index| index + length <= line.start);
+        let mut position = line.start;
+        for (number, &index) in matches.iter().enumerate().skip(first).take_while(|(_, &index)| index < line.end) {
+            let from = index.max(position);
+            let to = (index + length).min(line.end);
+            if from >= to {
+                continue;
+            }
+            job.append(&text[position..from], 0.0, plain.clone());
+            let background = if number == self.current {
+                ui.visuals().selection.bg_fill
+            } else {
+                ui.visuals().selection.bg_fill.linear_multiply(0.35)
+            };
+            job.append(&text[from..to], 0.0, format(background));
+            position = to;
+        }
+        job.append(&text[position..line.end], 0.0, plain);
+        job
+    }
+}
+
+// Byte offsets of every match of `query` in `text`
+fn find_all(text: &str, query: &str) -> Vec<usize> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let haystack = text.to_ascii_lowercase();
+    let needle = query.to_ascii_lowercase();
+    haystack.match_indices(&needle).map(|(index, _)| index).collect()
+}
@@ -0,0 +1,89 @@
+// ----------------------
+// Game Process Detection
+// ----------------------
+
+// Notices when the game itself is running, so bandwidth-heavy work
+// (auto-checks, link checks and downloads) can wait until it closes. The
+// process list is read every few seconds on a worker thread: with `tasklist`
+// on Windows and from `/proc` elsewhere (the game runs under Wine there).
+// The window is only repainted when the answer changes.
+
+use eframe::egui;
+use log::info;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Executables of the global and Chinese clients
+const GAME_PROCESSES: [&str; 2] = ["GenshinImpact.exe", "YuanShen.exe"];
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+pub struct GameWatch {
+    running: Arc<AtomicBool>, // The game was running at the last check
+    enabled: Arc<AtomicBool>, // Checks are wanted; the process list isn't read otherwise
+    started: bool,            // The watcher thread is running
+}
+
+impl GameWatch {
+    pub fn is_game_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    // Start the watcher thread on first use. It re-checks the process list
+    // every few seconds and only wakes the UI when the game starts or closes.
+    pub fn update(&mut self, ctx: &egui::Context, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if self.started {
+            return;
+        }
+        self.started = true;
+
+        let running = self.running.clone();
+        let enabled = self.enabled.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || loop {
+            let now_running = enabled.load(Ordering::Relaxed) && game_running();
+            if running.swap(now_running, Ordering::Relaxed) != now_running {
+                info!("Game {}.", if now_running { "started" } else { "closed" });
+                ctx.request_repaint();
+            }
+            std::thread::sleep(CHECK_INTERVAL);
+        });
+    }
+}
+
+// Whether a game process is in the process list
+#[cfg(windows)]
+fn game_running() -> bool {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let Ok(output) = std::process::Command::new("tasklist")
+        .args(["/NH", "/FO", "CSV"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+    else {
+        return false;
+    };
+    let list = String::from_utf8_lossy(&output.stdout).to_lowercase();
+    GAME_PROCESSES
+        .iter()
+        .any(|name| list.contains(&format!("\"{}\"", name.to_lowercase())))
+}
+
+#[cfg(not(windows))]
+fn game_running() -> bool {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        // Wine shows the Windows executable in the command line
+        let Ok(command) = std::fs::read(entry.path().join("cmdline")) else {
+            return false;
+        };
+        let command = String::from_utf8_lossy(&command).to_lowercase();
+        GAME_PROCESSES.iter().any(|name| command.contains(&name.to_lowercase()))
+    })
+}
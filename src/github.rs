@@ -0,0 +1,105 @@
+// ----------------------
+// GitHub Actions Output
+// ----------------------
+
+// `fetch --github` for scheduled workflows: the step gets outputs a later
+// step can test (e.g. `if: steps.scan.outputs.new_version == 'true'` before
+// posting to Discord), and the run page gets a Markdown summary of the
+// versions found. Both are appended to the files GitHub names in
+// `$GITHUB_OUTPUT` and `$GITHUB_STEP_SUMMARY`.
+
+use crate::history::{VersionKind, VersionRecord};
+use crate::report::{self, ReportTemplates};
+use crate::{bytes_to_gb, FetchResult, Major};
+use std::io::Write;
+use std::path::PathBuf;
+
+// The files the outputs and summary are appended to
+pub struct GithubFiles {
+    output: PathBuf,
+    summary: PathBuf,
+}
+
+impl GithubFiles {
+    // Only set inside a GitHub Actions job
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| {
+            std::env::var_os(name)
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from)
+                .ok_or_else(|| format!("--github needs ${} from a GitHub Actions job; it is not set.", name))
+        };
+        Ok(GithubFiles { output: var("GITHUB_OUTPUT")?, summary: var("GITHUB_STEP_SUMMARY")? })
+    }
+
+    // Set the step outputs and write the summary of a fetch
+    pub fn write(&self, result: &FetchResult, new_records: &[VersionRecord], templates: &ReportTemplates) -> Result<(), String> {
+        append(&self.output, &outputs(result, new_records))?;
+        append(&self.summary, &summary(result, new_records, templates))
+    }
+}
+
+fn append(path: &PathBuf, text: &str) -> Result<(), String> {
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| format!("GitHub output error ({}): {}", path.display(), e))
+}
+
+// `name=value` lines: whether anything is new, and the versions of the first game
+pub fn outputs(result: &FetchResult, new_records: &[VersionRecord]) -> String {
+    let new_kind = |kind| new_records.iter().any(|record| record.kind == kind);
+    let package = result.games.iter().find_map(|game| game.package.as_ref());
+    let live = package.and_then(|package| package.main.major.as_ref());
+    let pre_download = package
+        .and_then(|package| package.pre_download.as_ref())
+        .and_then(|pre_download| pre_download.major.as_ref());
+    let mut lines = vec![
+        format!("new_version={}", new_kind(VersionKind::Live)),
+        format!("new_pre_download={}", new_kind(VersionKind::PreDownload)),
+        format!("predownload_available={}", pre_download.is_some()),
+        format!("version={}", live.map(|major| major.version.as_str()).unwrap_or("")),
+        format!("predownload_version={}", pre_download.map(|major| major.version.as_str()).unwrap_or("")),
+    ];
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn total_gb(major: &Major) -> String {
+    let bytes: u64 = major.game_pkgs.iter().map(|pkg| pkg.size.parse::<u64>().unwrap_or(0)).sum();
+    format!("{:.2} GB", bytes_to_gb(&bytes.to_string()))
+}
+
+// Markdown for the run page: a table per game, what's new, and the full report folded away
+pub fn summary(result: &FetchResult, new_records: &[VersionRecord], templates: &ReportTemplates) -> String {
+    let mut text = String::new();
+    for game in &result.games {
+        let Some(package) = &game.package else {
+            continue;
+        };
+        text.push_str(&format!("## {}\n\n| | Version | Game Size |\n|---|---|---|\n", package.game.display_name()));
+        if let Some(major) = &package.main.major {
+            text.push_str(&format!("| Live | {} | {} |\n", major.version, total_gb(major)));
+        }
+        match package.pre_download.as_ref().and_then(|pre_download| pre_download.major.as_ref()) {
+            Some(major) => text.push_str(&format!("| Pre-download | {} | {} |\n", major.version, total_gb(major))),
+            None => text.push_str("| Pre-download | - | - |\n"),
+        }
+        text.push('\n');
+        for record in new_records.iter().filter(|record| record.biz == package.game.biz) {
+            let kind = match record.kind {
+                VersionKind::Live => "live version",
+                VersionKind::PreDownload => "pre-download",
+            };
+            text.push_str(&format!("**New {}:** {}\n\n", kind, record.version));
+        }
+    }
+    if new_records.is_empty() {
+        text.push_str("No new versions since the last run.\n\n");
+    }
+    let report = report::game_reports(&result.games, templates, None);
+    text.push_str(&format!("<details><summary>Report</summary>\n\n```\n{}\n```\n\n</details>\n", report.trim_end()));
+    text
+}
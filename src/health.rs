@@ -0,0 +1,142 @@
+// ----------------------
+// API Health
+// ----------------------
+
+// The outcomes of the window's recent fetches, shown in the status bar as a
+// coloured status with one bar per fetch. Failures are told apart by where
+// they happened: a request that never got an answer (DNS, connect, TLS) is a
+// network error, while an answer the scanner couldn't use (a non-zero retcode,
+// an unparseable body) is an API error. A row of grey bars points at your
+// connection; red ones at the API itself.
+
+use crate::theme::{status_label, Status};
+use chrono::{DateTime, Utc};
+use eframe::egui;
+use std::collections::VecDeque;
+
+// Outcomes kept, and shown as bars
+const SAMPLES: usize = 20;
+// Failures in a row that turn the status red
+const FAILURES_FOR_DOWN: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Ok,
+    ApiError,     // The API answered with something unusable
+    NetworkError, // No answer arrived
+}
+
+impl Outcome {
+    // Sort a fetch error into an API or a network error by its prefix
+    pub fn of_error(error: &str) -> Self {
+        if error.starts_with("Request error") || error.starts_with("Response text error") {
+            Outcome::NetworkError
+        } else {
+            Outcome::ApiError
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Outcome::Ok => "OK",
+            Outcome::ApiError => "API error",
+            Outcome::NetworkError => "network error",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HealthStatus {
+    Healthy, // No recent failures
+    Flaky,   // Some recent failures
+    Down,    // The last few fetches all failed
+}
+
+#[derive(Default)]
+pub struct Health {
+    outcomes: VecDeque<(DateTime<Utc>, Outcome)>,
+}
+
+impl Health {
+    pub fn record(&mut self, outcome: Outcome, time: DateTime<Utc>) {
+        if self.outcomes.len() == SAMPLES {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back((time, outcome));
+    }
+
+    // None until something was fetched
+    pub fn status(&self) -> Option<HealthStatus> {
+        if self.outcomes.is_empty() {
+            return None;
+        }
+        let failed_in_a_row = self.outcomes.iter().rev().take_while(|(_, outcome)| *outcome != Outcome::Ok).count();
+        Some(if failed_in_a_row >= FAILURES_FOR_DOWN {
+            HealthStatus::Down
+        } else if self.failures(Outcome::ApiError) + self.failures(Outcome::NetworkError) > 0 {
+            HealthStatus::Flaky
+        } else {
+            HealthStatus::Healthy
+        })
+    }
+
+    fn failures(&self, kind: Outcome) -> usize {
+        self.outcomes.iter().filter(|(_, outcome)| *outcome == kind).count()
+    }
+
+    // A few words for the status bar, blaming the network or the API
+    pub fn summary(&self) -> String {
+        let api = self.failures(Outcome::ApiError);
+        let network = self.failures(Outcome::NetworkError);
+        let last_failure = self.outcomes.iter().rev().map(|(_, outcome)| *outcome).find(|outcome| *outcome != Outcome::Ok);
+        match (self.status(), last_failure) {
+            (None, _) => "API not checked yet".to_string(),
+            (Some(HealthStatus::Healthy), _) => "API OK".to_string(),
+            (Some(HealthStatus::Down), Some(Outcome::NetworkError)) => "API unreachable (network)".to_string(),
+            (Some(HealthStatus::Down), _) => "API failing".to_string(),
+            (Some(HealthStatus::Flaky), _) if network > api => "API flaky (network)".to_string(),
+            (Some(HealthStatus::Flaky), _) => "API flaky".to_string(),
+        }
+    }
+
+    // The counts behind the status, and the last few outcomes with their times
+    pub fn details(&self) -> String {
+        let mut lines = vec![format!(
+            "Last {} fetches: {} OK, {} API errors, {} network errors",
+            self.outcomes.len(),
+            self.failures(Outcome::Ok),
+            self.failures(Outcome::ApiError),
+            self.failures(Outcome::NetworkError),
+        )];
+        lines.push("Red bars are answers from the API that couldn't be used; grey bars are requests that got no answer, which points at your connection.".to_string());
+        for (time, outcome) in self.outcomes.iter().rev().take(5) {
+            lines.push(format!("{}: {}", time.with_timezone(&chrono::Local).format("%H:%M:%S"), outcome.label()));
+        }
+        lines.join("\n")
+    }
+
+    // The status and one bar per recent fetch, oldest first
+    pub fn show(&self, ui: &mut egui::Ui) {
+        let Some(status) = self.status() else {
+            return;
+        };
+        let status = match status {
+            HealthStatus::Healthy => Status::Success,
+            HealthStatus::Flaky => Status::Warning,
+            HealthStatus::Down => Status::Error,
+        };
+        let label = status_label(ui, status, self.summary());
+        let (rect, bars) = ui.allocate_exact_size(egui::vec2(SAMPLES as f32 * 4.0, 12.0), egui::Sense::hover());
+        for (index, (_, outcome)) in self.outcomes.iter().enumerate() {
+            let color = match outcome {
+                Outcome::Ok => Status::Success.color(ui),
+                Outcome::ApiError => Status::Error.color(ui),
+                Outcome::NetworkError => ui.visuals().weak_text_color(),
+            };
+            let left = rect.left() + index as f32 * 4.0;
+            let bar = egui::Rect::from_min_max(egui::pos2(left, rect.top()), egui::pos2(left + 3.0, rect.bottom()));
+            ui.painter().rect_filled(bar, 0.0, color);
+        }
+        label.union(bars).on_hover_text(self.details());
+    }
+}
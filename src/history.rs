@@ -0,0 +1,451 @@
+// ----------------------
+// Version History
+// ----------------------
+
+// Records when each live and pre-download version was first seen, per game
+// (`biz`). The history is stored as JSON next to the settings and is used to
+// compute patch-cadence statistics and estimate the next release. The package
+// URLs of every version are kept as well, so they can be re-checked after the
+// version is gone from the API to see when the CDN stops serving them.
+
+use crate::archive::{ArchiveCheckRun, ArchiveProgress};
+#[cfg(feature = "gui")]
+use crate::linkcheck::{LinkCheck, LinkStatus};
+use crate::settings::config_path;
+use crate::{package_files, GamePackage, PackageFile};
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+// Archive check runs kept in the history
+const MAX_ARCHIVE_CHECKS: usize = 24;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum VersionKind {
+    Live,
+    PreDownload,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VersionRecord {
+    pub biz: String,
+    pub kind: VersionKind,
+    pub version: String,
+    pub first_seen: DateTime<Utc>,
+}
+
+// Free-text note attached to a version of a game
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VersionNote {
+    pub biz: String,
+    pub version: String,
+    pub text: String,
+}
+
+// A package URL of a recorded version and what the last link check found
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoredLink {
+    pub biz: String,
+    pub file: PackageFile,
+    pub last_checked: Option<DateTime<Utc>>,
+    pub gone: Option<u16>,                   // 403, 404 or 410 while the CDN refuses the file
+    pub gone_since: Option<DateTime<Utc>>,   // First check that found it refused
+}
+
+// A response field the models don't know and when the API started sending it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SchemaField {
+    pub path: String,   // Without array indices, e.g. data.game_packages[].main.major.sha256
+    pub first_seen: DateTime<Utc>,
+    pub source: String, // Game whose fetch first returned it
+}
+
+// A file the built-in downloader finished, and whether it passed its check
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DownloadRecord {
+    pub game: String,
+    pub version: String,
+    pub file_name: String,
+    pub size: u64,
+    pub finished: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>, // Why the file didn't match the API
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct History {
+    pub records: Vec<VersionRecord>,
+    pub notes: Vec<VersionNote>,
+    pub links: Vec<StoredLink>,
+    pub last_link_check: Option<DateTime<Utc>>, // When stored links were last re-checked
+    pub schema_fields: Vec<SchemaField>,        // Schema drift seen so far
+    pub archive_checks: Vec<ArchiveCheckRun>,   // Finished re-checks of the archived files
+    pub downloads: Vec<DownloadRecord>,         // Finished downloads, for the activity report
+}
+
+// Link health of one version: how many stored links it has and how many are gone
+#[cfg(feature = "gui")]
+pub struct LinkHealth {
+    pub total: usize,
+    pub checked: usize, // Links checked at least once
+    pub gone: usize,
+    pub gone_since: Option<DateTime<Utc>>, // Earliest failure among the gone links
+}
+
+// Statuses that mean the CDN has withdrawn or blocked a file, rather than a
+// passing network or server problem
+#[cfg(feature = "gui")]
+fn is_gone(status: &LinkStatus) -> Option<u16> {
+    match status {
+        LinkStatus::Broken(code @ (403 | 404 | 410)) => Some(*code),
+        _ => None,
+    }
+}
+
+// When a version was first seen as a pre-download and as the live version
+#[cfg(feature = "gui")]
+pub struct VersionTimeline {
+    pub version: String,
+    pub pre_download_seen: Option<DateTime<Utc>>,
+    pub live_seen: Option<DateTime<Utc>>,
+}
+
+// Patch-cadence statistics for one game
+#[cfg(feature = "gui")]
+pub struct Cadence {
+    pub releases: usize,                             // Live versions seen
+    pub average_interval: Duration,                  // Between live version bumps
+    pub average_pre_download_lead: Option<Duration>, // From pre-download appearing to release
+    pub last_release: DateTime<Utc>,
+    pub next_release_estimate: DateTime<Utc>,
+}
+
+impl History {
+    // Load the history from disk, starting empty if missing or invalid
+    pub fn load() -> Self {
+        let Some(path) = config_path("history.json") else {
+            return Self::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Invalid history file {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    // Save the history to disk
+    pub fn save(&self) -> Result<(), String> {
+        let path = config_path("history.json").ok_or_else(|| "No config directory available".to_string())?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("History save error: {}", e))?;
+        }
+
+        let contents = serde_json::to_string_pretty(self).map_err(|e| format!("History save error: {}", e))?;
+        std::fs::write(&path, contents).map_err(|e| {
+            error!("Failed to write history to {}: {}", path.display(), e);
+            format!("History save error: {}", e)
+        })
+    }
+
+    // Record the versions in a fetched package. Returns the versions that
+    // were seen for the first time.
+    pub fn record(&mut self, package: &GamePackage, now: DateTime<Utc>) -> Vec<VersionRecord> {
+        let biz = &package.game.biz;
+        let live = package.main.major.as_ref().map(|major| (VersionKind::Live, &major.version));
+        let pre_download = package
+            .pre_download
+            .as_ref()
+            .and_then(|pre| pre.major.as_ref())
+            .map(|major| (VersionKind::PreDownload, &major.version));
+
+        let mut new_records = Vec::new();
+        for (kind, version) in [live, pre_download].into_iter().flatten() {
+            let known = self
+                .records
+                .iter()
+                .any(|record| record.biz == *biz && record.kind == kind && record.version == *version);
+            if !known {
+                info!("First sighting of {} {:?} version {}.", biz, kind, version);
+                let record = VersionRecord {
+                    biz: biz.clone(),
+                    kind,
+                    version: version.clone(),
+                    first_seen: now,
+                };
+                self.records.push(record.clone());
+                new_records.push(record);
+            }
+        }
+        new_records
+    }
+
+    // Store the package URLs of a fetched package that aren't stored yet.
+    // Returns whether any were added.
+    pub fn record_links(&mut self, package: &GamePackage) -> bool {
+        let biz = &package.game.biz;
+        let mut added = false;
+        for file in package_files(package) {
+            if !self.links.iter().any(|link| link.file.url == file.url) {
+                self.links.push(StoredLink {
+                    biz: biz.clone(),
+                    file,
+                    last_checked: None,
+                    gone: None,
+                    gone_since: None,
+                });
+                added = true;
+            }
+        }
+        added
+    }
+
+    // Record the unknown fields of a response. Returns the ones that were
+    // seen for the first time.
+    pub fn record_schema(&mut self, fields: &[String], source: &str, now: DateTime<Utc>) -> Vec<SchemaField> {
+        let mut new_fields = Vec::new();
+        for path in fields {
+            if self.schema_fields.iter().any(|field| field.path == *path) {
+                continue;
+            }
+            warn!("The API started returning a new field: {}", path);
+            let field = SchemaField {
+                path: path.clone(),
+                first_seen: now,
+                source: source.to_string(),
+            };
+            self.schema_fields.push(field.clone());
+            new_fields.push(field);
+        }
+        new_fields
+    }
+
+    // Apply the results of a link check. Returns the links that were just
+    // found gone; links that come back are cleared.
+    #[cfg(feature = "gui")]
+    pub fn update_links(&mut self, checks: &[LinkCheck], now: DateTime<Utc>) -> Vec<StoredLink> {
+        let mut newly_gone = Vec::new();
+        for check in checks {
+            let Some(link) = self.links.iter_mut().find(|link| link.file.url == check.file.url) else {
+                continue;
+            };
+            link.last_checked = Some(now);
+            match (is_gone(&check.status), &check.status) {
+                (Some(code), _) => {
+                    if link.gone.is_none() {
+                        warn!("{} {} link is gone ({}): {}", link.biz, link.file.version, code, link.file.url);
+                        link.gone_since = Some(now);
+                        newly_gone.push(link.clone());
+                    }
+                    link.gone = Some(code);
+                }
+                (None, LinkStatus::Ok) => {
+                    link.gone = None;
+                    link.gone_since = None;
+                }
+                // Timeouts and server errors say nothing about the file itself
+                (None, _) => {}
+            }
+        }
+        self.last_link_check = Some(now);
+        newly_gone
+    }
+
+    // Record a download that finished, or that failed its size or MD5 check
+    pub fn record_download(&mut self, game: &str, file: &PackageFile, size: u64, failure: Option<String>, now: DateTime<Utc>) {
+        self.downloads.push(DownloadRecord {
+            game: game.to_string(),
+            version: file.version.clone(),
+            file_name: file.file_name(),
+            size,
+            finished: now,
+            failure,
+        });
+    }
+
+    // Keep the summary of a finished archive check. Only the most recent runs are kept.
+    pub fn record_archive_check(&mut self, progress: &ArchiveProgress, now: DateTime<Utc>) {
+        self.archive_checks.push(ArchiveCheckRun {
+            finished: now,
+            files: progress.checks.len(),
+            problems: progress.problems().into_iter().cloned().collect(),
+        });
+        let excess = self.archive_checks.len().saturating_sub(MAX_ARCHIVE_CHECKS);
+        self.archive_checks.drain(..excess);
+    }
+
+    // When the archive was last checked through to the end
+    #[cfg(feature = "gui")]
+    pub fn last_archive_check(&self) -> Option<DateTime<Utc>> {
+        self.archive_checks.last().map(|run| run.finished)
+    }
+
+    // Link health of a version, if any of its links are stored
+    #[cfg(feature = "gui")]
+    pub fn link_health(&self, biz: &str, version: &str) -> Option<LinkHealth> {
+        let links: Vec<&StoredLink> = self
+            .links
+            .iter()
+            .filter(|link| link.biz == biz && link.file.version == version)
+            .collect();
+        if links.is_empty() {
+            return None;
+        }
+        Some(LinkHealth {
+            total: links.len(),
+            checked: links.iter().filter(|link| link.last_checked.is_some()).count(),
+            gone: links.iter().filter(|link| link.gone.is_some()).count(),
+            gone_since: links.iter().filter_map(|link| link.gone_since).min(),
+        })
+    }
+
+    // The note for a version, if one was written
+    #[cfg(feature = "gui")]
+    pub fn note(&self, biz: &str, version: &str) -> Option<&str> {
+        self.notes
+            .iter()
+            .find(|note| note.biz == biz && note.version == version && !note.text.is_empty())
+            .map(|note| note.text.as_str())
+    }
+
+    // The note for a version, created empty if there is none yet
+    #[cfg(feature = "gui")]
+    pub fn note_mut(&mut self, biz: &str, version: &str) -> &mut String {
+        let index = match self.notes.iter().position(|note| note.biz == biz && note.version == version) {
+            Some(index) => index,
+            None => {
+                self.notes.push(VersionNote {
+                    biz: biz.to_string(),
+                    version: version.to_string(),
+                    text: String::new(),
+                });
+                self.notes.len() - 1
+            }
+        };
+        &mut self.notes[index].text
+    }
+
+    // Games with recorded versions, in the order they were first seen
+    #[cfg(feature = "gui")]
+    pub fn games(&self) -> Vec<String> {
+        let mut games: Vec<String> = Vec::new();
+        for record in &self.records {
+            if !games.contains(&record.biz) {
+                games.push(record.biz.clone());
+            }
+        }
+        games
+    }
+
+    // Every recorded version of a game, oldest first
+    #[cfg(feature = "gui")]
+    pub fn versions(&self, biz: &str) -> Vec<VersionTimeline> {
+        let mut versions: Vec<VersionTimeline> = Vec::new();
+        for record in self.records.iter().filter(|record| record.biz == biz) {
+            let index = match versions.iter().position(|timeline| timeline.version == record.version) {
+                Some(index) => index,
+                None => {
+                    versions.push(VersionTimeline {
+                        version: record.version.clone(),
+                        pre_download_seen: None,
+                        live_seen: None,
+                    });
+                    versions.len() - 1
+                }
+            };
+            match record.kind {
+                VersionKind::PreDownload => versions[index].pre_download_seen = Some(record.first_seen),
+                VersionKind::Live => versions[index].live_seen = Some(record.first_seen),
+            }
+        }
+        versions.sort_by_key(|timeline| timeline.pre_download_seen.or(timeline.live_seen));
+        versions
+    }
+
+    // Drop notes that were cleared
+    #[cfg(feature = "gui")]
+    pub fn prune_notes(&mut self) {
+        self.notes.retain(|note| !note.text.trim().is_empty());
+    }
+
+    // Records of one kind for a game, oldest first
+    #[cfg(feature = "gui")]
+    fn records_of(&self, biz: &str, kind: VersionKind) -> Vec<&VersionRecord> {
+        let mut records: Vec<&VersionRecord> = self
+            .records
+            .iter()
+            .filter(|record| record.biz == biz && record.kind == kind)
+            .collect();
+        records.sort_by_key(|record| record.first_seen);
+        records
+    }
+
+    // Cadence statistics for a game. Needs at least two live versions.
+    #[cfg(feature = "gui")]
+    pub fn cadence(&self, biz: &str) -> Option<Cadence> {
+        let releases = self.records_of(biz, VersionKind::Live);
+        if releases.len() < 2 {
+            return None;
+        }
+
+        let first = releases.first()?.first_seen;
+        let last_release = releases.last()?.first_seen;
+        let average_interval = (last_release - first) / (releases.len() as i32 - 1);
+
+        // Lead time: pre-download of a version first seen -> that version going live
+        let leads: Vec<Duration> = self
+            .records_of(biz, VersionKind::PreDownload)
+            .into_iter()
+            .filter_map(|pre| {
+                let release = releases.iter().find(|release| release.version == pre.version)?;
+                (release.first_seen > pre.first_seen).then(|| release.first_seen - pre.first_seen)
+            })
+            .collect();
+        let average_pre_download_lead = (!leads.is_empty())
+            .then(|| leads.iter().fold(Duration::zero(), |total, lead| total + *lead) / leads.len() as i32);
+
+        Some(Cadence {
+            releases: releases.len(),
+            average_interval,
+            average_pre_download_lead,
+            last_release,
+            next_release_estimate: last_release + average_interval,
+        })
+    }
+}
+
+// Helper function to format a duration compactly, e.g. "12d 4h", "3h 5m", "56m"
+pub fn format_duration(duration: Duration) -> String {
+    let sign = if duration < Duration::zero() { "-" } else { "" };
+    let duration = if duration < Duration::zero() { -duration } else { duration };
+    let days = duration.num_days();
+    let hours = duration.num_hours() - days * 24;
+    let minutes = duration.num_minutes() - duration.num_hours() * 60;
+    if days > 0 {
+        format!("{}{}d {}h", sign, days, hours)
+    } else if hours > 0 {
+        format!("{}{}h {}m", sign, hours, minutes)
+    } else if minutes > 0 {
+        format!("{}{}m", sign, minutes)
+    } else {
+        format!("{}{}s", sign, duration.num_seconds())
+    }
+}
+
+// How long until `format_duration` shows something else for a duration that
+// keeps growing (`counting_down` false) or shrinking (true)
+#[cfg(feature = "gui")]
+pub fn until_label_changes(duration: Duration, counting_down: bool) -> std::time::Duration {
+    let millis = duration.num_milliseconds().max(0);
+    // The smallest unit shown: seconds, then minutes, then hours past a day
+    let unit = match millis {
+        0..=59_999 => 1_000,
+        60_000..=86_399_999 => 60_000,
+        _ => 3_600_000,
+    };
+    let wait = if counting_down { millis % unit } else { unit - millis % unit };
+    std::time::Duration::from_millis(wait.max(1) as u64 + 10)
+}
@@ -0,0 +1,134 @@
+// ----------------------
+// Event Hooks
+// ----------------------
+
+// Shell commands run when something happens: a new live version, a new
+// pre-download, or a failed fetch. They get the details in environment
+// variables, so any automation (a chat bot, a download script, a home
+// automation trigger) can hang off the scanner without it knowing about it.
+// Hooks run from the window, `fetch`, `watch` and `tui` alike.
+//
+// Commands are run as written, never filled in as templates: the version,
+// the URLs and the error all come from the API, and pasted into a shell
+// command they would let the server decide what runs. In an environment
+// variable they are only ever data, as long as the command quotes them.
+
+use crate::export::sanitize_file_name;
+use crate::history::{VersionKind, VersionRecord};
+#[cfg(any(feature = "gui", feature = "tui"))]
+use crate::jobs::{self, JobKind};
+use crate::variables::{MessageTemplates, Variables};
+use crate::GamePackage;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+
+// Commands run per event; empty ones are skipped
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Hooks {
+    pub new_version: String,      // A live version not seen before
+    pub new_pre_download: String, // A pre-download not seen before
+    pub fetch_error: String,      // A fetch failed
+}
+
+// A hook command with the environment it runs in
+pub struct HookRun {
+    event: &'static str,
+    command: String,
+    env: Vec<(String, String)>,
+}
+
+impl HookRun {
+    // Run the command through the shell and wait for it. Its output goes to
+    // stderr, so it never mixes with a command's own output, or nowhere.
+    pub fn run(&self, show_output: bool) -> Result<(), String> {
+        info!("Running the {} hook: {}", self.event, self.command);
+        let mut command = if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.arg("/C").arg(&self.command);
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(&self.command);
+            command
+        };
+        command.envs(self.env.iter().map(|(name, value)| (name, value)));
+        if show_output {
+            command.stdout(std::io::stderr());
+        } else {
+            command.stdout(Stdio::null()).stderr(Stdio::null());
+        }
+        let status = command.status().map_err(|e| format!("Hook error ({}): {}", self.event, e))?;
+        if !status.success() {
+            return Err(format!("Hook error: the {} hook failed ({})", self.event, status));
+        }
+        Ok(())
+    }
+}
+
+// Run hooks one after the other, logging the ones that fail
+pub fn run_all(runs: &[HookRun], show_output: bool) {
+    for run in runs {
+        if let Err(e) = run.run(show_output) {
+            error!("{}", e);
+        }
+    }
+}
+
+// Run hooks on a worker thread, one after the other, so a slow command
+// doesn't hold up the window or the terminal UI
+#[cfg(any(feature = "gui", feature = "tui"))]
+pub fn spawn(runs: Vec<HookRun>, show_output: bool) {
+    if runs.is_empty() {
+        return;
+    }
+    let label = format!("Run {} hook(s)", runs.len());
+    jobs::spawn(JobKind::Hook, label, None, move || run_all(&runs, show_output));
+}
+
+// The hooks for the versions a fetch found for the first time
+pub fn version_hooks(hooks: &Hooks, packages: &[GamePackage], new_records: &[VersionRecord], messages: &MessageTemplates) -> Vec<HookRun> {
+    let mut runs = Vec::new();
+    for record in new_records {
+        let (event, command) = match record.kind {
+            VersionKind::Live => ("new_version", &hooks.new_version),
+            VersionKind::PreDownload => ("new_pre_download", &hooks.new_pre_download),
+        };
+        if command.trim().is_empty() {
+            continue;
+        }
+        let Some(package) = packages.iter().find(|package| package.game.biz == record.biz) else {
+            continue;
+        };
+        let Some(vars) = Variables::new_version(package, record, messages) else {
+            continue;
+        };
+        let mut env = vars.env();
+        env.push(("URLS_FILE".to_string(), urls_file(&vars)));
+        runs.push(HookRun { event, command: command.clone(), env });
+    }
+    runs
+}
+
+// The hook for a failed fetch of `source`, if one is set
+pub fn error_hook(hooks: &Hooks, source: &str, message: &str, messages: &MessageTemplates) -> Option<HookRun> {
+    if hooks.fetch_error.trim().is_empty() {
+        return None;
+    }
+    let vars = Variables::fetch_error(source, message, messages);
+    Some(HookRun { event: "fetch_error", command: hooks.fetch_error.clone(), env: vars.env() })
+}
+
+// Write the links of a version to a temporary file, one per line, for tools
+// that take a list of URLs. Returns its path. The name is built from API data,
+// so it is cleaned, and has no spaces in case a hook leaves `$URLS_FILE` unquoted.
+fn urls_file(vars: &Variables) -> String {
+    let urls: String = vars.links.iter().map(|url| format!("{}\n", url)).collect();
+    let name = format!("genshin_package_scanner_{}_{}_urls.txt", sanitize_file_name(&vars.biz), sanitize_file_name(&vars.version));
+    let path = std::env::temp_dir().join(name.replace(' ', "_"));
+    if let Err(e) = std::fs::write(&path, urls) {
+        error!("Hook error: could not write {}: {}", path.display(), e);
+    }
+    path.to_string_lossy().into_owned()
+}
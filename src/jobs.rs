@@ -0,0 +1,177 @@
+// ----------------------
+// Background Jobs
+// ----------------------
+
+// Fetches, link checks, hashing, downloads and the other slow operations run
+// as jobs on a small shared thread pool instead of a thread each, so a burst
+// of work queues up rather than spawning without bound. Every job is listed
+// with what it is doing for the Jobs tab. Workers are started as jobs arrive,
+// up to a fixed number, and exit again after sitting idle for a while.
+
+use crate::CancelToken;
+use log::error;
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+// Most jobs running at once; the rest wait in the queue
+const MAX_WORKERS: usize = 8;
+
+// How long an idle worker waits for work before exiting
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+// `tui` only fetches and runs hooks; the other kinds are the window's
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+pub enum JobKind {
+    Fetch,
+    LinkCheck,
+    Hashing,
+    Download,
+    Update,
+    Aria2,
+    Sound,
+    Upload,
+    Hook,
+}
+
+#[cfg(feature = "gui")]
+impl JobKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            JobKind::Fetch => "Fetch",
+            JobKind::LinkCheck => "Link check",
+            JobKind::Hashing => "Hashing",
+            JobKind::Download => "Download",
+            JobKind::Update => "Update",
+            JobKind::Aria2 => "aria2",
+            JobKind::Sound => "Sound",
+            JobKind::Upload => "Upload",
+            JobKind::Hook => "Hook",
+        }
+    }
+}
+
+// A queued or running job as shown in the Jobs tab
+#[derive(Clone)]
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+pub struct JobInfo {
+    pub id: u64,
+    pub kind: JobKind,
+    pub label: String,
+    pub queued_at: Instant,
+    pub started_at: Option<Instant>, // None while waiting for a worker
+    pub cancel: Option<CancelToken>, // Set when the job can be stopped from the Jobs tab
+}
+
+type Work = Box<dyn FnOnce() + Send>;
+
+struct PoolState {
+    queue: VecDeque<(u64, Work)>,
+    jobs: Vec<JobInfo>,
+    workers: usize, // Worker threads alive
+    idle: usize,    // Workers waiting for a job
+    next_id: u64,
+}
+
+struct Pool {
+    state: Mutex<PoolState>,
+    work_ready: Condvar,
+}
+
+fn pool() -> &'static Pool {
+    static POOL: OnceLock<Pool> = OnceLock::new();
+    POOL.get_or_init(|| Pool {
+        state: Mutex::new(PoolState {
+            queue: VecDeque::new(),
+            jobs: Vec::new(),
+            workers: 0,
+            idle: 0,
+            next_id: 0,
+        }),
+        work_ready: Condvar::new(),
+    })
+}
+
+fn lock_state(pool: &Pool) -> MutexGuard<'_, PoolState> {
+    pool.state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Queue a job. Pass its cancel token only if whoever started it notices the
+// token being cancelled, as it gets a Cancel button in the Jobs tab.
+pub fn spawn(kind: JobKind, label: impl Into<String>, cancel: Option<CancelToken>, work: impl FnOnce() + Send + 'static) {
+    let pool = pool();
+    let mut state = lock_state(pool);
+    let id = state.next_id;
+    state.next_id += 1;
+    state.jobs.push(JobInfo {
+        id,
+        kind,
+        label: label.into(),
+        queued_at: Instant::now(),
+        started_at: None,
+        cancel,
+    });
+    state.queue.push_back((id, Box::new(work)));
+
+    if state.queue.len() > state.idle && state.workers < MAX_WORKERS {
+        state.workers += 1;
+        std::thread::spawn(move || run_worker(pool));
+    } else {
+        pool.work_ready.notify_one();
+    }
+}
+
+// The queued and running jobs, oldest first
+#[cfg(feature = "gui")]
+pub fn list() -> Vec<JobInfo> {
+    lock_state(pool()).jobs.clone()
+}
+
+// Wait until no job is queued or running, for at most `timeout`. Returns
+// whether the pool went idle in time.
+#[cfg(feature = "gui")]
+pub fn wait_idle(timeout: Duration) -> bool {
+    let started = Instant::now();
+    while !lock_state(pool()).jobs.is_empty() {
+        if started.elapsed() >= timeout {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    true
+}
+
+// Take jobs from the queue until none has arrived for a while
+fn run_worker(pool: &Pool) {
+    loop {
+        let mut state = lock_state(pool);
+        let (id, work) = loop {
+            if let Some(job) = state.queue.pop_front() {
+                break job;
+            }
+            state.idle += 1;
+            let (next, timeout) = pool
+                .work_ready
+                .wait_timeout(state, IDLE_TIMEOUT)
+                .unwrap_or_else(|e| e.into_inner());
+            state = next;
+            state.idle -= 1;
+            if timeout.timed_out() && state.queue.is_empty() {
+                state.workers -= 1;
+                return;
+            }
+        };
+        if let Some(job) = state.jobs.iter_mut().find(|job| job.id == id) {
+            job.started_at = Some(Instant::now());
+        }
+        drop(state);
+
+        // A panicking job must not take the worker (and its slot) down with it
+        if catch_unwind(AssertUnwindSafe(work)).is_err() {
+            error!("Job {} panicked.", id);
+        }
+        lock_state(pool).jobs.retain(|job| job.id != id);
+    }
+}
@@ -0,0 +1,76 @@
+// ----------------------
+// Language Names
+// ----------------------
+
+// Audio language codes are labeled from a table in the settings, so new or
+// regional voice packs can be named without a new release. The table is kept
+// process-wide since labels are needed deep inside report rendering and
+// comparisons; loading the settings installs it. Codes the table doesn't know
+// are shown as they are and listed under a game's warnings.
+
+use crate::{AudioPackage, GamePackage, PreDownload};
+use serde::{Deserialize, Serialize};
+use std::sync::{OnceLock, RwLock};
+
+// Display name of one audio language code
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LanguageName {
+    pub code: String, // e.g. "ja-jp", matched case-insensitively
+    pub name: String, // e.g. "Japanese"
+}
+
+// The codes the API has used so far
+pub fn default_names() -> Vec<LanguageName> {
+    [("zh-cn", "Chinese"), ("en-us", "English"), ("ja-jp", "Japanese"), ("ko-kr", "Korean")]
+        .into_iter()
+        .map(|(code, name)| LanguageName {
+            code: code.to_string(),
+            name: name.to_string(),
+        })
+        .collect()
+}
+
+fn table() -> &'static RwLock<Vec<LanguageName>> {
+    static TABLE: OnceLock<RwLock<Vec<LanguageName>>> = OnceLock::new();
+    TABLE.get_or_init(|| RwLock::new(default_names()))
+}
+
+// Replace the table, e.g. after the settings were loaded or edited
+pub fn set_names(names: &[LanguageName]) {
+    let names = names.iter().filter(|entry| !entry.code.trim().is_empty()).cloned().collect();
+    *table().write().unwrap_or_else(|e| e.into_inner()) = names;
+}
+
+// Name of a code, if the table has one
+pub fn name(code: &str) -> Option<String> {
+    table()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|entry| entry.code.trim().eq_ignore_ascii_case(code.trim()))
+        .map(|entry| entry.name.clone())
+}
+
+// Warnings for the audio language codes of a game the table doesn't know
+pub fn unmapped_warnings(package: Option<&GamePackage>, pre_download: Option<&PreDownload>) -> Vec<String> {
+    let mut audio: Vec<&AudioPackage> = Vec::new();
+    if let Some(package) = package {
+        audio.extend(package.main.major.iter().flat_map(|major| &major.audio_pkgs));
+        audio.extend(package.main.patches.iter().flat_map(|patch| &patch.audio_pkgs));
+    }
+    if let Some(pre_download) = pre_download {
+        audio.extend(pre_download.major.iter().flat_map(|major| &major.audio_pkgs));
+        audio.extend(pre_download.patches.iter().flat_map(|patch| &patch.audio_pkgs));
+    }
+
+    let mut codes: Vec<&str> = Vec::new();
+    for pkg in audio {
+        if name(&pkg.language).is_none() && !codes.contains(&pkg.language.as_str()) {
+            codes.push(&pkg.language);
+        }
+    }
+    codes
+        .into_iter()
+        .map(|code| format!("Unmapped audio language code {} (name it under Games > Language Names)", code))
+        .collect()
+}
@@ -0,0 +1,177 @@
+// ----------------------
+// Legacy Launcher Resource API
+// ----------------------
+
+// Model for the pre-HYP `mdk/launcher/api/resource` payload, still found in
+// archived responses and on some private servers. Everything here is
+// normalized into the HYP structs so the rest of the app only deals with one
+// model.
+
+use crate::{AudioPackage, Game, GamePackage, Main, Major, Package, Patch, PreDownload};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct LegacyData {
+    game: LegacyGame,
+    #[serde(default)]
+    pre_download_game: Option<LegacyGame>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LegacyGame {
+    latest: LegacyLatest,
+    #[serde(default)]
+    diffs: Vec<LegacyDiff>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LegacyLatest {
+    version: String,
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    md5: String,
+    // In the legacy format `size` is the unpacked size and `package_size` the archive size
+    #[serde(default)]
+    size: String,
+    #[serde(default)]
+    package_size: String,
+    #[serde(default)]
+    voice_packs: Vec<LegacyVoicePack>,
+    #[serde(default)]
+    segments: Vec<LegacySegment>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LegacyDiff {
+    version: String,
+    path: String,
+    #[serde(default)]
+    md5: String,
+    #[serde(default)]
+    size: String,
+    #[serde(default)]
+    package_size: String,
+    #[serde(default)]
+    voice_packs: Vec<LegacyVoicePack>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LegacyVoicePack {
+    language: String,
+    path: String,
+    #[serde(default)]
+    md5: String,
+    #[serde(default)]
+    size: String,
+    #[serde(default)]
+    package_size: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct LegacySegment {
+    path: String,
+    #[serde(default)]
+    md5: String,
+    #[serde(default)]
+    package_size: String,
+}
+
+// Returns true if the `data` object looks like a legacy resource payload
+pub fn is_legacy_payload(data: &serde_json::Value) -> bool {
+    data.get("game_packages").is_none() && data.pointer("/game/latest").is_some()
+}
+
+impl LegacyData {
+    // Convert into the HYP model. Legacy payloads carry no game id, so the
+    // package is labelled as a legacy Genshin package.
+    pub fn into_game_package(self) -> GamePackage {
+        let (major, patches) = self.game.into_parts();
+        let pre_download = self.pre_download_game.map(|pre| {
+            let (major, patches) = pre.into_parts();
+            PreDownload {
+                major: Some(major),
+                patches,
+                res_list_url: None,
+            }
+        });
+
+        GamePackage {
+            game: Game {
+                id: String::new(),
+                biz: "hk4e_legacy".to_string(),
+            },
+            main: Main {
+                major: Some(major),
+                patches,
+                res_list_url: None,
+            },
+            pre_download,
+        }
+    }
+}
+
+impl LegacyGame {
+    fn into_parts(self) -> (Major, Vec<Patch>) {
+        let latest = self.latest;
+
+        // Split archives list their parts as segments; otherwise `path` is the whole package
+        let game_pkgs = if latest.segments.is_empty() {
+            vec![Package {
+                url: latest.path,
+                md5: latest.md5,
+                size: latest.package_size,
+                decompressed_size: latest.size,
+            }]
+        } else {
+            // Unpacked size is only known for the whole game, not per segment
+            latest
+                .segments
+                .into_iter()
+                .map(|segment| Package {
+                    url: segment.path,
+                    md5: segment.md5,
+                    size: segment.package_size,
+                    decompressed_size: String::new(),
+                })
+                .collect()
+        };
+
+        let major = Major {
+            version: latest.version,
+            game_pkgs,
+            audio_pkgs: latest.voice_packs.into_iter().map(LegacyVoicePack::into_audio_package).collect(),
+            res_list_url: None,
+        };
+
+        let patches = self
+            .diffs
+            .into_iter()
+            .map(|diff| Patch {
+                version: diff.version,
+                game_pkgs: vec![Package {
+                    url: diff.path,
+                    md5: diff.md5,
+                    size: diff.package_size,
+                    decompressed_size: diff.size,
+                }],
+                audio_pkgs: diff.voice_packs.into_iter().map(LegacyVoicePack::into_audio_package).collect(),
+                res_list_url: None,
+            })
+            .collect();
+
+        (major, patches)
+    }
+}
+
+impl LegacyVoicePack {
+    fn into_audio_package(self) -> AudioPackage {
+        AudioPackage {
+            language: self.language,
+            url: self.path,
+            md5: self.md5,
+            size: self.package_size,
+            decompressed_size: self.size,
+        }
+    }
+}
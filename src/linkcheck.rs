@@ -0,0 +1,133 @@
+// ----------------------
+// Link Checking
+// ----------------------
+
+// Checks that each package URL still answers. Every check asks for just the
+// first byte, so the same request shows whether the host honours Range
+// requests, which parallel segments and resumed downloads depend on.
+
+use crate::network;
+use crate::{CancelToken, PackageFile};
+use log::{info, warn};
+use reqwest::blocking::Client;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Most links checked at once
+const MAX_WORKERS: usize = 4;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkStatus {
+    Ok,
+    Broken(u16),    // The server answered with an HTTP error
+    Failed(String), // No answer at all
+}
+
+// Result of checking one URL
+#[derive(Clone, Debug)]
+pub struct LinkCheck {
+    pub file: PackageFile,
+    pub status: LinkStatus,
+    pub resumable: bool,   // The server answered the Range request with partial content
+    pub size: Option<u64>, // Size the server reports
+}
+
+impl LinkCheck {
+    // The server's size differs from the API's
+    pub fn size_differs(&self) -> bool {
+        self.file.size > 0 && self.size.is_some_and(|size| size != self.file.size)
+    }
+}
+
+// Link check running on a worker thread; the UI reads it every frame
+#[derive(Default)]
+pub struct LinkProgress {
+    pub total: usize,
+    pub checks: Vec<LinkCheck>,
+    pub finished: bool,
+}
+
+impl LinkProgress {
+    pub fn count(&self, status: &LinkStatus) -> usize {
+        self.checks.iter().filter(|check| check.status == *status).count()
+    }
+
+    pub fn resumable(&self) -> usize {
+        self.checks.iter().filter(|check| check.resumable).count()
+    }
+}
+
+// A link check running (or finished) as a background job
+pub struct LinkCheckJob {
+    pub progress: Arc<Mutex<LinkProgress>>,
+    pub cancel: CancelToken,
+}
+
+// Check links on several worker threads, adding each result to `progress` as it completes
+pub fn check_links(
+    files: &[PackageFile],
+    progress: &Mutex<LinkProgress>,
+    on_checked: &(dyn Fn() + Sync),
+    is_cancelled: &(dyn Fn() -> bool + Sync),
+) {
+    let urls: Vec<&str> = files.iter().map(|file| file.url.as_str()).collect();
+    let client = network::client_for(&urls);
+    let next = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        for _ in 0..MAX_WORKERS.min(files.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(file) = files.get(index) else {
+                    break;
+                };
+                if is_cancelled() {
+                    break;
+                }
+                let check = check_link(&client, file);
+                if let Ok(mut progress) = progress.lock() {
+                    progress.checks.push(check);
+                }
+                on_checked();
+            });
+        }
+    });
+}
+
+// Request the first byte of a file. A 206 answer means the host supports
+// ranges; a 200 means it ignored the Range header and would send everything.
+pub fn check_link(client: &Client, file: &PackageFile) -> LinkCheck {
+    let mut check = LinkCheck {
+        file: file.clone(),
+        status: LinkStatus::Ok,
+        resumable: false,
+        size: None,
+    };
+    // The body is never read; dropping the response closes the connection
+    let response = match client.get(&file.url).header(RANGE, "bytes=0-0").send() {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Link check failed for {}: {}", file.url, e);
+            check.status = LinkStatus::Failed(e.to_string());
+            return check;
+        }
+    };
+
+    let status = response.status();
+    let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok());
+    if status == StatusCode::PARTIAL_CONTENT {
+        check.resumable = true;
+        // Content-Range: bytes 0-0/<total size>
+        check.size = header(CONTENT_RANGE)
+            .and_then(|range| range.rsplit_once('/'))
+            .and_then(|(_, total)| total.parse().ok());
+    } else if status.is_success() {
+        check.size = header(CONTENT_LENGTH).and_then(|length| length.parse().ok());
+    } else {
+        warn!("Link check for {} returned {}", file.url, status);
+        check.status = LinkStatus::Broken(status.as_u16());
+    }
+    info!("Checked {}: {:?}, resumable: {}", file.url, check.status, check.resumable);
+    check
+}
@@ -1,43 +1,176 @@
-#![cfg_attr(target_os = "windows", windows_subsystem = "windows")]
+#![cfg_attr(all(target_os = "windows", feature = "gui"), windows_subsystem = "windows")]
 
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("HTTPS needs a TLS backend: enable the `native-tls` or `rustls` feature");
+
+#[cfg(feature = "gui")]
+use chrono::{DateTime, Utc};
+use clap::Parser;
+#[cfg(feature = "gui")]
 use eframe::egui;
-use eframe::egui::{Align, Layout};
-use reqwest;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
-use log::{info, error, debug};
+use std::io::Read;
+#[cfg(feature = "gui")]
+use std::ops::Range;
+#[cfg(feature = "gui")]
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "gui")]
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+#[cfg(feature = "gui")]
+use std::sync::Mutex;
+use log::{info, warn, error, debug};
+
+mod activity;
+mod archive;
+#[cfg(feature = "gui")]
+mod audio;
+#[cfg(feature = "gui")]
+mod aria2;
+mod cli;
+#[cfg(feature = "gui")]
+mod clipboard;
+mod compare;
+mod doctor;
+mod download;
+mod export;
+#[cfg(feature = "gui")]
+mod find;
+#[cfg(feature = "gui")]
+mod gamewatch;
+mod github;
+#[cfg(feature = "gui")]
+mod health;
+mod history;
+mod hooks;
+#[cfg(any(feature = "gui", feature = "tui"))]
+mod jobs;
+mod languages;
+mod legacy;
+#[cfg(feature = "gui")]
+mod linkcheck;
+mod mirror;
+mod network;
+mod polling;
+mod profiling;
+mod qr;
+mod replay;
+mod report;
+mod response;
+#[cfg(feature = "gui")]
+mod reslist;
+mod schema;
+mod settings;
+mod service;
+mod share;
+#[cfg(feature = "gui")]
+mod sound;
+mod systemd;
+mod theme;
+mod timing;
+#[cfg(feature = "gui")]
+mod torrent;
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(feature = "gui")]
+mod update;
+mod upload;
+mod variables;
+mod verify;
+mod version;
+#[cfg(feature = "gui")]
+mod workspace;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "gui")]
+use archive::{ArchiveJob, ArchiveProgress, ArchiveStatus, DedupJob, DedupProgress};
+#[cfg(feature = "gui")]
+use aria2::{Aria2, Aria2Rpc};
+#[cfg(feature = "gui")]
+use download::{DownloadStatus, Downloader, Priority};
+#[cfg(feature = "gui")]
+use gamewatch::GameWatch;
+#[cfg(feature = "gui")]
+use health::{Health, Outcome};
+#[cfg(feature = "gui")]
+use jobs::JobKind;
+#[cfg(feature = "gui")]
+use history::{History, VersionKind};
+#[cfg(feature = "gui")]
+use linkcheck::{LinkCheckJob, LinkProgress};
+#[cfg(feature = "gui")]
+use polling::Poller;
+use profiling::Metric;
+#[cfg(feature = "gui")]
+use mirror::{Mirror, MirrorStatus};
+#[cfg(feature = "gui")]
+use network::IpFamily;
+#[cfg(feature = "gui")]
+use report::{ReportFilter, ReportLanguage, ReportSection, ReportTemplates};
+#[cfg(feature = "gui")]
+use settings::{GameConfig, Settings};
+#[cfg(feature = "gui")]
+use share::ShareService;
+#[cfg(feature = "gui")]
+use theme::{status_label, Status, Theme};
+#[cfg(feature = "gui")]
+use upload::UploadBackend;
+#[cfg(feature = "gui")]
+use update::{DryRunJob, LockedChoice, Stage, StageState, UpdatePlan, Updater};
+#[cfg(feature = "gui")]
+use variables::Variables;
+#[cfg(feature = "gui")]
+use workspace::{GameWorkspace, WorkspaceContext};
+
+// Default data source: the HYP launcher API for Genshin Impact (global)
+const DEFAULT_API_URL: &str = "https://sg-hyp-api.hoyoverse.com/hyp/hyp-connect/api/getGamePackages?game_ids[]=gopR6Cufr3&launcher_id=VYTpXlbWo8";
+
+// The same API for the Chinese release, used for region comparisons
+#[cfg(feature = "gui")]
+const CN_API_URL: &str = "https://hyp-api.mihoyo.com/hyp/hyp-connect/api/getGamePackages?game_ids[]=1Z8W5NHUQb&launcher_id=jGHBHlcOq1";
+
+// How long notifications stay on screen
+#[cfg(feature = "gui")]
+const TOAST_SECONDS: i64 = 5;
 
 // ----------------------
 // Struct Definitions
 // ----------------------
 
-// Root API response
-#[derive(Deserialize, Serialize, Debug)]
-struct ApiResponse {
-    retcode: i32,
-    message: String,
-    data: Data,
-}
-
-#[derive(Deserialize, Serialize, Debug)]
-struct Data {
-    game_packages: Vec<GamePackage>,
-}
-
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct GamePackage {
     game: Game,
     main: Main,
     pre_download: Option<PreDownload>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct Game {
     id: String,
     biz: String,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+impl Game {
+    // Human-readable name for the game, derived from its biz code
+    fn display_name(&self) -> String {
+        map_biz_code(&self.biz)
+    }
+
+    // One-line identification, e.g. "Genshin Impact (hk4e_global, id gopR6Cufr3)"
+    fn label(&self) -> String {
+        if self.id.is_empty() {
+            format!("{} ({})", self.display_name(), self.biz)
+        } else {
+            format!("{} ({}, id {})", self.display_name(), self.biz, self.id)
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct Main {
     major: Option<Major>,
     patches: Vec<Patch>,
@@ -45,7 +178,7 @@ struct Main {
     res_list_url: Option<String>, // Made optional with default
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct PreDownload {
     major: Option<Major>,
     patches: Vec<Patch>,
@@ -53,14 +186,17 @@ struct PreDownload {
     res_list_url: Option<String>, // Made optional with default
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct Major {
     version: String,
     game_pkgs: Vec<Package>,
     audio_pkgs: Vec<AudioPackage>,
+    // Only some payloads carry it, so a missing one is no warning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    res_list_url: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct Package {
     url: String,
     md5: String,
@@ -68,7 +204,7 @@ struct Package {
     decompressed_size: String,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct AudioPackage {
     language: String,
     url: String,
@@ -77,41 +213,143 @@ struct AudioPackage {
     decompressed_size: String,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct Patch {
     version: String,
     game_pkgs: Vec<Package>,
     audio_pkgs: Vec<AudioPackage>,
-    #[serde(default)]
-    res_list_url: Option<String>, // Made optional with default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    res_list_url: Option<String>, // Made optional with default; most patches have none
 }
 
 // ----------------------
 // Application State
 // ----------------------
 
+#[cfg(feature = "gui")]
+#[derive(PartialEq, Clone, Copy)]
+enum Tab {
+    Packages,
+    Games,
+    Compare,
+    History,
+    Downloads,
+    Jobs,
+    Templates,
+}
+
+// Events sent from the fetch worker thread to the UI
+#[cfg(feature = "gui")]
+enum FetchEvent {
+    Completed(FetchResult),
+    Failed(FetchFailure),
+}
+
+// Shared flag used to ask a worker thread to stop
+#[derive(Clone, Default)]
+struct CancelToken(Arc<AtomicBool>);
+
+// Set once the app is closing; from then on every token reads as cancelled
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+// How long closing waits for cancelled jobs to wind down
+#[cfg(feature = "gui")]
+const SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(3);
+
+impl CancelToken {
+    #[cfg(feature = "gui")]
+    fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed) || SHUTTING_DOWN.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(feature = "gui")]
 struct GenshinApp {
-    data: Arc<Mutex<String>>,
-    formatted_message: Arc<Mutex<String>>,
-    pre_download_main_message: Arc<Mutex<String>>,    // For Pre-download (Main)
-    pre_download_patches_message: Arc<Mutex<String>>, // For Pre-download (Patches)
-    raw_main_data: Arc<Mutex<String>>,                // Raw JSON for main data
-    raw_pre_download_data: Arc<Mutex<String>>,        // Raw JSON for pre-download data
-    error_message: Arc<Mutex<String>>,                // Error messages
+    workspaces: Vec<GameWorkspace>,       // One per configured game
+    selected_workspace: usize,            // Workspace shown in the Packages tab
+    next_fetch_id: u64,
+    event_sender: Sender<(u64, FetchEvent)>, // Cloned into each fetch worker, tagged with its fetch id
+    event_receiver: Receiver<(u64, FetchEvent)>, // Drained at the start of every frame
+    settings: Settings,
+    tab: Tab,
+    template_draft: ReportTemplates,      // Templates being edited, applied on save
+    template_section: ReportSection,      // Which template is being edited
+    settings_status: String,              // Result of the last settings save
+    discord_chunks: Vec<String>,          // Pending multi-part Discord messages
+    compare_left: usize,                  // Workspaces shown in the Compare tab
+    compare_right: usize,
+    history: History,                     // First sightings of each version
+    next_poll: Option<DateTime<Utc>>,     // When the next automatic fetch runs
+    poller: Poller,                       // Spacing and backoff of automatic fetches
+    poll_failed: bool,                    // A fetch of the last auto-check failed
+    polled_fetches: Range<u64>,           // Fetch ids started by the last auto-check
+    toast: Option<(String, DateTime<Utc>)>, // Short notification and when it was raised
+    downloader: Downloader,               // Built-in download queue
+    aria2: Aria2,                         // Files handed to an aria2 daemon
+    mirror: Mirror,                       // Verified downloads being uploaded over SFTP
+    download_source: usize,               // Workspace whose files the Downloads tab adds
+    download_version: Option<String>,     // Version to add, None for all
+    link_watch: Option<LinkCheckJob>,     // Re-check of stored links in flight
+    archive_check: Option<ArchiveJob>,    // Re-hash of the archived downloads in flight
+    dedup: Option<DedupJob>,              // Last deduplication of the archived downloads
+    activity_month: activity::Month,      // Month picked for the activity report
+    update_plan: Option<Result<UpdatePlan, String>>, // Last install detected by the update wizard
+    updater: Option<Updater>,             // Update of the install, once started
+    dry_run: Option<DryRunJob>,           // Dry run of the detected plan
+    game_watch: GameWatch,                // Whether the game is running
+    on_hold: bool,                        // Downloads and auto-checks are held while the game runs
+    upload_status: Arc<Mutex<String>>,    // Outcome of the last snapshot upload
+    show_diagnostics: bool,               // Timings overlay, toggled with F12
+    health: Health,                       // Outcomes of recent fetches, for the status bar
 }
 
+#[cfg(feature = "gui")]
 impl Default for GenshinApp {
     fn default() -> Self {
+        let settings = Settings::load();
+        network::configure(&settings.network);
+        let (event_sender, event_receiver) = mpsc::channel();
         Self {
-            data: Arc::new(Mutex::new(
-                "Press 'Fetch Data' to get the latest data.".to_string(),
-            )),
-            formatted_message: Arc::new(Mutex::new(String::new())),
-            pre_download_main_message: Arc::new(Mutex::new(String::new())),      // Initialize empty
-            pre_download_patches_message: Arc::new(Mutex::new(String::new())),   // Initialize empty
-            raw_main_data: Arc::new(Mutex::new(String::new())),
-            raw_pre_download_data: Arc::new(Mutex::new(String::new())),
-            error_message: Arc::new(Mutex::new(String::new())),
+            workspaces: settings.games.iter().cloned().map(GameWorkspace::new).collect(),
+            selected_workspace: 0,
+            next_fetch_id: 0,
+            event_sender,
+            event_receiver,
+            template_draft: settings.templates.clone(),
+            settings,
+            tab: Tab::Packages,
+            template_section: ReportSection::Main,
+            settings_status: String::new(),
+            discord_chunks: Vec::new(),
+            compare_left: 0,
+            compare_right: 1,
+            history: History::load(),
+            next_poll: None,
+            poller: Poller::new(polling::MIN_INTERVAL),
+            poll_failed: false,
+            polled_fetches: 0..0,
+            toast: None,
+            downloader: Downloader::load(),
+            aria2: Aria2::default(),
+            mirror: Mirror::default(),
+            download_source: 0,
+            download_version: None,
+            link_watch: None,
+            archive_check: None,
+            dedup: None,
+            activity_month: activity::month_of(Utc::now()),
+            update_plan: None,
+            updater: None,
+            dry_run: None,
+            game_watch: GameWatch::default(),
+            on_hold: false,
+            upload_status: Arc::default(),
+            show_diagnostics: false,
+            health: Health::default(),
         }
     }
 }
@@ -120,496 +358,2901 @@ impl Default for GenshinApp {
 // eframe Application Implementation
 // ----------------------
 
+#[cfg(feature = "gui")]
 impl eframe::App for GenshinApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Clone Arc references for thread-safe access
-        let data_clone = Arc::clone(&self.data);
-        let formatted_message_clone = Arc::clone(&self.formatted_message);
-        let pre_download_main_message_clone = Arc::clone(&self.pre_download_main_message);
-        let pre_download_patches_message_clone = Arc::clone(&self.pre_download_patches_message);
-        let raw_main_data_clone = Arc::clone(&self.raw_main_data);
-        let raw_pre_download_data_clone = Arc::clone(&self.raw_pre_download_data);
-        let error_message_clone = Arc::clone(&self.error_message);
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
-                // Fetch Data Button
-                if ui.button("Fetch Data").clicked() {
-                    // Clear existing messages and data
-                    {
-                        let mut message_lock = formatted_message_clone.lock().unwrap();
-                        *message_lock = String::new();
-                    }
-                    {
-                        let mut pre_main_lock = pre_download_main_message_clone.lock().unwrap();
-                        *pre_main_lock = String::new();
-                    }
-                    {
-                        let mut pre_patches_lock = pre_download_patches_message_clone.lock().unwrap();
-                        *pre_patches_lock = String::new();
-                    }
-                    {
-                        let mut error_lock = error_message_clone.lock().unwrap();
-                        *error_lock = String::new();
-                    }
-                    {
-                        let mut raw_main_lock = raw_main_data_clone.lock().unwrap();
-                        *raw_main_lock = String::new();
-                    }
-                    {
-                        let mut raw_pre_download_lock = raw_pre_download_data_clone.lock().unwrap();
-                        *raw_pre_download_lock = String::new();
-                    }
-
-                    // Clone Arcs for the thread
-                    let data_clone_inner = Arc::clone(&data_clone);
-                    let formatted_message_clone_inner = Arc::clone(&formatted_message_clone);
-                    let pre_download_main_message_clone_inner = Arc::clone(&pre_download_main_message_clone);
-                    let pre_download_patches_message_clone_inner = Arc::clone(&pre_download_patches_message_clone);
-                    let raw_main_data_clone_inner = Arc::clone(&raw_main_data_clone);
-                    let raw_pre_download_data_clone_inner = Arc::clone(&raw_pre_download_data_clone);
-                    let error_message_clone_inner = Arc::clone(&error_message_clone);
-
-                    // Spawn a new thread to fetch data
-                    std::thread::spawn(move || {
-                        info!("Starting data fetch from API.");
-                        match fetch_and_process_data() {
-                            Ok((main_data, pre_download_data)) => {
-                                info!("Data fetch and processing successful.");
-
-                                // Update main data
-                                {
-                                    let mut data_lock = data_clone_inner.lock().unwrap();
-                                    *data_lock = main_data.clone();
-                                }
+        let frame_start = std::time::Instant::now();
+        if ctx.input(|input| input.key_pressed(egui::Key::F12)) {
+            self.show_diagnostics = !self.show_diagnostics;
+            timing::set_wanted(self.show_diagnostics);
+        }
 
-                                // Convert and update Main Data message
-                                let main_message = convert_main_to_message(&main_data);
-                                {
-                                    let mut formatted_lock = formatted_message_clone_inner.lock().unwrap();
-                                    *formatted_lock = main_message;
-                                }
+        // Apply any results delivered by fetch workers to the workspace that started them
+        while let Ok((fetch_id, event)) = self.event_receiver.try_recv() {
+            self.handle_fetch_event(ctx, fetch_id, event);
+        }
 
-                                // Update raw main data
-                                {
-                                    let mut raw_main_lock = raw_main_data_clone_inner.lock().unwrap();
-                                    *raw_main_lock = main_data.clone();
-                                }
+        self.hold_while_playing(ctx);
+        self.poll(ctx);
+        self.retry_busy_fetches(ctx);
+        self.poll_links(ctx);
+        self.poll_archive(ctx);
+        let finished = self.downloader.update(
+            ctx,
+            self.settings.download_connections,
+            self.settings.bandwidth_limit_mb as u64 * 1024 * 1024,
+        );
+        let mirror = self.settings.mirror.enabled && self.settings.mirror.is_configured();
+        for download in &finished {
+            let failure = match &download.status {
+                DownloadStatus::Failed(e) if e == download::SIZE_MISMATCH || e == download::MD5_MISMATCH => Some(e.clone()),
+                DownloadStatus::Failed(_) => continue, // Broke off, not a verification result
+                _ => None,
+            };
+            let (_, size) = download.progress();
+            self.history.record_download(&download.game, &download.file, size, failure, Utc::now());
+            if mirror && download.status == DownloadStatus::Done {
+                self.mirror.send(ctx, &self.settings.mirror, &download.game, download.file.clone(), download.dest.clone());
+            }
+        }
+        if !finished.is_empty() {
+            if let Err(e) = self.history.save() {
+                error!("{}", e);
+            }
+        }
+        if let Some(updater) = &mut self.updater {
+            updater.update(ctx, &mut self.downloader);
+        }
+        let aria2_rpc = self.aria2_rpc();
+        self.aria2.update(ctx, &aria2_rpc);
+        self.show_status_bar(ctx);
+        self.show_toast(ctx);
+        self.schedule_repaint(ctx);
 
-                                // Update pre_download data if available
-                                if let Some(pre_data) = pre_download_data.clone() {
-                                    info!("Pre-download data available.");
-                                    // Update raw pre-download data
-                                    {
-                                        let mut raw_pre_download_lock = raw_pre_download_data_clone_inner.lock().unwrap();
-                                        *raw_pre_download_lock = pre_data.clone();
-                                    }
-
-                                    // Convert pre-download (Main) data
-                                    let pre_main_msg = convert_pre_download_main_to_message(&pre_data);
-                                    {
-                                        let mut pre_main_lock = pre_download_main_message_clone_inner.lock().unwrap();
-                                        *pre_main_lock = pre_main_msg;
-                                    }
-
-                                    // Convert pre-download (Patches) data
-                                    // Extract Current Version from Pre-download (Main)
-                                    let current_version = extract_current_version(&pre_data).unwrap_or_else(|| "Unknown".to_string());
-                                    let pre_patches_msg = convert_pre_download_patches_to_message(&pre_data, &current_version);
-                                    {
-                                        let mut pre_patches_lock = pre_download_patches_message_clone_inner.lock().unwrap();
-                                        *pre_patches_lock = pre_patches_msg;
-                                    }
-                                } else {
-                                    info!("No pre-download data found.");
-                                }
-                            }
-                            Err(err) => {
-                                error!("Error during data fetch: {}", err);
-                                let mut error_lock = error_message_clone_inner.lock().unwrap();
-                                *error_lock = err;
-                            }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                // One tab per game when several are configured
+                if self.workspaces.len() > 1 {
+                    for (index, workspace) in self.workspaces.iter().enumerate() {
+                        let selected = self.tab == Tab::Packages && self.selected_workspace == index;
+                        let mut label = workspace.config.name.clone();
+                        if workspace.is_fetching() {
+                            label.push_str(" ⏳");
                         }
-                    });
+                        if ui.selectable_label(selected, label).clicked() {
+                            self.tab = Tab::Packages;
+                            self.selected_workspace = index;
+                        }
+                    }
+                } else {
+                    ui.selectable_value(&mut self.tab, Tab::Packages, "Packages");
                 }
+                ui.separator();
+                ui.selectable_value(&mut self.tab, Tab::Games, "Games");
+                ui.selectable_value(&mut self.tab, Tab::Compare, "Compare");
+                ui.selectable_value(&mut self.tab, Tab::History, "History");
+                ui.selectable_value(&mut self.tab, Tab::Downloads, "Downloads");
+                let job_count = jobs::list().len();
+                let jobs_label = if job_count > 0 { format!("Jobs ({})", job_count) } else { "Jobs".to_string() };
+                ui.selectable_value(&mut self.tab, Tab::Jobs, jobs_label);
+                ui.selectable_value(&mut self.tab, Tab::Templates, "Report Templates");
 
-                // Clear Button
-                if ui.button("Clear").clicked() {
-                    // Clear all fields
-                    {
-                        let mut data_lock = data_clone.lock().unwrap();
-                        *data_lock = String::new();
-                    }
-                    {
-                        let mut formatted_lock = formatted_message_clone.lock().unwrap();
-                        *formatted_lock = String::new();
-                    }
-                    {
-                        let mut pre_main_lock = pre_download_main_message_clone.lock().unwrap();
-                        *pre_main_lock = String::new();
-                    }
-                    {
-                        let mut pre_patches_lock = pre_download_patches_message_clone.lock().unwrap();
-                        *pre_patches_lock = String::new();
-                    }
-                    {
-                        let mut raw_main_lock = raw_main_data_clone.lock().unwrap();
-                        *raw_main_lock = String::new();
-                    }
-                    {
-                        let mut raw_pre_download_lock = raw_pre_download_data_clone.lock().unwrap();
-                        *raw_pre_download_lock = String::new();
-                    }
-                    {
-                        let mut error_lock = error_message_clone.lock().unwrap();
-                        *error_lock = String::new();
+                if self.workspaces.len() > 1 {
+                    ui.separator();
+                    if ui.button("Fetch All").clicked() {
+                        self.fetch_all(ctx);
                     }
                 }
+                ui.separator();
+                ui.menu_button("View", |ui| self.show_view_menu(ui));
             });
-
             ui.separator();
 
-            // Display Error Messages
-            let error_message = error_message_clone.lock().unwrap().clone();
-            if !error_message.is_empty() {
-                ui.colored_label(egui::Color32::RED, &error_message);
-                ui.separator();
-            }
-
-            // Display the main formatted message with a "Copy" button
-            let message = formatted_message_clone.lock().unwrap().clone();
-            if !message.is_empty() {
-                egui::CollapsingHeader::new("Main Data")
-                    .default_open(false) // Set to false to keep collapsed by default
-                    .show(ui, |ui| {
-                        ui.horizontal(|ui| {
-                            if ui.button("Copy").clicked() {
-                                ctx.output_mut(|o| o.copied_text = message.clone());
-                            }
-                        });
-                        ui.separator();
-                        egui::ScrollArea::vertical().show(ui, |ui| {
-                            ui.label(&message);
-                        });
-                    });
-            }
+            match self.tab {
+                Tab::Packages => {
+                    let pins_before = self.settings.pinned_packages.clone();
+                    let verify_hashes_before = self.settings.verify_hashes;
+                    let mut context = WorkspaceContext {
+                        templates: &self.settings.templates,
+                        event_sender: &self.event_sender,
+                        next_fetch_id: &mut self.next_fetch_id,
+                        discord_chunks: &mut self.discord_chunks,
+                        history: &self.history,
+                        pins: &mut self.settings.pinned_packages,
+                        toast: &mut self.toast,
+                        verify_hashes: &mut self.settings.verify_hashes,
+                        strict_parsing: self.settings.strict_parsing,
+                        share: &self.settings.share,
+                        hidden_sections: &self.settings.hidden_sections,
+                    };
+                    let workspace = &mut self.workspaces[self.selected_workspace];
+                    let open_sections_before = workspace.config.open_sections.clone();
+                    workspace.show(ui, &mut context);
 
-            // Display the pre-download main formatted message with a "Copy" button
-            let pre_main_message = pre_download_main_message_clone.lock().unwrap().clone();
-            if !pre_main_message.is_empty() {
-                egui::CollapsingHeader::new("Pre-download (Main)")
-                    .default_open(false)
-                    .show(ui, |ui| {
-                        ui.horizontal(|ui| {
-                            if ui.button("Copy").clicked() {
-                                ctx.output_mut(|o| o.copied_text = pre_main_message.clone());
-                            }
-                        });
-                        ui.separator();
-                        egui::ScrollArea::vertical().show(ui, |ui| {
-                            ui.label(&pre_main_message);
-                        });
-                    });
-            }
+                    // Which sections are open is kept per game, like the pins
+                    let mut sections_changed = false;
+                    if workspace.config.open_sections != open_sections_before {
+                        if let Some(game) = self.settings.games.get_mut(self.selected_workspace) {
+                            game.open_sections = workspace.config.open_sections.clone();
+                            sections_changed = true;
+                        }
+                    }
 
-            // Display the pre-download patches formatted message with a "Copy" button
-            let pre_patches_message = pre_download_patches_message_clone.lock().unwrap().clone();
-            if !pre_patches_message.is_empty() {
-                egui::CollapsingHeader::new("Pre-download (Patches)")
-                    .default_open(false)
-                    .show(ui, |ui| {
-                        ui.horizontal(|ui| {
-                            if ui.button("Copy").clicked() {
-                                ctx.output_mut(|o| o.copied_text = pre_patches_message.clone());
-                            }
-                        });
-                        ui.separator();
-                        egui::ScrollArea::vertical().show(ui, |ui| {
-                            ui.label(&pre_patches_message);
-                        });
-                    });
+                    // Pins and the hash choice are kept without needing an explicit save
+                    if self.settings.pinned_packages != pins_before
+                        || self.settings.verify_hashes != verify_hashes_before
+                        || sections_changed
+                    {
+                        if let Err(e) = self.settings.save() {
+                            error!("{}", e);
+                        }
+                    }
+                }
+                Tab::Games => self.show_games_tab(ui),
+                Tab::Compare => self.show_compare_tab(ui),
+                Tab::History => self.show_history_tab(ui),
+                Tab::Downloads => self.show_downloads_tab(ui),
+                Tab::Jobs => self.show_jobs_tab(ui),
+                Tab::Templates => self.show_templates_tab(ui),
             }
+        });
 
-            // Display Raw Main Data for Debugging
-            let raw_main_data = raw_main_data_clone.lock().unwrap().clone();
-            if !raw_main_data.is_empty() {
-                egui::CollapsingHeader::new("Raw Main Data")
-                    .default_open(false)
-                    .show(ui, |ui| {
-                        egui::ScrollArea::vertical().show(ui, |ui| {
-                            ui.monospace(&raw_main_data);
-                        });
-                    });
-            }
+        self.show_discord_window(ctx);
+        self.show_resume_prompt(ctx);
+        if self.show_diagnostics {
+            profiling::show_overlay(ctx);
+        }
+        profiling::record(Metric::Frame, frame_start.elapsed());
+    }
 
-            // Display Raw Pre-download Data for Debugging
-            let raw_pre_download_data = raw_pre_download_data_clone.lock().unwrap().clone();
-            if !raw_pre_download_data.is_empty() {
-                egui::CollapsingHeader::new("Raw Pre-download Data")
-                    .default_open(false)
-                    .show(ui, |ui| {
-                        egui::ScrollArea::vertical().show(ui, |ui| {
-                            ui.monospace(&raw_pre_download_data);
-                        });
-                    });
-            }
-        });
+    // Cancel every operation that takes a cancel token and give the jobs a
+    // moment to stop, so downloads leave their progress saved and nothing is
+    // left running, without hanging the exit on a job that won't finish
+    fn on_close_event(&mut self) -> bool {
+        info!("Closing, cancelling background jobs.");
+        SHUTTING_DOWN.store(true, Ordering::Relaxed);
+        if !jobs::wait_idle(SHUTDOWN_GRACE) {
+            warn!("{} job(s) still running at exit.", jobs::list().len());
+        }
+        self.downloader.save_now();
+        true
     }
 }
 
 // ----------------------
-// Main Function
+// Fetch Results
 // ----------------------
 
-fn main() -> eframe::Result<()> {
-    // Initialize the logger
-    env_logger::init();
+#[cfg(feature = "gui")]
+impl GenshinApp {
+    // Hand a worker event to the workspace that started the fetch and react to new versions
+    fn handle_fetch_event(&mut self, ctx: &egui::Context, fetch_id: u64, event: FetchEvent) {
+        let Some(workspace) = self.workspaces.iter_mut().find(|workspace| workspace.owns_fetch(fetch_id)) else {
+            debug!("Ignoring result of cancelled fetch {}", fetch_id);
+            return;
+        };
 
-    let native_options = eframe::NativeOptions::default();
-    eframe::run_native(
-        "Genshin Package Viewer",
-        native_options,
-        Box::new(|_cc| Box::new(GenshinApp::default())),
-    )
-}
+        let unknown_fields = match &event {
+            FetchEvent::Completed(result) => result.unknown_fields.clone(),
+            FetchEvent::Failed(_) => Vec::new(),
+        };
+        let completed = matches!(event, FetchEvent::Completed(_));
+        if is_url(&workspace.config.source) {
+            match &event {
+                FetchEvent::Completed(_) => self.health.record(Outcome::Ok, Utc::now()),
+                FetchEvent::Failed(failure) if failure.message != "Fetch cancelled." => {
+                    self.health.record(Outcome::of_error(&failure.message), Utc::now())
+                }
+                FetchEvent::Failed(_) => {}
+            }
+        }
+        let error_hook = match &event {
+            FetchEvent::Failed(failure) => {
+                hooks::error_hook(&self.settings.hooks, &workspace.config.source, &failure.message, &self.settings.messages)
+            }
+            FetchEvent::Completed(_) => None,
+        };
+        workspace.handle_fetch_event(event, &self.settings.templates);
+        // An auto-check counts as one failure however many of its games failed
+        if self.polled_fetches.contains(&fetch_id) {
+            if completed {
+                self.poller.succeeded();
+            } else if !self.poll_failed {
+                self.poll_failed = true;
+                self.poller.failed();
+                if self.poller.is_backing_off() {
+                    let delay = chrono::Duration::from_std(self.poller.next_delay()).unwrap_or_else(|_| chrono::Duration::zero());
+                    self.next_poll = Some(Utc::now() + delay);
+                }
+            }
+        }
+        if let Some(hook) = error_hook {
+            hooks::spawn(vec![hook], true);
+        }
+        if !completed {
+            return;
+        }
 
-// ----------------------
-// Helper Functions
-// ----------------------
+        // Auto-checks push fresh snapshots to the upload target
+        if self.polled_fetches.contains(&fetch_id) && self.settings.upload.after_auto_check && self.settings.upload.is_configured() {
+            start_upload(ctx, &self.settings, &self.upload_status, workspace.games().to_vec());
+        }
 
-// Function to fetch and process data from the API
-fn fetch_and_process_data() -> Result<(String, Option<String>), String> {
-    let url = "https://sg-hyp-api.hoyoverse.com/hyp/hyp-connect/api/getGamePackages?game_ids[]=gopR6Cufr3&launcher_id=VYTpXlbWo8";
+        // Note first sightings of new versions for the cadence statistics
+        let now = Utc::now();
+        let mut new_records = Vec::new();
+        let mut new_links = false;
+        let packages = workspace.game_packages();
+        for package in &packages {
+            new_records.extend(self.history.record(package, now));
+            new_links |= self.history.record_links(package);
+        }
+        hooks::spawn(hooks::version_hooks(&self.settings.hooks, &packages, &new_records, &self.settings.messages), true);
 
-    info!("Fetching data from URL: {}", url);
-    let response = reqwest::blocking::get(url)
-        .map_err(|e| {
-            error!("Request error: {}", e);
-            format!("Request error: {}", e)
-        })?
-        .text()
-        .map_err(|e| {
-            error!("Response text error: {}", e);
-            format!("Response text error: {}", e)
-        })?;
+        // Fields the API started sending are flagged once, when first seen
+        let new_fields = self.history.record_schema(&unknown_fields, &workspace.config.name, now);
+        if !new_fields.is_empty() {
+            let message = match new_fields.as_slice() {
+                [field] => format!("The API started returning a new field: {}", field.path),
+                _ => format!("The API started returning {} new fields; see the History tab", new_fields.len()),
+            };
+            self.toast = Some((message, now));
+        }
+
+        if new_records.is_empty() && !new_links && new_fields.is_empty() {
+            return;
+        }
+        if let Err(e) = self.history.save() {
+            error!("{}", e);
+        }
 
-    // Optional: Log the raw response for debugging
-    debug!("Raw Response: {}", response);
+        // Only auto-checks announce new pre-downloads; a manual fetch means the user is already looking
+        let new_pre_download = new_records.iter().find(|record| record.kind == VersionKind::PreDownload);
+        let Some(record) = new_pre_download.filter(|_| self.polled_fetches.contains(&fetch_id)) else {
+            return;
+        };
 
-    // Deserialize the JSON response into ApiResponse struct
-    let api_response: ApiResponse = match serde_json::from_str(&response) {
-        Ok(res) => {
-            info!("Successfully parsed JSON response.");
-            res
-        },
-        Err(e) => {
-            error!("JSON parse error: {}", e);
-            return Err(format!(
-                "JSON parse error: {}\nResponse: {}",
-                e, response
-            ));
+        if self.settings.alert_sound {
+            sound::play_alert(&self.settings.alert_sound_path);
         }
-    };
 
-    // Check if API returned an error
-    if api_response.retcode != 0 {
-        error!("API returned an error: {}", api_response.message);
-        return Err(format!("API returned an error: {}", api_response.message));
+        if self.settings.auto_copy_on_pre_download {
+            workspace.select_biz(&record.biz, &self.settings.templates);
+            let report = workspace.combined_report(self.settings.templates.language);
+            ctx.output_mut(|o| o.copied_text = report);
+            info!("Copied report for new pre-download {} {}.", record.biz, record.version);
+            let message = packages
+                .iter()
+                .find(|package| package.game.biz == record.biz)
+                .and_then(|package| Variables::new_version(package, record, &self.settings.messages))
+                .map(|vars| vars.message)
+                .unwrap_or_else(|| format!("New pre-download {} for {}", record.version, workspace.config.name));
+            self.toast = Some((format!("{}; report copied to clipboard", message), now));
+        }
     }
 
-    // Serialize main data back to JSON string for storage/display
-    let main_data = serde_json::to_string(&api_response.data.game_packages)
-        .map_err(|e| {
-            error!("Serialization error: {}", e);
-            format!("Serialization error: {}", e)
-        })?;
-
-    // Extract pre_download data if available and serialize it
-    let pre_download_data = if let Some(game_package) = api_response.data.game_packages.first() {
-        if let Some(pre_download) = &game_package.pre_download {
-            Some(
-                serde_json::to_string(pre_download)
-                    .map_err(|e| {
-                        error!("Pre-download Serialization error: {}", e);
-                        format!("Pre-download Serialization error: {}", e)
-                    })?,
-            )
-        } else {
-            None
+    // Short notification in the bottom-right corner, hidden after a few seconds
+    fn show_toast(&mut self, ctx: &egui::Context) {
+        let Some((message, raised_at)) = &self.toast else {
+            return;
+        };
+        if Utc::now() - *raised_at > chrono::Duration::seconds(TOAST_SECONDS) {
+            self.toast = None;
+            return;
         }
-    } else {
-        None
-    };
 
-    Ok((main_data, pre_download_data))
+        egui::Area::new("toast")
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -36.0])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(message);
+                });
+            });
+    }
 }
 
-// Helper function to extract Current Version from pre_download_data
-fn extract_current_version(pre_download_data: &str) -> Option<String> {
-    let pre_download: PreDownload = serde_json::from_str(pre_download_data).ok()?;
-    pre_download.major.as_ref().map(|m| m.version.clone())
-}
+// ----------------------
+// Polling and Status Bar
+// ----------------------
 
-// Helper function to convert size in bytes (as string) to gigabytes (as f64)
-fn bytes_to_gb(size_str: &str) -> f64 {
-    let bytes: f64 = size_str.parse().unwrap_or(0.0);
-    bytes / (1024.0 * 1024.0 * 1024.0)
-}
+#[cfg(feature = "gui")]
+impl GenshinApp {
+    // Hold downloads, aria2 jobs and auto-checks while the game is running,
+    // unless that has been turned off, and let them continue once it closes
+    fn hold_while_playing(&mut self, ctx: &egui::Context) {
+        self.game_watch.update(ctx, self.settings.pause_while_playing);
+        let hold = self.settings.pause_while_playing && self.game_watch.is_game_running();
+        if hold == self.on_hold {
+            return;
+        }
+        info!("{} downloads and auto-checks.", if hold { "Holding" } else { "Releasing" });
+        self.on_hold = hold;
+        self.downloader.hold(hold);
+        let aria2_rpc = self.aria2_rpc();
+        self.aria2.hold(ctx, &aria2_rpc, hold);
+    }
 
-// Function to convert main data JSON string to a formatted message
-fn convert_main_to_message(data: &str) -> String {
-    let game_packages: Vec<GamePackage> = serde_json::from_str(data).unwrap_or_default();
-    let mut output = String::new();
-
-    for game_package in game_packages {
-        if let Some(major) = game_package.main.major {
-            // Game Packages
-            output.push_str(&format!("Game Packages (Version {}):\n", major.version));
-            for (index, pkg) in major.game_pkgs.iter().enumerate() {
-                let part_number = index + 1;
-                let size_gb = bytes_to_gb(&pkg.size);
-                let decompressed_size_gb = bytes_to_gb(&pkg.decompressed_size);
-                output.push_str(&format!("[Part {}]\n", part_number));
-                output.push_str(&format!("[URL] {}\n", pkg.url));
-                output.push_str(&format!("[Size] {:.2}GB\n", size_gb));
-                output.push_str(&format!(
-                    "[Decompressed Size] {:.2}GB\n\n",
-                    decompressed_size_gb
-                ));
+    // Re-fetch every game when the auto-check interval has elapsed. While on
+    // hold a due check waits, and runs as soon as the hold is released. The
+    // interval has a minimum, some jitter, and grows while checks keep failing.
+    fn poll(&mut self, ctx: &egui::Context) {
+        if self.settings.poll_interval_minutes == 0 {
+            self.next_poll = None;
+            return;
+        }
+        if self.on_hold {
+            return;
+        }
+
+        let now = Utc::now();
+        self.poller.set_interval(std::time::Duration::from_secs(self.settings.poll_interval_minutes as u64 * 60));
+        let delay = |poller: &Poller| chrono::Duration::from_std(poller.next_delay()).unwrap_or_else(|_| chrono::Duration::zero());
+        let longest = chrono::Duration::from_std(self.poller.longest_delay()).unwrap_or_else(|_| chrono::Duration::zero());
+        match self.next_poll {
+            // Reschedule if the interval was shortened since the last check
+            Some(next_poll) if next_poll <= now + longest => {
+                if now >= next_poll {
+                    info!("Auto-check interval elapsed, fetching all games.");
+                    let first_fetch_id = self.next_fetch_id;
+                    self.fetch_all(ctx);
+                    self.polled_fetches = first_fetch_id..self.next_fetch_id;
+                    self.poll_failed = false;
+                    self.next_poll = Some(now + delay(&self.poller));
+                }
             }
+            _ => self.next_poll = Some(now + delay(&self.poller)),
+        }
+    }
 
-            // Audio Packages
-            output.push_str("Audio Packages:\n");
-            for audio_pkg in major.audio_pkgs {
-                let language_full = map_language_code(&audio_pkg.language);
-                let size_gb = bytes_to_gb(&audio_pkg.size);
-                let decompressed_size_gb = bytes_to_gb(&audio_pkg.decompressed_size);
-                output.push_str(&format!("[Language] {}\n", language_full));
-                output.push_str(&format!("[URL] {}\n", audio_pkg.url));
-                output.push_str(&format!("[Size] {:.2}GB\n", size_gb));
-                output.push_str(&format!(
-                    "[Decompressed Size] {:.2}GB\n\n",
-                    decompressed_size_gb
-                ));
+    // Fetch again the games whose last fetch found the API busy, once their
+    // retry is due
+    fn retry_busy_fetches(&mut self, ctx: &egui::Context) {
+        for workspace in &mut self.workspaces {
+            let mut context = WorkspaceContext {
+                templates: &self.settings.templates,
+                event_sender: &self.event_sender,
+                next_fetch_id: &mut self.next_fetch_id,
+                discord_chunks: &mut self.discord_chunks,
+                history: &self.history,
+                pins: &mut self.settings.pinned_packages,
+                toast: &mut self.toast,
+                verify_hashes: &mut self.settings.verify_hashes,
+                strict_parsing: self.settings.strict_parsing,
+                share: &self.settings.share,
+                hidden_sections: &self.settings.hidden_sections,
+            };
+            workspace.retry_if_due(ctx, &mut context);
+        }
+    }
+
+    // Re-check the stored links of every recorded version when the link check
+    // interval has elapsed, and alert when the CDN starts refusing any of them
+    fn poll_links(&mut self, ctx: &egui::Context) {
+        if let Some(job) = &self.link_watch {
+            let progress = job.progress.lock().unwrap_or_else(|e| e.into_inner());
+            if !progress.finished {
+                return;
             }
-        } else {
-            output.push_str("No major version data available.\n");
+            let newly_gone = self.history.update_links(&progress.checks, Utc::now());
+            drop(progress);
+            self.link_watch = None;
+            if let Err(e) = self.history.save() {
+                error!("{}", e);
+            }
+            self.alert_gone_links(&newly_gone);
+            return;
+        }
+
+        if self.on_hold || self.settings.link_check_interval_hours == 0 || self.history.links.is_empty() {
+            return;
+        }
+        let interval = chrono::Duration::hours(self.settings.link_check_interval_hours as i64);
+        if self.history.last_link_check.is_none_or(|last| Utc::now() - last >= interval) {
+            self.check_stored_links(ctx);
         }
     }
 
-    output
-}
+    // Start a re-check of every stored link on a worker thread
+    fn check_stored_links(&mut self, ctx: &egui::Context) {
+        let files: Vec<PackageFile> = self.history.links.iter().map(|link| link.file.clone()).collect();
+        let progress = Arc::new(Mutex::new(LinkProgress {
+            total: files.len(),
+            ..Default::default()
+        }));
+        let cancel = CancelToken::default();
+        self.link_watch = Some(LinkCheckJob {
+            progress: progress.clone(),
+            cancel: cancel.clone(),
+        });
 
-// Function to convert pre-download (Main) data JSON string to a formatted message
-fn convert_pre_download_main_to_message(pre_download_data: &str) -> String {
-    let pre_download: PreDownload = serde_json::from_str(pre_download_data)
-        .unwrap_or(PreDownload {
-            major: None,
-            patches: Vec::new(),
-            res_list_url: None,
+        let ctx = ctx.clone();
+        let label = format!("Re-check {} stored links", files.len());
+        jobs::spawn(JobKind::LinkCheck, label, Some(cancel.clone()), move || {
+            info!("Re-checking {} stored links.", files.len());
+            linkcheck::check_links(&files, &progress, &|| {}, &|| cancel.is_cancelled());
+            if let Ok(mut progress) = progress.lock() {
+                progress.finished = true;
+            }
+            ctx.request_repaint();
         });
+    }
 
-    if let Some(major) = &pre_download.major {
-        let mut output = String::new();
+    // Re-hash the archived downloads when the archive check interval has
+    // elapsed, and alert when any of them went missing or rotted
+    fn poll_archive(&mut self, ctx: &egui::Context) {
+        if let Some(job) = &self.archive_check {
+            let progress = job.progress.lock().unwrap_or_else(|e| e.into_inner());
+            if !progress.finished {
+                return;
+            }
+            // A stopped check is kept on screen but doesn't count as a run
+            if !job.cancel.is_cancelled() {
+                self.history.record_archive_check(&progress, Utc::now());
+                if let Err(e) = self.history.save() {
+                    error!("{}", e);
+                }
+                let problems = progress.problems().len();
+                if problems > 0 {
+                    if self.settings.alert_sound {
+                        sound::play_alert(&self.settings.alert_sound_path);
+                    }
+                    self.toast = Some((
+                        format!("Archive check: {} file(s) damaged or missing (see Downloads)", problems),
+                        Utc::now(),
+                    ));
+                }
+            }
+            return;
+        }
 
-        // Game Packages
-        output.push_str(&format!("Pre-download Game Packages (Version {}):\n", major.version));
-        for (index, pkg) in major.game_pkgs.iter().enumerate() {
-            let part_number = index + 1;
-            let size_gb = bytes_to_gb(&pkg.size);
-            let decompressed_size_gb = bytes_to_gb(&pkg.decompressed_size);
-            output.push_str(&format!("[Part {}]\n", part_number));
-            output.push_str(&format!("[URL] {}\n", pkg.url));
-            output.push_str(&format!("[Size] {:.2}GB\n", size_gb));
-            output.push_str(&format!(
-                "[Decompressed Size] {:.2}GB\n\n",
-                decompressed_size_gb
-            ));
+        if self.on_hold || self.settings.archive_check_interval_days == 0 {
+            return;
         }
+        let interval = chrono::Duration::days(self.settings.archive_check_interval_days as i64);
+        if self.history.last_archive_check().is_none_or(|last| Utc::now() - last >= interval) {
+            self.check_archive(ctx);
+        }
+    }
 
-        // Audio Packages
-        output.push_str("Pre-download Audio Packages:\n");
-        for audio_pkg in &major.audio_pkgs {
-            let language_full = map_language_code(&audio_pkg.language);
-            let size_gb = bytes_to_gb(&audio_pkg.size);
-            let decompressed_size_gb = bytes_to_gb(&audio_pkg.decompressed_size);
-            output.push_str(&format!("[Language] {}\n", language_full));
-            output.push_str(&format!("[URL] {}\n", audio_pkg.url));
-            output.push_str(&format!("[Size] {:.2}GB\n", size_gb));
-            output.push_str(&format!(
-                "[Decompressed Size] {:.2}GB\n\n",
-                decompressed_size_gb
-            ));
+    // Start a re-hash of every file listed in the manifests of the download folder
+    fn check_archive(&mut self, ctx: &egui::Context) {
+        let root = download::download_dir(&self.settings.download_folder);
+        let progress = Arc::new(Mutex::new(ArchiveProgress::default()));
+        let cancel = CancelToken::default();
+        self.archive_check = Some(ArchiveJob {
+            root: root.clone(),
+            progress: progress.clone(),
+            cancel: cancel.clone(),
+        });
+
+        let ctx = ctx.clone();
+        let label = format!("Check archive {}", root.display());
+        jobs::spawn(JobKind::Hashing, label, Some(cancel.clone()), move || {
+            archive::check_archive(&root, &progress, &|| cancel.is_cancelled());
+            if let Ok(mut progress) = progress.lock() {
+                progress.finished = true;
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    // Tell the user which versions just started disappearing from the CDN
+    fn alert_gone_links(&mut self, newly_gone: &[history::StoredLink]) {
+        let mut versions: Vec<String> = Vec::new();
+        for link in newly_gone {
+            let version = format!("{} {}", map_biz_code(&link.biz), link.file.version);
+            if !versions.contains(&version) {
+                versions.push(version);
+            }
+        }
+        if versions.is_empty() {
+            return;
         }
 
-        output
-    } else {
-        "No pre-download major version data available.".to_string()
+        if self.settings.alert_sound {
+            sound::play_alert(&self.settings.alert_sound_path);
+        }
+        self.toast = Some((
+            format!(
+                "{} link(s) of {} are no longer served by the CDN",
+                newly_gone.len(),
+                versions.join(", ")
+            ),
+            Utc::now(),
+        ));
     }
-}
 
-// Function to convert pre-download (Patches) data JSON string to a formatted message
-fn convert_pre_download_patches_to_message(pre_download_data: &str, current_version: &str) -> String {
-    let pre_download: PreDownload = serde_json::from_str(pre_download_data)
-        .unwrap_or(PreDownload {
-            major: None,
-            patches: Vec::new(),
-            res_list_url: None,
+    // "Last fetched 4m ago · next auto-check in 56m · API OK" for the shown game
+    fn show_status_bar(&self, ctx: &egui::Context) {
+        let now = Utc::now();
+        let workspace = &self.workspaces[self.selected_workspace];
+        let mut parts = Vec::new();
+
+        if workspace.is_fetching() {
+            parts.push("Fetching...".to_string());
+        } else {
+            match workspace.last_fetch() {
+                Some((fetched_at, _)) => {
+                    parts.push(format!("Last fetched {} ago", history::format_duration(now - fetched_at)))
+                }
+                None => parts.push("Not fetched yet".to_string()),
+            }
+        }
+
+        match self.next_poll {
+            Some(next_poll) => parts.push(format!(
+                "next auto-check in {}",
+                history::format_duration((next_poll - now).max(chrono::Duration::zero()))
+            )),
+            None => parts.push("auto-check off".to_string()),
+        }
+        if self.poller.is_backing_off() && self.next_poll.is_some() {
+            parts.push(format!("{} auto-checks failed, checking less often", self.poller.failures()));
+        }
+        if self.on_hold {
+            parts.push("game running, downloads and auto-checks paused".to_string());
+        }
+
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(parts.join(" · "));
+                self.health.show(ui);
+                if network::current().accept_invalid_certs {
+                    status_label(ui, Status::Error, "TLS certificate verification is off");
+                }
+            });
         });
+    }
 
-    if !pre_download.patches.is_empty() {
-        let mut output = String::new();
-        output.push_str("Pre-download Patches:\n\n");
+    // egui only redraws on input or when asked, so wake up for the next timed
+    // change: a relative time in the status bar or History tab ticking over,
+    // the toast expiring, or an auto-check or link check falling due. Workers
+    // ask for a repaint themselves when they have something new to show.
+    fn schedule_repaint(&self, ctx: &egui::Context) {
+        let now = Utc::now();
+        let mut waits = Vec::new();
 
-        for patch in &pre_download.patches {
-            // Add heading for each version
-            let patch_version_short = patch.version.trim_end_matches(".0").to_string(); // e.g., "5.0.0" -> "5.0"
-            output.push_str(&format!("# Version {}\n", patch_version_short));
-            // Version line: Previous Version to Current Version
-            output.push_str(&format!("Version: {} to {}\n", patch.version, current_version));
-            // Game Patch URLs
-            for pkg in &patch.game_pkgs {
-                let size_gb = bytes_to_gb(&pkg.size);
-                let decompressed_size_gb = bytes_to_gb(&pkg.decompressed_size);
-                output.push_str(&format!("[Game Patch URL] {}\n", pkg.url));
-                output.push_str(&format!("[Size] {:.2}GB\n", size_gb));
-                output.push_str(&format!(
-                    "[Decompressed Size] {:.2}GB\n\n",
-                    decompressed_size_gb
-                ));
+        if let Some((fetched_at, _)) = self.workspaces[self.selected_workspace].last_fetch() {
+            waits.push(history::until_label_changes(now - fetched_at, false));
+        }
+        // Retries run in the background too, so every workspace's counts
+        for retry_at in self.workspaces.iter().filter_map(|workspace| workspace.retry_at()) {
+            waits.push(history::until_label_changes(retry_at - now, true));
+        }
+        // Held checks wait for the game watch to report that the game closed
+        if let Some(next_poll) = self.next_poll.filter(|_| !self.on_hold) {
+            waits.push(history::until_label_changes(next_poll - now, true));
+        }
+        if let Some((_, raised_at)) = &self.toast {
+            let remaining = *raised_at + chrono::Duration::seconds(TOAST_SECONDS) - now;
+            waits.push(remaining.to_std().unwrap_or_default() + std::time::Duration::from_millis(10));
+        }
+        if let Some(last) = self.history.last_link_check {
+            if self.tab == Tab::History {
+                waits.push(history::until_label_changes(now - last, false));
             }
-            // Audio Patch URLs
-            for audio_pkg in &patch.audio_pkgs {
-                let language_full = map_language_code(&audio_pkg.language);
-                let size_gb = bytes_to_gb(&audio_pkg.size);
-                let decompressed_size_gb = bytes_to_gb(&audio_pkg.decompressed_size);
-                output.push_str(&format!("[Audio Patch Language] {}\n", language_full));
-                output.push_str(&format!("[URL] {}\n", audio_pkg.url));
-                output.push_str(&format!("[Size] {:.2}GB\n", size_gb));
-                output.push_str(&format!(
-                    "[Decompressed Size] {:.2}GB\n\n",
-                    decompressed_size_gb
-                ));
+            let idle = !self.on_hold && self.link_watch.is_none() && !self.history.links.is_empty();
+            if idle && self.settings.link_check_interval_hours > 0 {
+                let due = last + chrono::Duration::hours(self.settings.link_check_interval_hours as i64);
+                waits.push((due - now).to_std().unwrap_or_default());
+            }
+        }
+        if !self.on_hold && self.archive_check.is_none() && self.settings.archive_check_interval_days > 0 {
+            if let Some(last) = self.history.last_archive_check() {
+                let due = last + chrono::Duration::days(self.settings.archive_check_interval_days as i64);
+                waits.push((due - now).to_std().unwrap_or_default());
             }
         }
 
-        output
-    } else {
-        String::new()
+        if let Some(wait) = waits.into_iter().min() {
+            ctx.request_repaint_after(wait);
+        }
     }
 }
 
 // ----------------------
-// Language Mapping Function
+// Games Tab
 // ----------------------
 
-// Function to map language codes to full names
-fn map_language_code(code: &str) -> String {
-    match code.to_lowercase().as_str() {
-        "zh-cn" => "Chinese".to_string(),
-        "en-us" => "English".to_string(),
-        "ja-jp" => "Japanese".to_string(),
-        "ko-kr" => "Korean".to_string(),
-        other => other.to_string(), // Fallback to the original code if not matched
-    }
+#[cfg(feature = "gui")]
+impl GenshinApp {
+    fn show_games_tab(&mut self, ui: &mut egui::Ui) {
+        self.show_games_list(ui);
+        ui.separator();
+
+        // What happens when an auto-check finds a new pre-download
+        ui.strong("New Pre-download Alerts");
+        ui.checkbox(
+            &mut self.settings.auto_copy_on_pre_download,
+            "Copy report when a new pre-download is found",
+        );
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.settings.alert_sound, "Play a sound");
+            labelled(
+                ui,
+                "Sound file:",
+                egui::TextEdit::singleline(&mut self.settings.alert_sound_path)
+                    .hint_text("WAV, MP3 or OGG; empty for a beep")
+                    .desired_width(320.0),
+            );
+            if ui.button("Test").clicked() {
+                sound::play_alert(&self.settings.alert_sound_path);
+            }
+        });
+
+        ui.separator();
+
+        ui.strong("Appearance");
+        ui.horizontal(|ui| {
+            ui.label("Theme:");
+            let before = self.settings.theme;
+            egui::ComboBox::from_id_source("theme")
+                .selected_text(self.settings.theme.label())
+                .show_ui(ui, |ui| {
+                    for theme in Theme::ALL {
+                        ui.selectable_value(&mut self.settings.theme, theme, theme.label());
+                    }
+                });
+            if self.settings.theme != before {
+                self.settings.theme.apply(ui.ctx());
+                if let Err(e) = self.settings.save() {
+                    error!("{}", e);
+                }
+            }
+        });
+        ui.label("High contrast uses white on black and status colours that stay distinct with colour blindness.");
+
+        ui.separator();
+
+        // Fields the models don't know only raise warnings unless strict mode is on
+        ui.strong("Parsing");
+        let strict = ui
+            .checkbox(&mut self.settings.strict_parsing, "Strict mode: reject sections with unknown or missing fields")
+            .on_hover_text("For checking new API payloads; normally such fields are listed under Warnings and the rest is shown");
+        if strict.changed() {
+            if let Err(e) = self.settings.save() {
+                error!("{}", e);
+            }
+        }
+
+        ui.separator();
+        self.show_language_names(ui);
+
+        ui.separator();
+
+        // Paste service behind the Share menu of each game
+        ui.strong("Sharing");
+        ui.horizontal(|ui| {
+            ui.label("Service:");
+            let before = self.settings.share.service;
+            egui::ComboBox::from_id_source("share_service")
+                .selected_text(self.settings.share.service.label())
+                .show_ui(ui, |ui| {
+                    for service in ShareService::ALL {
+                        ui.selectable_value(&mut self.settings.share.service, service, service.label());
+                    }
+                });
+            let key = labelled(
+                ui,
+                "Key:",
+                egui::TextEdit::singleline(&mut self.settings.share.token)
+                    .password(true)
+                    .hint_text(self.settings.share.service.token_hint())
+                    .desired_width(320.0),
+            );
+            if self.settings.share.service != before || key.lost_focus() {
+                if let Err(e) = self.settings.save() {
+                    error!("{}", e);
+                }
+            }
+        });
+        ui.label("Gists are created secret and pastes unlisted: anyone with the link can open them.");
+
+        ui.separator();
+        self.show_upload_target(ui);
+
+        ui.separator();
+        self.show_hooks(ui);
+
+        ui.separator();
+        self.show_network(ui);
+    }
+
+    // Extra root certificates, no certificate checks at all as a last resort,
+    // the IP version and the local address connections are made from
+    fn show_network(&mut self, ui: &mut egui::Ui) {
+        ui.strong("Network");
+        let network = &mut self.settings.network;
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= labelled(
+                ui,
+                "CA bundle:",
+                egui::TextEdit::singleline(&mut network.ca_bundle)
+                    .hint_text("PEM file with your proxy's root certificate")
+                    .desired_width(420.0),
+            )
+            .lost_focus();
+        });
+        ui.label("Behind a TLS-inspecting corporate proxy, ask IT for its root certificate and point this at it.");
+        changed |= ui
+            .checkbox(&mut network.accept_invalid_certs, "Skip certificate verification (unsafe)")
+            .on_hover_text("Only if a CA bundle doesn't help: responses and downloads could then be read and changed by anyone in between")
+            .changed();
+        if network::current().accept_invalid_certs {
+            status_label(ui, Status::Error, network::INSECURE_WARNING);
+        }
+        ui.horizontal(|ui| {
+            ui.label("IP version:");
+            let before = network.ip_family;
+            egui::ComboBox::from_id_source("ip_family")
+                .selected_text(network.ip_family.label())
+                .show_ui(ui, |ui| {
+                    for family in IpFamily::ALL {
+                        ui.selectable_value(&mut network.ip_family, family, family.label());
+                    }
+                });
+            changed |= network.ip_family != before;
+            changed |= labelled(
+                ui,
+                "Bind to:",
+                egui::TextEdit::singleline(&mut network.bind)
+                    .hint_text("local IP address or interface, e.g. eth1")
+                    .desired_width(240.0),
+            )
+            .lost_focus();
+        });
+        if let Err(e) = network::local_address(network) {
+            status_label(ui, Status::Error, &e);
+        }
+        ui.horizontal(|ui| {
+            changed |= labelled(
+                ui,
+                "DNS over HTTPS:",
+                egui::TextEdit::singleline(&mut network.doh_url)
+                    .hint_text("empty for the system resolver")
+                    .desired_width(320.0),
+            )
+            .lost_focus();
+            ui.menu_button("Presets", |ui| {
+                for (name, url) in network::DOH_PRESETS {
+                    if ui.button(format!("{} ({})", name, url)).clicked() {
+                        network.doh_url = url.to_string();
+                        changed = true;
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+        ui.label("If your ISP's DNS gives wrong addresses for HoYoverse hosts, look them up here instead. Any endpoint with the JSON API works; an IP address in its URL (e.g. https://1.1.1.1/dns-query) needs no lookup itself.");
+        if changed {
+            network::configure(network);
+            if let Err(e) = self.settings.save() {
+                error!("{}", e);
+            }
+        }
+    }
+
+    // Shell commands run on new versions and failed fetches
+    fn show_hooks(&mut self, ui: &mut egui::Ui) {
+        ui.strong("Hooks");
+        ui.label("Run through the shell with the variables below set as $EVENT, $VERSION, $TOTAL_SIZE and so on, plus $URLS_FILE (a file listing the links, one per line). Commands and messages can also use them as {{version}}.");
+        let mut lost_focus = false;
+        let messages = &mut self.settings.messages;
+        egui::Grid::new("messages_grid").num_columns(2).show(ui, |ui| {
+            for (label, template) in [
+                ("New version message:", &mut messages.new_version),
+                ("New pre-download message:", &mut messages.new_pre_download),
+                ("Fetch error message:", &mut messages.fetch_error),
+            ] {
+                lost_focus |= labelled(ui, label, egui::TextEdit::singleline(template).desired_width(420.0)).lost_focus();
+                ui.end_row();
+            }
+        });
+        let hooks = &mut self.settings.hooks;
+        egui::Grid::new("hooks_grid").num_columns(2).show(ui, |ui| {
+            for (label, command, hint) in [
+                ("New version:", &mut hooks.new_version, "e.g. notify-send \"$GAME $VERSION is live\""),
+                ("New pre-download:", &mut hooks.new_pre_download, "e.g. aria2c -i \"$URLS_FILE\""),
+                ("Fetch error:", &mut hooks.fetch_error, "e.g. logger \"scanner: $ERROR\""),
+            ] {
+                lost_focus |= labelled(ui, label, egui::TextEdit::singleline(command).hint_text(hint).desired_width(420.0)).lost_focus();
+                ui.end_row();
+            }
+        });
+        egui::CollapsingHeader::new("Hook and Message Variables")
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.monospace(variables::VARIABLES_HELP);
+            });
+        if lost_focus {
+            if let Err(e) = self.settings.save() {
+                error!("{}", e);
+            }
+        }
+    }
+
+    // S3 bucket or WebDAV share that JSON snapshots and HTML reports are uploaded to
+    fn show_upload_target(&mut self, ui: &mut egui::Ui) {
+        ui.strong("Upload");
+        ui.label("Each game is uploaded as <biz>.json (its packages) and <biz>.html (the report), replacing the previous upload.");
+        let target = &mut self.settings.upload;
+        egui::Grid::new("upload_target_grid").num_columns(2).show(ui, |ui| {
+            ui.label("Service:");
+            egui::ComboBox::from_id_source("upload_backend")
+                .selected_text(target.backend.label())
+                .show_ui(ui, |ui| {
+                    for backend in UploadBackend::ALL {
+                        ui.selectable_value(&mut target.backend, backend, backend.label());
+                    }
+                });
+            ui.end_row();
+            if target.backend == UploadBackend::Off {
+                return;
+            }
+
+            let (url_hint, user_label, password_label) = match target.backend {
+                UploadBackend::S3 => ("https://my-bucket.s3.eu-central-1.amazonaws.com/genshin", "Access key ID:", "Secret access key:"),
+                _ => ("https://dav.example.org/remote.php/dav/files/me/genshin", "User:", "Password:"),
+            };
+            labelled(ui, "URL:", egui::TextEdit::singleline(&mut target.url).hint_text(url_hint).desired_width(420.0));
+            ui.end_row();
+            if target.backend == UploadBackend::S3 {
+                labelled(ui, "Region:", egui::TextEdit::singleline(&mut target.region).desired_width(160.0));
+                ui.end_row();
+            }
+            labelled(ui, user_label, egui::TextEdit::singleline(&mut target.username).desired_width(240.0));
+            ui.end_row();
+            labelled(ui, password_label, egui::TextEdit::singleline(&mut target.password).password(true).desired_width(240.0));
+            ui.end_row();
+        });
+        if target.backend == UploadBackend::Off {
+            return;
+        }
+        ui.checkbox(&mut target.after_auto_check, "Upload after each auto-check");
+
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                self.settings_status = match self.settings.save() {
+                    Ok(()) => "Upload settings saved.".to_string(),
+                    Err(e) => e,
+                };
+            }
+            let configured = self.settings.upload.is_configured();
+            if ui.add_enabled(configured, egui::Button::new("Upload Now")).clicked() {
+                let games: Vec<GameData> = self.workspaces.iter().flat_map(|workspace| workspace.games().to_vec()).collect();
+                if games.is_empty() {
+                    *self.upload_status.lock().unwrap_or_else(|e| e.into_inner()) = "Nothing fetched to upload yet.".to_string();
+                } else {
+                    start_upload(ui.ctx(), &self.settings, &self.upload_status, games);
+                }
+            }
+            let status = self.upload_status.lock().unwrap_or_else(|e| e.into_inner());
+            if !status.is_empty() {
+                ui.label(status.as_str());
+            }
+        });
+    }
+
+    // Names shown for audio language codes in reports, comparisons and package lists
+    fn show_language_names(&mut self, ui: &mut egui::Ui) {
+        ui.strong("Language Names");
+        ui.label("Audio language codes in the response are shown by these names. Codes missing here are shown as they are and listed under Warnings.");
+        let mut remove = None;
+        egui::Grid::new("language_names_grid").num_columns(3).striped(true).show(ui, |ui| {
+            ui.strong("Code");
+            ui.strong("Name");
+            ui.end_row();
+
+            for (index, entry) in self.settings.language_names.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(&mut entry.code).hint_text("e.g. zh-tw").desired_width(80.0));
+                ui.add(egui::TextEdit::singleline(&mut entry.name).desired_width(200.0));
+                if ui.button("Remove").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = remove {
+            self.settings.language_names.remove(index);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add Language").clicked() {
+                self.settings.language_names.push(languages::LanguageName {
+                    code: String::new(),
+                    name: String::new(),
+                });
+            }
+            if ui.button("Reset to Defaults").clicked() {
+                self.settings.language_names = languages::default_names();
+            }
+            if ui.button("Save").clicked() {
+                languages::set_names(&self.settings.language_names);
+                self.settings_status = match self.settings.save() {
+                    Ok(()) => "Language names saved.".to_string(),
+                    Err(e) => e,
+                };
+                for workspace in &mut self.workspaces {
+                    workspace.refresh_reports(&self.settings.templates);
+                }
+            }
+        });
+    }
+
+    // Sections of the Packages tab to show. Hidden ones stay hidden after
+    // every fetch until they're ticked again.
+    fn show_view_menu(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        for (key, title, _) in workspace::SECTIONS {
+            let mut shown = !self.settings.hidden_sections.iter().any(|section| section == key);
+            if ui.checkbox(&mut shown, title).changed() {
+                self.settings.hidden_sections.retain(|section| section != key);
+                if !shown {
+                    self.settings.hidden_sections.push(key.to_string());
+                }
+                changed = true;
+            }
+        }
+        ui.separator();
+        if ui.add_enabled(!self.settings.hidden_sections.is_empty(), egui::Button::new("Show All")).clicked() {
+            self.settings.hidden_sections.clear();
+            changed = true;
+            ui.close_menu();
+        }
+        if changed {
+            if let Err(e) = self.settings.save() {
+                error!("{}", e);
+            }
+        }
+    }
+
+    fn show_games_list(&mut self, ui: &mut egui::Ui) {
+        ui.label("Each game gets its own tab with its own source, fetch status and reports. The source is an API URL or the path to a saved JSON response.");
+        ui.separator();
+
+        let can_remove = self.workspaces.len() > 1;
+        let mut remove = None;
+        egui::Grid::new("games_grid").num_columns(3).striped(true).show(ui, |ui| {
+            ui.strong("Name");
+            ui.strong("Source");
+            ui.end_row();
+
+            for (index, workspace) in self.workspaces.iter_mut().enumerate() {
+                ui.text_edit_singleline(&mut workspace.config.name);
+                ui.add(egui::TextEdit::singleline(&mut workspace.config.source).desired_width(480.0));
+                if ui.add_enabled(can_remove, egui::Button::new("Remove")).clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(index) = remove {
+            let mut workspace = self.workspaces.remove(index);
+            workspace.cancel_fetch();
+            self.selected_workspace = self.selected_workspace.min(self.workspaces.len() - 1);
+            self.compare_left = self.compare_left.min(self.workspaces.len() - 1);
+            self.compare_right = self.compare_right.min(self.workspaces.len() - 1);
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Add Game").clicked() {
+                self.workspaces.push(GameWorkspace::new(GameConfig {
+                    name: format!("Game {}", self.workspaces.len() + 1),
+                    source: String::new(),
+                    ..GameConfig::default()
+                }));
+            }
+            labelled(ui, "Auto-check every", egui::DragValue::new(&mut self.settings.poll_interval_minutes).clamp_range(0..=1440));
+            ui.label(format!("minutes (0 = off, at least {})", polling::MIN_INTERVAL.as_secs() / 60));
+            ui.separator();
+            if ui.button("Save").clicked() {
+                self.settings.games = self.workspaces.iter().map(|workspace| workspace.config.clone()).collect();
+                self.settings_status = match self.settings.save() {
+                    Ok(()) => "Games saved.".to_string(),
+                    Err(e) => e,
+                };
+            }
+            if !self.settings_status.is_empty() {
+                ui.label(&self.settings_status);
+            }
+        });
+    }
+}
+
+// ----------------------
+// Compare Tab
+// ----------------------
+
+#[cfg(feature = "gui")]
+impl GenshinApp {
+    fn show_compare_tab(&mut self, ui: &mut egui::Ui) {
+        if self.workspaces.len() < 2 {
+            ui.label("Comparing needs two games, e.g. the global and CN releases.");
+            if ui.button("Add Genshin Impact (China)").clicked() {
+                self.workspaces.push(GameWorkspace::new(GameConfig {
+                    name: "Genshin Impact (China)".to_string(),
+                    source: CN_API_URL.to_string(),
+                    ..GameConfig::default()
+                }));
+                self.compare_left = 0;
+                self.compare_right = self.workspaces.len() - 1;
+            }
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            for (id, selected) in [("compare_left", &mut self.compare_left), ("compare_right", &mut self.compare_right)] {
+                egui::ComboBox::from_id_source(id)
+                    .selected_text(&self.workspaces[*selected].config.name)
+                    .show_ui(ui, |ui| {
+                        for (index, workspace) in self.workspaces.iter().enumerate() {
+                            ui.selectable_value(selected, index, &workspace.config.name);
+                        }
+                    });
+            }
+            if ui.button("Fetch Both").clicked() {
+                let ctx = ui.ctx().clone();
+                for index in [self.compare_left, self.compare_right] {
+                    if self.workspaces[index].is_fetching() {
+                        continue;
+                    }
+                    let mut context = WorkspaceContext {
+                        templates: &self.settings.templates,
+                        event_sender: &self.event_sender,
+                        next_fetch_id: &mut self.next_fetch_id,
+                        discord_chunks: &mut self.discord_chunks,
+                        history: &self.history,
+                        pins: &mut self.settings.pinned_packages,
+                        toast: &mut self.toast,
+                        verify_hashes: &mut self.settings.verify_hashes,
+                        strict_parsing: self.settings.strict_parsing,
+                        share: &self.settings.share,
+                        hidden_sections: &self.settings.hidden_sections,
+                    };
+                    self.workspaces[index].start_fetch(&ctx, &mut context);
+                }
+            }
+            if self.workspaces[self.compare_left].is_fetching() || self.workspaces[self.compare_right].is_fetching() {
+                ui.spinner();
+            }
+        });
+        ui.separator();
+
+        let left = self.workspaces[self.compare_left].game_package();
+        let right = self.workspaces[self.compare_right].game_package();
+        let (Some(left), Some(right)) = (left, right) else {
+            ui.label("Fetch both games to compare them.");
+            return;
+        };
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("compare_grid").num_columns(3).striped(true).show(ui, |ui| {
+                ui.strong("");
+                ui.strong(left.game.label());
+                ui.strong(right.game.label());
+                ui.end_row();
+
+                for row in compare::compare(left, right) {
+                    ui.label(&row.item);
+                    if row.differs {
+                        // Marked with a symbol too, for readers who can't tell the colour apart
+                        let color = ui.visuals().warn_fg_color;
+                        ui.colored_label(color, format!("≠ {}", row.left));
+                        ui.colored_label(color, format!("≠ {}", row.right));
+                    } else {
+                        ui.label(&row.left);
+                        ui.label(&row.right);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
+
+// ----------------------
+// History Tab
+// ----------------------
+
+#[cfg(feature = "gui")]
+impl GenshinApp {
+    fn show_history_tab(&mut self, ui: &mut egui::Ui) {
+        let games = self.history.games();
+        if games.is_empty() {
+            ui.label("No versions recorded yet. Versions are recorded each time data is fetched.");
+            return;
+        }
+
+        // Periodic re-check of the links of older versions
+        let interval_before = self.settings.link_check_interval_hours;
+        ui.horizontal(|ui| {
+            labelled(
+                ui,
+                "Re-check stored links every",
+                egui::DragValue::new(&mut self.settings.link_check_interval_hours).clamp_range(0..=720),
+            );
+            ui.label("hours (0 = off)");
+            let checking = self.link_watch.is_some();
+            if ui.add_enabled(!checking && !self.history.links.is_empty(), egui::Button::new("Check Now")).clicked() {
+                self.check_stored_links(ui.ctx());
+            }
+            if let Some(job) = &self.link_watch {
+                ui.spinner();
+                if ui.add_enabled(!job.cancel.is_cancelled(), egui::Button::new("Stop")).clicked() {
+                    job.cancel.cancel();
+                }
+            } else if let Some(last) = self.history.last_link_check {
+                ui.label(format!("Last checked {} ago", history::format_duration(Utc::now() - last)));
+            }
+        });
+        if self.settings.link_check_interval_hours != interval_before {
+            if let Err(e) = self.settings.save() {
+                error!("{}", e);
+            }
+        }
+
+        // Summary of a month as Markdown
+        ui.horizontal(|ui| {
+            ui.label("Activity report for");
+            egui::ComboBox::from_id_source("activity_month")
+                .selected_text(activity::month_label(self.activity_month))
+                .show_ui(ui, |ui| {
+                    for month in activity::months(&self.history) {
+                        ui.selectable_value(&mut self.activity_month, month, activity::month_label(month));
+                    }
+                });
+            if ui.button("Copy").clicked() {
+                let report = activity::monthly_report(&self.history, self.activity_month);
+                ui.ctx().output_mut(|o| o.copied_text = report);
+                self.toast = Some(("Copied the activity report".to_string(), Utc::now()));
+            }
+            if ui.button("Export Markdown").clicked() {
+                let message = match activity::export_report(&self.history, self.activity_month) {
+                    Ok(path) => format!("Exported to {}", path.display()),
+                    Err(e) => e,
+                };
+                self.toast = Some((message, Utc::now()));
+            }
+        });
+        ui.separator();
+
+        // Fields the API has started sending that the models don't know
+        if !self.history.schema_fields.is_empty() {
+            egui::CollapsingHeader::new(format!("API Schema Changes ({})", self.history.schema_fields.len()))
+                .default_open(false)
+                .show(ui, |ui| {
+                    if ui.button("Copy").clicked() {
+                        let fields: Vec<&str> = self.history.schema_fields.iter().map(|field| field.path.as_str()).collect();
+                        ui.ctx().output_mut(|o| o.copied_text = fields.join("\n"));
+                    }
+                    egui::Grid::new("schema_fields").num_columns(3).striped(true).show(ui, |ui| {
+                        ui.strong("Field");
+                        ui.strong("First Seen");
+                        ui.strong("Seen In");
+                        ui.end_row();
+                        for field in &self.history.schema_fields {
+                            ui.monospace(&field.path);
+                            ui.label(field.first_seen.format("%Y-%m-%d %H:%M UTC").to_string());
+                            ui.label(&field.source);
+                            ui.end_row();
+                        }
+                    });
+                });
+            ui.separator();
+        }
+
+        let mut save = false;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for biz in games {
+                ui.heading(format!("{} ({})", map_biz_code(&biz), biz));
+                egui::Grid::new(format!("history_{}", biz)).num_columns(5).striped(true).show(ui, |ui| {
+                    ui.strong("Version");
+                    ui.strong("Pre-download Seen");
+                    ui.strong("Live Seen");
+                    ui.strong("Links");
+                    ui.strong("Notes");
+                    ui.end_row();
+
+                    let format_seen = |seen: Option<DateTime<Utc>>| {
+                        seen.map(|seen| seen.format("%Y-%m-%d %H:%M UTC").to_string())
+                            .unwrap_or_else(|| "-".to_string())
+                    };
+                    for timeline in self.history.versions(&biz) {
+                        ui.label(&timeline.version);
+                        ui.label(format_seen(timeline.pre_download_seen));
+                        ui.label(format_seen(timeline.live_seen));
+                        match self.history.link_health(&biz, &timeline.version) {
+                            Some(health) if health.gone > 0 => {
+                                status_label(ui, Status::Error, format!("{} of {} gone", health.gone, health.total))
+                                    .on_hover_text(format!("Refused by the CDN since {}", format_seen(health.gone_since)));
+                            }
+                            Some(health) if health.checked == 0 => {
+                                ui.label(format!("{} not checked yet", health.total));
+                            }
+                            Some(health) => {
+                                ui.label(format!("{} OK", health.total));
+                            }
+                            None => {
+                                ui.label("-");
+                            }
+                        }
+                        let note = self.history.note_mut(&biz, &timeline.version);
+                        let response = ui.add(
+                            egui::TextEdit::singleline(note)
+                                .hint_text("e.g. links went live at 03:12 UTC")
+                                .desired_width(360.0),
+                        );
+                        // Notes are saved once editing finishes
+                        save |= response.lost_focus();
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+            }
+        });
+
+        if save {
+            self.history.prune_notes();
+            if let Err(e) = self.history.save() {
+                error!("{}", e);
+            }
+        }
+    }
+}
+
+// ----------------------
+// Downloads Tab
+// ----------------------
+
+#[cfg(feature = "gui")]
+impl GenshinApp {
+    fn show_downloads_tab(&mut self, ui: &mut egui::Ui) {
+        let settings_before = (
+            self.settings.download_folder.clone(),
+            self.settings.download_connections,
+            self.settings.bandwidth_limit_mb,
+            self.settings.pause_while_playing,
+        );
+        ui.horizontal(|ui| {
+            labelled(
+                ui,
+                "Folder:",
+                egui::TextEdit::singleline(&mut self.settings.download_folder)
+                    .hint_text(download::download_dir("").display().to_string())
+                    .desired_width(360.0),
+            );
+            labelled(ui, "Connections per file:", egui::DragValue::new(&mut self.settings.download_connections).clamp_range(1..=16));
+            labelled(
+                ui,
+                "Speed limit:",
+                egui::Slider::new(&mut self.settings.bandwidth_limit_mb, 0..=1000)
+                    .logarithmic(true)
+                    .suffix(" MB/s"),
+            );
+            if self.settings.bandwidth_limit_mb == 0 {
+                ui.label("(unlimited)");
+            }
+        });
+        ui.checkbox(&mut self.settings.pause_while_playing, "Pause downloads and auto-checks while the game is running")
+            .on_hover_text("Resumes them when GenshinImpact.exe or YuanShen.exe closes");
+        let settings_after = (
+            self.settings.download_folder.clone(),
+            self.settings.download_connections,
+            self.settings.bandwidth_limit_mb,
+            self.settings.pause_while_playing,
+        );
+        if settings_after != settings_before {
+            if let Err(e) = self.settings.save() {
+                error!("{}", e);
+            }
+        }
+        ui.separator();
+
+        // Add the files of a fetched game
+        self.download_source = self.download_source.min(self.workspaces.len() - 1);
+        let aria2_rpc = self.aria2_rpc();
+        let workspace = &self.workspaces[self.download_source];
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("download_source")
+                .selected_text(&workspace.config.name)
+                .show_ui(ui, |ui| {
+                    for (index, workspace) in self.workspaces.iter().enumerate() {
+                        ui.selectable_value(&mut self.download_source, index, &workspace.config.name);
+                    }
+                });
+
+            let Some(package) = workspace.game_package() else {
+                ui.label("Fetch this game's data to download its files.");
+                return;
+            };
+            let files = package_files(package);
+            let mut versions: Vec<String> = Vec::new();
+            for file in &files {
+                if !versions.contains(&file.version) {
+                    versions.push(file.version.clone());
+                }
+            }
+            egui::ComboBox::from_id_source("download_version")
+                .selected_text(self.download_version.as_deref().unwrap_or("All versions"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.download_version, None, "All versions");
+                    for version in &versions {
+                        ui.selectable_value(&mut self.download_version, Some(version.clone()), version);
+                    }
+                });
+
+            let selected: Vec<PackageFile> = files
+                .into_iter()
+                .filter(|file| self.download_version.as_ref().is_none_or(|version| file.version == *version))
+                .collect();
+            let folder = download::download_dir(&self.settings.download_folder);
+            if ui.button("Add to Downloads").clicked() {
+                let game = package.game.display_name();
+                for file in selected.iter().cloned() {
+                    self.downloader.add(&game, file, &folder);
+                }
+            }
+            if ui.button("Send to aria2").clicked() {
+                let game = package.game.display_name();
+                let files = selected
+                    .into_iter()
+                    .map(|file| {
+                        let dir = download::version_dir(&folder, &game, &file.version);
+                        (file, dir)
+                    })
+                    .collect();
+                self.aria2.send(ui.ctx(), aria2_rpc, files);
+            }
+        });
+        ui.separator();
+
+        self.show_aria2(ui);
+        self.show_mirror(ui);
+        self.show_archive_check(ui);
+        self.show_update_wizard(ui);
+
+        if self.downloader.downloads.is_empty() {
+            ui.label("Nothing queued.");
+            return;
+        }
+        // Overall progress of each version in the queue
+        for version in self.downloader.version_progress() {
+            let fraction = if version.size > 0 { version.downloaded as f32 / version.size as f32 } else { 0.0 };
+            ui.horizontal(|ui| {
+                ui.label(format!("{} {}", version.game, version.version));
+                ui.add(egui::ProgressBar::new(fraction).desired_width(320.0).text(format!(
+                    "{:.2} / {:.2} GB, {} of {} files done",
+                    bytes_to_gb(&version.downloaded.to_string()),
+                    bytes_to_gb(&version.size.to_string()),
+                    version.done,
+                    version.files
+                )));
+            });
+        }
+        if ui.button("Clear Finished").clicked() {
+            self.downloader.clear_finished();
+        }
+        ui.separator();
+
+        // Actions are applied after drawing the list
+        enum QueueAction {
+            Move(usize, isize),
+            Pause(usize),
+            Resume(usize),
+            Cancel(usize),
+        }
+        let mut action = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("downloads").num_columns(8).striped(true).show(ui, |ui| {
+                ui.label("");
+                ui.strong("File");
+                ui.strong("Version");
+                ui.strong("Priority");
+                ui.strong("Speed Limit");
+                ui.strong("Progress");
+                ui.strong("Status");
+                ui.end_row();
+
+                let count = self.downloader.downloads.len();
+                for (index, download) in self.downloader.downloads.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        if icon_button(ui, index > 0, "⏶", "Move up").clicked() {
+                            action = Some(QueueAction::Move(index, -1));
+                        }
+                        if icon_button(ui, index + 1 < count, "⏷", "Move down").clicked() {
+                            action = Some(QueueAction::Move(index, 1));
+                        }
+                    });
+                    ui.label(download.file.file_name()).on_hover_text(download.dest.display().to_string());
+                    ui.label(format!("{} {}", download.game, download.file.version));
+                    egui::ComboBox::from_id_source(("download_priority", index))
+                        .selected_text(download.priority.label())
+                        .show_ui(ui, |ui| {
+                            for priority in Priority::ALL {
+                                ui.selectable_value(&mut download.priority, priority, priority.label());
+                            }
+                        });
+                    ui.add(
+                        egui::Slider::new(&mut download.speed_limit_mb, 0..=1000)
+                            .logarithmic(true)
+                            .suffix(" MB/s"),
+                    )
+                    .on_hover_text("Caps this file on top of the overall limit (0 = no cap of its own)");
+
+                    let (downloaded, size) = download.progress();
+                    let fraction = if size > 0 { downloaded as f32 / size as f32 } else { 0.0 };
+                    ui.add(
+                        egui::ProgressBar::new(fraction)
+                            .desired_width(240.0)
+                            .text(format!("{:.2} / {:.2} GB", bytes_to_gb(&downloaded.to_string()), bytes_to_gb(&size.to_string()))),
+                    );
+                    match &download.status {
+                        DownloadStatus::Queued => ui.label("Queued"),
+                        DownloadStatus::Downloading => ui.label("Downloading"),
+                        DownloadStatus::Paused => ui.label("Paused"),
+                        DownloadStatus::Done => status_label(ui, Status::Success, "Done"),
+                        DownloadStatus::Failed(e) => status_label(ui, Status::Error, "Failed").on_hover_text(e),
+                        DownloadStatus::Cancelled => ui.label("Cancelled"),
+                    };
+
+                    ui.horizontal(|ui| match download.status {
+                        DownloadStatus::Queued | DownloadStatus::Downloading => {
+                            if ui.button("Pause").clicked() {
+                                action = Some(QueueAction::Pause(index));
+                            }
+                            if ui.button("Cancel").clicked() {
+                                action = Some(QueueAction::Cancel(index));
+                            }
+                        }
+                        DownloadStatus::Paused | DownloadStatus::Failed(_) => {
+                            if ui.button("Resume").clicked() {
+                                action = Some(QueueAction::Resume(index));
+                            }
+                            if ui.button("Cancel").clicked() {
+                                action = Some(QueueAction::Cancel(index));
+                            }
+                        }
+                        _ => {}
+                    });
+                    ui.end_row();
+                }
+            });
+        });
+        match action {
+            Some(QueueAction::Move(index, offset)) => self.downloader.move_by(index, offset),
+            Some(QueueAction::Pause(index)) => self.downloader.pause(index),
+            Some(QueueAction::Resume(index)) => self.downloader.resume(index),
+            Some(QueueAction::Cancel(index)) => self.downloader.cancel(index),
+            None => {}
+        }
+
+        // Keep the progress bars moving
+        if self.downloader.is_active() {
+            ui.ctx().request_repaint_after(std::time::Duration::from_millis(250));
+        }
+    }
+
+    // Offer to continue the downloads left unfinished in the last session
+    fn show_resume_prompt(&mut self, ctx: &egui::Context) {
+        if self.downloader.restored == 0 {
+            return;
+        }
+        egui::Window::new("Unfinished Downloads")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} download(s) were not finished last time. Resume where they left off?",
+                    self.downloader.restored
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Resume").clicked() {
+                        self.downloader.resume_restored();
+                        self.tab = Tab::Downloads;
+                    }
+                    if ui.button("Later").clicked() {
+                        // They stay paused in the Downloads tab
+                        self.downloader.restored = 0;
+                    }
+                    if ui.button("Discard").clicked() {
+                        self.downloader.discard_restored();
+                    }
+                });
+            });
+    }
+
+    // Connection settings and progress of files sent to aria2
+    fn show_aria2(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("aria2")
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let url = labelled(ui, "RPC URL:", egui::TextEdit::singleline(&mut self.settings.aria2_rpc_url).desired_width(280.0));
+                    let secret = labelled(
+                        ui,
+                        "Secret:",
+                        egui::TextEdit::singleline(&mut self.settings.aria2_secret)
+                            .password(true)
+                            .desired_width(160.0),
+                    );
+                    if url.lost_focus() || secret.lost_focus() {
+                        if let Err(e) = self.settings.save() {
+                            error!("{}", e);
+                        }
+                    }
+                });
+
+                let state = self.aria2.state();
+                if let Some(e) = &state.error {
+                    status_label(ui, Status::Error, e);
+                }
+                if state.jobs.is_empty() {
+                    ui.label("Start aria2 with --enable-rpc, then use \"Send to aria2\" to hand it the selected files.");
+                    return;
+                }
+                egui::Grid::new("aria2_jobs").num_columns(4).striped(true).show(ui, |ui| {
+                    for job in &state.jobs {
+                        ui.label(&job.name);
+                        let fraction = if job.total > 0 { job.completed as f32 / job.total as f32 } else { 0.0 };
+                        ui.add(egui::ProgressBar::new(fraction).desired_width(240.0).show_percentage());
+                        ui.label(&job.status);
+                        ui.label(format!("{:.1} MB/s", job.speed as f64 / (1024.0 * 1024.0)));
+                        ui.end_row();
+                    }
+                });
+                drop(state);
+                if ui.button("Clear Finished").clicked() {
+                    self.aria2.clear_finished();
+                }
+            });
+    }
+
+    // "Update my install": detect the installed version, plan the patch to
+    // the live version of the selected game and run it stage by stage
+    fn show_update_wizard(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Update My Install")
+            .default_open(false)
+            .show(ui, |ui| {
+                let paths_before = (self.settings.install_folder.clone(), self.settings.hpatchz_path.clone());
+                ui.horizontal(|ui| {
+                    labelled(
+                        ui,
+                        "Install folder:",
+                        egui::TextEdit::singleline(&mut self.settings.install_folder)
+                            .hint_text("Folder with config.ini, e.g. .../Genshin Impact game")
+                            .desired_width(360.0),
+                    );
+                    labelled(ui, "hpatchz:", egui::TextEdit::singleline(&mut self.settings.hpatchz_path).desired_width(200.0));
+                });
+                if (self.settings.install_folder.clone(), self.settings.hpatchz_path.clone()) != paths_before {
+                    if let Err(e) = self.settings.save() {
+                        error!("{}", e);
+                    }
+                }
+
+                let running = self.updater.as_ref().is_some_and(|updater| updater.is_running());
+                let workspace = &self.workspaces[self.download_source];
+                ui.horizontal(|ui| {
+                    let can_detect = !running && !self.settings.install_folder.trim().is_empty();
+                    if ui.add_enabled(can_detect, egui::Button::new("Detect")).clicked() {
+                        let dir = PathBuf::from(self.settings.install_folder.trim());
+                        self.update_plan = Some(match workspace.game_package() {
+                            Some(package) => update::detect(&dir)
+                                .and_then(|install| update::plan(&package.game.display_name(), install, package)),
+                            None => Err(format!("Fetch {} first to plan an update.", workspace.config.name)),
+                        });
+                        if let Some(job) = self.dry_run.take() {
+                            job.cancel.cancel();
+                        }
+                    }
+                    let plan = self.update_plan.as_ref().and_then(|plan| plan.as_ref().ok());
+                    if let Some(plan) = plan.filter(|_| !running) {
+                        if ui.button("Dry Run").clicked() {
+                            if let Some(job) = self.dry_run.take() {
+                                job.cancel.cancel();
+                            }
+                            let root = download::download_dir(&self.settings.download_folder);
+                            self.dry_run = Some(update::start_dry_run(ui.ctx(), plan.clone(), &root));
+                        }
+                        if ui.button("Start Update").clicked() {
+                            let root = download::download_dir(&self.settings.download_folder);
+                            info!("Updating {} from {} to {}.", plan.install.dir.display(), plan.install.version, plan.target);
+                            self.updater = Some(Updater::new(plan.clone(), &root, self.settings.hpatchz_path.trim()));
+                        }
+                    }
+                    if running && ui.button("Stop").clicked() {
+                        if let Some(updater) = &mut self.updater {
+                            updater.stop(&mut self.downloader);
+                        }
+                    }
+                });
+
+                match &self.update_plan {
+                    Some(Ok(plan)) => {
+                        let size: u64 = plan.files.iter().map(|file| file.size).sum();
+                        let languages = if plan.install.languages.is_empty() {
+                            "no voice-overs".to_string()
+                        } else {
+                            plan.install.languages.join(", ")
+                        };
+                        ui.label(format!(
+                            "Installed {} ({}) → {}: {} file(s), {:.2} GB to download",
+                            plan.install.version,
+                            languages,
+                            plan.target,
+                            plan.files.len(),
+                            bytes_to_gb(&size.to_string())
+                        ));
+                    }
+                    Some(Err(e)) => {
+                        status_label(ui, Status::Error, e);
+                    }
+                    None => {}
+                }
+                if let Some(job) = &self.dry_run {
+                    let result = job.result.lock().unwrap_or_else(|e| e.into_inner());
+                    match result.as_ref() {
+                        None => {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("Reading the patch archives...");
+                                if ui.add_enabled(!job.cancel.is_cancelled(), egui::Button::new("Cancel")).clicked() {
+                                    job.cancel.cancel();
+                                }
+                            });
+                        }
+                        Some(Ok(dry_run)) => {
+                            ui.horizontal(|ui| {
+                                ui.label(format!(
+                                    "Dry run: {} patched, {} replaced, {} added, {} deleted, peak disk use {:.2} GB",
+                                    dry_run.patched.len(),
+                                    dry_run.replaced.len(),
+                                    dry_run.added.len(),
+                                    dry_run.deleted.len(),
+                                    bytes_to_gb(&dry_run.peak_disk.to_string())
+                                ));
+                                if ui.button("Export Plan").clicked() {
+                                    let message = match update::write_dry_run(dry_run) {
+                                        Ok(path) => format!("Exported to {}", path.display()),
+                                        Err(e) => e,
+                                    };
+                                    self.toast = Some((message, Utc::now()));
+                                }
+                            });
+                        }
+                        Some(Err(e)) => {
+                            status_label(ui, Status::Error, format!("Dry run failed: {}", e));
+                        }
+                    }
+                }
+
+                let Some(updater) = &self.updater else {
+                    return;
+                };
+                ui.separator();
+                if let Some(path) = updater.locked_file() {
+                    status_label(
+                        ui,
+                        Status::Warning,
+                        format!("{} is in use. Close the game and launcher, then retry.", path.display()),
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button("Retry").clicked() {
+                            updater.answer_locked(LockedChoice::Retry);
+                        }
+                        if ui.button("Skip File").clicked() {
+                            updater.answer_locked(LockedChoice::Skip);
+                        }
+                    });
+                }
+                egui::Grid::new("update_stages").num_columns(2).show(ui, |ui| {
+                    for (stage, state) in Stage::ALL.iter().zip(updater.stages().iter()) {
+                        ui.label(stage.label());
+                        match state {
+                            StageState::Pending => ui.label("Waiting"),
+                            StageState::Running(message) => ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label(message);
+                            })
+                            .response,
+                            StageState::Done(summary) => status_label(ui, Status::Success, summary),
+                            StageState::Failed(e) => status_label(ui, Status::Error, e),
+                            StageState::Stopped => ui.label("Stopped"),
+                        };
+                        ui.end_row();
+                    }
+                });
+                if running {
+                    ui.ctx().request_repaint_after(std::time::Duration::from_millis(250));
+                }
+            });
+    }
+
+    // SFTP server verified downloads are copied to, and the uploads so far
+    fn show_mirror(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("SFTP Mirror")
+            .default_open(false)
+            .show(ui, |ui| {
+                let target = &mut self.settings.mirror;
+                let mut edited = Vec::new();
+                egui::Grid::new("mirror_target_grid").num_columns(2).show(ui, |ui| {
+                    let host_label = ui.label("Host:");
+                    ui.horizontal(|ui| {
+                        edited.push(
+                            ui.add(
+                                egui::TextEdit::singleline(&mut target.host)
+                                    .hint_text("user@mirror.example.org or an ~/.ssh/config alias")
+                                    .desired_width(280.0),
+                            )
+                            .labelled_by(host_label.id),
+                        );
+                        edited.push(labelled(ui, "Port:", egui::DragValue::new(&mut target.port).clamp_range(1..=65535)));
+                    });
+                    ui.end_row();
+                    edited.push(labelled(
+                        ui,
+                        "Remote folder:",
+                        egui::TextEdit::singleline(&mut target.remote_dir).hint_text("/srv/mirror/games").desired_width(280.0),
+                    ));
+                    ui.end_row();
+                    edited.push(
+                        labelled(
+                            ui,
+                            "Key file:",
+                            egui::TextEdit::singleline(&mut target.identity_file)
+                                .hint_text("empty for ssh's default keys and agent")
+                                .desired_width(280.0),
+                        ),
+                    );
+                    ui.end_row();
+                });
+                let enabled = ui.checkbox(&mut target.enabled, "Upload each download once it has been verified");
+                if enabled.changed() || edited.iter().any(|response| response.lost_focus() || response.drag_released()) {
+                    if let Err(e) = self.settings.save() {
+                        error!("{}", e);
+                    }
+                }
+                ui.label("Uses the ssh and sftp commands with key authentication; passwords can't be entered here.");
+
+                let configured = self.settings.mirror.is_configured();
+                let done: Vec<(String, PackageFile, PathBuf)> = self
+                    .downloader
+                    .downloads
+                    .iter()
+                    .filter(|download| download.status == DownloadStatus::Done)
+                    .map(|download| (download.game.clone(), download.file.clone(), download.dest.clone()))
+                    .collect();
+                let label = format!("Upload Finished Downloads ({})", done.len());
+                if ui.add_enabled(configured && !done.is_empty(), egui::Button::new(label)).clicked() {
+                    for (game, file, dest) in done {
+                        self.mirror.send(ui.ctx(), &self.settings.mirror, &game, file, dest);
+                    }
+                }
+
+                let jobs: Vec<(u64, String, MirrorStatus)> =
+                    self.mirror.jobs().iter().map(|job| (job.id, job.name.clone(), job.status.clone())).collect();
+                if jobs.is_empty() {
+                    return;
+                }
+                egui::Grid::new("mirror_jobs").num_columns(3).striped(true).show(ui, |ui| {
+                    for (id, name, status) in &jobs {
+                        ui.label(name);
+                        match status {
+                            MirrorStatus::Queued => ui.label("Queued"),
+                            MirrorStatus::Uploading => ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("Uploading")
+                            })
+                            .inner,
+                            MirrorStatus::Resuming(bytes) => ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label(format!("Resuming after {:.2} GB", bytes_to_gb(&bytes.to_string())))
+                            })
+                            .inner,
+                            MirrorStatus::Verifying => ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("Checking MD5 on the server")
+                            })
+                            .inner,
+                            MirrorStatus::Done => status_label(ui, Status::Success, "Uploaded and verified"),
+                            MirrorStatus::Failed(e) => status_label(ui, Status::Error, e),
+                        };
+                        if matches!(status, MirrorStatus::Failed(_)) && ui.button("Retry").clicked() {
+                            self.mirror.retry(ui.ctx(), &self.settings.mirror, *id);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if ui.button("Clear Finished").clicked() {
+                    self.mirror.clear_finished();
+                }
+            });
+    }
+
+    // Scheduled re-hash of the archived downloads and the damage it found
+    fn show_archive_check(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Archive Check")
+            .default_open(false)
+            .show(ui, |ui| {
+                let checking = self
+                    .archive_check
+                    .as_ref()
+                    .is_some_and(|job| !job.progress.lock().unwrap_or_else(|e| e.into_inner()).finished);
+                ui.horizontal(|ui| {
+                    let interval = labelled(
+                        ui,
+                        "Re-check archived files every",
+                        egui::DragValue::new(&mut self.settings.archive_check_interval_days).clamp_range(0..=365),
+                    );
+                    ui.label("days (0 = off)");
+                    if interval.changed() {
+                        if let Err(e) = self.settings.save() {
+                            error!("{}", e);
+                        }
+                    }
+                    if ui.add_enabled(!checking, egui::Button::new("Check Now")).clicked() {
+                        self.check_archive(ui.ctx());
+                    }
+                    if let Some(job) = self.archive_check.as_ref().filter(|_| checking) {
+                        ui.spinner();
+                        if ui.add_enabled(!job.cancel.is_cancelled(), egui::Button::new("Stop")).clicked() {
+                            job.cancel.cancel();
+                        }
+                    } else if let Some(last) = self.history.last_archive_check() {
+                        ui.label(format!("Last checked {} ago", history::format_duration(Utc::now() - last)));
+                    }
+                });
+                ui.label("Re-hashes the files listed in the manifest.json files below the download folder.");
+                self.show_dedup(ui);
+
+                let Some(job) = &self.archive_check else {
+                    // The outcome of the last run is kept in the history
+                    if let Some(run) = self.history.archive_checks.last() {
+                        ui.label(format!("{} files checked, {} damaged or missing.", run.files, run.problems.len()));
+                        for check in &run.problems {
+                            status_label(ui, Status::Error, format!("{}  {}", check.path.display(), check.status.label()));
+                        }
+                    }
+                    return;
+                };
+                let progress = job.progress.lock().unwrap_or_else(|e| e.into_inner());
+                let fraction = if progress.total > 0 { progress.checks.len() as f32 / progress.total as f32 } else { 0.0 };
+                ui.add(
+                    egui::ProgressBar::new(fraction)
+                        .desired_width(320.0)
+                        .text(format!("{} of {} files", progress.checks.len(), progress.total)),
+                );
+                for check in progress.problems() {
+                    let status = match check.status {
+                        ArchiveStatus::Unreadable(_) => Status::Warning,
+                        _ => Status::Error,
+                    };
+                    status_label(ui, status, format!("{} {}  {}", check.version, check.path.display(), check.status.label()));
+                }
+                for e in &progress.errors {
+                    status_label(ui, Status::Warning, e);
+                }
+                if progress.finished && ui.button("Export Report").clicked() {
+                    let result = archive::write_report(&job.root, &progress);
+                    self.toast = Some((
+                        match result {
+                            Ok(path) => format!("Exported to {}", path.display()),
+                            Err(e) => e,
+                        },
+                        Utc::now(),
+                    ));
+                }
+            });
+    }
+
+    // Hard-link files that are identical across versions, or preview the savings
+    fn show_dedup(&mut self, ui: &mut egui::Ui) {
+        let running = self
+            .dedup
+            .as_ref()
+            .is_some_and(|job| !job.progress.lock().unwrap_or_else(|e| e.into_inner()).finished);
+        ui.horizontal(|ui| {
+            ui.label("Identical files across versions:");
+            if ui.add_enabled(!running, egui::Button::new("Find")).clicked() {
+                self.start_dedup(ui.ctx(), true);
+            }
+            if ui
+                .add_enabled(!running, egui::Button::new("Link Duplicates"))
+                .on_hover_text("Replaces each copy with a hard link to one file; every version folder keeps its file")
+                .clicked()
+            {
+                self.start_dedup(ui.ctx(), false);
+            }
+            if let Some(job) = self.dedup.as_ref().filter(|_| running) {
+                ui.spinner();
+                if ui.add_enabled(!job.cancel.is_cancelled(), egui::Button::new("Stop")).clicked() {
+                    job.cancel.cancel();
+                }
+            }
+        });
+        if let Some(job) = self.dedup.as_ref().filter(|_| !running) {
+            let progress = job.progress.lock().unwrap_or_else(|e| e.into_inner());
+            ui.label(archive::dedup_report(&progress, job.dry_run).trim_end());
+        }
+    }
+
+    // Find (and unless `dry_run`, link) the duplicates below the download folder on a worker thread
+    fn start_dedup(&mut self, ctx: &egui::Context, dry_run: bool) {
+        let root = download::download_dir(&self.settings.download_folder);
+        let progress = Arc::new(Mutex::new(DedupProgress::default()));
+        let cancel = CancelToken::default();
+        self.dedup = Some(DedupJob {
+            dry_run,
+            progress: progress.clone(),
+            cancel: cancel.clone(),
+        });
+
+        let ctx = ctx.clone();
+        let label = format!("Deduplicate {}", root.display());
+        jobs::spawn(JobKind::Hashing, label, Some(cancel.clone()), move || {
+            archive::deduplicate(&root, dry_run, &progress, &|| cancel.is_cancelled());
+            if let Ok(mut progress) = progress.lock() {
+                progress.finished = true;
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    // Connection details of the aria2 daemon from the settings
+    fn aria2_rpc(&self) -> Aria2Rpc {
+        Aria2Rpc {
+            url: self.settings.aria2_rpc_url.clone(),
+            secret: self.settings.aria2_secret.clone(),
+        }
+    }
+}
+
+// ----------------------
+// Batched Fetching
+// ----------------------
+
+// One game waiting on a batched request
+#[cfg(feature = "gui")]
+struct BatchTarget {
+    fetch_id: u64,
+    game_ids: Vec<String>,
+    cancel: CancelToken,
+}
+
+#[cfg(feature = "gui")]
+impl GenshinApp {
+    // Fetch every idle game. Games whose sources are the same endpoint with
+    // different `game_ids[]` are fetched with one request listing all of the
+    // ids, and the response is split back out per game.
+    fn fetch_all(&mut self, ctx: &egui::Context) {
+        let mut batches: Vec<(String, Vec<usize>)> = Vec::new();
+        let mut singles = Vec::new();
+
+        for (index, workspace) in self.workspaces.iter().enumerate() {
+            if workspace.is_fetching() {
+                continue;
+            }
+            match split_game_ids(&workspace.config.source) {
+                Some((endpoint, game_ids)) if !game_ids.is_empty() => {
+                    match batches.iter_mut().find(|(batch_endpoint, _)| *batch_endpoint == endpoint) {
+                        Some((_, members)) => members.push(index),
+                        None => batches.push((endpoint, vec![index])),
+                    }
+                }
+                _ => singles.push(index),
+            }
+        }
+
+        for (endpoint, members) in batches {
+            if members.len() == 1 {
+                singles.push(members[0]);
+                continue;
+            }
+
+            let mut all_game_ids: Vec<String> = Vec::new();
+            let mut targets = Vec::new();
+            for index in members {
+                let (_, game_ids) = split_game_ids(&self.workspaces[index].config.source).unwrap_or_default();
+                for game_id in &game_ids {
+                    if !all_game_ids.contains(game_id) {
+                        all_game_ids.push(game_id.clone());
+                    }
+                }
+                let fetch_id = self.next_fetch_id;
+                self.next_fetch_id += 1;
+                let cancel = self.workspaces[index].begin_fetch(fetch_id);
+                targets.push(BatchTarget {
+                    fetch_id,
+                    game_ids,
+                    cancel,
+                });
+            }
+
+            let source = with_game_ids(&endpoint, &all_game_ids);
+            let strict = self.settings.strict_parsing;
+            let sender = self.event_sender.clone();
+            let ctx = ctx.clone();
+
+            let label = format!("Fetch {} games", targets.len());
+            jobs::spawn(JobKind::Fetch, label, None, move || {
+                info!("Starting batched fetch of {} games.", targets.len());
+                // Only abort the download once every game in the batch has been cancelled
+                let result = fetch_with_body(&source, strict, &|| {
+                    targets.iter().all(|target| target.cancel.is_cancelled())
+                });
+
+                for target in &targets {
+                    if target.cancel.is_cancelled() {
+                        info!("Fetch {} cancelled.", target.fetch_id);
+                        continue;
+                    }
+                    let event = match &result {
+                        Ok(result) => {
+                            let games: Vec<GameData> = result
+                                .games
+                                .iter()
+                                .filter(|game| target.game_ids.contains(&game.game.id))
+                                .cloned()
+                                .collect();
+                            if games.is_empty() {
+                                FetchEvent::Failed(FetchFailure::new(format!(
+                                    "Response contained no data for game id {}",
+                                    target.game_ids.join(", ")
+                                )))
+                            } else {
+                                FetchEvent::Completed(FetchResult {
+                                    games,
+                                    parse_errors: result.parse_errors.clone(),
+                                    unknown_fields: result.unknown_fields.clone(),
+                                })
+                            }
+                        }
+                        Err(err) => FetchEvent::Failed(err.clone()),
+                    };
+                    // The receiver only disappears when the app is shutting down
+                    let _ = sender.send((target.fetch_id, event));
+                }
+                // Wake the UI so the results show up immediately
+                ctx.request_repaint();
+            });
+        }
+
+        for index in singles {
+            let mut context = WorkspaceContext {
+                templates: &self.settings.templates,
+                event_sender: &self.event_sender,
+                next_fetch_id: &mut self.next_fetch_id,
+                discord_chunks: &mut self.discord_chunks,
+                history: &self.history,
+                pins: &mut self.settings.pinned_packages,
+                toast: &mut self.toast,
+                verify_hashes: &mut self.settings.verify_hashes,
+                strict_parsing: self.settings.strict_parsing,
+                share: &self.settings.share,
+                hidden_sections: &self.settings.hidden_sections,
+            };
+            self.workspaces[index].start_fetch(ctx, &mut context);
+        }
+    }
+}
+
+// ----------------------
+// Jobs Tab
+// ----------------------
+
+#[cfg(feature = "gui")]
+impl GenshinApp {
+    // What the background job pool is doing right now
+    fn show_jobs_tab(&mut self, ui: &mut egui::Ui) {
+        let jobs = jobs::list();
+        if jobs.is_empty() {
+            ui.label("Nothing is running.");
+            return;
+        }
+
+        let since = |instant: std::time::Instant| {
+            history::format_duration(chrono::Duration::from_std(instant.elapsed()).unwrap_or_else(|_| chrono::Duration::zero()))
+        };
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("jobs").num_columns(4).striped(true).show(ui, |ui| {
+                ui.strong("Job");
+                ui.strong("Kind");
+                ui.strong("State");
+                ui.end_row();
+
+                for job in &jobs {
+                    ui.label(&job.label);
+                    ui.label(job.kind.label());
+                    match job.started_at {
+                        Some(started_at) => ui.label(format!("Running for {}", since(started_at))),
+                        None => ui.label(format!("Queued for {}", since(job.queued_at))),
+                    };
+                    match &job.cancel {
+                        Some(cancel) if cancel.is_cancelled() => {
+                            ui.label("Stopping...");
+                        }
+                        Some(cancel) => {
+                            if ui.button("Cancel").clicked() {
+                                info!("Cancelling job {}.", job.label);
+                                cancel.cancel();
+                            }
+                        }
+                        None => {
+                            ui.label("");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+        // Keep the times and the list current while anything is running
+        ui.ctx().request_repaint_after(std::time::Duration::from_secs(1));
+    }
+}
+
+// ----------------------
+// Report Templates Tab
+// ----------------------
+
+#[cfg(feature = "gui")]
+impl GenshinApp {
+    fn show_templates_tab(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            for section in ReportSection::ALL {
+                ui.selectable_value(&mut self.template_section, section, section.title());
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Report language:");
+            egui::ComboBox::from_id_source("report_language")
+                .selected_text(self.template_draft.language.label())
+                .show_ui(ui, |ui| {
+                    for language in ReportLanguage::ALL {
+                        ui.selectable_value(&mut self.template_draft.language, language, language.label());
+                    }
+                });
+            ui.label("(applies to the labels in the default, BBCode and Discord templates)");
+        });
+
+        egui::CollapsingHeader::new("Available Variables")
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.monospace(report::TEMPLATE_VARIABLES_HELP);
+            });
+
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                self.settings.templates = self.template_draft.clone();
+                self.settings_status = match self.settings.save() {
+                    Ok(()) => "Templates saved.".to_string(),
+                    Err(e) => e,
+                };
+                for workspace in &mut self.workspaces {
+                    workspace.refresh_reports(&self.settings.templates);
+                }
+            }
+            if ui.button("Revert").clicked() {
+                self.template_draft = self.settings.templates.clone();
+            }
+            if ui.button("Reset to Default").clicked() {
+                let defaults = ReportTemplates::default();
+                *self.template_draft.get_mut(self.template_section) = defaults.get(self.template_section).to_string();
+            }
+            if self.template_draft != self.settings.templates {
+                ui.label("(unsaved changes)");
+            } else if !self.settings_status.is_empty() {
+                ui.label(&self.settings_status);
+            }
+        });
+        ui.separator();
+
+        // Live preview against the data last fetched in the selected game's workspace
+        let mut preview = self.workspaces[self.selected_workspace].render_section(
+            self.template_section,
+            self.template_draft.get(self.template_section),
+            self.template_draft.language,
+            &ReportFilter::default(),
+        );
+        if preview.is_empty() {
+            preview = "Fetch data to preview this template.".to_string();
+        }
+
+        let template = self.template_draft.get_mut(self.template_section);
+
+        ui.columns(2, |columns| {
+            columns[0].label("Template");
+            egui::ScrollArea::vertical().id_source("template_editor").show(&mut columns[0], |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(template)
+                        .code_editor()
+                        .desired_rows(20)
+                        .desired_width(f32::INFINITY),
+                );
+            });
+
+            columns[1].label("Preview");
+            egui::ScrollArea::vertical().id_source("template_preview").show(&mut columns[1], |ui| {
+                ui.label(preview);
+            });
+        });
+    }
+
+    // Window listing the parts of a report that had to be split for Discord
+    fn show_discord_window(&mut self, ctx: &egui::Context) {
+        if self.discord_chunks.is_empty() {
+            return;
+        }
+
+        let mut open = true;
+        egui::Window::new("Discord Messages").open(&mut open).show(ctx, |ui| {
+            ui.label(format!(
+                "The report is longer than Discord's {}-character limit. Copy and send each part in order.",
+                report::DISCORD_MESSAGE_LIMIT
+            ));
+            ui.separator();
+            let total = self.discord_chunks.len();
+            for (index, chunk) in self.discord_chunks.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.button("Copy").clicked() {
+                        ui.ctx().output_mut(|o| o.copied_text = chunk.clone());
+                    }
+                    ui.label(format!("Part {}/{} ({} characters)", index + 1, total, chunk.chars().count()));
+                });
+            }
+        });
+
+        if !open {
+            self.discord_chunks.clear();
+        }
+    }
+}
+
+// ----------------------
+// Snapshot Uploads
+// ----------------------
+
+// Upload the snapshots of these games on a worker thread; the outcome is
+// shown in the Games tab
+#[cfg(feature = "gui")]
+fn start_upload(ctx: &egui::Context, settings: &Settings, status: &Arc<Mutex<String>>, games: Vec<GameData>) {
+    let target = settings.upload.clone();
+    let templates = settings.templates.clone();
+    let status = status.clone();
+    let ctx = ctx.clone();
+    *status.lock().unwrap_or_else(|e| e.into_inner()) = "Uploading...".to_string();
+    let label = format!("Upload snapshots to {}", target.url.trim());
+    jobs::spawn(JobKind::Upload, label, None, move || {
+        let files = upload::snapshot_files(&games, &templates);
+        let message = match upload::upload_files(&target, &files) {
+            Ok(count) => format!("Uploaded {} files at {}", count, chrono::Local::now().format("%H:%M")),
+            Err(e) => e,
+        };
+        *status.lock().unwrap_or_else(|e| e.into_inner()) = message;
+        ctx.request_repaint();
+    });
+}
+
+// ----------------------
+// Main Function
+// ----------------------
+
+#[cfg(feature = "gui")]
+fn main() -> eframe::Result<()> {
+    run_command_line();
+
+    let native_options = eframe::NativeOptions::default();
+    eframe::run_native(
+        "Genshin Package Viewer",
+        native_options,
+        Box::new(|cc| {
+            install_cjk_font(&cc.egui_ctx);
+            let app = GenshinApp::default();
+            app.settings.theme.apply(&cc.egui_ctx);
+            Box::new(app)
+        }),
+    )
+}
+
+// Built without the window, the program is its command line
+#[cfg(not(feature = "gui"))]
+fn main() {
+    run_command_line();
+    eprintln!("This build has no window (it was built without the `gui` feature); give it a command.\n");
+    let _ = <cli::Cli as clap::CommandFactory>::command().print_help();
+    std::process::exit(cli::EXIT_USAGE);
+}
+
+// Run the subcommand given on the command line and exit. Returns if there is none.
+fn run_command_line() {
+    // Initialize the logger
+    env_logger::init();
+
+    // A subcommand runs on the command line instead of opening the window
+    if std::env::args_os().len() > 1 {
+        cli::attach_console();
+    }
+    // clap exits with 2 on bad arguments, which scripts would read as an API
+    // error, so usage errors get their own code
+    let cli = match cli::Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            std::process::exit(if e.use_stderr() { cli::EXIT_USAGE } else { cli::EXIT_NO_CHANGE });
+        }
+    };
+    // Recording and replaying cover the window as well as the commands
+    let started = match (&cli.record, &cli.replay) {
+        (Some(path), _) => replay::start_recording(path),
+        (_, Some(path)) => replay::start_replay(path),
+        _ => Ok(()),
+    };
+    if let Err(e) = started {
+        eprintln!("{}", e);
+        std::process::exit(cli::EXIT_USAGE);
+    }
+    network::set_flags(network::Flags {
+        ca_bundle: cli.ca_bundle,
+        insecure: cli.insecure,
+        ip_family: cli.ip_family,
+        bind: cli.bind,
+        doh_url: cli.doh,
+    });
+    if let Some(command) = cli.command {
+        std::process::exit(cli::run(command));
+    }
+}
+
+// egui's built-in fonts have no Chinese, Japanese or Korean glyphs. Borrow a
+// system font as a fallback so localized reports show up in the window.
+#[cfg(feature = "gui")]
+fn install_cjk_font(ctx: &egui::Context) {
+    const CANDIDATES: [&str; 7] = [
+        "C:\\Windows\\Fonts\\msyh.ttc",
+        "C:\\Windows\\Fonts\\YuGothM.ttc",
+        "C:\\Windows\\Fonts\\malgun.ttf",
+        "/System/Library/Fonts/PingFang.ttc",
+        "/System/Library/Fonts/Hiragino Sans GB.ttc",
+        "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+        "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    ];
+    let Some(data) = CANDIDATES.iter().find_map(|path| std::fs::read(path).ok()) else {
+        warn!("No CJK font found; localized reports may not display in the window.");
+        return;
+    };
+    let mut fonts = egui::FontDefinitions::default();
+    fonts.font_data.insert("cjk".to_string(), egui::FontData::from_owned(data));
+    for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+        fonts.families.entry(family).or_default().push("cjk".to_string());
+    }
+    ctx.set_fonts(fonts);
+}
+
+// ----------------------
+// Helper Functions
+// ----------------------
+
+// Result of a fetch: the data of every game in the response plus any field-level errors
+struct FetchResult {
+    games: Vec<GameData>,
+    parse_errors: Vec<String>,
+    unknown_fields: Vec<String>, // Fields the models don't know, for the schema drift log
+}
+
+// The displayable sections of one game in a response, parsed once by the
+// fetch worker and handed to the UI as they are
+#[derive(Clone)]
+struct GameData {
+    game: Game,
+    package: Option<GamePackage>,      // None if the main section failed to parse
+    pre_download: Option<PreDownload>, // The pre-download section, if any
+    // Listed by the window; without it only the schema ones are logged, as they are found
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    warnings: Vec<String>, // Fields skipped or defaulted while parsing, unmapped language codes
+}
+
+// A failed fetch: the error, and the start of the response if one came
+#[derive(Clone, Debug)]
+struct FetchFailure {
+    message: String,
+    body: Option<String>, // Pretty-printed when it is whole JSON
+}
+
+impl FetchFailure {
+    fn new(message: impl Into<String>) -> Self {
+        FetchFailure { message: message.into(), body: None }
+    }
+}
+
+// Function to fetch and process data from the API (or a saved payload on disk).
+// In strict mode sections that don't match the models exactly are rejected.
+fn fetch_and_process_data(source: &str, strict: bool, is_cancelled: &dyn Fn() -> bool) -> Result<FetchResult, String> {
+    fetch_with_body(source, strict, is_cancelled).map_err(|failure| {
+        if let Some(body) = &failure.body {
+            debug!("Response of the failed fetch: {}", body);
+        }
+        failure.message
+    })
+}
+
+// Like `fetch_and_process_data`, keeping the start of the response of a
+// failed fetch for the window's error details
+fn fetch_with_body(source: &str, strict: bool, is_cancelled: &dyn Fn() -> bool) -> Result<FetchResult, FetchFailure> {
+    let start = std::time::Instant::now();
+    let input: Box<dyn Read> = if is_url(source) {
+        info!("Fetching data from URL: {}", source);
+        replay::get(source).map_err(|e| {
+            error!("{}", e);
+            FetchFailure::new(e)
+        })?
+    } else {
+        info!("Reading data from file: {}", source);
+        let file = std::fs::File::open(source).map_err(|e| {
+            error!("File read error: {}", e);
+            FetchFailure::new(format!("File read error ({}): {}", source, e))
+        })?;
+        Box::new(file)
+    };
+
+    // Parse straight from the body as it arrives, into the models a package at
+    // a time, instead of reading it into a string or a JSON tree first. A
+    // cancelled fetch fails the next read, which drops the connection.
+    let mut reader = CapturingRead::new(CancellableRead { inner: input, is_cancelled });
+    let mut packages = PackageParser::new(strict);
+    let envelope = match response::read(std::io::BufReader::new(&mut reader), &mut |package| packages.add(package)) {
+        Ok(envelope) => envelope,
+        Err(_) if is_cancelled() => return Err(FetchFailure::new("Fetch cancelled.")),
+        Err(e) if e.is_io() => {
+            error!("Response text error: {}", e);
+            return Err(reader.failure(format!("Response text error: {}", e)));
+        }
+        Err(e) => {
+            // Show what came back from the part of the body the reader kept.
+            // The parser stops at the error, so read on up to the capture limit
+            // first: the details then hold more than the first buffer.
+            error!("JSON parse error: {}", e);
+            reader.capture_rest();
+            let body = String::from_utf8_lossy(&reader.captured).into_owned();
+            debug!("Raw Response: {}", body);
+            let start: String = body.chars().take(RAW_BODY_PREVIEW).collect();
+            return Err(reader.failure(format!("JSON parse error: {}\nResponse starts with: {}", e, start.trim())));
+        }
+    };
+    // Packages were parsed while the body came in; only the rest counts as the fetch
+    profiling::record(Metric::Fetch, start.elapsed().saturating_sub(packages.elapsed));
+
+    let parse_start = std::time::Instant::now();
+    let parsed = packages.elapsed;
+    let result = process_response(envelope, packages).map_err(|message| reader.failure(message));
+    profiling::record(Metric::Parse, parsed + parse_start.elapsed());
+    result
+}
+
+// Characters of an unparseable response shown in the error
+const RAW_BODY_PREVIEW: usize = 300;
+// Bytes kept of every response in case it fails: an API error whole, and
+// the first buffer the parser reads of anything else
+const RAW_BODY_KEEP: usize = 8 * 1024;
+// Bytes of a response kept for the details of a fetch once it has failed
+const RAW_BODY_CAPTURE: usize = 256 * 1024;
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+// Reader that keeps the first `RAW_BODY_KEEP` bytes it passes on, and what
+// is read after a failure up to `RAW_BODY_CAPTURE`
+struct CapturingRead<R: Read> {
+    inner: R,
+    captured: Vec<u8>,
+    limit: usize,
+    skipped: bool, // Bytes went by without being kept since the last ones that were
+}
+
+impl<R: Read> CapturingRead<R> {
+    fn new(inner: R) -> Self {
+        CapturingRead { inner, captured: Vec::new(), limit: RAW_BODY_KEEP, skipped: false }
+    }
+
+    // Read the rest of a failed response, keeping it up to the capture limit.
+    // Whatever went by between the first bytes and the failure is marked as left out.
+    fn capture_rest(&mut self) {
+        self.limit = RAW_BODY_CAPTURE;
+        let room = RAW_BODY_CAPTURE.saturating_sub(self.captured.len()) as u64;
+        let _ = std::io::copy(&mut self.take(room), &mut std::io::sink());
+    }
+
+    // A failure with what was read of the response
+    fn failure(&self, message: String) -> FetchFailure {
+        if self.captured.is_empty() {
+            return FetchFailure::new(message);
+        }
+        let body = serde_json::from_slice::<serde_json::Value>(&self.captured)
+            .ok()
+            .and_then(|value| serde_json::to_string_pretty(&value).ok())
+            .unwrap_or_else(|| String::from_utf8_lossy(&self.captured).into_owned());
+        FetchFailure { message, body: Some(body) }
+    }
+}
+
+impl<R: Read> Read for CapturingRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let kept = read.min(self.limit.saturating_sub(self.captured.len()));
+        if kept > 0 && self.skipped {
+            self.captured.extend_from_slice(b"\n[...]\n");
+            self.skipped = false;
+        }
+        self.captured.extend_from_slice(&buf[..kept]);
+        self.skipped |= kept < read;
+        Ok(read)
+    }
+}
+
+// Reader that fails once its fetch has been cancelled
+struct CancellableRead<'a, R: Read> {
+    inner: R,
+    is_cancelled: &'a dyn Fn() -> bool,
+}
+
+impl<R: Read> Read for CancellableRead<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if (self.is_cancelled)() {
+            return Err(std::io::Error::other("Fetch cancelled."));
+        }
+        self.inner.read(buf)
+    }
+}
+
+// Function to validate a response's envelope and put its games together
+fn process_response(envelope: response::Envelope, packages: PackageParser) -> Result<FetchResult, String> {
+
+    // Validate the envelope before looking at the payload
+    let retcode: i32 = envelope_field(envelope.retcode.as_ref(), "retcode")?;
+    let message: String = envelope_field(envelope.message.as_ref(), "message")?;
+    info!("Successfully parsed JSON response.");
+
+    // Check if API returned an error
+    if retcode != 0 {
+        error!("API returned an error: {} (retcode {})", message, retcode);
+        if polling::is_transient(retcode, &message) {
+            return Err(format!("{} (retcode {}): {}", polling::BUSY_ERROR, retcode, message));
+        }
+        return Err(format!("API returned an error: {}", message));
+    }
+
+    match envelope.data {
+        response::Data::Packages => {}
+        // Older launcher payloads use a different schema; normalize them into the HYP model
+        response::Data::Legacy(data) => {
+            info!("Detected legacy launcher resource payload.");
+            let legacy_data: legacy::LegacyData = deserialize_at(&data, "data")?;
+            let game_package = legacy_data.into_game_package();
+            return Ok(FetchResult {
+                games: vec![GameData {
+                    game: game_package.game.clone(),
+                    pre_download: game_package.pre_download.clone(),
+                    package: Some(game_package),
+                    warnings: Vec::new(),
+                }],
+                parse_errors: Vec::new(),
+                unknown_fields: Vec::new(),
+            });
+        }
+        response::Data::Missing => {
+            error!("Schema error: data.game_packages is missing or not an array");
+            return Err("Schema error at data.game_packages: missing or not an array".to_string());
+        }
+    }
+
+    // Fields around the packages count as drift too
+    let mut unknown_fields = envelope.findings.unknown_fields;
+    for field in packages.unknown_fields {
+        if !unknown_fields.contains(&field) {
+            unknown_fields.push(field);
+        }
+    }
+    let PackageParser { games, parse_errors, .. } = packages;
+
+    for e in &parse_errors {
+        warn!("Schema error: {}", e);
+    }
+
+    if games.is_empty() && !parse_errors.is_empty() {
+        return Err(format!("Schema errors:\n{}", parse_errors.join("\n")));
+    }
+
+    Ok(FetchResult {
+        games,
+        parse_errors,
+        unknown_fields,
+    })
+}
+
+// A field of the envelope, which every response must have
+fn envelope_field<T: DeserializeOwned>(value: Option<&serde_json::Value>, name: &str) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("Schema error at {}: missing field", name))?;
+    deserialize_at(value, name)
+}
+
+// The games of a response, parsed one package at a time as the body streams in.
+// Each package is validated on its own so that a broken field only hides the
+// section it belongs to.
+struct PackageParser {
+    strict: bool,
+    count: usize,
+    games: Vec<GameData>,
+    parse_errors: Vec<String>,
+    unknown_fields: Vec<String>,
+    elapsed: std::time::Duration, // Spent parsing, for the diagnostics
+}
+
+impl PackageParser {
+    fn new(strict: bool) -> Self {
+        PackageParser {
+            strict,
+            count: 0,
+            games: Vec::new(),
+            parse_errors: Vec::new(),
+            unknown_fields: Vec::new(),
+            elapsed: std::time::Duration::ZERO,
+        }
+    }
+
+    fn add(&mut self, package: serde_json::Value) {
+        let start = std::time::Instant::now();
+        let index = self.count;
+        self.count += 1;
+        if let Some(game) = self.parse(&package, index) {
+            self.games.push(game);
+        }
+        self.elapsed += start.elapsed();
+    }
+
+    fn parse(&mut self, package: &serde_json::Value, index: usize) -> Option<GameData> {
+        let strict = self.strict;
+        let parse_errors = &mut self.parse_errors;
+        let path = format!("data.game_packages[{}]", index);
+
+        // Sections are parsed leniently; what they skip or fill in becomes a warning
+        let mut findings = schema::Findings::default();
+        schema::check_keys(package, &path, &["game", "main", "pre_download"], &mut findings);
+        if strict {
+            parse_errors.append(&mut findings.warnings);
+        }
+        let game = schema::deserialize_lenient::<Game>(&package["game"], &format!("{}.game", path), strict, &mut findings);
+        let main = schema::deserialize_lenient::<Main>(&package["main"], &format!("{}.main", path), strict, &mut findings);
+        let pre = schema::deserialize_lenient::<Option<PreDownload>>(
+            &package["pre_download"],
+            &format!("{}.pre_download", path),
+            strict,
+            &mut findings,
+        );
+        for warning in &findings.warnings {
+            warn!("Schema warning: {}", warning);
+        }
+        for field in findings.unknown_fields {
+            if !self.unknown_fields.contains(&field) {
+                self.unknown_fields.push(field);
+            }
+        }
+        let warnings = findings.warnings;
+
+        let pre = match pre {
+            Ok(pre) => pre,
+            Err(e) => {
+                parse_errors.push(e);
+                None
+            }
+        };
+
+        let game = match game {
+            Ok(game) => game,
+            Err(e) => {
+                parse_errors.push(e);
+                parse_errors.extend(main.err());
+                // Without the game block the package can't be labelled; keep its pre-download anyway
+                return pre.map(|pre| GameData {
+                    game: Game {
+                        id: String::new(),
+                        biz: format!("unknown #{}", index + 1),
+                    },
+                    package: None,
+                    pre_download: Some(pre),
+                    warnings,
+                });
+            }
+        };
+
+        let package = match main {
+            Ok(main) => Some(GamePackage {
+                game: game.clone(),
+                main,
+                pre_download: pre.clone(),
+            }),
+            Err(e) => {
+                parse_errors.push(e);
+                None
+            }
+        };
+
+        if package.is_none() && pre.is_none() {
+            return None;
+        }
+        let mut warnings = warnings;
+        warnings.extend(languages::unmapped_warnings(package.as_ref(), pre.as_ref()));
+
+        Some(GameData {
+            game,
+            package,
+            pre_download: pre,
+            warnings,
+        })
+    }
+}
+
+
+// A downloadable file of a game with the version it installs. Patches count
+// as files of the version they update to.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PackageFile {
+    version: String,
+    url: String,
+    md5: String,
+    size: u64, // Bytes, 0 if the API didn't say
+}
+
+impl PackageFile {
+    // Name the file is saved under, taken from its URL
+    fn file_name(&self) -> String {
+        export::file_name_from_url(&self.url)
+    }
+}
+
+// Every file of the live and pre-download versions of a package
+fn package_files(package: &GamePackage) -> Vec<PackageFile> {
+    let mut files = Vec::new();
+    let mut versions = vec![(package.main.major.as_ref(), &package.main.patches)];
+    if let Some(pre_download) = &package.pre_download {
+        versions.push((pre_download.major.as_ref(), &pre_download.patches));
+    }
+
+    for (major, patches) in versions {
+        let Some(major) = major else {
+            continue;
+        };
+        let mut add = |url: &str, md5: &str, size: &str| {
+            files.push(PackageFile {
+                version: major.version.clone(),
+                url: url.to_string(),
+                md5: md5.to_string(),
+                size: size.parse().unwrap_or(0),
+            })
+        };
+        for pkg in &major.game_pkgs {
+            add(&pkg.url, &pkg.md5, &pkg.size);
+        }
+        for pkg in &major.audio_pkgs {
+            add(&pkg.url, &pkg.md5, &pkg.size);
+        }
+        for patch in patches {
+            for pkg in &patch.game_pkgs {
+                add(&pkg.url, &pkg.md5, &pkg.size);
+            }
+            for pkg in &patch.audio_pkgs {
+                add(&pkg.url, &pkg.md5, &pkg.size);
+            }
+        }
+    }
+    files
+}
+
+// Split an API URL into the endpoint without its `game_ids[]` parameters and
+// the game ids themselves. Returns None for sources that aren't URLs.
+#[cfg(feature = "gui")]
+fn split_game_ids(source: &str) -> Option<(String, Vec<String>)> {
+    let mut url = reqwest::Url::parse(source.trim()).ok()?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+
+    let (game_ids, other): (Vec<_>, Vec<_>) = url
+        .query_pairs()
+        .into_owned()
+        .partition(|(key, _)| key == "game_ids[]");
+    if other.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(other);
+    }
+
+    Some((url.to_string(), game_ids.into_iter().map(|(_, id)| id).collect()))
+}
+
+// Add `game_ids[]` parameters for each id to an endpoint URL
+#[cfg(feature = "gui")]
+fn with_game_ids(endpoint: &str, game_ids: &[String]) -> String {
+    match reqwest::Url::parse(endpoint) {
+        Ok(mut url) => {
+            url.query_pairs_mut()
+                .extend_pairs(game_ids.iter().map(|id| ("game_ids[]", id)));
+            url.to_string()
+        }
+        Err(_) => endpoint.to_string(),
+    }
+}
+
+// Deserialize a JSON value, reporting the exact path of the field that failed
+fn deserialize_at<T: DeserializeOwned>(value: &serde_json::Value, base_path: &str) -> Result<T, String> {
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let inner = e.path().to_string();
+        let path = match (base_path.is_empty(), inner.as_str()) {
+            (true, _) => inner.clone(),
+            (false, ".") => base_path.to_string(),
+            (false, _) if inner.starts_with('[') => format!("{}{}", base_path, inner),
+            (false, _) => format!("{}.{}", base_path, inner),
+        };
+        format!("Schema error at {}: {}", path, e.inner())
+    })
+}
+
+// Helper function to convert size in bytes (as string) to gigabytes (as f64)
+fn bytes_to_gb(size_str: &str) -> f64 {
+    let bytes: f64 = size_str.parse().unwrap_or(0.0);
+    bytes / (1024.0 * 1024.0 * 1024.0)
+}
+
+// ----------------------
+// Language Mapping Function
+// ----------------------
+
+// Function to map language codes to full names, using the table in the settings
+fn map_language_code(code: &str) -> String {
+    languages::name(code).unwrap_or_else(|| code.to_lowercase()) // Fallback to the original code if not mapped
+}
+
+// ----------------------
+// Game Name Mapping Function
+// ----------------------
+
+// Function to map biz codes (e.g. "hk4e_global") to game names
+fn map_biz_code(biz: &str) -> String {
+    let (game, region) = biz.split_once('_').unwrap_or((biz, ""));
+    let name = match game {
+        "hk4e" => "Genshin Impact",
+        "hkrpg" => "Honkai: Star Rail",
+        "nap" => "Zenless Zone Zero",
+        "bh3" => "Honkai Impact 3rd",
+        _ => return biz.to_string(), // Fallback to the original code if not matched
+    };
+    match region {
+        "global" => name.to_string(),
+        "cn" => format!("{} (China)", name),
+        "legacy" => format!("{} (Legacy API)", name),
+        other => format!("{} ({})", name, other),
+    }
+}
+
+// ----------------------
+// Accessibility
+// ----------------------
+
+// Add a widget after its visible label, so screen readers announce the field by that label
+#[cfg(feature = "gui")]
+fn labelled(ui: &mut egui::Ui, label: impl Into<egui::WidgetText>, widget: impl egui::Widget) -> egui::Response {
+    let label = ui.label(label);
+    ui.add(widget).labelled_by(label.id)
+}
+
+// A button showing only a symbol, described in words for screen readers and on hover
+#[cfg(feature = "gui")]
+fn icon_button(ui: &mut egui::Ui, enabled: bool, icon: &str, description: &str) -> egui::Response {
+    let response = ui.add_enabled(enabled, egui::Button::new(icon)).on_hover_text(description);
+    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, description));
+    response
 }
@@ -0,0 +1,298 @@
+// ----------------------
+// SFTP Mirror
+// ----------------------
+
+// For mirror operators: once a download has finished and passed its MD5
+// check, it can be uploaded to a remote server over SFTP into the same
+// `<game>/<version>/` layout. The transfer goes through the system's OpenSSH
+// `sftp` and `ssh` commands, like updates go through `hpatchz`, so keys, agents
+// and `~/.ssh/config` host aliases work as they do in a terminal. A partial
+// upload is continued with `reput`, and every upload is confirmed by
+// comparing the remote file's MD5 with the API's before it counts as done.
+
+use crate::export::sanitize_file_name;
+#[cfg(feature = "gui")]
+use crate::jobs::{self, JobKind};
+use crate::PackageFile;
+#[cfg(feature = "gui")]
+use eframe::egui;
+#[cfg(feature = "gui")]
+use log::error;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+#[cfg(feature = "gui")]
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+#[cfg(feature = "gui")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "gui")]
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// The server verified downloads are uploaded to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MirrorTarget {
+    pub enabled: bool,         // Upload each download once it is verified
+    pub host: String,          // e.g. "mirror@files.example.org" or a host alias from ~/.ssh/config
+    pub port: u16,
+    pub remote_dir: String,    // Folder on the server holding the game folders
+    pub identity_file: String, // Private key, empty for ssh's defaults and agent
+}
+
+impl Default for MirrorTarget {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: 22,
+            remote_dir: String::new(),
+            identity_file: String::new(),
+        }
+    }
+}
+
+impl MirrorTarget {
+    pub fn is_configured(&self) -> bool {
+        !self.host.trim().is_empty() && !self.remote_dir.trim().is_empty()
+    }
+
+    // Where a file of a game version goes on the server
+    pub fn remote_path(&self, game: &str, file: &PackageFile) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            self.remote_dir.trim().trim_end_matches('/'),
+            sanitize_file_name(game),
+            sanitize_file_name(&file.version),
+            file.file_name()
+        )
+    }
+
+    // Options shared by ssh and sftp; only the port flag differs
+    fn command(&self, program: &str, port_flag: &str) -> Command {
+        let mut command = Command::new(program);
+        command.args(["-o", "BatchMode=yes", port_flag, &self.port.to_string()]);
+        if !self.identity_file.trim().is_empty() {
+            command.arg("-i").arg(self.identity_file.trim());
+        }
+        command
+    }
+
+    // Run a shell command on the server and return its standard output
+    fn run_remote(&self, script: &str) -> Result<String, String> {
+        let output = self
+            .command("ssh", "-p")
+            .arg(self.host.trim())
+            .arg("--")
+            .arg(script)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| format!("Could not run ssh: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("ssh failed ({}): {}", output.status, stderr.trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    // Size of a remote file, None if it doesn't exist
+    fn remote_size(&self, path: &str) -> Option<u64> {
+        let output = self.run_remote(&format!("wc -c < {} 2>/dev/null", shell_quote(path))).ok()?;
+        output.trim().parse().ok()
+    }
+
+    // MD5 of a remote file, lowercase hex (md5sum on Linux, md5 on the BSDs)
+    fn remote_md5(&self, path: &str) -> Result<String, String> {
+        let path = shell_quote(path);
+        let output = self.run_remote(&format!("md5sum {} 2>/dev/null || md5 -q {}", path, path))?;
+        output
+            .split_whitespace()
+            .next()
+            .map(str::to_lowercase)
+            .ok_or_else(|| "The server returned no MD5".to_string())
+    }
+
+    // Transfer a file with sftp in batch mode; `resume` appends to the partial remote file
+    fn put(&self, local: &Path, remote: &str, resume: bool) -> Result<(), String> {
+        let mut child = self
+            .command("sftp", "-P")
+            .args(["-b", "-"])
+            .arg(self.host.trim())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Could not run sftp: {}", e))?;
+        let batch = format!(
+            "{} {} {}\n",
+            if resume { "reput" } else { "put" },
+            sftp_quote(&local.to_string_lossy()),
+            sftp_quote(remote)
+        );
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(batch.as_bytes()).map_err(|e| format!("sftp error: {}", e))?;
+        }
+        let output = child.wait_with_output().map_err(|e| format!("sftp error: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("sftp failed ({}): {}", output.status, stderr.trim()));
+        }
+        Ok(())
+    }
+}
+
+// Quote a path for the remote POSIX shell
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+// Quote a path for an sftp batch file
+fn sftp_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Step of an upload, as shown in the Downloads tab. The command line only
+// reports the steps of the upload under way.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+pub enum MirrorStatus {
+    Queued,
+    Uploading,
+    Resuming(u64), // Bytes already on the server
+    Verifying,
+    Done,
+    Failed(String),
+}
+
+// Upload a verified file and confirm its MD5 on the server. Files already
+// there in full are only checked. Returns the remote path.
+pub fn mirror_file(
+    target: &MirrorTarget,
+    game: &str,
+    file: &PackageFile,
+    local: &Path,
+    on_status: &dyn Fn(MirrorStatus),
+) -> Result<String, String> {
+    let remote = target.remote_path(game, file);
+    let size = std::fs::metadata(local)
+        .map(|metadata| metadata.len())
+        .map_err(|e| format!("Mirror error ({}): {}", local.display(), e))?;
+    let md5_matches = |path: &str| -> Result<bool, String> {
+        Ok(file.md5.is_empty() || target.remote_md5(path)?.eq_ignore_ascii_case(&file.md5))
+    };
+
+    let existing = target.remote_size(&remote);
+    if existing == Some(size) && md5_matches(&remote)? {
+        info!("{} is already on the mirror.", remote);
+        return Ok(remote);
+    }
+    let folder = remote.rsplit_once('/').map(|(folder, _)| folder).unwrap_or(".");
+    target.run_remote(&format!("mkdir -p {}", shell_quote(folder)))?;
+
+    // A partial upload is continued; anything else on the server is replaced
+    match existing {
+        Some(existing) if existing > 0 && existing < size => {
+            on_status(MirrorStatus::Resuming(existing));
+            target.put(local, &remote, true)?;
+        }
+        _ => {
+            on_status(MirrorStatus::Uploading);
+            target.put(local, &remote, false)?;
+        }
+    }
+
+    // Only a file whose MD5 matches the API's counts as mirrored; a bad one is
+    // removed so the next attempt starts over
+    on_status(MirrorStatus::Verifying);
+    if !md5_matches(&remote)? {
+        if let Err(e) = target.run_remote(&format!("rm -f {}", shell_quote(&remote))) {
+            warn!("Could not remove bad upload {}: {}", remote, e);
+        }
+        return Err(format!("MD5 mismatch on the server after uploading {}", remote));
+    }
+    info!("Mirrored {} to {}:{}.", local.display(), target.host.trim(), remote);
+    Ok(remote)
+}
+
+// A file handed to the mirror and how far it got
+#[cfg(feature = "gui")]
+#[derive(Clone)]
+pub struct MirrorJob {
+    pub id: u64,
+    pub name: String,
+    pub status: MirrorStatus,
+    game: String,
+    file: PackageFile,
+    local: PathBuf,
+}
+
+// Uploads to the mirror, updated by worker threads
+#[cfg(feature = "gui")]
+#[derive(Default)]
+pub struct Mirror {
+    jobs: Arc<Mutex<Vec<MirrorJob>>>,
+    next_id: AtomicU64,
+}
+
+#[cfg(feature = "gui")]
+impl Mirror {
+    pub fn jobs(&self) -> MutexGuard<'_, Vec<MirrorJob>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Queue the upload of a verified download
+    #[cfg(feature = "gui")]
+    pub fn send(&self, ctx: &egui::Context, target: &MirrorTarget, game: &str, file: PackageFile, local: PathBuf) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.jobs().push(MirrorJob {
+            id,
+            name: file.file_name(),
+            status: MirrorStatus::Queued,
+            game: game.to_string(),
+            file,
+            local,
+        });
+        self.start(ctx, target, id);
+    }
+
+    // Try a failed upload again; a partial file on the server is resumed
+    #[cfg(feature = "gui")]
+    pub fn retry(&self, ctx: &egui::Context, target: &MirrorTarget, id: u64) {
+        if let Some(job) = self.jobs().iter_mut().find(|job| job.id == id) {
+            job.status = MirrorStatus::Queued;
+        }
+        self.start(ctx, target, id);
+    }
+
+    #[cfg(feature = "gui")]
+    fn start(&self, ctx: &egui::Context, target: &MirrorTarget, id: u64) {
+        let Some(job) = self.jobs().iter().find(|job| job.id == id).cloned() else {
+            return;
+        };
+        let jobs = self.jobs.clone();
+        let target = target.clone();
+        let ctx = ctx.clone();
+        let set_status = move |status: MirrorStatus| {
+            if let Some(job) = jobs.lock().unwrap_or_else(|e| e.into_inner()).iter_mut().find(|job| job.id == id) {
+                job.status = status;
+            }
+            ctx.request_repaint();
+        };
+        jobs::spawn(JobKind::Upload, format!("Mirror {}", job.name), None, move || {
+            let result = mirror_file(&target, &job.game, &job.file, &job.local, &set_status);
+            match result {
+                Ok(_) => set_status(MirrorStatus::Done),
+                Err(e) => {
+                    error!("Mirror upload of {} failed: {}", job.name, e);
+                    set_status(MirrorStatus::Failed(e));
+                }
+            }
+        });
+    }
+
+    // Forget uploads that are done. Failed ones stay so they can be retried.
+    pub fn clear_finished(&self) {
+        self.jobs().retain(|job| job.status != MirrorStatus::Done);
+    }
+}
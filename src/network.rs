@@ -0,0 +1,420 @@
+// ----------------------
+// Network Settings
+// ----------------------
+
+// How the HTTP clients of fetches, downloads, link checks, uploads and
+// shares connect. Behind a TLS-inspecting corporate proxy every HTTPS
+// request fails with an opaque "Request error", because the proxy signs the
+// traffic with its own root certificate. An extra CA bundle adds that root to
+// the trusted ones; skipping verification altogether is there as a last
+// resort, with warnings wherever it is in effect.
+//
+// Some CDNs misbehave over IPv6 in some regions, so connections can prefer or
+// be limited to one IP version, and be made from a given local address or
+// interface. To prefer a version, the hosts a client will talk to are looked
+// up ahead and their addresses put in that order; hyper tries the first
+// address's version first and falls back to the other.
+//
+// Where an ISP's DNS returns wrong addresses for HoYoverse domains, host
+// names can be looked up over DNS-over-HTTPS instead, with the same
+// look-ahead. aria2's RPC calls go to the local daemon and keep a plain client.
+
+use log::{error, warn};
+use reqwest::blocking::{Client, ClientBuilder};
+use reqwest::Certificate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct NetworkSettings {
+    pub ca_bundle: String,          // PEM file of extra root certificates, e.g. a corporate proxy's
+    pub accept_invalid_certs: bool, // Skip certificate verification entirely
+    pub ip_family: IpFamily,        // IP version connections prefer or are limited to
+    pub bind: String,               // Local IP address or interface name, empty for any
+    pub doh_url: String,            // DNS-over-HTTPS endpoint (JSON API), empty for the system resolver
+}
+
+// Which IP version to connect over
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum IpFamily {
+    #[default]
+    Any, // Whatever the system resolver returns first
+    PreferIpv4,
+    PreferIpv6,
+    Ipv4Only,
+    Ipv6Only,
+}
+
+impl IpFamily {
+    #[cfg(feature = "gui")]
+    pub const ALL: [IpFamily; 5] =
+        [IpFamily::Any, IpFamily::PreferIpv4, IpFamily::PreferIpv6, IpFamily::Ipv4Only, IpFamily::Ipv6Only];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            IpFamily::Any => "System default",
+            IpFamily::PreferIpv4 => "Prefer IPv4",
+            IpFamily::PreferIpv6 => "Prefer IPv6",
+            IpFamily::Ipv4Only => "IPv4 only",
+            IpFamily::Ipv6Only => "IPv6 only",
+        }
+    }
+
+    // Put the addresses of the preferred version first and drop the ones
+    // that aren't allowed
+    pub fn arrange(self, addresses: Vec<IpAddr>) -> Vec<IpAddr> {
+        let (v4, v6): (Vec<IpAddr>, Vec<IpAddr>) = addresses.iter().copied().partition(IpAddr::is_ipv4);
+        match self {
+            // The system's order already follows its own preferences (RFC 6724, gai.conf)
+            IpFamily::Any => addresses,
+            IpFamily::PreferIpv4 => v4.into_iter().chain(v6).collect(),
+            IpFamily::PreferIpv6 => v6.into_iter().chain(v4).collect(),
+            IpFamily::Ipv4Only => v4,
+            IpFamily::Ipv6Only => v6,
+        }
+    }
+}
+
+// Command-line flags that override the saved settings for a run
+#[derive(Default)]
+pub struct Flags {
+    pub ca_bundle: Option<String>,
+    pub insecure: bool,
+    pub ip_family: Option<IpFamily>,
+    pub bind: Option<String>,
+    pub doh_url: Option<String>,
+}
+
+struct State {
+    saved: NetworkSettings,
+    flags: Flags,
+}
+
+static STATE: RwLock<State> = RwLock::new(State {
+    saved: NetworkSettings {
+        ca_bundle: String::new(),
+        accept_invalid_certs: false,
+        ip_family: IpFamily::Any,
+        bind: String::new(),
+        doh_url: String::new(),
+    },
+    flags: Flags { ca_bundle: None, insecure: false, ip_family: None, bind: None, doh_url: None },
+});
+
+pub const INSECURE_WARNING: &str =
+    "TLS certificate verification is OFF: anyone on the network path can read and change API responses and downloads.";
+
+// Apply the command-line flags for the rest of the run
+pub fn set_flags(flags: Flags) {
+    STATE.write().unwrap_or_else(|e| e.into_inner()).flags = flags;
+}
+
+// Use the saved settings, e.g. after they were loaded or changed
+pub fn configure(settings: &NetworkSettings) {
+    STATE.write().unwrap_or_else(|e| e.into_inner()).saved = settings.clone();
+    if current().accept_invalid_certs {
+        warn!("{}", INSECURE_WARNING);
+    }
+}
+
+// The settings in effect, flags applied
+pub fn current() -> NetworkSettings {
+    let state = STATE.read().unwrap_or_else(|e| e.into_inner());
+    let flags = &state.flags;
+    NetworkSettings {
+        ca_bundle: flags.ca_bundle.clone().unwrap_or_else(|| state.saved.ca_bundle.clone()),
+        accept_invalid_certs: flags.insecure || state.saved.accept_invalid_certs,
+        ip_family: flags.ip_family.unwrap_or(state.saved.ip_family),
+        bind: flags.bind.clone().unwrap_or_else(|| state.saved.bind.clone()),
+        doh_url: flags.doh_url.clone().unwrap_or_else(|| state.saved.doh_url.clone()),
+    }
+}
+
+// The certificates in a PEM bundle
+pub fn load_ca_bundle(path: &str) -> Result<Vec<Certificate>, String> {
+    let pem = std::fs::read(path).map_err(|e| format!("CA bundle error ({}): {}", path, e))?;
+    let certificates = Certificate::from_pem_bundle(&pem).map_err(|e| format!("CA bundle error ({}): {}", path, e))?;
+    if certificates.is_empty() {
+        return Err(format!("CA bundle error ({}): no certificates found", path));
+    }
+    Ok(certificates)
+}
+
+// A client builder with the settings in effect, for requests that need more
+// options such as a timeout. A CA bundle that can't be read or an address
+// that can't be bound is logged and left out.
+pub fn client_builder() -> ClientBuilder {
+    builder_for(&[])
+}
+
+// A client builder for requests to `urls`, whose hosts are looked up ahead
+// when an IP version is preferred or DNS-over-HTTPS is used
+pub fn builder_for(urls: &[&str]) -> ClientBuilder {
+    let settings = current();
+    let mut builder = Client::builder();
+    match local_address(&settings) {
+        Ok(address) => builder = builder.local_address(address),
+        Err(e) => error!("{}", e),
+    }
+    if settings.ip_family != IpFamily::Any || !settings.doh_url.trim().is_empty() {
+        for host in hosts(urls) {
+            let addresses: Vec<SocketAddr> =
+                resolve(&host, &settings).into_iter().map(|ip| SocketAddr::new(ip, 0)).collect();
+            if !addresses.is_empty() {
+                builder = builder.resolve_to_addrs(&host, &addresses);
+            }
+        }
+    }
+    if !settings.ca_bundle.trim().is_empty() {
+        match load_ca_bundle(settings.ca_bundle.trim()) {
+            Ok(certificates) => {
+                for certificate in certificates {
+                    builder = builder.add_root_certificate(certificate);
+                }
+            }
+            Err(e) => error!("{}", e),
+        }
+    }
+    if settings.accept_invalid_certs {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    builder
+}
+
+pub fn client_for(urls: &[&str]) -> Client {
+    builder_for(urls).build().unwrap_or_else(|e| {
+        error!("Request error: {}; using the default client", e);
+        Client::new()
+    })
+}
+
+// GET `url` with the settings in effect
+pub fn get(url: &str) -> Result<reqwest::blocking::Response, String> {
+    client_for(&[url]).get(url).send().map_err(|e| format!("Request error: {}", describe(&e)))
+}
+
+// The distinct host names of some URLs; IP addresses need no lookup
+fn hosts(urls: &[&str]) -> BTreeSet<String> {
+    urls.iter()
+        .filter_map(|url| reqwest::Url::parse(url).ok())
+        .filter_map(|url| url.domain().map(str::to_string))
+        .collect()
+}
+
+// The addresses of `host` in the order connections should try them. When
+// DNS-over-HTTPS fails the system resolver takes over, as it would for a
+// host without addresses here.
+pub fn resolve(host: &str, settings: &NetworkSettings) -> Vec<IpAddr> {
+    let doh_url = settings.doh_url.trim();
+    if !doh_url.is_empty() {
+        match doh_lookup(doh_url, host, settings.ip_family) {
+            Ok(addresses) => return settings.ip_family.arrange(addresses),
+            Err(e) => warn!("{}; using the system resolver", e),
+        }
+    }
+    settings.ip_family.arrange(system_lookup(host))
+}
+
+pub fn system_lookup(host: &str) -> Vec<IpAddr> {
+    match (host, 0).to_socket_addrs() {
+        Ok(addresses) => addresses.map(|address| address.ip()).collect(),
+        Err(e) => {
+            warn!("Lookup of {} failed: {}", host, e);
+            Vec::new()
+        }
+    }
+}
+
+// ----------------------
+// DNS over HTTPS
+// ----------------------
+
+// Endpoints of the JSON API (`?name=...&type=A`) most public resolvers offer
+#[cfg(feature = "gui")]
+pub const DOH_PRESETS: [(&str, &str); 3] = [
+    ("Cloudflare", "https://cloudflare-dns.com/dns-query"),
+    ("Google", "https://dns.google/resolve"),
+    ("AliDNS", "https://dns.alidns.com/resolve"),
+];
+
+const DOH_TIMEOUT: Duration = Duration::from_secs(10);
+// Answers are kept at least this long, however short their TTL
+const DOH_MIN_TTL: u64 = 60;
+
+// Answers by endpoint and host, with when they expire
+type DohCache = HashMap<(String, String), (Instant, Vec<IpAddr>)>;
+static DOH_CACHE: Mutex<Option<DohCache>> = Mutex::new(None);
+
+#[derive(Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u32,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    #[serde(rename = "TTL", default)]
+    ttl: u64,
+    data: String,
+}
+
+// Look up the A and AAAA records of `host` (only those of an allowed IP
+// version) at a DNS-over-HTTPS endpoint
+pub fn doh_lookup(doh_url: &str, host: &str, family: IpFamily) -> Result<Vec<IpAddr>, String> {
+    let key = (doh_url.to_string(), host.to_string());
+    {
+        let cache = DOH_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((expires, addresses)) = cache.as_ref().and_then(|cache| cache.get(&key)) {
+            if *expires > Instant::now() {
+                return Ok(family.arrange(addresses.clone()));
+            }
+        }
+    }
+
+    // The endpoint itself is looked up by the system resolver; an IP address
+    // in its URL (e.g. https://1.1.1.1/dns-query) avoids that too
+    let client = client_builder()
+        .timeout(DOH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("DNS-over-HTTPS error: {}", e))?;
+    let mut addresses = Vec::new();
+    let mut ttl = u64::MAX;
+    let types: &[(&str, u16)] = match family {
+        IpFamily::Ipv4Only => &[("A", 1)],
+        IpFamily::Ipv6Only => &[("AAAA", 28)],
+        _ => &[("A", 1), ("AAAA", 28)],
+    };
+    for (name, code) in types {
+        let response: DohResponse = client
+            .get(doh_url)
+            .query(&[("name", host), ("type", name)])
+            .header(reqwest::header::ACCEPT, "application/dns-json")
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .map_err(|e| format!("DNS-over-HTTPS error ({} {}): {}", host, name, describe(&e)))?;
+        if response.status != 0 {
+            return Err(format!("DNS-over-HTTPS error ({} {}): DNS status {}", host, name, response.status));
+        }
+        for answer in response.answer.iter().filter(|answer| answer.record_type == *code) {
+            if let Ok(address) = answer.data.parse::<IpAddr>() {
+                addresses.push(address);
+                ttl = ttl.min(answer.ttl);
+            }
+        }
+    }
+    if addresses.is_empty() {
+        return Err(format!("DNS-over-HTTPS error: no addresses for {}", host));
+    }
+    let expires = Instant::now() + Duration::from_secs(ttl.max(DOH_MIN_TTL));
+    let mut cache = DOH_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.get_or_insert_with(HashMap::new).insert(key, (expires, addresses.clone()));
+    Ok(family.arrange(addresses))
+}
+
+// ----------------------
+// Local Address
+// ----------------------
+
+// The address connections are made from: the bind setting (an address, or
+// an interface's address of the allowed IP version), or the unspecified
+// address of a version that connections are limited to
+pub fn local_address(settings: &NetworkSettings) -> Result<Option<IpAddr>, String> {
+    let bind = settings.bind.trim();
+    if bind.is_empty() {
+        return Ok(match settings.ip_family {
+            IpFamily::Ipv4Only => Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            IpFamily::Ipv6Only => Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+            _ => None,
+        });
+    }
+    if let Ok(address) = bind.parse::<IpAddr>() {
+        return Ok(Some(address));
+    }
+    let addresses = interface_addresses(bind)?;
+    if addresses.is_empty() {
+        return Err(format!("Bind error: no interface named {} with an IP address", bind));
+    }
+    // Link-local IPv6 addresses can't be bound without a scope
+    let usable = addresses.into_iter().filter(|address| match address {
+        IpAddr::V6(v6) => v6.segments()[0] & 0xffc0 != 0xfe80,
+        IpAddr::V4(_) => true,
+    });
+    settings
+        .ip_family
+        .arrange(usable.collect())
+        .first()
+        .copied()
+        .map(Some)
+        .ok_or_else(|| format!("Bind error: {} has no address of the allowed IP version", bind))
+}
+
+// The IP addresses of a network interface
+#[cfg(unix)]
+fn interface_addresses(name: &str) -> Result<Vec<IpAddr>, String> {
+    let mut list: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut list) } != 0 {
+        return Err(format!("Bind error: {}", std::io::Error::last_os_error()));
+    }
+    let mut addresses = Vec::new();
+    let mut cursor = list;
+    while !cursor.is_null() {
+        let entry = unsafe { &*cursor };
+        cursor = entry.ifa_next;
+        if entry.ifa_addr.is_null() || unsafe { std::ffi::CStr::from_ptr(entry.ifa_name) }.to_bytes() != name.as_bytes() {
+            continue;
+        }
+        match i32::from(unsafe { (*entry.ifa_addr).sa_family }) {
+            libc::AF_INET => {
+                let address = unsafe { &*(entry.ifa_addr as *const libc::sockaddr_in) };
+                addresses.push(IpAddr::V4(Ipv4Addr::from(u32::from_be(address.sin_addr.s_addr))));
+            }
+            libc::AF_INET6 => {
+                let address = unsafe { &*(entry.ifa_addr as *const libc::sockaddr_in6) };
+                addresses.push(IpAddr::V6(Ipv6Addr::from(address.sin6_addr.s6_addr)));
+            }
+            _ => {}
+        }
+    }
+    unsafe { libc::freeifaddrs(list) };
+    Ok(addresses)
+}
+
+#[cfg(not(unix))]
+fn interface_addresses(name: &str) -> Result<Vec<IpAddr>, String> {
+    Err(format!("Bind error: interface names such as {} only work on Linux and macOS; use the interface's IP address", name))
+}
+
+// reqwest's message alone is often just "error sending request"; the causes
+// underneath say whether it was DNS, the certificate or a timeout
+pub fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut chain = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        // Some errors already repeat their cause in their own message
+        let cause_text = cause.to_string();
+        if !chain.contains(&cause_text) {
+            chain = format!("{}: {}", chain, cause_text);
+        }
+        source = cause.source();
+    }
+    chain
+}
+
+// The error with its causes, and what to do when a certificate was refused
+pub fn describe(error: &dyn std::error::Error) -> String {
+    let chain = error_chain(error);
+    if chain.to_lowercase().contains("certificate") {
+        format!("{} (behind a TLS-inspecting proxy? Set its root certificate as the CA bundle, or pass --ca-bundle)", chain)
+    } else {
+        chain
+    }
+}
@@ -0,0 +1,125 @@
+// ----------------------
+// Polite Polling
+// ----------------------
+
+// Everyone running `watch` or auto-checks would otherwise hit the API in
+// lockstep at patch time, and keep hitting it while it's down. Three rules
+// keep the load down: fetches are at least `MIN_INTERVAL` apart; each wait
+// gets up to a tenth more at random, so clients started together drift
+// apart; and after `FAILURES_BEFORE_BACKOFF` failures in a row the wait
+// doubles with every further failure, up to `MAX_BACKOFF`, until a fetch
+// succeeds again.
+//
+// An answer saying the API is busy or under maintenance is no reason to give
+// up on a fetch either: the window tries it again after `RETRY_DELAYS`,
+// showing the countdown in place of the error.
+
+use crate::history;
+use log::{info, warn};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+pub const MIN_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const JITTER: f64 = 0.1; // Largest random addition, as a fraction of the wait
+const FAILURES_BEFORE_BACKOFF: u32 = 3;
+const MAX_BACKOFF: Duration = Duration::from_secs(6 * 3600);
+
+// Retcodes and message words of answers from an overloaded, throttling or
+// down-for-maintenance API. -1 is not one: it also means a bad launcher id.
+const TRANSIENT_RETCODES: [i32; 4] = [-500, -502, -503, -504];
+const TRANSIENT_WORDS: [&str; 6] = ["busy", "maintenance", "too frequent", "too many", "try again later", "稍后"];
+// Waits before each retry of a fetch the API turned away
+#[cfg(any(feature = "gui", test))]
+pub const RETRY_DELAYS: [Duration; 3] = [Duration::from_secs(30), Duration::from_secs(120), Duration::from_secs(300)];
+// How the error of such a fetch starts
+pub const BUSY_ERROR: &str = "API busy";
+
+// When the next fetch of a polling loop is due
+#[derive(Debug)]
+pub struct Poller {
+    interval: Duration,
+    failures: u32, // Failed fetches since the last success
+}
+
+impl Poller {
+    pub fn new(interval: Duration) -> Self {
+        Poller { interval: interval.max(MIN_INTERVAL), failures: 0 }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval.max(MIN_INTERVAL);
+    }
+
+    pub fn succeeded(&mut self) {
+        if self.is_backing_off() {
+            info!("Fetching works again; back to every {}.", label(self.interval));
+        }
+        self.failures = 0;
+    }
+
+    pub fn failed(&mut self) {
+        self.failures += 1;
+        if self.is_backing_off() {
+            warn!("{} fetches in a row failed; waiting {} before the next.", self.failures, label(self.wait()));
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    pub fn is_backing_off(&self) -> bool {
+        self.failures >= FAILURES_BEFORE_BACKOFF
+    }
+
+    // The wait before the next fetch, without jitter
+    pub fn wait(&self) -> Duration {
+        if !self.is_backing_off() {
+            return self.interval;
+        }
+        let doublings = (self.failures - FAILURES_BEFORE_BACKOFF + 1).min(16);
+        (self.interval * 2u32.pow(doublings)).min(MAX_BACKOFF.max(self.interval))
+    }
+
+    // The wait before the next fetch, jitter included
+    pub fn next_delay(&self) -> Duration {
+        let wait = self.wait();
+        wait + wait.mul_f64(JITTER * random_fraction())
+    }
+
+    // The longest `next_delay` can be at the moment
+    #[cfg(any(feature = "gui", test))]
+    pub fn longest_delay(&self) -> Duration {
+        self.wait().mul_f64(1.0 + JITTER)
+    }
+}
+
+// Whether an error answer from the API is likely to go away by itself
+pub fn is_transient(retcode: i32, message: &str) -> bool {
+    let message = message.to_lowercase();
+    TRANSIENT_RETCODES.contains(&retcode) || TRANSIENT_WORDS.iter().any(|word| message.contains(word))
+}
+
+// The wait before retrying a fetch that failed with `error`, after `retries`
+// retries so far. None if the error is not a busy API or the retries are used up.
+#[cfg(any(feature = "gui", test))]
+pub fn retry_delay(error: &str, retries: usize) -> Option<Duration> {
+    if !error.starts_with(BUSY_ERROR) {
+        return None;
+    }
+    RETRY_DELAYS.get(retries).copied()
+}
+
+// A number in [0, 1). Each RandomState is seeded from the OS, which is random
+// enough to spread clients apart without a random number crate.
+fn random_fraction() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn label(duration: Duration) -> String {
+    history::format_duration(chrono::Duration::from_std(duration).unwrap_or_else(|_| chrono::Duration::zero()))
+}
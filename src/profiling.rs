@@ -0,0 +1,154 @@
+// ----------------------
+// Diagnostics Overlay
+// ----------------------
+
+// Timings of the steps that grow with the payload and the feature set: the
+// fetch (download and JSON decoding, which overlap because the body is
+// parsed as it arrives), parsing into the models (done package by package
+// during the fetch, but counted apart from it), report formatting, and the
+// window's own work per frame. Each step keeps its recent samples, and F12
+// shows their last, average and worst values over the window, with the
+// phases of the last API fetch (see timing.rs) in an expander below.
+
+#[cfg(feature = "gui")]
+use crate::timing;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[cfg(feature = "gui")]
+use eframe::egui;
+
+// Samples kept per step
+const SAMPLES: usize = 120;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    Fetch,
+    Parse,
+    Format,
+    #[cfg(feature = "gui")]
+    Frame,
+}
+
+#[cfg(feature = "gui")]
+impl Metric {
+    pub const ALL: [Metric; 4] = [Metric::Fetch, Metric::Parse, Metric::Format, Metric::Frame];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Metric::Fetch => "Fetch (download + JSON)",
+            Metric::Parse => "Parse (models)",
+            Metric::Format => "Format (one report)",
+            Metric::Frame => "Frame (UI update)",
+        }
+    }
+}
+
+static TIMINGS: Mutex<[VecDeque<Duration>; 4]> =
+    Mutex::new([VecDeque::new(), VecDeque::new(), VecDeque::new(), VecDeque::new()]);
+
+pub fn record(metric: Metric, duration: Duration) {
+    let mut timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    let samples = &mut timings[metric as usize];
+    if samples.len() == SAMPLES {
+        samples.pop_front();
+    }
+    samples.push_back(duration);
+}
+
+// Summary of the recent samples of a step
+#[cfg(feature = "gui")]
+pub struct Stats {
+    pub last: Duration,
+    pub average: Duration,
+    pub max: Duration,
+    pub count: usize,
+}
+
+#[cfg(feature = "gui")]
+pub fn stats(metric: Metric) -> Option<Stats> {
+    let timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    let samples = &timings[metric as usize];
+    Some(Stats {
+        last: *samples.back()?,
+        average: samples.iter().sum::<Duration>() / samples.len() as u32,
+        max: samples.iter().copied().max()?,
+        count: samples.len(),
+    })
+}
+
+#[cfg(feature = "gui")]
+fn millis(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
+
+// The overlay in the corner of the window
+#[cfg(feature = "gui")]
+pub fn show_overlay(ctx: &egui::Context) {
+    egui::Window::new("Diagnostics")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+        .resizable(false)
+        .collapsible(true)
+        .show(ctx, |ui| {
+            egui::Grid::new("diagnostics").striped(true).show(ui, |ui| {
+                ui.strong("Step");
+                ui.strong("Last");
+                ui.strong("Average");
+                ui.strong("Max");
+                ui.strong("Samples");
+                ui.end_row();
+                for metric in Metric::ALL {
+                    ui.label(metric.label());
+                    match stats(metric) {
+                        Some(stats) => {
+                            ui.monospace(millis(stats.last));
+                            ui.monospace(millis(stats.average));
+                            ui.monospace(millis(stats.max));
+                            ui.label(stats.count.to_string());
+                        }
+                        None => {
+                            ui.label("-");
+                            ui.label("-");
+                            ui.label("-");
+                            ui.label("0");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+            // Time between the last two frames, including egui's painting
+            let dt = ctx.input(|input| input.unstable_dt);
+            ui.label(format!("Frame interval: {:.1} ms", dt * 1000.0));
+            ui.small("The window only repaints on input or progress, so the interval is not a frame rate. F12 hides this.");
+            show_last_fetch(ui);
+        });
+}
+
+// DNS, connect, TLS, first byte and total of the last API fetch
+#[cfg(feature = "gui")]
+fn show_last_fetch(ui: &mut egui::Ui) {
+    egui::CollapsingHeader::new("Last fetch").id_source("last_fetch").show(ui, |ui| {
+        let Some(timings) = timing::last() else {
+            ui.label("No API fetch yet.");
+            return;
+        };
+        ui.label(format!("{} at {}", timings.url, timings.time.with_timezone(&chrono::Local).format("%H:%M:%S")));
+        if let Some(address) = timings.address {
+            ui.label(format!("Connected to {}", address));
+        }
+        egui::Grid::new("last_fetch_phases").striped(true).show(ui, |ui| {
+            for (label, duration) in timings.phases() {
+                ui.label(label);
+                ui.monospace(duration.map(timing::millis).unwrap_or_else(|| "-".to_string()));
+                ui.end_row();
+            }
+        });
+        if !timings.note.is_empty() {
+            ui.small(&timings.note);
+        }
+        if ui.button("Copy").on_hover_text("Copy the timings, e.g. for a bug report").clicked() {
+            ui.ctx().output_mut(|o| o.copied_text = timings.summary());
+        }
+    });
+}
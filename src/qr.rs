@@ -0,0 +1,99 @@
+// ----------------------
+// QR Codes
+// ----------------------
+
+// A QR code per package URL, so a link can be scanned and downloaded on
+// another device. Codes are shown in the window and saved as PNG files in the
+// export folder, black on white with the four-module quiet zone scanners need.
+
+#[cfg(feature = "gui")]
+use crate::export;
+#[cfg(feature = "gui")]
+use crate::PackageFile;
+#[cfg(any(feature = "gui", test))]
+use image::{GrayImage, Luma};
+#[cfg(feature = "gui")]
+use log::{error, info};
+#[cfg(any(feature = "gui", test))]
+use qrcode::{Color, QrCode};
+#[cfg(feature = "gui")]
+use std::path::{Path, PathBuf};
+
+#[cfg(any(feature = "gui", test))]
+pub const QUIET_ZONE: usize = 4; // Light modules around the code
+#[cfg(feature = "gui")]
+const PNG_MODULE_PIXELS: u32 = 8; // Size of one module in saved PNGs
+
+// The modules of a QR code, without the quiet zone
+#[cfg(any(feature = "gui", test))]
+#[derive(Clone, Debug)]
+pub struct QrMatrix {
+    pub width: usize, // Modules per side
+    dark: Vec<bool>,  // Row by row
+}
+
+#[cfg(any(feature = "gui", test))]
+impl QrMatrix {
+    pub fn new(text: &str) -> Result<Self, String> {
+        let code = QrCode::new(text.as_bytes()).map_err(|e| format!("QR code error: {}", e))?;
+        Ok(Self {
+            width: code.width(),
+            dark: code.to_colors().into_iter().map(|color| color == Color::Dark).collect(),
+        })
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.width + x]
+    }
+
+    // Modules per side including the quiet zone
+    pub fn full_width(&self) -> usize {
+        self.width + QUIET_ZONE * 2
+    }
+
+    // Render with `scale` pixels per module, quiet zone included
+    pub fn to_image(&self, scale: u32) -> GrayImage {
+        let side = self.full_width() as u32 * scale;
+        GrayImage::from_fn(side, side, |x, y| {
+            let (x, y) = ((x / scale) as usize, (y / scale) as usize);
+            let inside = (QUIET_ZONE..QUIET_ZONE + self.width).contains(&x) && (QUIET_ZONE..QUIET_ZONE + self.width).contains(&y);
+            if inside && self.is_dark(x - QUIET_ZONE, y - QUIET_ZONE) {
+                Luma([0])
+            } else {
+                Luma([255])
+            }
+        })
+    }
+}
+
+// Save the QR code of one file's URL, named after the file. Returns the path.
+#[cfg(feature = "gui")]
+pub fn export_qr_png(game_title: &str, file: &PackageFile) -> Result<PathBuf, String> {
+    let path = export::export_dir()?.join(export::export_file_name(&format!("{} {}", game_title, file.file_name()), "png"));
+    save_png(&file.url, &path)?;
+    info!("Exported QR code to {}", path.display());
+    Ok(path)
+}
+
+// Save the QR codes of several files into one new folder. Returns the folder.
+#[cfg(feature = "gui")]
+pub fn export_qr_pngs(game_title: &str, files: &[PackageFile]) -> Result<PathBuf, String> {
+    let folder_name = export::export_file_name(&format!("{} QR Codes", game_title), "");
+    let dir = export::export_dir()?.join(folder_name.trim_end_matches('.'));
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Export error ({}): {}", dir.display(), e))?;
+
+    for file in files {
+        let path = dir.join(format!("{}.png", export::sanitize_file_name(&file.file_name())));
+        save_png(&file.url, &path)?;
+    }
+    info!("Exported {} QR codes to {}", files.len(), dir.display());
+    Ok(dir)
+}
+
+#[cfg(feature = "gui")]
+fn save_png(url: &str, path: &Path) -> Result<(), String> {
+    QrMatrix::new(url)?.to_image(PNG_MODULE_PIXELS).save(path).map_err(|e| {
+        error!("Failed to write QR code {}: {}", path.display(), e);
+        format!("QR code export error: {}", e)
+    })
+}
@@ -0,0 +1,147 @@
+// ----------------------
+// Record and Replay
+// ----------------------
+
+// `--record FILE` saves every API request the program makes, with its full
+// response, to a JSON file. `--replay FILE` answers the same requests from
+// such a file instead of the network. A recording reproduces a bug report
+// exactly as the reporter saw it, runs a demo offline, and drives tests
+// without the live API. Package downloads are not recorded; only the API
+// fetches of the window and the command line are.
+
+use crate::network;
+use crate::timing;
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+// One request and the response it got
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Exchange {
+    pub method: String,
+    pub url: String,
+    pub recorded: DateTime<Utc>,
+    pub status: u16,
+    pub headers: Vec<(String, String)>, // Response headers in the order received
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Recording {
+    exchanges: Vec<Exchange>,
+}
+
+// Saves each exchange as it happens, so a recording survives the program
+// being stopped
+pub struct Recorder {
+    path: PathBuf,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl Recorder {
+    // Start a recording, replacing any file at `path`
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let recorder = Recorder { path: path.to_path_buf(), exchanges: Mutex::new(Vec::new()) };
+        recorder.save(&[])?;
+        info!("Recording requests to {}", path.display());
+        Ok(recorder)
+    }
+
+    // Fetch `url` from the network and record the exchange. Returns the body.
+    pub fn get(&self, url: &str) -> Result<Vec<u8>, String> {
+        let response = network::get(url)?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+            .collect();
+        let body = response.bytes().map_err(|e| format!("Response text error: {}", e))?.to_vec();
+        let exchange = Exchange {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            recorded: Utc::now(),
+            status,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        };
+        let mut exchanges = self.exchanges.lock().unwrap_or_else(|e| e.into_inner());
+        exchanges.push(exchange);
+        // A recording that can't be written shouldn't fail the fetch itself
+        if let Err(e) = self.save(&exchanges) {
+            error!("{}", e);
+        }
+        Ok(body)
+    }
+
+    fn save(&self, exchanges: &[Exchange]) -> Result<(), String> {
+        let recording = Recording { exchanges: exchanges.to_vec() };
+        let json = serde_json::to_string_pretty(&recording).map_err(|e| format!("Recording error: {}", e))?;
+        std::fs::write(&self.path, json).map_err(|e| format!("Recording error ({}): {}", self.path.display(), e))
+    }
+}
+
+// Answers requests from a recording. Repeated requests for a URL get its
+// recorded responses in order, then the last one again, so `watch` and
+// automatic checks keep running through the end of a recording.
+pub struct Replay {
+    exchanges: Vec<Exchange>,
+    served: Mutex<HashMap<String, usize>>, // Responses already given per URL
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Replay error ({}): {}", path.display(), e))?;
+        let recording: Recording =
+            serde_json::from_str(&text).map_err(|e| format!("Replay error ({}): {}", path.display(), e))?;
+        info!("Replaying {} recorded requests from {}", recording.exchanges.len(), path.display());
+        Ok(Replay { exchanges: recording.exchanges, served: Mutex::new(HashMap::new()) })
+    }
+
+    // The recorded response to the next request for `url`
+    pub fn respond(&self, url: &str) -> Result<&Exchange, String> {
+        let recorded: Vec<&Exchange> = self.exchanges.iter().filter(|exchange| exchange.url == url).collect();
+        let Some(last) = recorded.last() else {
+            return Err(format!("Replay error: no recorded response for {}", url));
+        };
+        let mut served = self.served.lock().unwrap_or_else(|e| e.into_inner());
+        let count = served.entry(url.to_string()).or_default();
+        let exchange = recorded.get(*count).unwrap_or(last);
+        *count += 1;
+        Ok(exchange)
+    }
+}
+
+enum Mode {
+    Record(Recorder),
+    Replay(Replay),
+}
+
+static MODE: OnceLock<Mode> = OnceLock::new();
+
+// Record every request from now on
+pub fn start_recording(path: &Path) -> Result<(), String> {
+    let _ = MODE.set(Mode::Record(Recorder::create(path)?));
+    Ok(())
+}
+
+// Answer every request from a recording from now on
+pub fn start_replay(path: &Path) -> Result<(), String> {
+    let _ = MODE.set(Mode::Replay(Replay::load(path)?));
+    Ok(())
+}
+
+// GET `url` from the network, the recording being replayed, or the network
+// while recording. Errors read like the network's would.
+pub fn get(url: &str) -> Result<Box<dyn Read>, String> {
+    match MODE.get() {
+        None if timing::wanted() => timing::get(url),
+        None => network::get(url).map(|response| Box::new(response) as Box<dyn Read>),
+        Some(Mode::Record(recorder)) => Ok(Box::new(Cursor::new(recorder.get(url)?))),
+        Some(Mode::Replay(replay)) => Ok(Box::new(Cursor::new(replay.respond(url)?.body.clone().into_bytes()))),
+    }
+}
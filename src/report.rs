@@ -0,0 +1,1172 @@
+// ----------------------
+// Report Templates
+// ----------------------
+
+// The formatted messages are rendered from Handlebars templates so users can
+// change the layout without a new release. The default templates reproduce
+// the built-in report format, with their text taken from `labels` in the
+// chosen report language.
+
+use crate::export::file_name_from_url;
+use crate::history::VersionKind;
+use crate::profiling::{self, Metric};
+use crate::variables::Variables;
+use crate::version::{self, Version};
+use crate::{bytes_to_gb, map_language_code, AudioPackage, GameData, GamePackage, Major, Package, PreDownload};
+use chrono::{DateTime, Utc};
+use handlebars::{handlebars_helper, Handlebars};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const DEFAULT_MAIN_TEMPLATE: &str = "\
+{{#each packages}}
+{{#if major}}
+{{#if @root.include_game}}
+{{@root.labels.game_packages}} ({{@root.labels.version}} {{major.version}}):
+{{#each major.game_pkgs}}
+[{{@root.labels.part}} {{part}}] {{file_name}}
+[{{@root.labels.url}}] {{url}}
+[{{@root.labels.size}}] {{size}}
+[{{@root.labels.decompressed_size}}] {{decompressed_size}}
+[{{@root.labels.md5}}] {{md5}}
+
+{{/each}}
+{{/if}}
+{{#if @root.include_audio}}
+{{@root.labels.audio_packages}}{{#unless @root.include_game}} ({{@root.labels.version}} {{major.version}}){{/unless}}:
+{{#each major.audio_pkgs}}
+[{{@root.labels.language}}] {{language}}
+[{{@root.labels.file}}] {{file_name}}
+[{{@root.labels.url}}] {{url}}
+[{{@root.labels.size}}] {{size}}
+[{{@root.labels.decompressed_size}}] {{decompressed_size}}
+[{{@root.labels.md5}}] {{md5}}
+
+{{/each}}
+{{/if}}
+{{else}}
+{{@root.labels.no_major}}
+{{/if}}
+{{/each}}
+";
+
+pub const DEFAULT_PRE_DOWNLOAD_MAIN_TEMPLATE: &str = "\
+{{#if major}}
+{{#if include_game}}
+{{@root.labels.pre_download_game_packages}} ({{@root.labels.version}} {{major.version}}):
+{{#each major.game_pkgs}}
+[{{@root.labels.part}} {{part}}] {{file_name}}
+[{{@root.labels.url}}] {{url}}
+[{{@root.labels.size}}] {{size}}
+[{{@root.labels.decompressed_size}}] {{decompressed_size}}
+[{{@root.labels.md5}}] {{md5}}
+
+{{/each}}
+{{/if}}
+{{#if include_audio}}
+{{@root.labels.pre_download_audio_packages}}{{#unless include_game}} ({{@root.labels.version}} {{major.version}}){{/unless}}:
+{{#each major.audio_pkgs}}
+[{{@root.labels.language}}] {{language}}
+[{{@root.labels.file}}] {{file_name}}
+[{{@root.labels.url}}] {{url}}
+[{{@root.labels.size}}] {{size}}
+[{{@root.labels.decompressed_size}}] {{decompressed_size}}
+[{{@root.labels.md5}}] {{md5}}
+
+{{/each}}
+{{/if}}
+{{else}}
+{{@root.labels.no_pre_download}}{{/if}}";
+
+pub const DEFAULT_PRE_DOWNLOAD_PATCHES_TEMPLATE: &str = "\
+{{#if patches}}
+{{@root.labels.pre_download_patches}}:
+
+{{#each patches}}
+# {{@root.labels.version}} {{version_short}}
+{{@root.labels.version}}: {{from_version}} {{@root.labels.to}} {{to_version}}
+{{#if @root.include_game}}
+{{#each game_pkgs}}
+[{{@root.labels.file}}] {{file_name}}
+[{{@root.labels.game_patch_url}}] {{url}}
+[{{@root.labels.size}}] {{size}}
+[{{@root.labels.decompressed_size}}] {{decompressed_size}}
+[{{@root.labels.md5}}] {{md5}}
+
+{{/each}}
+{{/if}}
+{{#if @root.include_audio}}
+{{#each audio_pkgs}}
+[{{@root.labels.audio_patch_language}}] {{language}}
+[{{@root.labels.file}}] {{file_name}}
+[{{@root.labels.url}}] {{url}}
+[{{@root.labels.size}}] {{size}}
+[{{@root.labels.decompressed_size}}] {{decompressed_size}}
+[{{@root.labels.md5}}] {{md5}}
+
+{{/each}}
+{{/if}}
+{{/each}}
+{{/if}}
+";
+
+// Changes made to the default templates since they were first saved to the
+// settings, newest first, as (text now, text before). Undoing them one after
+// the other gives back each earlier default.
+const DEFAULT_TEMPLATE_CHANGES: [(&str, &str); 4] = [
+    (
+        "{{@root.labels.version}}: {{from_version}} {{@root.labels.to}} {{to_version}}\n",
+        "{{@root.labels.version}}: {{version}} {{@root.labels.to}} {{../current_version}}\n",
+    ),
+    ("[{{@root.labels.part}} {{part}}] {{file_name}}\n", "[{{@root.labels.part}} {{part}}]\n"),
+    ("[{{@root.labels.file}}] {{file_name}}\n", ""),
+    ("[{{@root.labels.md5}}] {{md5}}\n", ""),
+];
+
+// ----------------------
+// BBCode Templates
+// ----------------------
+
+// Built-in templates for forums that use BBCode; audio and patch details are
+// wrapped in spoilers to keep posts short.
+
+pub const BBCODE_MAIN_TEMPLATE: &str = "\
+{{#each packages}}
+{{#if major}}
+[size=150][b]{{@root.labels.game_packages}} ({{@root.labels.version}} {{major.version}})[/b][/size]
+{{#if @root.include_game}}
+[list]
+{{#each major.game_pkgs}}
+[*][b]{{@root.labels.part}} {{part}}[/b] ({{file_name}}): [url={{url}}]{{url}}[/url]
+{{@root.labels.size}}: {{size}} ({{@root.labels.decompressed}}: {{decompressed_size}})
+{{@root.labels.md5}}: {{md5}}
+{{/each}}
+[/list]
+{{/if}}
+{{#if @root.include_audio}}
+[spoiler={{@root.labels.audio_packages}}]
+[list]
+{{#each major.audio_pkgs}}
+[*][b]{{language}}[/b] ({{file_name}}): [url={{url}}]{{url}}[/url]
+{{@root.labels.size}}: {{size}} ({{@root.labels.decompressed}}: {{decompressed_size}})
+{{@root.labels.md5}}: {{md5}}
+{{/each}}
+[/list]
+[/spoiler]
+{{/if}}
+{{else}}
+{{@root.labels.no_major}}
+{{/if}}
+{{/each}}
+";
+
+pub const BBCODE_PRE_DOWNLOAD_MAIN_TEMPLATE: &str = "\
+{{#if major}}
+[size=150][b]{{@root.labels.pre_download_game_packages}} ({{@root.labels.version}} {{major.version}})[/b][/size]
+{{#if include_game}}
+[list]
+{{#each major.game_pkgs}}
+[*][b]{{@root.labels.part}} {{part}}[/b] ({{file_name}}): [url={{url}}]{{url}}[/url]
+{{@root.labels.size}}: {{size}} ({{@root.labels.decompressed}}: {{decompressed_size}})
+{{@root.labels.md5}}: {{md5}}
+{{/each}}
+[/list]
+{{/if}}
+{{#if include_audio}}
+[spoiler={{@root.labels.pre_download_audio_packages}}]
+[list]
+{{#each major.audio_pkgs}}
+[*][b]{{language}}[/b] ({{file_name}}): [url={{url}}]{{url}}[/url]
+{{@root.labels.size}}: {{size}} ({{@root.labels.decompressed}}: {{decompressed_size}})
+{{@root.labels.md5}}: {{md5}}
+{{/each}}
+[/list]
+[/spoiler]
+{{/if}}
+{{else}}
+{{@root.labels.no_pre_download}}{{/if}}";
+
+pub const BBCODE_PRE_DOWNLOAD_PATCHES_TEMPLATE: &str = "\
+{{#if patches}}
+[size=150][b]{{@root.labels.pre_download_patches}}[/b][/size]
+{{#each patches}}
+[spoiler={{@root.labels.version}} {{from_version}} {{@root.labels.to}} {{to_version}}]
+[list]
+{{#if @root.include_game}}
+{{#each game_pkgs}}
+[*][b]{{@root.labels.game_patch}}[/b] ({{file_name}}): [url={{url}}]{{url}}[/url]
+{{@root.labels.size}}: {{size}} ({{@root.labels.decompressed}}: {{decompressed_size}})
+{{@root.labels.md5}}: {{md5}}
+{{/each}}
+{{/if}}
+{{#if @root.include_audio}}
+{{#each audio_pkgs}}
+[*][b]{{language}} {{@root.labels.audio_patch}}[/b] ({{file_name}}): [url={{url}}]{{url}}[/url]
+{{@root.labels.size}}: {{size}} ({{@root.labels.decompressed}}: {{decompressed_size}})
+{{@root.labels.md5}}: {{md5}}
+{{/each}}
+{{/if}}
+[/list]
+[/spoiler]
+{{/each}}
+{{/if}}
+";
+
+// ----------------------
+// Discord Templates
+// ----------------------
+
+// Built-in Markdown templates for Discord. URLs are wrapped in `<...>` to
+// suppress link embeds, and sizes are shown as inline code.
+
+pub const DISCORD_MAIN_TEMPLATE: &str = "\
+{{#each packages}}
+{{#if major}}
+{{#if @root.include_game}}
+**{{@root.labels.game_packages}} ({{@root.labels.version}} {{major.version}})**
+{{#each major.game_pkgs}}
+{{@root.labels.part}} {{part}} (`{{file_name}}`): <{{url}}>
+{{@root.labels.size}}: `{{size}}` | {{@root.labels.decompressed}}: `{{decompressed_size}}` | {{@root.labels.md5}}: `{{md5}}`
+{{/each}}
+
+{{/if}}
+{{#if @root.include_audio}}
+**{{@root.labels.audio_packages}}{{#unless @root.include_game}} ({{@root.labels.version}} {{major.version}}){{/unless}}**
+{{#each major.audio_pkgs}}
+{{language}} (`{{file_name}}`): <{{url}}>
+{{@root.labels.size}}: `{{size}}` | {{@root.labels.decompressed}}: `{{decompressed_size}}` | {{@root.labels.md5}}: `{{md5}}`
+{{/each}}
+
+{{/if}}
+{{else}}
+{{@root.labels.no_major}}
+{{/if}}
+{{/each}}
+";
+
+pub const DISCORD_PRE_DOWNLOAD_MAIN_TEMPLATE: &str = "\
+{{#if major}}
+{{#if include_game}}
+**{{@root.labels.pre_download_game_packages}} ({{@root.labels.version}} {{major.version}})**
+{{#each major.game_pkgs}}
+{{@root.labels.part}} {{part}} (`{{file_name}}`): <{{url}}>
+{{@root.labels.size}}: `{{size}}` | {{@root.labels.decompressed}}: `{{decompressed_size}}` | {{@root.labels.md5}}: `{{md5}}`
+{{/each}}
+
+{{/if}}
+{{#if include_audio}}
+**{{@root.labels.pre_download_audio_packages}}{{#unless include_game}} ({{@root.labels.version}} {{major.version}}){{/unless}}**
+{{#each major.audio_pkgs}}
+{{language}} (`{{file_name}}`): <{{url}}>
+{{@root.labels.size}}: `{{size}}` | {{@root.labels.decompressed}}: `{{decompressed_size}}` | {{@root.labels.md5}}: `{{md5}}`
+{{/each}}
+{{/if}}
+{{else}}
+{{@root.labels.no_pre_download}}{{/if}}";
+
+pub const DISCORD_PRE_DOWNLOAD_PATCHES_TEMPLATE: &str = "\
+{{#if patches}}
+**{{@root.labels.pre_download_patches}}**
+
+{{#each patches}}
+__{{@root.labels.version}} {{from_version}} {{@root.labels.to}} {{to_version}}__
+{{#if @root.include_game}}
+{{#each game_pkgs}}
+{{@root.labels.game_patch}} (`{{file_name}}`): <{{url}}>
+{{@root.labels.size}}: `{{size}}` | {{@root.labels.decompressed}}: `{{decompressed_size}}` | {{@root.labels.md5}}: `{{md5}}`
+{{/each}}
+{{/if}}
+{{#if @root.include_audio}}
+{{#each audio_pkgs}}
+{{language}} {{@root.labels.audio_patch}} (`{{file_name}}`): <{{url}}>
+{{@root.labels.size}}: `{{size}}` | {{@root.labels.decompressed}}: `{{decompressed_size}}` | {{@root.labels.md5}}: `{{md5}}`
+{{/each}}
+{{/if}}
+
+{{/each}}
+{{/if}}
+";
+
+// ----------------------
+// HTML Templates
+// ----------------------
+
+// Built-in templates for rich-text copies: bold headings, tables and real
+// links, so pasting into Word, Google Docs or Outlook keeps the layout. Data
+// from the API and the language table goes through the `html` helper, which
+// escapes it.
+
+pub const HTML_MAIN_TEMPLATE: &str = "\
+{{#each packages}}
+{{#if major}}
+{{#if @root.include_game}}
+<h3>{{@root.labels.game_packages}} ({{@root.labels.version}} {{html major.version}})</h3>
+<table border=\"1\" cellpadding=\"4\" cellspacing=\"0\">
+<tr><th>{{@root.labels.part}}</th><th>{{@root.labels.file}}</th><th>{{@root.labels.url}}</th><th>{{@root.labels.size}}</th><th>{{@root.labels.decompressed_size}}</th><th>{{@root.labels.md5}}</th></tr>
+{{#each major.game_pkgs}}
+<tr><td>{{part}}</td><td>{{html file_name}}</td><td><a href=\"{{html url}}\">{{html url}}</a></td><td>{{size}}</td><td>{{decompressed_size}}</td><td><code>{{html md5}}</code></td></tr>
+{{/each}}
+</table>
+{{/if}}
+{{#if @root.include_audio}}
+<h3>{{@root.labels.audio_packages}}{{#unless @root.include_game}} ({{@root.labels.version}} {{html major.version}}){{/unless}}</h3>
+<table border=\"1\" cellpadding=\"4\" cellspacing=\"0\">
+<tr><th>{{@root.labels.language}}</th><th>{{@root.labels.file}}</th><th>{{@root.labels.url}}</th><th>{{@root.labels.size}}</th><th>{{@root.labels.decompressed_size}}</th><th>{{@root.labels.md5}}</th></tr>
+{{#each major.audio_pkgs}}
+<tr><td>{{html language}}</td><td>{{html file_name}}</td><td><a href=\"{{html url}}\">{{html url}}</a></td><td>{{size}}</td><td>{{decompressed_size}}</td><td><code>{{html md5}}</code></td></tr>
+{{/each}}
+</table>
+{{/if}}
+{{else}}
+<p>{{@root.labels.no_major}}</p>
+{{/if}}
+{{/each}}
+";
+
+pub const HTML_PRE_DOWNLOAD_MAIN_TEMPLATE: &str = "\
+{{#if major}}
+{{#if include_game}}
+<h3>{{@root.labels.pre_download_game_packages}} ({{@root.labels.version}} {{html major.version}})</h3>
+<table border=\"1\" cellpadding=\"4\" cellspacing=\"0\">
+<tr><th>{{@root.labels.part}}</th><th>{{@root.labels.file}}</th><th>{{@root.labels.url}}</th><th>{{@root.labels.size}}</th><th>{{@root.labels.decompressed_size}}</th><th>{{@root.labels.md5}}</th></tr>
+{{#each major.game_pkgs}}
+<tr><td>{{part}}</td><td>{{html file_name}}</td><td><a href=\"{{html url}}\">{{html url}}</a></td><td>{{size}}</td><td>{{decompressed_size}}</td><td><code>{{html md5}}</code></td></tr>
+{{/each}}
+</table>
+{{/if}}
+{{#if include_audio}}
+<h3>{{@root.labels.pre_download_audio_packages}}{{#unless include_game}} ({{@root.labels.version}} {{html major.version}}){{/unless}}</h3>
+<table border=\"1\" cellpadding=\"4\" cellspacing=\"0\">
+<tr><th>{{@root.labels.language}}</th><th>{{@root.labels.file}}</th><th>{{@root.labels.url}}</th><th>{{@root.labels.size}}</th><th>{{@root.labels.decompressed_size}}</th><th>{{@root.labels.md5}}</th></tr>
+{{#each major.audio_pkgs}}
+<tr><td>{{html language}}</td><td>{{html file_name}}</td><td><a href=\"{{html url}}\">{{html url}}</a></td><td>{{size}}</td><td>{{decompressed_size}}</td><td><code>{{html md5}}</code></td></tr>
+{{/each}}
+</table>
+{{/if}}
+{{else}}
+<p>{{@root.labels.no_pre_download}}</p>{{/if}}";
+
+pub const HTML_PRE_DOWNLOAD_PATCHES_TEMPLATE: &str = "\
+{{#if patches}}
+<h3>{{@root.labels.pre_download_patches}}</h3>
+{{#each patches}}
+<h4>{{@root.labels.version}} {{html from_version}} {{@root.labels.to}} {{html to_version}}</h4>
+<table border=\"1\" cellpadding=\"4\" cellspacing=\"0\">
+<tr><th></th><th>{{@root.labels.file}}</th><th>{{@root.labels.url}}</th><th>{{@root.labels.size}}</th><th>{{@root.labels.decompressed_size}}</th><th>{{@root.labels.md5}}</th></tr>
+{{#if @root.include_game}}
+{{#each game_pkgs}}
+<tr><td>{{@root.labels.game_patch}}</td><td>{{html file_name}}</td><td><a href=\"{{html url}}\">{{html url}}</a></td><td>{{size}}</td><td>{{decompressed_size}}</td><td><code>{{html md5}}</code></td></tr>
+{{/each}}
+{{/if}}
+{{#if @root.include_audio}}
+{{#each audio_pkgs}}
+<tr><td>{{html language}} {{@root.labels.audio_patch}}</td><td>{{html file_name}}</td><td><a href=\"{{html url}}\">{{html url}}</a></td><td>{{size}}</td><td>{{decompressed_size}}</td><td><code>{{html md5}}</code></td></tr>
+{{/each}}
+{{/if}}
+</table>
+{{/each}}
+{{/if}}
+";
+
+// Discord rejects messages longer than this many characters
+#[cfg(feature = "gui")]
+pub const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+// Variables available to templates, shown in the settings tab
+#[cfg(feature = "gui")]
+pub const TEMPLATE_VARIABLES_HELP: &str = "\
+Main: packages[] { game_id, game_name, biz, major }, include_game, include_audio
+Pre-download (Main): major, include_game, include_audio
+Pre-download (Patches): current_version, patches[] { from_version, to_version, version, version_short, game_pkgs, audio_pkgs }, include_game, include_audio
+patches[]: one per version patched from, newest first; version is from_version, version_short drops trailing .0 parts (5.0.0 -> 5.0)
+include_game / include_audio: false when copying only audio / only game packages
+major: { version, game_pkgs, audio_pkgs }
+game_pkgs[]: { part, file_name, url, md5, size, decompressed_size, size_bytes, decompressed_size_bytes }
+audio_pkgs[]: { language, language_code, file_name, url, md5, size, decompressed_size, size_bytes, decompressed_size_bytes }
+labels: report text in the report language, e.g. labels.size; inside #each use @root.labels.size
+vars: the section's version as hooks and messages see it, e.g. vars.total_size_gb, vars.file_count, vars.timestamp";
+
+// User-editable templates, one per report section, and the language their
+// labels are rendered in
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ReportTemplates {
+    pub main: String,
+    pub pre_download_main: String,
+    pub pre_download_patches: String,
+    pub language: ReportLanguage,
+}
+
+// The report sections, each rendered from its own template
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ReportSection {
+    Main,
+    PreDownloadMain,
+    PreDownloadPatches,
+}
+
+impl ReportSection {
+    pub const ALL: [ReportSection; 3] = [
+        ReportSection::Main,
+        ReportSection::PreDownloadMain,
+        ReportSection::PreDownloadPatches,
+    ];
+
+    // Name of the section's template in the settings
+    #[cfg(feature = "gui")]
+    pub fn key(self) -> &'static str {
+        match self {
+            ReportSection::Main => "main",
+            ReportSection::PreDownloadMain => "pre_download_main",
+            ReportSection::PreDownloadPatches => "pre_download_patches",
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn title(self) -> &'static str {
+        match self {
+            ReportSection::Main => "Main Data",
+            ReportSection::PreDownloadMain => "Pre-download (Main)",
+            ReportSection::PreDownloadPatches => "Pre-download (Patches)",
+        }
+    }
+}
+
+// Which package types a partial copy includes
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum PackageCategory {
+    #[default]
+    All,
+    Game,
+    Audio,
+}
+
+// Narrows a rendered section down to part of the data
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct ReportFilter {
+    pub category: PackageCategory,
+    pub patch_version: Option<String>, // Only render this patch (Patches section)
+}
+
+impl ReportFilter {
+    #[cfg(feature = "gui")]
+    pub fn category(category: PackageCategory) -> Self {
+        Self {
+            category,
+            patch_version: None,
+        }
+    }
+
+    fn include_game(&self) -> bool {
+        self.category != PackageCategory::Audio
+    }
+
+    fn include_audio(&self) -> bool {
+        self.category != PackageCategory::Game
+    }
+}
+
+// Built-in alternative formats offered by the copy buttons
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ExportFormat {
+    BBCode,
+    Discord,
+    Html,
+}
+
+impl ReportTemplates {
+    pub fn get(&self, section: ReportSection) -> &str {
+        match section {
+            ReportSection::Main => &self.main,
+            ReportSection::PreDownloadMain => &self.pre_download_main,
+            ReportSection::PreDownloadPatches => &self.pre_download_patches,
+        }
+    }
+
+    pub fn get_mut(&mut self, section: ReportSection) -> &mut String {
+        match section {
+            ReportSection::Main => &mut self.main,
+            ReportSection::PreDownloadMain => &mut self.pre_download_main,
+            ReportSection::PreDownloadPatches => &mut self.pre_download_patches,
+        }
+    }
+
+    // Templates saved before reports were localized spell their text out in
+    // English, and ones saved before a `DEFAULT_TEMPLATE_CHANGES` change lack
+    // it. Ones still at such an old default are switched to the current
+    // default so the report language and the new lines apply to them.
+    pub fn upgrade_defaults(&mut self) {
+        let defaults = Self::default();
+        for section in ReportSection::ALL {
+            let default = defaults.get(section);
+            let mut earlier = vec![default.to_string()];
+            for (now, before) in DEFAULT_TEMPLATE_CHANGES {
+                let undone = earlier[earlier.len() - 1].replace(now, before);
+                earlier.push(undone);
+            }
+            let is_old_default = earlier
+                .iter()
+                .any(|old| old == self.get(section) || ReportLanguage::English.spell_out(old) == self.get(section));
+            if is_old_default {
+                *self.get_mut(section) = default.to_string();
+            }
+        }
+    }
+}
+
+// Template for a section in one of the built-in export formats
+pub fn builtin_template(format: ExportFormat, section: ReportSection) -> &'static str {
+    match (format, section) {
+        (ExportFormat::BBCode, ReportSection::Main) => BBCODE_MAIN_TEMPLATE,
+        (ExportFormat::BBCode, ReportSection::PreDownloadMain) => BBCODE_PRE_DOWNLOAD_MAIN_TEMPLATE,
+        (ExportFormat::BBCode, ReportSection::PreDownloadPatches) => BBCODE_PRE_DOWNLOAD_PATCHES_TEMPLATE,
+        (ExportFormat::Discord, ReportSection::Main) => DISCORD_MAIN_TEMPLATE,
+        (ExportFormat::Discord, ReportSection::PreDownloadMain) => DISCORD_PRE_DOWNLOAD_MAIN_TEMPLATE,
+        (ExportFormat::Discord, ReportSection::PreDownloadPatches) => DISCORD_PRE_DOWNLOAD_PATCHES_TEMPLATE,
+        (ExportFormat::Html, ReportSection::Main) => HTML_MAIN_TEMPLATE,
+        (ExportFormat::Html, ReportSection::PreDownloadMain) => HTML_PRE_DOWNLOAD_MAIN_TEMPLATE,
+        (ExportFormat::Html, ReportSection::PreDownloadPatches) => HTML_PRE_DOWNLOAD_PATCHES_TEMPLATE,
+    }
+}
+
+impl Default for ReportTemplates {
+    fn default() -> Self {
+        Self {
+            main: DEFAULT_MAIN_TEMPLATE.to_string(),
+            pre_download_main: DEFAULT_PRE_DOWNLOAD_MAIN_TEMPLATE.to_string(),
+            pre_download_patches: DEFAULT_PRE_DOWNLOAD_PATCHES_TEMPLATE.to_string(),
+            language: ReportLanguage::default(),
+        }
+    }
+}
+
+// ----------------------
+// Report Languages
+// ----------------------
+
+// Reports can be pasted into Chinese, Japanese and Korean communities as they
+// are: headers, field names and audio language names come from these tables
+// in the chosen language. Only the text around the data is translated; URLs,
+// versions and sizes stay as the API gives them.
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ReportLanguage {
+    #[default]
+    English,
+    ChineseSimplified,
+    Japanese,
+    Korean,
+}
+
+// Report text by label: English, Simplified Chinese, Japanese, Korean
+const LABELS: [(&str, [&str; 4]); 26] = [
+    ("game_packages", ["Game Packages", "游戏本体", "ゲーム本体", "게임 패키지"]),
+    ("audio_packages", ["Audio Packages", "语音包", "ボイスパック", "음성 패키지"]),
+    (
+        "pre_download_game_packages",
+        ["Pre-download Game Packages", "预下载游戏本体", "事前ダウンロード ゲーム本体", "사전 다운로드 게임 패키지"],
+    ),
+    (
+        "pre_download_audio_packages",
+        ["Pre-download Audio Packages", "预下载语音包", "事前ダウンロード ボイスパック", "사전 다운로드 음성 패키지"],
+    ),
+    (
+        "pre_download_patches",
+        ["Pre-download Patches", "预下载更新包", "事前ダウンロード 差分パッチ", "사전 다운로드 패치"],
+    ),
+    ("version", ["Version", "版本", "バージョン", "버전"]),
+    ("to", ["to", "至", "→", "→"]),
+    ("part", ["Part", "分卷", "パート", "파트"]),
+    ("url", ["URL", "链接", "URL", "URL"]),
+    ("size", ["Size", "大小", "サイズ", "크기"]),
+    ("decompressed_size", ["Decompressed Size", "解压后大小", "展開後サイズ", "압축 해제 크기"]),
+    ("decompressed", ["Decompressed", "解压后", "展開後", "압축 해제"]),
+    ("md5", ["MD5", "MD5", "MD5", "MD5"]),
+    ("file", ["File", "文件", "ファイル", "파일"]),
+    ("language", ["Language", "语言", "言語", "언어"]),
+    ("game_patch", ["Game Patch", "游戏更新包", "ゲーム差分パッチ", "게임 패치"]),
+    ("game_patch_url", ["Game Patch URL", "游戏更新包链接", "ゲーム差分パッチ URL", "게임 패치 URL"]),
+    ("audio_patch", ["Audio Patch", "语音更新包", "ボイス差分パッチ", "음성 패치"]),
+    ("audio_patch_language", ["Audio Patch Language", "语音更新包语言", "ボイス差分パッチ 言語", "음성 패치 언어"]),
+    (
+        "no_major",
+        ["No major version data available.", "暂无版本数据。", "バージョンデータがありません。", "버전 데이터가 없습니다."],
+    ),
+    (
+        "no_pre_download",
+        [
+            "No pre-download major version data available.",
+            "暂无预下载版本数据。",
+            "事前ダウンロードのバージョンデータがありません。",
+            "사전 다운로드 버전 데이터가 없습니다.",
+        ],
+    ),
+    ("package_report", ["Package Report", "安装包报告", "パッケージレポート", "패키지 보고서"]),
+    ("generated", ["Generated", "生成时间", "生成日時", "생성 시각"]),
+    ("section_main", ["Main Data", "本体数据", "本体データ", "본체 데이터"]),
+    (
+        "section_pre_download_main",
+        ["Pre-download (Main)", "预下载（本体）", "事前ダウンロード（本体）", "사전 다운로드 (본체)"],
+    ),
+    (
+        "section_pre_download_patches",
+        ["Pre-download (Patches)", "预下载（更新包）", "事前ダウンロード（差分パッチ）", "사전 다운로드 (패치)"],
+    ),
+];
+
+// Audio language names in the languages other than English
+const LANGUAGE_NAMES: [(&str, [&str; 3]); 4] = [
+    ("zh-cn", ["中文", "中国語", "중국어"]),
+    ("en-us", ["英语", "英語", "영어"]),
+    ("ja-jp", ["日语", "日本語", "일본어"]),
+    ("ko-kr", ["韩语", "韓国語", "한국어"]),
+];
+
+impl ReportLanguage {
+    #[cfg(feature = "gui")]
+    pub const ALL: [ReportLanguage; 4] = [
+        ReportLanguage::English,
+        ReportLanguage::ChineseSimplified,
+        ReportLanguage::Japanese,
+        ReportLanguage::Korean,
+    ];
+
+    #[cfg(feature = "gui")]
+    pub fn label(self) -> &'static str {
+        match self {
+            ReportLanguage::English => "English",
+            ReportLanguage::ChineseSimplified => "Chinese (Simplified)",
+            ReportLanguage::Japanese => "Japanese",
+            ReportLanguage::Korean => "Korean",
+        }
+    }
+
+    // Column of this language in the tables above
+    fn column(self) -> usize {
+        self as usize
+    }
+
+    pub fn text(self, key: &str) -> &'static str {
+        LABELS
+            .iter()
+            .find(|(label, _)| *label == key)
+            .map(|(_, texts)| texts[self.column()])
+            .unwrap_or_default()
+    }
+
+    // Name of an audio language code; codes without a translation keep their English name
+    pub fn language_name(self, code: &str) -> String {
+        let translated = match self.column().checked_sub(1) {
+            Some(column) => LANGUAGE_NAMES
+                .iter()
+                .find(|(known, _)| known.eq_ignore_ascii_case(code))
+                .map(|(_, names)| names[column]),
+            None => None,
+        };
+        translated.map(str::to_string).unwrap_or_else(|| map_language_code(code))
+    }
+
+    pub fn section_title(self, section: ReportSection) -> &'static str {
+        self.text(match section {
+            ReportSection::Main => "section_main",
+            ReportSection::PreDownloadMain => "section_pre_download_main",
+            ReportSection::PreDownloadPatches => "section_pre_download_patches",
+        })
+    }
+
+    // Title of a game's combined report, e.g. "Genshin Impact Package Report"
+    pub fn report_title(self, game_name: &str) -> String {
+        format!("{} {}", game_name, self.text("package_report"))
+    }
+
+    // Every label in this language, as templates see them
+    fn labels(self) -> BTreeMap<&'static str, &'static str> {
+        LABELS.iter().map(|(key, texts)| (*key, texts[self.column()])).collect()
+    }
+
+    // A template with its labels replaced by their text in this language
+    fn spell_out(self, template: &str) -> String {
+        LABELS.iter().fold(template.to_string(), |template, (key, texts)| {
+            template.replace(&format!("{{{{@root.labels.{}}}}}", key), texts[self.column()])
+        })
+    }
+}
+
+// ----------------------
+// Template Context
+// ----------------------
+
+#[derive(Serialize)]
+struct MainContext {
+    packages: Vec<GamePackageContext>,
+    include_game: bool,
+    include_audio: bool,
+    labels: BTreeMap<&'static str, &'static str>,
+    vars: Option<Variables>,
+}
+
+#[derive(Serialize)]
+struct GamePackageContext {
+    game_id: String,
+    game_name: String,
+    biz: String,
+    major: Option<MajorContext>,
+}
+
+#[derive(Serialize)]
+struct PreDownloadMainContext {
+    major: Option<MajorContext>,
+    include_game: bool,
+    include_audio: bool,
+    labels: BTreeMap<&'static str, &'static str>,
+    vars: Option<Variables>,
+}
+
+#[derive(Serialize)]
+struct PatchesContext {
+    current_version: String, // The pre-download's version
+    patches: Vec<PatchContext>,
+    include_game: bool,
+    include_audio: bool,
+    labels: BTreeMap<&'static str, &'static str>,
+    vars: Option<Variables>,
+}
+
+#[derive(Serialize)]
+struct MajorContext {
+    version: String,
+    game_pkgs: Vec<PackageContext>,
+    audio_pkgs: Vec<AudioPackageContext>,
+}
+
+#[derive(Serialize)]
+struct PatchContext {
+    from_version: String,
+    to_version: String,
+    version: String,       // Same as from_version, for templates saved before it
+    version_short: String, // e.g., "5.0.0" -> "5.0"
+    game_pkgs: Vec<PackageContext>,
+    audio_pkgs: Vec<AudioPackageContext>,
+}
+
+#[derive(Serialize)]
+struct PackageContext {
+    part: usize,
+    file_name: String, // Name the file is saved under, taken from its URL
+    url: String,
+    md5: String,
+    size: String,
+    decompressed_size: String,
+    size_bytes: u64,
+    decompressed_size_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct AudioPackageContext {
+    language: String,
+    language_code: String,
+    file_name: String,
+    url: String,
+    md5: String,
+    size: String,
+    decompressed_size: String,
+    size_bytes: u64,
+    decompressed_size_bytes: u64,
+}
+
+impl MajorContext {
+    fn new(major: &Major, language: ReportLanguage) -> Self {
+        Self {
+            version: major.version.clone(),
+            game_pkgs: game_pkgs_context(&major.game_pkgs),
+            audio_pkgs: audio_pkgs_context(&major.audio_pkgs, language),
+        }
+    }
+}
+
+fn game_pkgs_context(pkgs: &[Package]) -> Vec<PackageContext> {
+    pkgs.iter()
+        .enumerate()
+        .map(|(index, pkg)| PackageContext {
+            part: index + 1,
+            file_name: file_name_from_url(&pkg.url),
+            url: pkg.url.clone(),
+            md5: pkg.md5.clone(),
+            size: format_gb(&pkg.size),
+            decompressed_size: format_gb(&pkg.decompressed_size),
+            size_bytes: pkg.size.parse().unwrap_or(0),
+            decompressed_size_bytes: pkg.decompressed_size.parse().unwrap_or(0),
+        })
+        .collect()
+}
+
+fn audio_pkgs_context(pkgs: &[AudioPackage], language: ReportLanguage) -> Vec<AudioPackageContext> {
+    pkgs.iter()
+        .map(|pkg| AudioPackageContext {
+            language: language.language_name(&pkg.language),
+            language_code: pkg.language.clone(),
+            file_name: file_name_from_url(&pkg.url),
+            url: pkg.url.clone(),
+            md5: pkg.md5.clone(),
+            size: format_gb(&pkg.size),
+            decompressed_size: format_gb(&pkg.decompressed_size),
+            size_bytes: pkg.size.parse().unwrap_or(0),
+            decompressed_size_bytes: pkg.decompressed_size.parse().unwrap_or(0),
+        })
+        .collect()
+}
+
+// Helper function to format a byte count string as "12.34GB"
+fn format_gb(size_str: &str) -> String {
+    format!("{:.2}GB", bytes_to_gb(size_str))
+}
+
+// `{{html value}}` escapes a value for the HTML templates
+handlebars_helper!(html: |text: str| handlebars::html_escape(text));
+
+// `{{json value}}` writes a value as JSON, e.g. a quoted and escaped string
+handlebars_helper!(json: |value: Json| serde_json::to_string(value).unwrap_or_default());
+
+// Render a template against a context; template errors are returned as the message text
+pub fn render<T: Serialize>(template: &str, context: &T) -> String {
+    let mut handlebars = Handlebars::new();
+    // Reports are plain text, not HTML; the HTML templates escape what they need
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars.register_helper("html", Box::new(html));
+    handlebars.register_helper("json", Box::new(json));
+    handlebars
+        .render_template(template, context)
+        .unwrap_or_else(|e| format!("Template error: {}", e))
+}
+
+// ----------------------
+// Report Conversion
+// ----------------------
+
+// Render one report section from the parsed package and pre-download.
+// Returns an empty string if the section has no data to render.
+pub fn render_section(
+    section: ReportSection,
+    template: &str,
+    language: ReportLanguage,
+    package: Option<&GamePackage>,
+    pre_download: Option<&PreDownload>,
+    filter: &ReportFilter,
+) -> String {
+    let start = std::time::Instant::now();
+    let vars = |kind| package.and_then(|package| Variables::version(package, kind, Utc::now()));
+    let text = match (section, package, pre_download) {
+        (ReportSection::Main, Some(package), _) => {
+            convert_main_to_message(std::slice::from_ref(package), template, language, filter)
+        }
+        (ReportSection::PreDownloadMain, _, Some(pre_download)) => {
+            convert_pre_download_main_to_message(pre_download, vars(VersionKind::PreDownload), template, language, filter)
+        }
+        (ReportSection::PreDownloadPatches, _, Some(pre_download)) => {
+            let current_version = pre_download
+                .major
+                .as_ref()
+                .map(|major| major.version.clone())
+                .unwrap_or_else(|| "Unknown".to_string());
+            let vars = vars(VersionKind::PreDownload);
+            convert_pre_download_patches_to_message(pre_download, vars, &current_version, template, language, filter)
+        }
+        _ => String::new(),
+    };
+    profiling::record(Metric::Format, start.elapsed());
+    text
+}
+
+// Function to convert game packages to a formatted message
+pub fn convert_main_to_message(
+    game_packages: &[GamePackage],
+    template: &str,
+    language: ReportLanguage,
+    filter: &ReportFilter,
+) -> String {
+    let context = MainContext {
+        packages: game_packages
+            .iter()
+            .map(|game_package| GamePackageContext {
+                game_id: game_package.game.id.clone(),
+                game_name: game_package.game.display_name(),
+                biz: game_package.game.biz.clone(),
+                major: game_package.main.major.as_ref().map(|major| MajorContext::new(major, language)),
+            })
+            .collect(),
+        include_game: filter.include_game(),
+        include_audio: filter.include_audio(),
+        labels: language.labels(),
+        // With several games the variables are those of the first
+        vars: game_packages.first().and_then(|package| Variables::version(package, VersionKind::Live, Utc::now())),
+    };
+
+    render(template, &context)
+}
+
+// Function to convert pre-download (Main) data to a formatted message
+pub fn convert_pre_download_main_to_message(
+    pre_download: &PreDownload,
+    vars: Option<Variables>,
+    template: &str,
+    language: ReportLanguage,
+    filter: &ReportFilter,
+) -> String {
+    let context = PreDownloadMainContext {
+        major: pre_download.major.as_ref().map(|major| MajorContext::new(major, language)),
+        include_game: filter.include_game(),
+        include_audio: filter.include_audio(),
+        labels: language.labels(),
+        vars,
+    };
+
+    render(template, &context)
+}
+
+// Function to convert pre-download (Patches) data to a formatted message
+pub fn convert_pre_download_patches_to_message(
+    pre_download: &PreDownload,
+    vars: Option<Variables>,
+    current_version: &str,
+    template: &str,
+    language: ReportLanguage,
+    filter: &ReportFilter,
+) -> String {
+    let context = PatchesContext {
+        current_version: current_version.to_string(),
+        patches: patch_groups(pre_download, current_version)
+            .into_iter()
+            .filter(|group| filter.patch_version.as_ref().is_none_or(|version| &group.from == version))
+            .map(|group| PatchContext {
+                version_short: version::short(&group.from),
+                version: group.from.clone(),
+                from_version: group.from,
+                to_version: group.to,
+                game_pkgs: game_pkgs_context(&group.game_pkgs),
+                audio_pkgs: audio_pkgs_context(&group.audio_pkgs, language),
+            })
+            .collect(),
+        include_game: filter.include_game(),
+        include_audio: filter.include_audio(),
+        labels: language.labels(),
+        vars,
+    };
+
+    render(template, &context)
+}
+
+// Versions of the pre-download patches, newest first, for per-patch copy menus
+#[cfg(any(feature = "gui", test))]
+pub fn patch_versions(pre_download: Option<&PreDownload>) -> Vec<String> {
+    let mut versions: Vec<String> = pre_download
+        .map(|pre_download| patch_groups(pre_download, "").into_iter().map(|group| group.from).collect())
+        .unwrap_or_default();
+    versions.dedup();
+    versions
+}
+
+// The packages of the patches from one version to another
+struct PatchGroup {
+    from: String,
+    to: String,
+    game_pkgs: Vec<Package>,
+    audio_pkgs: Vec<AudioPackage>,
+}
+
+// The pre-download's patches merged by the versions they go from and to,
+// newest origin first. The target is read from the patch's file names, and
+// is `fallback` (the pre-download's version) when they don't name one.
+fn patch_groups(pre_download: &PreDownload, fallback: &str) -> Vec<PatchGroup> {
+    let mut groups: Vec<PatchGroup> = Vec::new();
+    for patch in &pre_download.patches {
+        let urls = patch.game_pkgs.iter().map(|pkg| &pkg.url).chain(patch.audio_pkgs.iter().map(|pkg| &pkg.url));
+        let to = urls
+            .filter_map(|url| version::patch_target(&file_name_from_url(url), &patch.version))
+            .next()
+            .unwrap_or_else(|| fallback.to_string());
+        let same_version = |a: &str, b: &str| match (Version::parse(a), Version::parse(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => a == b,
+        };
+        match groups.iter_mut().find(|group| same_version(&group.from, &patch.version) && same_version(&group.to, &to)) {
+            Some(group) => {
+                group.game_pkgs.extend(patch.game_pkgs.iter().cloned());
+                group.audio_pkgs.extend(patch.audio_pkgs.iter().cloned());
+            }
+            None => groups.push(PatchGroup {
+                from: patch.version.clone(),
+                to,
+                game_pkgs: patch.game_pkgs.clone(),
+                audio_pkgs: patch.audio_pkgs.clone(),
+            }),
+        }
+    }
+    groups.sort_by(|a, b| version::newest_first(&a.from, &b.from).then_with(|| version::newest_first(&a.to, &b.to)));
+    groups
+}
+
+// Concatenate rendered sections into one report with a header per section
+// and a generation timestamp. Empty sections are skipped.
+pub fn combined_report(
+    title: &str,
+    sections: &[(ReportSection, String)],
+    language: ReportLanguage,
+    generated_at: DateTime<Utc>,
+) -> String {
+    let mut output = String::new();
+    output.push_str(title);
+    output.push('\n');
+    output.push_str(&format!(
+        "{}: {}\n\n",
+        language.text("generated"),
+        generated_at.format("%Y-%m-%d %H:%M:%S UTC")
+    ));
+
+    for (section, message) in sections {
+        if message.trim().is_empty() {
+            continue;
+        }
+        output.push_str(&format!("==== {} ====\n\n", language.section_title(*section)));
+        output.push_str(message.trim_end());
+        output.push_str("\n\n");
+    }
+
+    output
+}
+
+// The HTML version of `combined_report`. It is a fragment, so reports of
+// several games can follow each other.
+pub fn combined_html(
+    title: &str,
+    sections: &[(ReportSection, String)],
+    language: ReportLanguage,
+    generated_at: DateTime<Utc>,
+) -> String {
+    let mut output = format!("<h1>{}</h1>\n", handlebars::html_escape(title));
+    output.push_str(&format!(
+        "<p>{}: {}</p>\n",
+        language.text("generated"),
+        generated_at.format("%Y-%m-%d %H:%M:%S UTC")
+    ));
+
+    for (section, message) in sections {
+        if message.trim().is_empty() {
+            continue;
+        }
+        output.push_str(&format!("<h2>{}</h2>\n", language.section_title(*section)));
+        output.push_str(message.trim());
+        output.push('\n');
+    }
+
+    output
+}
+
+// The combined report of every game, from the user's templates or a built-in format
+pub fn game_reports(games: &[GameData], templates: &ReportTemplates, format: Option<ExportFormat>) -> String {
+    let mut output = String::new();
+    for game in games {
+        let sections: Vec<(ReportSection, String)> = ReportSection::ALL
+            .iter()
+            .map(|&section| {
+                let template = match format {
+                    Some(format) => builtin_template(format, section),
+                    None => templates.get(section),
+                };
+                let text = render_section(
+                    section,
+                    template,
+                    templates.language,
+                    game.package.as_ref(),
+                    game.pre_download.as_ref(),
+                    &ReportFilter::default(),
+                );
+                (section, text)
+            })
+            .collect();
+        let title = templates.language.report_title(&game.game.display_name());
+        let combined = match format {
+            Some(ExportFormat::Html) => combined_html,
+            _ => combined_report,
+        };
+        output.push_str(&combined(&title, &sections, templates.language, Utc::now()));
+    }
+    output
+}
+
+// ----------------------
+// Discord Message Splitting
+// ----------------------
+
+// Split a report into messages that fit Discord's character limit. Splits
+// happen at blank lines where possible, then at line breaks, and only cut a
+// line as a last resort. Multi-part output is numbered "(1/3)" etc.
+#[cfg(feature = "gui")]
+pub fn split_for_discord(text: &str) -> Vec<String> {
+    let text = text.trim();
+    if text.chars().count() <= DISCORD_MESSAGE_LIMIT {
+        return vec![text.to_string()];
+    }
+
+    // Leave room for the "(nn/nn)\n" part header
+    let budget = DISCORD_MESSAGE_LIMIT - 16;
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for block in text.split("\n\n") {
+        for piece in split_to_fit(block, budget) {
+            let separator = if current.is_empty() { "" } else { "\n\n" };
+            if current.chars().count() + separator.len() + piece.chars().count() > budget {
+                chunks.push(std::mem::take(&mut current));
+                current = piece;
+            } else {
+                current.push_str(separator);
+                current.push_str(&piece);
+            }
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    let total = chunks.len();
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| format!("({}/{})\n{}", index + 1, total, chunk))
+        .collect()
+}
+
+// Break a paragraph into pieces no longer than `budget` characters
+#[cfg(feature = "gui")]
+fn split_to_fit(block: &str, budget: usize) -> Vec<String> {
+    if block.chars().count() <= budget {
+        return vec![block.to_string()];
+    }
+
+    let mut pieces: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for line in block.lines() {
+        let mut line: Vec<char> = line.chars().collect();
+        // Hard-cut lines that are longer than a whole message
+        while line.len() > budget {
+            if !current.is_empty() {
+                pieces.push(std::mem::take(&mut current));
+            }
+            pieces.push(line.drain(..budget).collect());
+        }
+        let line: String = line.into_iter().collect();
+
+        let separator = if current.is_empty() { "" } else { "\n" };
+        if current.chars().count() + separator.len() + line.chars().count() > budget {
+            pieces.push(std::mem::take(&mut current));
+            current = line;
+        } else {
+            current.push_str(separator);
+            current.push_str(&line);
+        }
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
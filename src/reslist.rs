@@ -0,0 +1,144 @@
+// ----------------------
+// Resource Lists
+// ----------------------
+
+// Loads the list of scattered files (`pkg_version`) that a version's
+// `res_list_url` points at. These lists run to tens of MB, so the response is
+// streamed to a file in the cache folder and then parsed a line at a time,
+// rather than read into one string and parsed whole.
+
+use crate::download::part_path;
+use crate::export::sanitize_file_name;
+use crate::jobs::{self, JobKind};
+use crate::network;
+use crate::CancelToken;
+use eframe::egui;
+use log::{error, info};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// A line of `pkg_version` or `hdifffiles.txt`
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceEntry {
+    pub remote_name: String,
+    #[serde(default)]
+    pub md5: String,
+    #[serde(default)]
+    pub file_size: u64,
+}
+
+pub struct ResourceList {
+    pub version: String,
+    pub entries: Vec<ResourceEntry>,
+    pub path: PathBuf, // Cached copy of the list
+}
+
+impl ResourceList {
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.file_size).sum()
+    }
+}
+
+// A list being loaded as a background job; holds the result once done
+pub struct ResourceListJob {
+    pub version: String,
+    pub result: Arc<Mutex<Option<Result<ResourceList, String>>>>,
+    pub cancel: CancelToken,
+}
+
+// Read the entries of a `pkg_version`-style list one line at a time. `name`
+// is only used in error messages.
+pub fn read_entries(reader: impl BufRead, name: &str) -> Result<Vec<ResourceEntry>, String> {
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| format!("File read error ({}): {}", name, e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|e| format!("Invalid {} line: {}", name, e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+// Load the list under `res_list_url` for a version as a background job
+pub fn start_load(ctx: &egui::Context, biz: &str, version: &str, res_list_url: &str) -> ResourceListJob {
+    let job = ResourceListJob {
+        version: version.to_string(),
+        result: Arc::default(),
+        cancel: CancelToken::default(),
+    };
+    let url = format!("{}/pkg_version", res_list_url.trim().trim_end_matches('/'));
+    let file_name = sanitize_file_name(&format!("{}_{}_pkg_version", biz, version));
+    let version = version.to_string();
+    let result = job.result.clone();
+    let cancel = job.cancel.clone();
+    let ctx = ctx.clone();
+    let label = format!("Load file list of {}", version);
+    jobs::spawn(JobKind::Fetch, label, Some(cancel.clone()), move || {
+        let list = load(&url, &file_name, &cancel).map(|(path, entries)| ResourceList { version, entries, path });
+        if let Err(e) = &list {
+            error!("{}", e);
+        }
+        *result.lock().unwrap_or_else(|e| e.into_inner()) = Some(list);
+        ctx.request_repaint();
+    });
+    job
+}
+
+fn load(url: &str, file_name: &str, cancel: &CancelToken) -> Result<(PathBuf, Vec<ResourceEntry>), String> {
+    let dir = dirs::cache_dir()
+        .ok_or_else(|| "No cache folder available".to_string())?
+        .join("genshin_package_scanner")
+        .join("res_list");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("File list error ({}): {}", dir.display(), e))?;
+    let path = dir.join(file_name);
+
+    info!("Fetching file list {}", url);
+    download_to(url, &path, cancel)?;
+    let input = File::open(&path).map_err(|e| format!("File read error ({}): {}", path.display(), e))?;
+    let entries = read_entries(BufReader::new(input), "pkg_version")?;
+    info!("File list {} has {} entries.", url, entries.len());
+    Ok((path, entries))
+}
+
+// Stream a response body into `path`, via a `.part` file so a cancelled or
+// failed transfer never replaces a good copy
+fn download_to(url: &str, path: &Path, cancel: &CancelToken) -> Result<(), String> {
+    let mut response = network::get(url)?
+        .error_for_status()
+        .map_err(|e| format!("Request error: {}", e))?;
+    let part = part_path(path);
+    let file = File::create(&part).map_err(|e| format!("File list error ({}): {}", part.display(), e))?;
+
+    // Whatever stops the transfer, the partial file isn't left in the cache
+    let written = write_body(&mut response, file, &part, cancel)
+        .and_then(|()| std::fs::rename(&part, path).map_err(|e| format!("File list error ({}): {}", path.display(), e)));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&part);
+    }
+    written
+}
+
+// Copy the body into the `.part` file, closing it before returning
+fn write_body(response: &mut impl Read, file: File, part: &Path, cancel: &CancelToken) -> Result<(), String> {
+    let mut output = BufWriter::new(file);
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        if cancel.is_cancelled() {
+            return Err("File list download cancelled.".to_string());
+        }
+        let read = response.read(&mut buffer).map_err(|e| format!("Response text error: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        output
+            .write_all(&buffer[..read])
+            .map_err(|e| format!("File list error ({}): {}", part.display(), e))?;
+    }
+    output.flush().map_err(|e| format!("File list error ({}): {}", part.display(), e))
+}
@@ -0,0 +1,208 @@
+// ----------------------
+// Response Streaming
+// ----------------------
+
+// An API response is read straight off the connection instead of into a JSON
+// tree first: the envelope field by field, and the game packages one at a
+// time. Each package is handed over as JSON while it is parsed into the
+// models, since lenient parsing compares the two (see schema.rs), and is
+// dropped before the next one is read, so no more than one package is ever
+// held as JSON. Legacy payloads, which are old and small, are still kept
+// whole to be normalized.
+
+use crate::legacy;
+use crate::schema::Findings;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::io::Read;
+
+// The envelope of a response whose packages have already been handed over
+pub struct Envelope {
+    pub retcode: Option<Value>,
+    pub message: Option<Value>,
+    pub data: Data,
+    pub findings: Findings, // Fields around the packages the models don't know
+}
+
+pub enum Data {
+    Packages,       // `game_packages` was read, package by package
+    Legacy(Value),  // An older launcher payload
+    Missing,        // No data, or `game_packages` isn't an array
+}
+
+// Read a response, passing each game package to `on_package` as soon as it is complete
+pub fn read(reader: impl Read, on_package: &mut dyn FnMut(Value)) -> Result<Envelope, serde_json::Error> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let envelope = deserializer.deserialize_map(EnvelopeVisitor { on_package })?;
+    deserializer.end()?;
+    Ok(envelope)
+}
+
+struct EnvelopeVisitor<'a> {
+    on_package: &'a mut dyn FnMut(Value),
+}
+
+impl<'de> Visitor<'de> for EnvelopeVisitor<'_> {
+    type Value = Envelope;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an API response object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Envelope, A::Error> {
+        let mut envelope = Envelope {
+            retcode: None,
+            message: None,
+            data: Data::Missing,
+            findings: Findings::default(),
+        };
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "retcode" => envelope.retcode = Some(map.next_value()?),
+                "message" => envelope.message = Some(map.next_value()?),
+                "data" => {
+                    let (data, unknown) = map.next_value_seed(DataSeed { on_package: &mut *self.on_package })?;
+                    envelope.data = data;
+                    for key in unknown {
+                        envelope.findings.unknown(&format!("data.{}", key));
+                    }
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                    envelope.findings.unknown(&key);
+                }
+            }
+        }
+        Ok(envelope)
+    }
+}
+
+// `data`, with the keys next to `game_packages`
+struct DataSeed<'a> {
+    on_package: &'a mut dyn FnMut(Value),
+}
+
+impl<'de> DeserializeSeed<'de> for DataSeed<'_> {
+    type Value = (Data, Vec<String>);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for DataSeed<'_> {
+    type Value = (Data, Vec<String>);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("the response data")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut packages = false;
+        // Anything else is kept, as a legacy payload is made of it
+        let mut others = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "game_packages" {
+                packages = map.next_value_seed(PackagesSeed { on_package: &mut *self.on_package })?;
+            } else {
+                others.insert(key, map.next_value()?);
+            }
+        }
+        let others = Value::Object(others);
+        if packages {
+            let unknown = others.as_object().map(|others| others.keys().cloned().collect()).unwrap_or_default();
+            Ok((Data::Packages, unknown))
+        } else if legacy::is_legacy_payload(&others) {
+            Ok((Data::Legacy(others), Vec::new()))
+        } else {
+            Ok((Data::Missing, Vec::new()))
+        }
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok((Data::Missing, Vec::new()))
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<Self::Value, E> {
+        self.visit_unit()
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<Self::Value, E> {
+        self.visit_unit()
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<Self::Value, E> {
+        self.visit_unit()
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<Self::Value, E> {
+        self.visit_unit()
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<Self::Value, E> {
+        self.visit_unit()
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok((Data::Missing, Vec::new()))
+    }
+}
+
+// `game_packages`, handing over one package at a time. False if it isn't an array.
+struct PackagesSeed<'a> {
+    on_package: &'a mut dyn FnMut(Value),
+}
+
+impl<'de> DeserializeSeed<'de> for PackagesSeed<'_> {
+    type Value = bool;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<bool, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for PackagesSeed<'_> {
+    type Value = bool;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of game packages")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<bool, A::Error> {
+        while let Some(package) = seq.next_element::<Value>()? {
+            (self.on_package)(package);
+        }
+        Ok(true)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<bool, A::Error> {
+        while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+        Ok(false)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<bool, E> {
+        Ok(false)
+    }
+}
@@ -0,0 +1,129 @@
+// ----------------------
+// Lenient Parsing
+// ----------------------
+
+// The API's schema shifts now and then (`res_list_url` already had to become
+// optional), so sections are parsed leniently: fields the models don't know
+// are skipped and missing optional fields take their defaults. Both are
+// collected as warnings and shown next to the data that did parse. Strict
+// mode, for maintainers checking a new payload, rejects a section with any
+// warning as if it had failed to parse.
+//
+// Unknown fields are also collected with their array indices dropped, as the
+// schema drift the History tab keeps track of: a new field, such as a new
+// package type next to `game_pkgs`, is noticed the first time it shows up.
+
+use crate::deserialize_at;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+// What parsing a response turned up besides the data
+#[derive(Default)]
+pub struct Findings {
+    pub warnings: Vec<String>,       // Fields skipped or defaulted, for the Warnings section
+    pub unknown_fields: Vec<String>, // Paths of fields the models don't know, without indices
+}
+
+impl Findings {
+    pub fn unknown(&mut self, path: &str) {
+        self.warnings.push(format!("Unknown field {}", path));
+        let path = general_path(path);
+        if !self.unknown_fields.contains(&path) {
+            self.unknown_fields.push(path);
+        }
+    }
+}
+
+// Parse a section like `deserialize_at`, adding the fields that were skipped
+// or defaulted to `findings`
+pub fn deserialize_lenient<T: DeserializeOwned + Serialize>(
+    value: &Value,
+    path: &str,
+    strict: bool,
+    findings: &mut Findings,
+) -> Result<T, String> {
+    let parsed: T = deserialize_at(value, path)?;
+
+    // Written back out, the model has exactly the fields it knows, so comparing
+    // it with the input shows what was ignored and what was filled in
+    let known = serde_json::to_value(&parsed).map_err(|e| format!("Schema error at {}: {}", path, e))?;
+    let mut found = Findings::default();
+    diff_fields(value, &known, path, &mut found);
+
+    // Unknown fields count as drift whether or not strict mode keeps the section
+    for field in found.unknown_fields {
+        if !findings.unknown_fields.contains(&field) {
+            findings.unknown_fields.push(field);
+        }
+    }
+    if strict && !found.warnings.is_empty() {
+        return Err(format!("Strict parsing rejected {}:\n  {}", path, found.warnings.join("\n  ")));
+    }
+    findings.warnings.extend(found.warnings);
+    Ok(parsed)
+}
+
+// Note the keys of an object outside the `known` ones, e.g. a new section next to `main`
+pub fn check_keys(value: &Value, path: &str, known: &[&str], findings: &mut Findings) {
+    let Some(object) = value.as_object() else {
+        return;
+    };
+    for key in object.keys().filter(|key| !known.contains(&key.as_str())) {
+        findings.unknown(&field_path(path, key));
+    }
+}
+
+fn diff_fields(input: &Value, known: &Value, path: &str, found: &mut Findings) {
+    match (input, known) {
+        (Value::Object(input), Value::Object(known)) => {
+            for (key, value) in input {
+                match known.get(key) {
+                    Some(known) => diff_fields(value, known, &field_path(path, key), found),
+                    None => found.unknown(&field_path(path, key)),
+                }
+            }
+            for key in known.keys().filter(|key| !input.contains_key(*key)) {
+                found
+                    .warnings
+                    .push(format!("Missing field {} (using default)", field_path(path, key)));
+            }
+        }
+        (Value::Array(input), Value::Array(known)) => {
+            for (index, (input, known)) in input.iter().zip(known).enumerate() {
+                diff_fields(input, known, &format!("{}[{}]", path, index), found);
+            }
+        }
+        _ => {}
+    }
+}
+
+// A field path without array indices, so a field counts once however many
+// packages or patches carry it, e.g. `data.game_packages[].main.major.sha256`
+fn general_path(path: &str) -> String {
+    let mut general = String::with_capacity(path.len());
+    let mut in_index = false;
+    for c in path.chars() {
+        match c {
+            '[' => {
+                in_index = true;
+                general.push('[');
+            }
+            ']' => {
+                in_index = false;
+                general.push(']');
+            }
+            _ if in_index => {}
+            _ => general.push(c),
+        }
+    }
+    general
+}
+
+fn field_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
@@ -0,0 +1,181 @@
+// ----------------------
+// Background Service
+// ----------------------
+
+// Registers `watch` to start with the machine, so monitoring survives reboots
+// without anyone logged in. On Linux a systemd unit is written and enabled
+// (the unit uses the notify protocol and watchdog, see `systemd.rs`). On
+// Windows a Task Scheduler task runs it at startup under the current account;
+// an actual Windows service would need the program to answer the service
+// control manager, and the task gets the same result with the user's own
+// settings and history. Like the other system integrations, this goes through
+// the system's own tools (`systemctl`, `schtasks`).
+
+use log::info;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+const UNIT_NAME: &str = "genshin-package-scanner.service";
+const TASK_NAME: &str = "Genshin Package Scanner";
+
+// How the installed watcher runs
+pub struct ServiceOptions {
+    pub interval: Duration,
+    pub upload: bool,
+    pub system: bool, // Linux: system unit running as the current user, instead of a user unit
+}
+
+impl ServiceOptions {
+    // Arguments of the `watch` command the service runs
+    fn watch_args(&self) -> Vec<String> {
+        let mut args = vec!["watch".to_string(), "--interval".to_string(), format!("{}s", self.interval.as_secs())];
+        if self.upload {
+            args.push("--upload".to_string());
+        }
+        args
+    }
+}
+
+fn current_exe() -> Result<PathBuf, String> {
+    std::env::current_exe().map_err(|e| format!("Service error: can't find this program's path: {}", e))
+}
+
+// The systemd unit running `exe` as a watcher
+pub fn systemd_unit(exe: &str, options: &ServiceOptions) -> String {
+    // systemd expands specifiers such as %h in ExecStart, so a literal % is doubled
+    let quote = |arg: &str| {
+        let arg = arg.replace('%', "%%");
+        if arg.contains(char::is_whitespace) || arg.contains('"') {
+            format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+        } else {
+            arg
+        }
+    };
+    let command: Vec<String> = std::iter::once(exe.to_string()).chain(options.watch_args()).map(|arg| quote(&arg)).collect();
+    // A system unit runs as the user installing it, so the settings and history are
+    // theirs. Installing one takes sudo, so that's SUDO_USER rather than USER (root).
+    let user = if options.system {
+        std::env::var("SUDO_USER")
+            .or_else(|_| std::env::var("USER"))
+            .map(|user| format!("User={}\n", user))
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+    format!(
+        "[Unit]\n\
+         Description=Genshin Package Scanner watcher\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         {}ExecStart={}\n\
+         WatchdogSec=5min\n\
+         Restart=on-failure\n\
+         RestartSec=30\n\
+         \n\
+         [Install]\n\
+         WantedBy={}\n",
+        user,
+        command.join(" "),
+        if options.system { "multi-user.target" } else { "default.target" }
+    )
+}
+
+// Where the unit file goes
+fn unit_path(system: bool) -> Result<PathBuf, String> {
+    if system {
+        return Ok(PathBuf::from("/etc/systemd/system").join(UNIT_NAME));
+    }
+    dirs::config_dir()
+        .map(|dir| dir.join("systemd").join("user").join(UNIT_NAME))
+        .ok_or_else(|| "Service error: no config folder for user units".to_string())
+}
+
+// Run a system tool, failing with its error output
+fn run(program: &str, args: &[&str]) -> Result<(), String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Service error: could not run {}: {}", program, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Service error: {} {} failed: {}", program, args.join(" "), stderr.trim()));
+    }
+    Ok(())
+}
+
+fn systemctl(system: bool, args: &[&str]) -> Result<(), String> {
+    let mut all = if system { vec![] } else { vec!["--user"] };
+    all.extend_from_slice(args);
+    run("systemctl", &all)
+}
+
+// Register and start the watcher. Returns what was done, for the user.
+pub fn install(options: &ServiceOptions) -> Result<String, String> {
+    let exe = current_exe()?;
+    let exe = exe.to_string_lossy();
+    if cfg!(windows) {
+        // /RP without a password makes schtasks ask for it, so the task can
+        // run while nobody is logged in
+        let user = match (std::env::var("USERDOMAIN"), std::env::var("USERNAME")) {
+            (Ok(domain), Ok(user)) => format!("{}\\{}", domain, user),
+            (_, Ok(user)) => user,
+            _ => return Err("Service error: USERNAME is not set".to_string()),
+        };
+        let command = format!("\"{}\" {}", exe, options.watch_args().join(" "));
+        let status = Command::new("schtasks")
+            .args(["/Create", "/F", "/TN", TASK_NAME, "/SC", "ONSTART", "/RU", &user, "/RP", "/TR", &command])
+            .status()
+            .map_err(|e| format!("Service error: could not run schtasks: {}", e))?;
+        if !status.success() {
+            return Err(format!("Service error: schtasks failed ({})", status));
+        }
+        run("schtasks", &["/Run", "/TN", TASK_NAME])?;
+        info!("Installed scheduled task {}", TASK_NAME);
+        return Ok(format!("Created the scheduled task \"{}\"; it starts the watcher at every boot.", TASK_NAME));
+    }
+    if !cfg!(target_os = "linux") {
+        return Err("Service error: only systemd (Linux) and Task Scheduler (Windows) are supported".to_string());
+    }
+
+    let path = unit_path(options.system)?;
+    if let Some(folder) = path.parent() {
+        std::fs::create_dir_all(folder).map_err(|e| format!("Service error ({}): {}", folder.display(), e))?;
+    }
+    std::fs::write(&path, systemd_unit(&exe, options)).map_err(|e| format!("Service error ({}): {}", path.display(), e))?;
+    systemctl(options.system, &["daemon-reload"])?;
+    systemctl(options.system, &["enable", "--now", UNIT_NAME])?;
+    info!("Installed {}", path.display());
+    let mut message = format!("Wrote {} and started it.", path.display());
+    if !options.system {
+        message.push_str("\nUser units stop at logout; run `loginctl enable-linger` to keep it running without a session.");
+    }
+    Ok(message)
+}
+
+// Stop and remove the watcher registered by `install`
+pub fn uninstall(system: bool) -> Result<String, String> {
+    if cfg!(windows) {
+        // A task that isn't running can't be ended; deleting it is what counts
+        let _ = run("schtasks", &["/End", "/TN", TASK_NAME]);
+        run("schtasks", &["/Delete", "/F", "/TN", TASK_NAME])?;
+        return Ok(format!("Removed the scheduled task \"{}\".", TASK_NAME));
+    }
+    if !cfg!(target_os = "linux") {
+        return Err("Service error: only systemd (Linux) and Task Scheduler (Windows) are supported".to_string());
+    }
+
+    let path = unit_path(system)?;
+    if !path.exists() {
+        return Err(format!("Service error: {} is not installed", path.display()));
+    }
+    // Already stopped or disabled units are fine
+    let _ = systemctl(system, &["disable", "--now", UNIT_NAME]);
+    std::fs::remove_file(&path).map_err(|e| format!("Service error ({}): {}", path.display(), e))?;
+    systemctl(system, &["daemon-reload"])?;
+    info!("Removed {}", path.display());
+    Ok(format!("Stopped and removed {}.", path.display()))
+}
@@ -0,0 +1,180 @@
+// ----------------------
+// Persistent Settings
+// ----------------------
+
+// User settings are stored as JSON in the platform config directory
+// (e.g. `%APPDATA%\genshin_package_scanner\settings.json` on Windows).
+
+use crate::hooks::Hooks;
+use crate::languages::{self, LanguageName};
+use crate::mirror::MirrorTarget;
+use crate::network::NetworkSettings;
+use crate::report::ReportTemplates;
+use crate::share::ShareTarget;
+use crate::theme::Theme;
+use crate::upload::UploadTarget;
+use crate::variables::MessageTemplates;
+use crate::verify::HashAlgorithm;
+use crate::DEFAULT_API_URL;
+#[cfg(feature = "gui")]
+use log::{error, info};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub templates: ReportTemplates,
+    pub games: Vec<GameConfig>,
+    pub poll_interval_minutes: u32, // Automatic re-fetch interval, 0 = off
+    pub auto_copy_on_pre_download: bool, // Copy the report when an auto-check finds a new pre-download
+    pub alert_sound: bool,                // Play a sound when an auto-check finds a new pre-download
+    pub alert_sound_path: String,         // Sound file to play, empty for the built-in beep
+    pub pinned_packages: Vec<PackagePin>, // Packages shown at the top of a game's results
+    pub verify_hashes: HashAlgorithm,     // Hashes computed when verifying local files
+    pub download_folder: String,          // Where downloads are saved, empty for the default
+    pub download_connections: u32,        // Parallel connections per file
+    pub bandwidth_limit_mb: u32,          // Total download speed cap in MB/s, 0 = unlimited
+    pub aria2_rpc_url: String,            // JSON-RPC endpoint of an aria2 daemon
+    pub aria2_secret: String,             // Its --rpc-secret, empty if none
+    pub link_check_interval_hours: u32,   // Re-check of stored package links, 0 = off
+    pub archive_check_interval_days: u32, // Re-hash of the files in the download folder, 0 = off
+    pub install_folder: String,           // Game install updated by the update wizard
+    pub hpatchz_path: String,             // hpatchz executable used to apply hdiff files
+    pub pause_while_playing: bool,        // Hold downloads and auto-checks while the game runs
+    pub strict_parsing: bool,             // Reject response sections with unknown or missing fields
+    pub theme: Theme,                     // Look of the window
+    pub language_names: Vec<LanguageName>, // Names of audio language codes
+    pub share: ShareTarget,               // Paste service reports are shared to
+    pub upload: UploadTarget,             // S3 bucket or WebDAV share exports are uploaded to
+    pub mirror: MirrorTarget,             // SFTP server verified downloads are copied to
+    pub hooks: Hooks,                     // Shell commands run on new versions and failed fetches
+    pub messages: MessageTemplates,       // Announcements of new versions and failed fetches
+    pub network: NetworkSettings,         // CA bundle and certificate checks of every request
+    pub hidden_sections: Vec<String>,     // Result sections hidden from the View menu, by key
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            templates: ReportTemplates::default(),
+            games: vec![GameConfig::default()],
+            poll_interval_minutes: 0,
+            auto_copy_on_pre_download: false,
+            alert_sound: false,
+            alert_sound_path: String::new(),
+            pinned_packages: Vec::new(),
+            verify_hashes: HashAlgorithm::default(),
+            download_folder: String::new(),
+            download_connections: 4,
+            bandwidth_limit_mb: 0,
+            aria2_rpc_url: "http://localhost:6800/jsonrpc".to_string(),
+            aria2_secret: String::new(),
+            link_check_interval_hours: 24,
+            archive_check_interval_days: 30,
+            install_folder: String::new(),
+            hpatchz_path: "hpatchz".to_string(),
+            pause_while_playing: true,
+            strict_parsing: false,
+            theme: Theme::default(),
+            language_names: languages::default_names(),
+            share: ShareTarget::default(),
+            upload: UploadTarget::default(),
+            mirror: MirrorTarget::default(),
+            hooks: Hooks::default(),
+            messages: MessageTemplates::default(),
+            network: NetworkSettings::default(),
+            hidden_sections: Vec::new(),
+        }
+    }
+}
+
+// A configured game: the display name of its tab and where its data comes from
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GameConfig {
+    pub name: String,
+    pub source: String, // API URL or path to a saved JSON payload
+    #[serde(default)]
+    pub open_sections: BTreeMap<String, bool>, // Result sections left open or closed, by key
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            name: "Genshin Impact".to_string(),
+            source: DEFAULT_API_URL.to_string(),
+            open_sections: BTreeMap::new(),
+        }
+    }
+}
+
+// A pinned package of the live version. Pins refer to a package by its
+// position (game part or audio language) rather than its URL, so they keep
+// pointing at the same package when a new version is released.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PackagePin {
+    pub biz: String,
+    pub target: PinTarget,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum PinTarget {
+    GamePart(usize),   // 1-based part number
+    Audio(String),     // Language code, e.g. "ja-jp"
+}
+
+// Location of a file in the app's config directory, if the platform has one
+pub fn config_path(file_name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("genshin_package_scanner").join(file_name))
+}
+
+// Location of the settings file
+fn settings_path() -> Option<PathBuf> {
+    config_path("settings.json")
+}
+
+impl Settings {
+    // Load settings from disk, falling back to defaults if missing or invalid
+    pub fn load() -> Self {
+        let Some(path) = settings_path() else {
+            return Self::default();
+        };
+
+        let mut settings: Settings = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Invalid settings file {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+
+        settings.templates.upgrade_defaults();
+        languages::set_names(&settings.language_names);
+
+        // There is always at least one game tab
+        if settings.games.is_empty() {
+            settings.games.push(GameConfig::default());
+        }
+        settings
+    }
+
+    // Save settings to disk
+    #[cfg(feature = "gui")]
+    pub fn save(&self) -> Result<(), String> {
+        let path = settings_path().ok_or_else(|| "No config directory available".to_string())?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Settings save error: {}", e))?;
+        }
+
+        let contents = serde_json::to_string_pretty(self).map_err(|e| format!("Settings save error: {}", e))?;
+        std::fs::write(&path, contents).map_err(|e| {
+            error!("Failed to write settings to {}: {}", path.display(), e);
+            format!("Settings save error: {}", e)
+        })?;
+
+        info!("Settings saved to {}", path.display());
+        Ok(())
+    }
+}
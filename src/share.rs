@@ -0,0 +1,181 @@
+// ----------------------
+// Sharing
+// ----------------------
+
+// Publishes a report or the package JSON to a paste service and hands back
+// its link, so a community can be given the data with one click. Gists are
+// created secret (reachable by link, not listed on the profile) and pastes
+// unlisted. Both services need a key, kept in the settings.
+
+#[cfg(feature = "gui")]
+use crate::export;
+#[cfg(feature = "gui")]
+use crate::network;
+#[cfg(feature = "gui")]
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
+use serde_json::{json, Value};
+#[cfg(feature = "gui")]
+use std::time::Duration;
+
+#[cfg(feature = "gui")]
+const GIST_API_URL: &str = "https://api.github.com/gists";
+#[cfg(feature = "gui")]
+const PASTEBIN_API_URL: &str = "https://pastebin.com/api/api_post.php";
+#[cfg(feature = "gui")]
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ShareService {
+    #[default]
+    GitHubGist,
+    Pastebin,
+}
+
+#[cfg(feature = "gui")]
+impl ShareService {
+    pub const ALL: [ShareService; 2] = [ShareService::GitHubGist, ShareService::Pastebin];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ShareService::GitHubGist => "GitHub Gist",
+            ShareService::Pastebin => "Pastebin",
+        }
+    }
+
+    // What goes into the key field
+    pub fn token_hint(self) -> &'static str {
+        match self {
+            ShareService::GitHubGist => "Personal access token with the gist scope",
+            ShareService::Pastebin => "API developer key from pastebin.com/doc_api",
+        }
+    }
+}
+
+// Where shared reports go
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ShareTarget {
+    pub service: ShareService,
+    pub token: String, // Gist token or Pastebin developer key
+}
+
+#[cfg(feature = "gui")]
+impl ShareTarget {
+    pub fn is_configured(&self) -> bool {
+        !self.token.trim().is_empty()
+    }
+}
+
+// What is being shared; picks the file name and syntax highlighting
+#[cfg(feature = "gui")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShareFormat {
+    Text,
+    Json,
+}
+
+#[cfg(feature = "gui")]
+impl ShareFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ShareFormat::Text => "txt",
+            ShareFormat::Json => "json",
+        }
+    }
+}
+
+// Upload `content` under `title` and return the link to it
+#[cfg(feature = "gui")]
+pub fn share(target: &ShareTarget, title: &str, content: &str, format: ShareFormat) -> Result<String, String> {
+    if !target.is_configured() {
+        return Err(format!("Share error: no {} key set (see Games > Sharing)", target.service.label()));
+    }
+    let client = network::client_builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|e| format!("Share error: {}", e))?;
+
+    let result = match target.service {
+        ShareService::GitHubGist => create_gist(&client, target.token.trim(), title, content, format),
+        ShareService::Pastebin => create_paste(&client, target.token.trim(), title, content, format),
+    };
+    match &result {
+        Ok(url) => info!("Shared {} to {}", title, url),
+        Err(e) => error!("{}", e),
+    }
+    result
+}
+
+#[cfg(feature = "gui")]
+fn create_gist(
+    client: &reqwest::blocking::Client,
+    token: &str,
+    title: &str,
+    content: &str,
+    format: ShareFormat,
+) -> Result<String, String> {
+    let file_name = format!("{}.{}", export::sanitize_file_name(title), format.extension());
+    let body = json!({
+        "description": title,
+        "public": false,
+        "files": { file_name: { "content": content } },
+    });
+    let response = client
+        .post(GIST_API_URL)
+        .bearer_auth(token)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .header(reqwest::header::USER_AGENT, "genshin_package_scanner")
+        .json(&body)
+        .send()
+        .map_err(|e| format!("Gist error: {}", e))?;
+
+    let status = response.status();
+    let reply: Value = response.json().map_err(|e| format!("Gist error: {}", e))?;
+    if !status.is_success() {
+        let message = reply.get("message").and_then(Value::as_str).unwrap_or("no details");
+        return Err(format!("Gist error: HTTP {}: {}", status.as_u16(), message));
+    }
+    reply
+        .get("html_url")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "Gist error: the response has no link".to_string())
+}
+
+#[cfg(feature = "gui")]
+fn create_paste(
+    client: &reqwest::blocking::Client,
+    dev_key: &str,
+    title: &str,
+    content: &str,
+    format: ShareFormat,
+) -> Result<String, String> {
+    let syntax = match format {
+        ShareFormat::Text => "text",
+        ShareFormat::Json => "json",
+    };
+    let form = [
+        ("api_dev_key", dev_key),
+        ("api_option", "paste"),
+        ("api_paste_code", content),
+        ("api_paste_name", title),
+        ("api_paste_format", syntax),
+        ("api_paste_private", "1"), // Unlisted
+    ];
+    let reply = client
+        .post(PASTEBIN_API_URL)
+        .form(&form)
+        .send()
+        .and_then(|response| response.text())
+        .map_err(|e| format!("Pastebin error: {}", e))?;
+
+    // Pastebin answers with the link, or with a "Bad API request" message
+    let reply = reply.trim();
+    if reply.starts_with("http") {
+        Ok(reply.to_string())
+    } else {
+        Err(format!("Pastebin error: {}", reply))
+    }
+}
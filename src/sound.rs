@@ -0,0 +1,44 @@
+// ----------------------
+// Sound Alerts
+// ----------------------
+
+// Plays the alert sound when an auto-check finds a new pre-download. The
+// sound is a user-chosen audio file (WAV, MP3 or OGG) or a short built-in
+// beep when no file is set.
+
+use crate::jobs::{self, JobKind};
+use log::{error, info};
+use rodio::source::{SineWave, Source};
+use rodio::{Decoder, OutputStream, Sink};
+use std::fs::File;
+use std::io::BufReader;
+use std::time::Duration;
+
+// Play the alert as a background job so decoding and playback never block the UI
+pub fn play_alert(sound_path: &str) {
+    let sound_path = sound_path.trim().to_string();
+    jobs::spawn(JobKind::Sound, "Alert sound", None, move || {
+        if let Err(e) = play(&sound_path) {
+            error!("{}", e);
+        }
+    });
+}
+
+// Function to play a sound file, or the built-in beep if the path is empty
+fn play(sound_path: &str) -> Result<(), String> {
+    // The stream has to stay alive until playback ends
+    let (_stream, handle) = OutputStream::try_default().map_err(|e| format!("Audio output error: {}", e))?;
+    let sink = Sink::try_new(&handle).map_err(|e| format!("Audio output error: {}", e))?;
+
+    if sound_path.is_empty() {
+        sink.append(SineWave::new(880.0).take_duration(Duration::from_millis(600)).amplify(0.25));
+    } else {
+        info!("Playing alert sound {}", sound_path);
+        let file = File::open(sound_path).map_err(|e| format!("Alert sound error ({}): {}", sound_path, e))?;
+        let source = Decoder::new(BufReader::new(file)).map_err(|e| format!("Alert sound error ({}): {}", sound_path, e))?;
+        sink.append(source);
+    }
+
+    sink.sleep_until_end();
+    Ok(())
+}
@@ -0,0 +1,67 @@
+// ----------------------
+// systemd Notifications
+// ----------------------
+
+// When `watch` runs as a systemd service with `Type=notify`, it reports
+// readiness and a status line (shown by `systemctl status`) over the socket
+// in `$NOTIFY_SOCKET`. With `WatchdogSec=` set, it also pings the watchdog
+// while its loop is alive, so systemd restarts a monitor that hung, e.g. on
+// a connection that never returns. Outside systemd, and on other systems,
+// these calls do nothing.
+
+use std::time::Duration;
+
+// Send a notification such as "READY=1" or "STATUS=..."; errors are ignored,
+// as there's nobody to report them to
+#[cfg(unix)]
+pub fn notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let Ok(socket) = UnixDatagram::unbound() else {
+        return;
+    };
+    let path = path.to_string_lossy().into_owned();
+    // A leading '@' names a socket in Linux's abstract namespace
+    #[cfg(target_os = "linux")]
+    if let Some(name) = path.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        if let Ok(address) = std::os::unix::net::SocketAddr::from_abstract_name(name) {
+            let _ = socket.send_to_addr(state.as_bytes(), &address);
+        }
+        return;
+    }
+    let _ = socket.send_to(state.as_bytes(), path);
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) {}
+
+// How often systemd expects a watchdog ping, if the service has a watchdog
+// meant for this process
+pub fn watchdog_interval() -> Option<Duration> {
+    let pid = std::env::var("WATCHDOG_PID").ok();
+    if pid.is_some_and(|pid| pid.trim() != std::process::id().to_string()) {
+        return None;
+    }
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.trim().parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+// Sleep for `duration`, pinging the watchdog twice per watchdog interval
+pub fn sleep(duration: Duration, watchdog: Option<Duration>) {
+    let Some(watchdog) = watchdog else {
+        std::thread::sleep(duration);
+        return;
+    };
+    let step = (watchdog / 2).max(Duration::from_millis(100));
+    let mut remaining = duration;
+    while !remaining.is_zero() {
+        let nap = remaining.min(step);
+        std::thread::sleep(nap);
+        remaining -= nap;
+        notify("WATCHDOG=1");
+    }
+}
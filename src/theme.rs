@@ -0,0 +1,130 @@
+// ----------------------
+// Themes and Status Colours
+// ----------------------
+
+// The window can use egui's dark or light theme, or a high-contrast theme:
+// white text on black, heavier outlines, and status colours from the
+// Okabe-Ito palette, which stay distinct with the common kinds of colour
+// blindness. In every theme, OK, warning and error states are shown with an
+// icon as well as a colour, so they never rely on colour alone.
+
+#[cfg(feature = "gui")]
+use eframe::egui;
+#[cfg(feature = "gui")]
+use egui::{Color32, Stroke};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+#[cfg(feature = "gui")]
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::HighContrast];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+            Theme::HighContrast => "High contrast",
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl Theme {
+    // Switch the window to this theme
+    pub fn apply(self, ctx: &egui::Context) {
+        let (visuals, success) = match self {
+            Theme::Dark => (egui::Visuals::dark(), Color32::from_rgb(0x5c, 0xd0, 0x6c)),
+            Theme::Light => (egui::Visuals::light(), Color32::from_rgb(0x1b, 0x7f, 0x2e)),
+            Theme::HighContrast => (high_contrast(), Color32::from_rgb(0x56, 0xb4, 0xe9)), // Sky blue
+        };
+        ctx.set_visuals(visuals);
+        ctx.data_mut(|data| data.insert_temp(success_id(), success));
+    }
+}
+
+#[cfg(feature = "gui")]
+fn high_contrast() -> egui::Visuals {
+    let mut visuals = egui::Visuals::dark();
+    visuals.override_text_color = Some(Color32::WHITE);
+    visuals.panel_fill = Color32::BLACK;
+    visuals.window_fill = Color32::BLACK;
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.faint_bg_color = Color32::from_gray(40);
+    visuals.window_stroke = Stroke::new(2.0, Color32::WHITE);
+    visuals.hyperlink_color = Color32::from_rgb(0x7f, 0xd4, 0xff);
+    visuals.warn_fg_color = Color32::from_rgb(0xf0, 0xe4, 0x42); // Yellow
+    visuals.error_fg_color = Color32::from_rgb(0xff, 0x8c, 0x1a); // Orange, kept apart from the blue OK colour
+    visuals.selection.bg_fill = Color32::from_rgb(0x00, 0x5f, 0xb8);
+    visuals.selection.stroke = Stroke::new(2.0, Color32::WHITE);
+
+    // Every widget gets a solid outline, thicker while hovered or pressed
+    let widgets = &mut visuals.widgets;
+    for (state, width) in [
+        (&mut widgets.noninteractive, 1.0),
+        (&mut widgets.inactive, 1.5),
+        (&mut widgets.hovered, 2.5),
+        (&mut widgets.active, 2.5),
+        (&mut widgets.open, 2.0),
+    ] {
+        state.bg_stroke = Stroke::new(width, Color32::WHITE);
+        state.fg_stroke = Stroke::new(width.max(1.5), Color32::WHITE);
+    }
+    widgets.inactive.bg_fill = Color32::from_gray(25);
+    widgets.inactive.weak_bg_fill = Color32::from_gray(25);
+    widgets.hovered.bg_fill = Color32::from_gray(60);
+    widgets.hovered.weak_bg_fill = Color32::from_gray(60);
+    widgets.active.bg_fill = Color32::from_gray(90);
+    widgets.active.weak_bg_fill = Color32::from_gray(90);
+    visuals
+}
+
+#[cfg(feature = "gui")]
+fn success_id() -> egui::Id {
+    egui::Id::new("theme_success_color")
+}
+
+// How a result turned out
+#[cfg(feature = "gui")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    Success,
+    Warning,
+    Error,
+}
+
+#[cfg(feature = "gui")]
+impl Status {
+    pub fn icon(self) -> &'static str {
+        match self {
+            Status::Success => "✔",
+            Status::Warning => "⚠",
+            Status::Error => "✖",
+        }
+    }
+
+    // The colour of this state in the current theme
+    pub fn color(self, ui: &egui::Ui) -> Color32 {
+        match self {
+            Status::Success => ui
+                .ctx()
+                .data(|data| data.get_temp(success_id()))
+                .unwrap_or(Color32::from_rgb(0x5c, 0xd0, 0x6c)),
+            Status::Warning => ui.visuals().warn_fg_color,
+            Status::Error => ui.visuals().error_fg_color,
+        }
+    }
+}
+
+// A label with the icon and colour of `status` in front of `text`
+#[cfg(feature = "gui")]
+pub fn status_label(ui: &mut egui::Ui, status: Status, text: impl std::fmt::Display) -> egui::Response {
+    let color = status.color(ui);
+    ui.colored_label(color, format!("{} {}", status.icon(), text))
+}
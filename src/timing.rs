@@ -0,0 +1,393 @@
+// ----------------------
+// Request Timings
+// ----------------------
+
+// Where the time of an API fetch went: the DNS lookup, the TCP connect, the
+// TLS handshake, the wait for the first byte and the whole transfer. The last
+// fetch's are shown in the diagnostics window (F12) and every fetch's are
+// logged at debug level, so a slow fetch can be pinned on a phase without a
+// packet capture. Fetches are only timed while the window is open or debug
+// logging is on; the rest go to `network::get` as they are.
+//
+// reqwest doesn't report its phases. For a direct HTTPS fetch the host is
+// looked up and connected to here, and reqwest is pointed at a tunnel on a
+// loopback port that carries its bytes over that connection, like a local
+// proxy would. TLS stays end to end; the tunnel only reads the record headers
+// and takes the client's first encrypted record as the end of the handshake.
+// Fetches through a proxy or from a bound local address go the usual way and
+// get only the wait and the total.
+
+use crate::network;
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// How long the tunnel waits for reqwest to connect to it
+const ACCEPT_TIMEOUT: Duration = Duration::from_secs(30);
+// Longest CONNECT request the tunnel reads
+const MAX_HEAD: usize = 8192;
+// Proxy variables reqwest follows for HTTPS; with any set, it gets its way
+const PROXY_VARIABLES: [&str; 4] = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+// The phases of one request, each timed on its own except the first byte and
+// the total, which count from the start. None where it wasn't measured.
+#[derive(Clone, Debug, Default)]
+pub struct RequestTimings {
+    pub url: String,
+    pub time: DateTime<Utc>,
+    pub address: Option<SocketAddr>, // The server address connected to
+    pub dns: Option<Duration>,
+    pub connect: Option<Duration>,
+    pub tls: Option<Duration>,
+    pub first_byte: Option<Duration>, // Until the response headers arrived
+    pub total: Option<Duration>,      // Until the end of the body
+    pub note: String,                 // Why phases are missing, or the error the request ended with
+}
+
+impl RequestTimings {
+    pub fn phases(&self) -> [(&'static str, Option<Duration>); 5] {
+        [
+            ("DNS lookup", self.dns),
+            ("TCP connect", self.connect),
+            ("TLS handshake", self.tls),
+            ("First byte", self.first_byte),
+            ("Total", self.total),
+        ]
+    }
+
+    // One line per phase, for the log and bug reports
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!("{} at {}", self.url, self.time.to_rfc3339())];
+        if let Some(address) = self.address {
+            lines.push(format!("connected to {}", address));
+        }
+        for (label, duration) in self.phases() {
+            lines.push(format!("{}: {}", label, duration.map(millis).unwrap_or_else(|| "-".to_string())));
+        }
+        if !self.note.is_empty() {
+            lines.push(self.note.clone());
+        }
+        lines.join("\n")
+    }
+}
+
+pub fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+static LAST: Mutex<Option<RequestTimings>> = Mutex::new(None);
+
+// Set while the diagnostics window is open
+static WANTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "gui")]
+pub fn set_wanted(wanted: bool) {
+    WANTED.store(wanted, Ordering::Relaxed);
+}
+
+// Whether fetches should be timed: someone is looking at the timings
+pub fn wanted() -> bool {
+    WANTED.load(Ordering::Relaxed) || log::log_enabled!(log::Level::Debug)
+}
+
+// The timings of the last API fetch, once its headers arrived or it failed
+#[cfg(feature = "gui")]
+pub fn last() -> Option<RequestTimings> {
+    LAST.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn store(timings: &RequestTimings) {
+    *LAST.lock().unwrap_or_else(|e| e.into_inner()) = Some(timings.clone());
+}
+
+// GET `url` like `network::get`, timing the phases of the request. The total
+// is taken when the body has been read to the end.
+pub fn get(url: &str) -> Result<Box<dyn Read>, String> {
+    let start = Instant::now();
+    let mut timings = RequestTimings { url: url.to_string(), time: Utc::now(), ..Default::default() };
+    let tunnel = match direct_target(url) {
+        Ok((host, port)) => open_tunnel(&host, port, start, &mut timings),
+        Err(reason) => {
+            timings.note = reason;
+            None
+        }
+    };
+    let client = match &tunnel {
+        Some(tunnel) => reqwest::Proxy::https(format!("http://{}", tunnel.address))
+            .and_then(|proxy| network::client_builder().proxy(proxy).build())
+            .unwrap_or_else(|e| {
+                debug!("Request error: {}; timing without the tunnel", e);
+                network::client_for(&[url])
+            }),
+        None => network::client_for(&[url]),
+    };
+
+    let result = client.get(url).send();
+    if let Some(tunnel) = &tunnel {
+        timings.tls = tunnel.handshake();
+    }
+    match result {
+        Ok(response) => {
+            timings.first_byte = Some(start.elapsed());
+            store(&timings);
+            Ok(Box::new(TimedBody { inner: response, start, timings }))
+        }
+        Err(e) => {
+            let message = format!("Request error: {}", network::describe(&e));
+            timings.note = match timings.note.as_str() {
+                "" => message.clone(),
+                note => format!("{}\n{}", message, note),
+            };
+            store(&timings);
+            debug!("Timings of {}", timings.summary());
+            Err(message)
+        }
+    }
+}
+
+// The host and port of a URL reqwest would connect to directly over HTTPS,
+// or why its phases can't be timed
+fn direct_target(url: &str) -> Result<(String, u16), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Not timed: {}", e))?;
+    if parsed.scheme() != "https" {
+        return Err("DNS, connect and TLS are only timed for HTTPS.".to_string());
+    }
+    if proxy_configured() {
+        return Err("DNS, connect and TLS are not timed through a proxy.".to_string());
+    }
+    if !network::current().bind.trim().is_empty() {
+        return Err("DNS, connect and TLS are not timed from a bound local address.".to_string());
+    }
+    let host = parsed.host_str().unwrap_or_default().trim_start_matches('[').trim_end_matches(']').to_string();
+    Ok((host, parsed.port_or_known_default().unwrap_or(443)))
+}
+
+fn proxy_configured() -> bool {
+    PROXY_VARIABLES.iter().any(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()))
+        || system_proxy_enabled()
+}
+
+// Windows' own proxy setting, which reqwest follows too
+#[cfg(windows)]
+fn system_proxy_enabled() -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    let wide = |text: &str| -> Vec<u16> { std::ffi::OsStr::new(text).encode_wide().chain(std::iter::once(0)).collect() };
+    let key = wide("Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings");
+    let value = wide("ProxyEnable");
+    let mut enabled: u32 = 0;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    let status = unsafe {
+        winapi::um::winreg::RegGetValueW(
+            winapi::um::winreg::HKEY_CURRENT_USER,
+            key.as_ptr(),
+            value.as_ptr(),
+            winapi::um::winreg::RRF_RT_REG_DWORD,
+            std::ptr::null_mut(),
+            &mut enabled as *mut u32 as *mut _,
+            &mut size,
+        )
+    };
+    status == 0 && enabled != 0
+}
+
+#[cfg(not(windows))]
+fn system_proxy_enabled() -> bool {
+    false
+}
+
+// A loopback port that carries one tunnelled connection to the server
+struct Tunnel {
+    address: SocketAddr,
+    connected: Instant,                          // When the server connection was made
+    handshake_done: Arc<Mutex<Option<Instant>>>, // When the client's first encrypted record went by
+}
+
+impl Tunnel {
+    fn handshake(&self) -> Option<Duration> {
+        let done = (*self.handshake_done.lock().unwrap_or_else(|e| e.into_inner()))?;
+        Some(done.saturating_duration_since(self.connected))
+    }
+}
+
+// Look `host` up and connect to it, timing both, and open a tunnel to the
+// connection. None (with a note) if any of it fails; the request then goes
+// the usual way and fails or succeeds on its own.
+fn open_tunnel(host: &str, port: u16, start: Instant, timings: &mut RequestTimings) -> Option<Tunnel> {
+    let addresses = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => {
+            let addresses = network::resolve(host, &network::current());
+            timings.dns = Some(start.elapsed());
+            addresses
+        }
+    };
+    if addresses.is_empty() {
+        timings.note = format!("{} has no address to connect to.", host);
+        return None;
+    }
+
+    let connecting = Instant::now();
+    let mut errors = Vec::new();
+    let mut server = None;
+    for ip in addresses {
+        let address = SocketAddr::new(ip, port);
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                timings.address = Some(address);
+                server = Some(stream);
+                break;
+            }
+            Err(e) => errors.push(format!("{}: {}", address, e)),
+        }
+    }
+    let Some(server) = server else {
+        timings.note = format!("Connect failed: {}", errors.join("; "));
+        return None;
+    };
+    timings.connect = Some(connecting.elapsed());
+
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).and_then(|listener| {
+        listener.set_nonblocking(true)?;
+        Ok(listener)
+    }) {
+        Ok(listener) => listener,
+        Err(e) => {
+            timings.note = format!("TLS not timed: {}", e);
+            return None;
+        }
+    };
+    let tunnel = Tunnel {
+        address: listener.local_addr().ok()?,
+        connected: Instant::now(),
+        handshake_done: Arc::new(Mutex::new(None)),
+    };
+    let handshake_done = tunnel.handshake_done.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = run_tunnel(listener, server, &handshake_done) {
+            debug!("Timing tunnel closed: {}", e);
+        }
+    });
+    Some(tunnel)
+}
+
+// Take reqwest's connection, answer its CONNECT request (the server is
+// connected to already) and pass bytes both ways until either side closes
+fn run_tunnel(listener: TcpListener, server: TcpStream, handshake_done: &Arc<Mutex<Option<Instant>>>) -> std::io::Result<()> {
+    let deadline = Instant::now() + ACCEPT_TIMEOUT;
+    let client = loop {
+        match listener.accept() {
+            Ok((stream, _)) => break stream,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock && Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(5))
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    drop(listener);
+    client.set_nonblocking(false)?;
+    read_head(&client)?;
+    (&client).write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")?;
+
+    let (from_client, to_server) = (client.try_clone()?, server.try_clone()?);
+    let handshake_done = handshake_done.clone();
+    let outgoing = std::thread::spawn(move || forward_client(from_client, to_server, &handshake_done));
+    let _ = std::io::copy(&mut &server, &mut &client);
+    let _ = client.shutdown(Shutdown::Both);
+    let _ = server.shutdown(Shutdown::Both);
+    let _ = outgoing.join();
+    Ok(())
+}
+
+// Read a request head up to its blank line, and not a byte further
+fn read_head(mut stream: &TcpStream) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > MAX_HEAD || stream.read(&mut byte)? == 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "no CONNECT request"));
+        }
+        head.push(byte[0]);
+    }
+    Ok(())
+}
+
+// Copy the client's bytes to the server, noting when the handshake is over
+fn forward_client(mut client: TcpStream, mut server: TcpStream, handshake_done: &Mutex<Option<Instant>>) {
+    let mut buffer = [0u8; 16384];
+    let mut records = RecordScanner::default();
+    let mut done = false;
+    loop {
+        let read = match client.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        if !done && records.scan(&buffer[..read]) {
+            done = true;
+            *handshake_done.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+        }
+        if server.write_all(&buffer[..read]).is_err() {
+            break;
+        }
+    }
+    let _ = server.shutdown(Shutdown::Both);
+}
+
+// Follows the TLS record headers of one direction of a connection: a type
+// byte, two version bytes and a two-byte length
+#[derive(Default)]
+pub struct RecordScanner {
+    header: Vec<u8>,
+    remaining: usize, // Bytes left of the current record's body
+}
+
+const APPLICATION_DATA: u8 = 23;
+
+impl RecordScanner {
+    // Whether `bytes` hold the start of an application data record, which
+    // the client only sends once the handshake is over (in TLS 1.3 the
+    // Finished message already travels as one)
+    pub fn scan(&mut self, mut bytes: &[u8]) -> bool {
+        while !bytes.is_empty() {
+            if self.remaining > 0 {
+                let skipped = self.remaining.min(bytes.len());
+                self.remaining -= skipped;
+                bytes = &bytes[skipped..];
+                continue;
+            }
+            self.header.push(bytes[0]);
+            bytes = &bytes[1..];
+            if self.header.len() == 5 {
+                if self.header[0] == APPLICATION_DATA {
+                    return true;
+                }
+                self.remaining = u16::from_be_bytes([self.header[3], self.header[4]]) as usize;
+                self.header.clear();
+            }
+        }
+        false
+    }
+}
+
+// A response body that records the total once it has been read to the end
+struct TimedBody {
+    inner: reqwest::blocking::Response,
+    start: Instant,
+    timings: RequestTimings,
+}
+
+impl Read for TimedBody {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 && !buf.is_empty() && self.timings.total.is_none() {
+            self.timings.total = Some(self.start.elapsed());
+            store(&self.timings);
+            debug!("Timings of {}", self.timings.summary());
+        }
+        Ok(read)
+    }
+}
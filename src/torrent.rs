@@ -0,0 +1,329 @@
+// ----------------------
+// Torrent Files
+// ----------------------
+
+// Builds .torrent files from a verified download folder so preservation
+// groups can re-seed old versions. BitTorrent v1 torrents carry each file's
+// MD5 from the API; v2 torrents carry each file's SHA-256 merkle root. When
+// every file comes from one CDN directory, the original links can be added
+// as a web seed.
+
+use crate::export;
+use crate::PackageFile;
+use log::{info, warn};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// Size of the v2 merkle tree leaves
+const BLOCK_SIZE: u64 = 16 * 1024;
+
+// Pieces are sized so a torrent has about this many
+const TARGET_PIECES: u64 = 2000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TorrentVersion {
+    V1,
+    V2,
+}
+
+impl TorrentVersion {
+    pub const ALL: [TorrentVersion; 2] = [TorrentVersion::V1, TorrentVersion::V2];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TorrentVersion::V1 => "BitTorrent v1",
+            TorrentVersion::V2 => "BitTorrent v2",
+        }
+    }
+}
+
+pub struct TorrentOptions {
+    pub version: TorrentVersion,
+    pub trackers: Vec<String>, // Announce URLs, one tier each
+    pub web_seeds: bool,       // Add the CDN links as a web seed
+}
+
+// ----------------------
+// Bencoding
+// ----------------------
+
+enum Bencode {
+    Int(u64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<Vec<u8>, Bencode>), // Keys are kept sorted as the format requires
+}
+
+impl Bencode {
+    fn string(value: &str) -> Self {
+        Bencode::Bytes(value.as_bytes().to_vec())
+    }
+
+    fn dict(entries: Vec<(&str, Bencode)>) -> Self {
+        Bencode::Dict(entries.into_iter().map(|(key, value)| (key.as_bytes().to_vec(), value)).collect())
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Bencode::Int(value) => out.extend(format!("i{}e", value).as_bytes()),
+            Bencode::Bytes(bytes) => {
+                out.extend(format!("{}:", bytes.len()).as_bytes());
+                out.extend(bytes);
+            }
+            Bencode::List(items) => {
+                out.push(b'l');
+                for item in items {
+                    item.encode(out);
+                }
+                out.push(b'e');
+            }
+            Bencode::Dict(entries) => {
+                out.push(b'd');
+                for (key, value) in entries {
+                    Bencode::Bytes(key.clone()).encode(out);
+                    value.encode(out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+}
+
+// ----------------------
+// Torrent Creation
+// ----------------------
+
+// Hash the files (found in `folder` under their URL's file name) and write a
+// torrent to the export folder. Returns the path of the .torrent file.
+pub fn create_torrent(
+    folder: &Path,
+    files: &[PackageFile],
+    options: &TorrentOptions,
+    is_cancelled: &dyn Fn() -> bool,
+) -> Result<PathBuf, String> {
+    let mut files = files.to_vec();
+    files.sort_by_key(|file| file.file_name());
+    if files.is_empty() {
+        return Err("No verified files to put in a torrent.".to_string());
+    }
+    let mut sizes = Vec::new();
+    for file in &files {
+        let path = folder.join(file.file_name());
+        let metadata = std::fs::metadata(&path).map_err(|e| format!("File read error ({}): {}", path.display(), e))?;
+        sizes.push(metadata.len());
+    }
+    let piece_length = piece_length_for(sizes.iter().sum());
+
+    // A multi-file web seed is fetched as `<seed>/<name>/<file>`, so it only
+    // works when the torrent is named after the CDN directory holding every file
+    let cdn_directory = common_directory(&files);
+    if options.web_seeds && cdn_directory.is_none() {
+        warn!("Files come from different CDN directories, leaving out the web seed.");
+    }
+    let name = match &cdn_directory {
+        Some((_, directory)) if options.web_seeds => directory.clone(),
+        _ => folder
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "packages".to_string()),
+    };
+
+    let mut info = vec![
+        ("name", Bencode::string(&name)),
+        ("piece length", Bencode::Int(piece_length)),
+    ];
+    let mut torrent = Vec::new();
+    match options.version {
+        TorrentVersion::V1 => {
+            let pieces = v1_pieces(folder, &files, piece_length, is_cancelled)?;
+            let entries = files
+                .iter()
+                .zip(&sizes)
+                .map(|(file, size)| {
+                    Bencode::dict(vec![
+                        ("length", Bencode::Int(*size)),
+                        ("path", Bencode::List(vec![Bencode::string(&file.file_name())])),
+                        ("md5sum", Bencode::string(&file.md5.to_lowercase())),
+                    ])
+                })
+                .collect();
+            info.push(("files", Bencode::List(entries)));
+            info.push(("pieces", Bencode::Bytes(pieces)));
+        }
+        TorrentVersion::V2 => {
+            let mut file_tree = BTreeMap::new();
+            let mut piece_layers = BTreeMap::new();
+            for (file, size) in files.iter().zip(&sizes) {
+                let mut entry = vec![("length", Bencode::Int(*size))];
+                if *size > 0 {
+                    let (root, layer) = v2_file_hashes(&folder.join(file.file_name()), *size, piece_length, is_cancelled)?;
+                    entry.push(("pieces root", Bencode::Bytes(root.to_vec())));
+                    // Only files longer than one piece have a piece layer
+                    if *size > piece_length {
+                        piece_layers.insert(root.to_vec(), Bencode::Bytes(layer));
+                    }
+                }
+                let leaf = Bencode::dict(vec![("", Bencode::dict(entry))]);
+                file_tree.insert(file.file_name().into_bytes(), leaf);
+            }
+            info.push(("file tree", Bencode::Dict(file_tree)));
+            info.push(("meta version", Bencode::Int(2)));
+            torrent.push(("piece layers", Bencode::Dict(piece_layers)));
+        }
+    }
+
+    torrent.push(("info", Bencode::dict(info)));
+    torrent.push(("created by", Bencode::string("Genshin Package Scanner")));
+    torrent.push(("creation date", Bencode::Int(chrono::Utc::now().timestamp().max(0) as u64)));
+    let trackers: Vec<&String> = options.trackers.iter().filter(|tracker| !tracker.trim().is_empty()).collect();
+    if let Some(first) = trackers.first() {
+        torrent.push(("announce", Bencode::string(first.trim())));
+        let tiers = trackers
+            .iter()
+            .map(|tracker| Bencode::List(vec![Bencode::string(tracker.trim())]))
+            .collect();
+        torrent.push(("announce-list", Bencode::List(tiers)));
+    }
+    if let Some((parent, _)) = cdn_directory.filter(|_| options.web_seeds) {
+        torrent.push(("url-list", Bencode::List(vec![Bencode::string(&parent)])));
+    }
+
+    let mut contents = Vec::new();
+    Bencode::dict(torrent).encode(&mut contents);
+    let path = export::export_dir()?.join(format!("{}.torrent", export::sanitize_file_name(&name)));
+    std::fs::write(&path, contents).map_err(|e| format!("Torrent export error: {}", e))?;
+    info!("Wrote torrent {}", path.display());
+    Ok(path)
+}
+
+// Helper function to pick a power-of-two piece length between 256 KiB and 16 MiB
+fn piece_length_for(total_size: u64) -> u64 {
+    (total_size / TARGET_PIECES)
+        .next_power_of_two()
+        .clamp(256 * 1024, 16 * 1024 * 1024)
+}
+
+// The CDN directory every file is in, as (parent URL with a trailing slash, directory name)
+fn common_directory(files: &[PackageFile]) -> Option<(String, String)> {
+    let directory = |file: &PackageFile| {
+        let url = file.url.split(['?', '#']).next().unwrap_or(&file.url);
+        url.rsplit_once('/').map(|(directory, _)| directory.to_string())
+    };
+    let first = directory(files.first()?)?;
+    if files.iter().any(|file| directory(file).as_ref() != Some(&first)) {
+        return None;
+    }
+    let (parent, name) = first.rsplit_once('/')?;
+    if name.is_empty() || !parent.contains("://") {
+        return None;
+    }
+    Some((format!("{}/", parent), name.to_string()))
+}
+
+// SHA-1 of each piece of the files laid end to end
+fn v1_pieces(folder: &Path, files: &[PackageFile], piece_length: u64, is_cancelled: &dyn Fn() -> bool) -> Result<Vec<u8>, String> {
+    let mut pieces = Vec::new();
+    let mut hasher = Sha1::new();
+    let mut in_piece = 0;
+    let mut buffer = vec![0u8; 1024 * 1024];
+
+    for file in files {
+        let path = folder.join(file.file_name());
+        let mut input = File::open(&path).map_err(|e| format!("File read error ({}): {}", path.display(), e))?;
+        loop {
+            if is_cancelled() {
+                return Err("Torrent creation cancelled.".to_string());
+            }
+            let read = input
+                .read(&mut buffer)
+                .map_err(|e| format!("File read error ({}): {}", path.display(), e))?;
+            if read == 0 {
+                break;
+            }
+            let mut data = &buffer[..read];
+            while !data.is_empty() {
+                let take = data.len().min((piece_length - in_piece) as usize);
+                hasher.update(&data[..take]);
+                in_piece += take as u64;
+                data = &data[take..];
+                if in_piece == piece_length {
+                    pieces.extend(hasher.finalize_reset());
+                    in_piece = 0;
+                }
+            }
+        }
+    }
+    if in_piece > 0 {
+        pieces.extend(hasher.finalize());
+    }
+    Ok(pieces)
+}
+
+// The merkle root of a file and its piece layer (the hashes of each piece's subtree)
+fn v2_file_hashes(path: &Path, size: u64, piece_length: u64, is_cancelled: &dyn Fn() -> bool) -> Result<([u8; 32], Vec<u8>), String> {
+    let mut input = File::open(path).map_err(|e| format!("File read error ({}): {}", path.display(), e))?;
+    let blocks_per_piece = (piece_length / BLOCK_SIZE) as usize;
+    let mut piece_hashes = Vec::new();
+    let mut buffer = Vec::with_capacity(piece_length as usize);
+
+    loop {
+        if is_cancelled() {
+            return Err("Torrent creation cancelled.".to_string());
+        }
+        buffer.clear();
+        (&mut input)
+            .take(piece_length)
+            .read_to_end(&mut buffer)
+            .map_err(|e| format!("File read error ({}): {}", path.display(), e))?;
+        if buffer.is_empty() {
+            break;
+        }
+
+        let leaves: Vec<[u8; 32]> = buffer.chunks(BLOCK_SIZE as usize).map(|block| Sha256::digest(block).into()).collect();
+        // A file of at most one piece is a single tree over its blocks
+        if size <= piece_length {
+            let width = leaves.len().next_power_of_two();
+            return Ok((merkle_root(leaves, width, [0; 32]), Vec::new()));
+        }
+        piece_hashes.push(merkle_root(leaves, blocks_per_piece, [0; 32]));
+    }
+
+    let width = piece_hashes.len().next_power_of_two();
+    let root = merkle_root(piece_hashes.clone(), width, padding_hash(blocks_per_piece));
+    Ok((root, piece_hashes.concat()))
+}
+
+// Root of a tree whose bottom layer is `nodes` padded to `width` with `padding`
+fn merkle_root(mut nodes: Vec<[u8; 32]>, width: usize, padding: [u8; 32]) -> [u8; 32] {
+    nodes.resize(width.max(1), padding);
+    while nodes.len() > 1 {
+        nodes = nodes
+            .chunks(2)
+            .map(|pair| {
+                let mut hasher = Sha256::new();
+                hasher.update(pair[0]);
+                hasher.update(pair[1]);
+                hasher.finalize().into()
+            })
+            .collect();
+    }
+    nodes[0]
+}
+
+// Root of a subtree of `leaves` zero leaves, used to pad the piece layer
+fn padding_hash(leaves: usize) -> [u8; 32] {
+    let mut hash = [0; 32];
+    let mut width = 1;
+    while width < leaves {
+        let mut hasher = Sha256::new();
+        hasher.update(hash);
+        hasher.update(hash);
+        hash = hasher.finalize().into();
+        width *= 2;
+    }
+    hash
+}
@@ -0,0 +1,440 @@
+// ----------------------
+// Terminal UI
+// ----------------------
+
+// `tui` shows the Packages tab of the window in a terminal, for machines that
+// are only reachable over SSH: the live version and the pre-download each
+// with a table of their files and the report rendered from the user's
+// templates. Fetches run in the background and are recorded in the shared
+// history like the window's. Copying goes through the terminal (OSC 52), so
+// the text lands on the clipboard of the machine the SSH session runs on.
+
+use crate::cli::record_fetch;
+use crate::export;
+use crate::history::History;
+use crate::hooks::{self, Hooks};
+use crate::variables::{MessageTemplates, Variables};
+use crate::jobs::{self, JobKind};
+use crate::report::{self, ReportFilter, ReportSection, ReportTemplates};
+use crate::settings::Settings;
+use crate::{bytes_to_gb, fetch_and_process_data, map_language_code, FetchResult, GameData, Major, Patch};
+use chrono::{DateTime, Local, Utc};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState, Tabs, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::io::Write;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+// How often the screen checks for a finished fetch while no key is pressed
+const TICK: Duration = Duration::from_millis(200);
+
+const HELP: &str = "f fetch  Tab main/pre-download  g game  ↑↓ file  PgUp/PgDn scroll  c copy report  u copy URL  e export  m MD5 list  q quit";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Panel {
+    Main,
+    PreDownload,
+}
+
+// A file of the shown version, one table row
+struct FileRow {
+    kind: String,
+    version: String,
+    name: String,
+    size: String,
+    md5: String,
+    url: String,
+}
+
+struct TuiApp {
+    source: String,
+    strict: bool,
+    game: Option<String>, // Only show this biz code
+    templates: ReportTemplates,
+    history: History,
+    games: Vec<GameData>,
+    parse_errors: Vec<String>,
+    selected_game: usize,
+    panel: Panel,
+    table: TableState,
+    scroll: u16,    // Lines of the report scrolled past
+    status: String, // Outcome of the last action
+    fetching: Option<Receiver<Result<FetchResult, String>>>, // Fetch in flight
+    fetched_at: Option<DateTime<Local>>,
+    hooks: Hooks,
+    messages: MessageTemplates,
+}
+
+// Open the terminal UI on `source` and run it until the user quits
+pub fn run(settings: &Settings, source: String, strict: bool, game: Option<String>) -> Result<(), String> {
+    let mut app = TuiApp {
+        source,
+        strict,
+        game,
+        templates: settings.templates.clone(),
+        history: History::load(),
+        games: Vec::new(),
+        parse_errors: Vec::new(),
+        selected_game: 0,
+        panel: Panel::Main,
+        table: TableState::default(),
+        scroll: 0,
+        status: String::new(),
+        fetching: None,
+        fetched_at: None,
+        hooks: settings.hooks.clone(),
+        messages: settings.messages.clone(),
+    };
+    app.fetch();
+
+    // `init` also restores the terminal if anything panics
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl TuiApp {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), String> {
+        loop {
+            // Fetches log errors to stderr, over the screen, so it is drawn anew
+            if self.poll_fetch() {
+                terminal.clear().map_err(|e| format!("Terminal error: {}", e))?;
+            }
+            terminal.draw(|frame| self.draw(frame)).map_err(|e| format!("Terminal error: {}", e))?;
+            if !event::poll(TICK).map_err(|e| format!("Terminal error: {}", e))? {
+                continue;
+            }
+            let Event::Key(key) = event::read().map_err(|e| format!("Terminal error: {}", e))? else {
+                continue;
+            };
+            // Windows reports releases as well as presses
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('f') | KeyCode::F(5) => self.fetch(),
+                KeyCode::Tab | KeyCode::Left | KeyCode::Right => {
+                    self.panel = if self.panel == Panel::Main { Panel::PreDownload } else { Panel::Main };
+                    self.reset_view();
+                }
+                KeyCode::Char('g') if !self.games.is_empty() => {
+                    self.selected_game = (self.selected_game + 1) % self.games.len();
+                    self.reset_view();
+                }
+                KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+                KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+                KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+                KeyCode::Char('c') => {
+                    let report = self.report();
+                    self.status = if report.trim().is_empty() {
+                        "Nothing to copy.".to_string()
+                    } else {
+                        copy_to_terminal_clipboard(&report)
+                    };
+                }
+                KeyCode::Char('u') => {
+                    let rows = self.rows();
+                    self.status = match self.table.selected().and_then(|index| rows.get(index)) {
+                        Some(row) => copy_to_terminal_clipboard(&row.url),
+                        None => "Select a file first.".to_string(),
+                    };
+                }
+                KeyCode::Char('e') => self.status = self.export_report(),
+                KeyCode::Char('m') => self.status = self.export_checksums(),
+                _ => {}
+            }
+        }
+    }
+
+    fn reset_view(&mut self) {
+        self.table.select(None);
+        self.scroll = 0;
+    }
+
+    // ----------------------
+    // Fetching
+    // ----------------------
+
+    fn fetch(&mut self) {
+        if self.fetching.is_some() {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let source = self.source.clone();
+        let strict = self.strict;
+        jobs::spawn(JobKind::Fetch, format!("Fetch {}", self.source), None, move || {
+            let _ = sender.send(fetch_and_process_data(&source, strict, &|| false));
+        });
+        self.fetching = Some(receiver);
+        self.status = "Fetching...".to_string();
+    }
+
+    // Take the outcome of the fetch in flight. Returns whether it finished.
+    fn poll_fetch(&mut self) -> bool {
+        let Some(receiver) = &self.fetching else {
+            return false;
+        };
+        let Ok(result) = receiver.try_recv() else {
+            return false;
+        };
+        self.fetching = None;
+        match result {
+            Ok(mut result) => {
+                if let Some(biz) = &self.game {
+                    result.games.retain(|game| game.game.biz == *biz);
+                }
+                let new_records = record_fetch(&mut self.history, &result, Utc::now());
+                // Hook output would land on the screen, so it is dropped
+                let packages: Vec<_> = result.games.iter().filter_map(|game| game.package.clone()).collect();
+                hooks::spawn(hooks::version_hooks(&self.hooks, &packages, &new_records, &self.messages), false);
+                self.fetched_at = Some(Local::now());
+                let message = new_records.first().and_then(|record| {
+                    let package = packages.iter().find(|package| package.game.biz == record.biz)?;
+                    Some(Variables::new_version(package, record, &self.messages)?.message)
+                });
+                self.status = match message {
+                    Some(message) => message,
+                    None if result.games.is_empty() => "The response has no matching game.".to_string(),
+                    None => "Fetched.".to_string(),
+                };
+                self.parse_errors = result.parse_errors;
+                self.games = result.games;
+                self.selected_game = self.selected_game.min(self.games.len().saturating_sub(1));
+            }
+            Err(e) => {
+                hooks::spawn(hooks::error_hook(&self.hooks, &self.source, &e, &self.messages).into_iter().collect(), false);
+                self.status = e;
+            }
+        }
+        true
+    }
+
+    // ----------------------
+    // Data of the Shown Panel
+    // ----------------------
+
+    fn game(&self) -> Option<&GameData> {
+        self.games.get(self.selected_game)
+    }
+
+    fn rows(&self) -> Vec<FileRow> {
+        let Some(game) = self.game() else {
+            return Vec::new();
+        };
+        let (major, patches) = match self.panel {
+            Panel::Main => match &game.package {
+                Some(package) => (package.main.major.as_ref(), package.main.patches.as_slice()),
+                None => return Vec::new(),
+            },
+            Panel::PreDownload => match &game.pre_download {
+                Some(pre_download) => (pre_download.major.as_ref(), pre_download.patches.as_slice()),
+                None => return Vec::new(),
+            },
+        };
+        file_rows(major, patches)
+    }
+
+    // The report of the shown panel from the user's templates
+    fn report(&self) -> String {
+        let Some(game) = self.game() else {
+            return String::new();
+        };
+        let sections: &[ReportSection] = match self.panel {
+            Panel::Main => &[ReportSection::Main],
+            Panel::PreDownload => &[ReportSection::PreDownloadMain, ReportSection::PreDownloadPatches],
+        };
+        let texts: Vec<String> = sections
+            .iter()
+            .map(|&section| {
+                report::render_section(
+                    section,
+                    self.templates.get(section),
+                    self.templates.language,
+                    game.package.as_ref(),
+                    game.pre_download.as_ref(),
+                    &ReportFilter::default(),
+                )
+            })
+            .filter(|text| !text.trim().is_empty())
+            .collect();
+        texts.join("\n\n")
+    }
+
+    // Write the combined report of the shown game to the export folder
+    fn export_report(&self) -> String {
+        let Some(game) = self.game() else {
+            return "Nothing to export.".to_string();
+        };
+        let text = report::game_reports(std::slice::from_ref(game), &self.templates, None);
+        let title = self.templates.language.report_title(&game.game.display_name());
+        let result = export::export_dir().and_then(|dir| {
+            let path = dir.join(export::export_file_name(&title, "txt"));
+            std::fs::write(&path, text).map_err(|e| format!("Export error ({}): {}", path.display(), e))?;
+            Ok(path)
+        });
+        match result {
+            Ok(path) => format!("Exported to {}", path.display()),
+            Err(e) => e,
+        }
+    }
+
+    fn export_checksums(&self) -> String {
+        let Some(package) = self.game().and_then(|game| game.package.as_ref()) else {
+            return "Nothing to export.".to_string();
+        };
+        match export::export_md5_checksums(&package.game.display_name(), package) {
+            Ok(paths) => match paths.first().and_then(|path| path.parent()) {
+                Some(folder) => format!("Exported {} checksum files to {}", paths.len(), folder.display()),
+                None => "Nothing to export.".to_string(),
+            },
+            Err(e) => e,
+        }
+    }
+
+    // ----------------------
+    // Drawing
+    // ----------------------
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [tabs_area, table_area, report_area, status_area, help_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Percentage(45),
+            Constraint::Min(5),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let title = match self.game() {
+            Some(game) if self.games.len() > 1 => {
+                format!(" {} ({} of {}, g for the next) ", game.game.label(), self.selected_game + 1, self.games.len())
+            }
+            Some(game) => format!(" {} ", game.game.label()),
+            None => " Genshin Package Scanner ".to_string(),
+        };
+        let pre_download_version = self
+            .game()
+            .and_then(|game| game.pre_download.as_ref())
+            .and_then(|pre_download| pre_download.major.as_ref())
+            .map(|major| format!("Pre-download {}", major.version))
+            .unwrap_or_else(|| "Pre-download (none)".to_string());
+        let main_version = self
+            .game()
+            .and_then(|game| game.package.as_ref())
+            .and_then(|package| package.main.major.as_ref())
+            .map(|major| format!("Main {}", major.version))
+            .unwrap_or_else(|| "Main".to_string());
+        let tabs = Tabs::new(vec![main_version, pre_download_version])
+            .select(if self.panel == Panel::Main { 0 } else { 1 })
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+            .block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(tabs, tabs_area);
+
+        let rows: Vec<Row> = self
+            .rows()
+            .into_iter()
+            .map(|row| Row::new(vec![row.kind, row.version, row.name, row.size, row.md5]))
+            .collect();
+        let empty = rows.is_empty();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(24),
+                Constraint::Length(9),
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Length(32),
+            ],
+        )
+        .header(Row::new(vec!["Kind", "Version", "File", "Size", "MD5"]).style(Style::default().add_modifier(Modifier::BOLD)))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .block(Block::default().borders(Borders::ALL).title(if empty { " Files (none) " } else { " Files " }));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let report = Paragraph::new(self.report())
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0))
+            .block(Block::default().borders(Borders::ALL).title(" Report "));
+        frame.render_widget(report, report_area);
+
+        let mut status = self.status.clone();
+        if let Some(fetched_at) = self.fetched_at {
+            status = format!("{}  (last fetch {})", status, fetched_at.format("%H:%M:%S"));
+        }
+        if !self.parse_errors.is_empty() {
+            status.push_str(&format!("  {} sections failed to parse", self.parse_errors.len()));
+        }
+        let style = if self.fetching.is_some() { Style::default().fg(Color::Yellow) } else { Style::default() };
+        frame.render_widget(Paragraph::new(Line::styled(status, style)), status_area);
+        frame.render_widget(Paragraph::new(Line::styled(HELP, Style::default().fg(Color::DarkGray))), help_area);
+    }
+}
+
+// The files of a version: full game and audio packages, then the patches
+fn file_rows(major: Option<&Major>, patches: &[Patch]) -> Vec<FileRow> {
+    let mut rows = Vec::new();
+    let Some(major) = major else {
+        return rows;
+    };
+    let mut add = |kind: String, url: &str, size: &str, md5: &str| {
+        rows.push(FileRow {
+            kind,
+            version: major.version.clone(),
+            name: export::file_name_from_url(url),
+            size: format!("{:.2} GB", bytes_to_gb(size)),
+            md5: md5.to_string(),
+            url: url.to_string(),
+        });
+    };
+    for package in &major.game_pkgs {
+        add("Game".to_string(), &package.url, &package.size, &package.md5);
+    }
+    for audio in &major.audio_pkgs {
+        add(format!("Audio {}", map_language_code(&audio.language)), &audio.url, &audio.size, &audio.md5);
+    }
+    for patch in patches {
+        for package in &patch.game_pkgs {
+            add(format!("Patch from {}", patch.version), &package.url, &package.size, &package.md5);
+        }
+        for audio in &patch.audio_pkgs {
+            let kind = format!("Audio {} from {}", map_language_code(&audio.language), patch.version);
+            add(kind, &audio.url, &audio.size, &audio.md5);
+        }
+    }
+    rows
+}
+
+// Put text on the clipboard of the terminal the UI is shown in, which over
+// SSH is the user's own machine. Terminals that don't support OSC 52 ignore it.
+fn copy_to_terminal_clipboard(text: &str) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    let mut stdout = std::io::stdout();
+    match stdout.write_all(sequence.as_bytes()).and_then(|_| stdout.flush()) {
+        Ok(()) => "Copied (through the terminal; it must allow OSC 52 clipboard access).".to_string(),
+        Err(e) => format!("Copy error: {}", e),
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
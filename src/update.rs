@@ -0,0 +1,925 @@
+// ----------------------
+// Install Updates
+// ----------------------
+
+// "Update my install" wizard. The installed version is read from the
+// launcher's `config.ini` and the patch from it is planned: the game patch
+// plus the patches of the installed voice-over languages. The patch files are
+// downloaded through the download queue and checked, then extracted over the
+// install, their hdiff files are applied with `hpatchz`, the files listed in
+// `deletefiles.txt` are removed and the install is re-checked against its
+// `pkg_version` lists. The update can be stopped between stages, and a dry
+// run lists everything it would change before anything is touched. Files the
+// game or launcher has open are not fatal: the user is asked to retry or skip.
+
+use crate::download::{self, DownloadStatus, Downloader};
+use crate::jobs::{self, JobKind};
+use crate::linkcheck::{self, LinkStatus};
+use crate::network;
+use crate::reslist;
+use crate::verify::{self, FileStatus, HashAlgorithm, VerifyProgress};
+use crate::{CancelToken, GamePackage, PackageFile};
+use eframe::egui;
+use log::{error, info, warn};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stage {
+    Download,
+    Verify,
+    Extract,
+    Patch,
+    Delete,
+    Reverify,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Stage::Download,
+        Stage::Verify,
+        Stage::Extract,
+        Stage::Patch,
+        Stage::Delete,
+        Stage::Reverify,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Stage::Download => "Download patch files",
+            Stage::Verify => "Verify MD5",
+            Stage::Extract => "Extract",
+            Stage::Patch => "Apply hdiff",
+            Stage::Delete => "Process deletefiles",
+            Stage::Reverify => "Re-verify install",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StageState {
+    Pending,
+    Running(String), // What the stage is doing right now
+    Done(String),    // Summary of what it did
+    Failed(String),
+    Stopped,
+}
+
+// What to do about a file another process has open
+#[derive(Clone, Copy, PartialEq)]
+pub enum LockedChoice {
+    Retry,
+    Skip,
+}
+
+// A locked file the worker is waiting on, until the user chooses
+struct LockedFile {
+    path: PathBuf,
+    choice: Option<LockedChoice>,
+}
+
+// A game install found on disk
+#[derive(Clone)]
+pub struct Installation {
+    pub dir: PathBuf,
+    pub version: String,
+    pub languages: Vec<String>, // Installed voice-overs, as named by the launcher, e.g. "English(US)"
+}
+
+// What an update will download to bring an install to the live version
+#[derive(Clone)]
+pub struct UpdatePlan {
+    pub game: String,
+    pub install: Installation,
+    pub target: String,
+    pub files: Vec<PackageFile>,
+}
+
+// Read the installed version from the launcher's `config.ini`, and the
+// voice-overs from the `Audio_<language>_pkg_version` files next to it
+pub fn detect(dir: &Path) -> Result<Installation, String> {
+    let config = std::fs::read_to_string(dir.join("config.ini"))
+        .map_err(|e| format!("No config.ini in {}: {}", dir.display(), e))?;
+    let version = config
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("game_version="))
+        .map(|version| version.trim().to_string())
+        .filter(|version| !version.is_empty())
+        .ok_or_else(|| "config.ini has no game_version.".to_string())?;
+
+    let mut languages = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(language) = name.strip_prefix("Audio_").and_then(|name| name.strip_suffix("_pkg_version")) {
+                languages.push(language.to_string());
+            }
+        }
+    }
+    languages.sort();
+    info!("Found {} install at {} with voice-overs {:?}.", version, dir.display(), languages);
+    Ok(Installation {
+        dir: dir.to_path_buf(),
+        version,
+        languages,
+    })
+}
+
+// Helper function to map API language codes to the launcher's voice-over names
+fn launcher_language(code: &str) -> String {
+    match code.to_lowercase().as_str() {
+        "zh-cn" => "Chinese".to_string(),
+        "en-us" => "English(US)".to_string(),
+        "ja-jp" => "Japanese".to_string(),
+        "ko-kr" => "Korean".to_string(),
+        other => other.to_string(),
+    }
+}
+
+// Pick the patch from the installed version to the live one
+pub fn plan(game: &str, install: Installation, package: &GamePackage) -> Result<UpdatePlan, String> {
+    let major = package.main.major.as_ref().ok_or_else(|| "The API lists no live version.".to_string())?;
+    if install.version == major.version {
+        return Err(format!("Already on the live version {}.", major.version));
+    }
+    let patch = package
+        .main
+        .patches
+        .iter()
+        .find(|patch| patch.version == install.version)
+        .ok_or_else(|| {
+            format!(
+                "No patch from {} to {}; the full {} package is needed.",
+                install.version, major.version, major.version
+            )
+        })?;
+
+    let file = |url: &str, md5: &str, size: &str| PackageFile {
+        version: major.version.clone(),
+        url: url.to_string(),
+        md5: md5.to_string(),
+        size: size.parse().unwrap_or(0),
+    };
+    let mut files: Vec<PackageFile> = patch.game_pkgs.iter().map(|pkg| file(&pkg.url, &pkg.md5, &pkg.size)).collect();
+    for pkg in &patch.audio_pkgs {
+        if install.languages.contains(&launcher_language(&pkg.language)) {
+            files.push(file(&pkg.url, &pkg.md5, &pkg.size));
+        }
+    }
+    if files.is_empty() {
+        return Err(format!("The patch from {} has no files.", install.version));
+    }
+
+    Ok(UpdatePlan {
+        game: game.to_string(),
+        target: major.version.clone(),
+        install,
+        files,
+    })
+}
+
+// ----------------------
+// Running an Update
+// ----------------------
+
+// A running (or finished) update. The download stage is driven every frame
+// through the download queue; the remaining stages run on a worker thread.
+pub struct Updater {
+    pub plan: UpdatePlan,
+    folder: PathBuf, // Where the patch files are downloaded to
+    hpatchz: String,
+    stages: Arc<Mutex<Vec<StageState>>>,
+    stop: CancelToken,
+    locked: Arc<Mutex<Option<LockedFile>>>,
+    worker_started: bool,
+}
+
+impl Updater {
+    pub fn new(plan: UpdatePlan, download_root: &Path, hpatchz: &str) -> Self {
+        Self {
+            folder: download::version_dir(download_root, &plan.game, &plan.target),
+            plan,
+            hpatchz: hpatchz.to_string(),
+            stages: Arc::new(Mutex::new(vec![StageState::Pending; Stage::ALL.len()])),
+            stop: CancelToken::default(),
+            locked: Arc::default(),
+            worker_started: false,
+        }
+    }
+
+    pub fn stages(&self) -> MutexGuard<'_, Vec<StageState>> {
+        self.stages.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Not yet finished, failed or stopped
+    pub fn is_running(&self) -> bool {
+        let stages = self.stages();
+        !self.is_stopped()
+            && !stages.iter().any(|state| matches!(state, StageState::Failed(_)))
+            && stages.iter().any(|state| matches!(state, StageState::Pending | StageState::Running(_)))
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_cancelled()
+    }
+
+    // A file the worker can't replace because another process has it open
+    pub fn locked_file(&self) -> Option<PathBuf> {
+        let locked = self.locked.lock().unwrap_or_else(|e| e.into_inner());
+        locked.as_ref().filter(|locked| locked.choice.is_none()).map(|locked| locked.path.clone())
+    }
+
+    pub fn answer_locked(&self, choice: LockedChoice) {
+        if let Some(locked) = self.locked.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            locked.choice = Some(choice);
+        }
+    }
+
+    // Stop before the next stage. The current file operation finishes first,
+    // and queued patch downloads are paused.
+    pub fn stop(&mut self, downloader: &mut Downloader) {
+        info!("Stopping the update to {}.", self.plan.target);
+        self.stop.cancel();
+        for index in self.download_indices(downloader) {
+            downloader.pause(index);
+        }
+        let mut stages = self.stages();
+        for state in stages.iter_mut() {
+            if matches!(state, StageState::Pending | StageState::Running(_)) {
+                *state = StageState::Stopped;
+            }
+        }
+    }
+
+    // Advance the download stage and start the worker once it is done. Called every frame.
+    pub fn update(&mut self, ctx: &egui::Context, downloader: &mut Downloader) {
+        if self.is_stopped() || self.worker_started {
+            return;
+        }
+
+        let state = self.stages()[0].clone();
+        match state {
+            StageState::Pending => {
+                // Files already downloaded (and the right size) are not fetched again
+                let missing: Vec<PackageFile> = self
+                    .plan
+                    .files
+                    .iter()
+                    .filter(|file| {
+                        std::fs::metadata(self.folder.join(file.file_name()))
+                            .map(|metadata| metadata.len() != file.size)
+                            .unwrap_or(true)
+                    })
+                    .cloned()
+                    .collect();
+                let root = self.folder.parent().and_then(Path::parent).unwrap_or(Path::new("."));
+                for file in missing {
+                    downloader.add(&self.plan.game, file, root);
+                }
+                self.stages()[0] = StageState::Running("Waiting for the download queue".to_string());
+            }
+            StageState::Running(_) => {
+                let indices = self.download_indices(downloader);
+                let failed = indices.iter().find_map(|index| match &downloader.downloads[*index].status {
+                    DownloadStatus::Failed(e) => Some(e.clone()),
+                    DownloadStatus::Cancelled => Some("A patch download was cancelled".to_string()),
+                    _ => None,
+                });
+                let done = indices
+                    .iter()
+                    .filter(|index| downloader.downloads[**index].status == DownloadStatus::Done)
+                    .count();
+                let mut stages = self.stages();
+                if let Some(e) = failed {
+                    stages[0] = StageState::Failed(e);
+                } else if done == indices.len() {
+                    stages[0] = StageState::Done(format!("{} file(s) ready", self.plan.files.len()));
+                    drop(stages);
+                    self.start_worker(ctx);
+                } else {
+                    stages[0] = StageState::Running(format!("{} of {} file(s) downloaded", done, indices.len()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Queue entries of this update's patch files
+    fn download_indices(&self, downloader: &Downloader) -> Vec<usize> {
+        downloader
+            .downloads
+            .iter()
+            .enumerate()
+            .filter(|(_, download)| {
+                download.dest.parent() == Some(self.folder.as_path())
+                    && self.plan.files.iter().any(|file| file.url == download.file.url)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    // Run the stages after the download on a worker thread
+    fn start_worker(&mut self, ctx: &egui::Context) {
+        self.worker_started = true;
+        let plan = self.plan.clone();
+        let folder = self.folder.clone();
+        let hpatchz = self.hpatchz.clone();
+        let stages = self.stages.clone();
+        let stop = self.stop.clone();
+        let guard = FileGuard {
+            locked: self.locked.clone(),
+            stop: stop.clone(),
+            ctx: ctx.clone(),
+        };
+        let ctx = ctx.clone();
+        let label = format!("Update {} to {}", plan.game, plan.target);
+        jobs::spawn(JobKind::Update, label, Some(stop.clone()), move || {
+            let set = |stage: Stage, state: StageState| {
+                let index = Stage::ALL.iter().position(|other| *other == stage).unwrap_or(0);
+                stages.lock().unwrap_or_else(|e| e.into_inner())[index] = state;
+                ctx.request_repaint();
+            };
+            let mut lists = PatchLists::default();
+            for stage in Stage::ALL.into_iter().skip(1) {
+                if stop.is_cancelled() {
+                    return;
+                }
+                set(stage, StageState::Running("Working...".to_string()));
+                let progress = |message: String| set(stage, StageState::Running(message));
+                let result = match stage {
+                    Stage::Download => continue,
+                    Stage::Verify => verify_downloads(&folder, &plan.files, &stop),
+                    Stage::Extract => extract_all(&folder, &plan, &mut lists, &guard, &progress),
+                    Stage::Patch => apply_hdiffs(&plan.install.dir, &hpatchz, &lists.hdiff, &guard, &progress),
+                    Stage::Delete => delete_files(&plan.install.dir, &lists.delete, &guard),
+                    Stage::Reverify => reverify(&plan.install.dir, &stop, &progress)
+                        .and_then(|summary| set_installed_version(&plan.install.dir, &plan.target).map(|_| summary)),
+                };
+                match result {
+                    Ok(summary) => set(stage, StageState::Done(summary)),
+                    Err(_) if stop.is_cancelled() => {
+                        set(stage, StageState::Stopped);
+                        return;
+                    }
+                    Err(e) => {
+                        error!("Update stage \"{}\" failed: {}", stage.label(), e);
+                        set(stage, StageState::Failed(e));
+                        return;
+                    }
+                }
+            }
+            info!("Updated {} to {}.", plan.install.dir.display(), plan.target);
+        });
+    }
+}
+
+// Files the patch archives asked to patch and delete, collected while extracting
+#[derive(Default)]
+struct PatchLists {
+    hdiff: Vec<String>,
+    delete: Vec<String>,
+}
+
+impl PatchLists {
+    // Read an archive entry into the lists if it is `hdifffiles.txt` or
+    // `deletefiles.txt`. Returns whether it was one of them.
+    fn read(&mut self, name: &Path, entry: &mut dyn std::io::Read) -> Result<bool, String> {
+        let is_hdiff = name == Path::new("hdifffiles.txt");
+        if !is_hdiff && name != Path::new("deletefiles.txt") {
+            return Ok(false);
+        }
+        let mut contents = String::new();
+        entry
+            .read_to_string(&mut contents)
+            .map_err(|e| format!("Extract error ({}): {}", name.display(), e))?;
+        let lines = contents.lines().map(str::trim).filter(|line| !line.is_empty());
+        if is_hdiff {
+            for line in lines {
+                let entry: reslist::ResourceEntry = serde_json::from_str(line).map_err(|e| format!("Invalid hdifffiles.txt line: {}", e))?;
+                self.hdiff.push(entry.remote_name);
+            }
+        } else {
+            self.delete.extend(lines.map(str::to_string));
+        }
+        Ok(true)
+    }
+}
+
+// Check every downloaded patch file against the API's MD5
+fn verify_downloads(folder: &Path, files: &[PackageFile], stop: &CancelToken) -> Result<String, String> {
+    let progress = Mutex::new(VerifyProgress {
+        total: files.len(),
+        ..Default::default()
+    });
+    verify::verify_files(folder, files, HashAlgorithm::Md5, &progress, &|| {}, &|| stop.is_cancelled());
+    let progress = progress.into_inner().unwrap_or_else(|e| e.into_inner());
+    let bad: Vec<String> = progress
+        .checks
+        .iter()
+        .filter(|check| check.status != FileStatus::Ok)
+        .map(|check| check.file.file_name())
+        .collect();
+    if !bad.is_empty() {
+        return Err(format!("Failed MD5 check: {}", bad.join(", ")));
+    }
+    Ok(format!("{} file(s) OK", progress.checks.len()))
+}
+
+// Lets the worker's file operations wait for the user when a file is locked
+struct FileGuard {
+    locked: Arc<Mutex<Option<LockedFile>>>,
+    stop: CancelToken,
+    ctx: egui::Context,
+}
+
+impl FileGuard {
+    // Run a file operation. While the file is locked by another process
+    // (usually the running game or launcher) ask the user whether to retry or
+    // skip it. Returns false if it was skipped.
+    fn run(&self, path: &Path, mut operation: impl FnMut() -> std::io::Result<()>) -> Result<bool, String> {
+        loop {
+            let e = match operation() {
+                Ok(()) => return Ok(true),
+                Err(e) if is_locked(&e) => e,
+                Err(e) => return Err(format!("File error ({}): {}", path.display(), e)),
+            };
+            warn!("{} is locked: {}", path.display(), e);
+            *self.locked.lock().unwrap_or_else(|e| e.into_inner()) = Some(LockedFile {
+                path: path.to_path_buf(),
+                choice: None,
+            });
+            self.ctx.request_repaint();
+
+            let choice = loop {
+                if self.stop.is_cancelled() {
+                    *self.locked.lock().unwrap_or_else(|e| e.into_inner()) = None;
+                    return Err("Update stopped.".to_string());
+                }
+                let locked = self.locked.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(choice) = locked.as_ref().and_then(|locked| locked.choice) {
+                    break choice;
+                }
+                drop(locked);
+                std::thread::sleep(Duration::from_millis(200));
+            };
+            *self.locked.lock().unwrap_or_else(|e| e.into_inner()) = None;
+            if choice == LockedChoice::Skip {
+                warn!("Skipped locked file {}", path.display());
+                return Ok(false);
+            }
+        }
+    }
+}
+
+// Windows refuses to replace or delete a file another process has open
+// (sharing, lock or access violation). Other systems don't lock files.
+fn is_locked(e: &std::io::Error) -> bool {
+    cfg!(windows) && matches!(e.raw_os_error(), Some(5 | 32 | 33))
+}
+
+// Path of a file in the install. Windows paths longer than 260 characters
+// only work with the `\\?\` prefix, which also needs backslashes throughout.
+fn install_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !cfg!(windows) || !path.is_absolute() {
+        return path;
+    }
+    let path = path.to_string_lossy().replace('/', "\\");
+    if path.starts_with(r"\\?\") {
+        PathBuf::from(path)
+    } else if let Some(share) = path.strip_prefix(r"\\") {
+        PathBuf::from(format!(r"\\?\UNC\{}", share))
+    } else {
+        PathBuf::from(format!(r"\\?\{}", path))
+    }
+}
+
+// Extract each patch archive over the install. `hdifffiles.txt` and
+// `deletefiles.txt` are read instead of written, since every archive has its own.
+// Each file is written to a temporary name first, so a locked target can be
+// retried without reading the archive again.
+fn extract_all(
+    folder: &Path,
+    plan: &UpdatePlan,
+    lists: &mut PatchLists,
+    guard: &FileGuard,
+    progress: &dyn Fn(String),
+) -> Result<String, String> {
+    let mut extracted = 0;
+    let mut skipped = 0;
+    for (index, file) in plan.files.iter().enumerate() {
+        progress(format!("{} ({} of {})", file.file_name(), index + 1, plan.files.len()));
+        let path = folder.join(file.file_name());
+        let input = File::open(&path).map_err(|e| format!("Extract error ({}): {}", path.display(), e))?;
+        let mut archive = zip::ZipArchive::new(input).map_err(|e| format!("Extract error ({}): {}", path.display(), e))?;
+        for entry_index in 0..archive.len() {
+            let mut entry = archive
+                .by_index(entry_index)
+                .map_err(|e| format!("Extract error ({}): {}", path.display(), e))?;
+            let Some(name) = entry.enclosed_name().map(Path::to_path_buf) else {
+                warn!("Skipping unsafe path {} in {}", entry.name(), path.display());
+                continue;
+            };
+            if entry.is_dir() {
+                continue;
+            }
+            if guard.stop.is_cancelled() {
+                return Err("Update stopped.".to_string());
+            }
+
+            if lists.read(&name, &mut entry)? {
+                continue;
+            }
+
+            let name = name.to_string_lossy();
+            let dest = install_path(&plan.install.dir, &name);
+            let temp = install_path(&plan.install.dir, &format!("{}.extracting", name));
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| format!("Extract error ({}): {}", parent.display(), e))?;
+            }
+            let mut output = File::create(&temp).map_err(|e| format!("Extract error ({}): {}", temp.display(), e))?;
+            std::io::copy(&mut entry, &mut output).map_err(|e| format!("Extract error ({}): {}", dest.display(), e))?;
+            drop(output);
+            if guard.run(&dest, || std::fs::rename(&temp, &dest))? {
+                extracted += 1;
+            } else {
+                let _ = std::fs::remove_file(&temp);
+                skipped += 1;
+            }
+        }
+    }
+    let mut summary = format!(
+        "{} file(s) extracted, {} to patch, {} to delete",
+        extracted,
+        lists.hdiff.len(),
+        lists.delete.len()
+    );
+    if skipped > 0 {
+        summary.push_str(&format!(", {} locked file(s) skipped", skipped));
+    }
+    Ok(summary)
+}
+
+// Apply each `<file>.hdiff` to its file with hpatchz, replacing the file once patched
+fn apply_hdiffs(dir: &Path, hpatchz: &str, files: &[String], guard: &FileGuard, progress: &dyn Fn(String)) -> Result<String, String> {
+    let mut skipped = 0;
+    for (index, name) in files.iter().enumerate() {
+        if guard.stop.is_cancelled() {
+            return Err("Update stopped.".to_string());
+        }
+        progress(format!("{} ({} of {})", name, index + 1, files.len()));
+        let old = install_path(dir, name);
+        let diff = install_path(dir, &format!("{}.hdiff", name));
+        let patched = install_path(dir, &format!("{}.patched", name));
+        let status = Command::new(hpatchz)
+            .arg("-f")
+            .arg(&old)
+            .arg(&diff)
+            .arg(&patched)
+            .status()
+            .map_err(|e| format!("Could not run {}: {}", hpatchz, e))?;
+        if !status.success() {
+            let _ = std::fs::remove_file(&patched);
+            return Err(format!("hpatchz failed on {} ({})", name, status));
+        }
+        if !guard.run(&old, || std::fs::rename(&patched, &old))? {
+            let _ = std::fs::remove_file(&patched);
+            skipped += 1;
+            continue;
+        }
+        std::fs::remove_file(&diff).map_err(|e| format!("Patch error ({}): {}", diff.display(), e))?;
+    }
+    if skipped > 0 {
+        return Ok(format!("{} file(s) patched, {} locked file(s) skipped", files.len() - skipped, skipped));
+    }
+    Ok(format!("{} file(s) patched", files.len()))
+}
+
+// Remove the files the patch no longer ships
+fn delete_files(dir: &Path, files: &[String], guard: &FileGuard) -> Result<String, String> {
+    let mut deleted = 0;
+    let mut skipped = 0;
+    for name in files {
+        let path = install_path(dir, name);
+        if !path.exists() {
+            continue;
+        }
+        if guard.run(&path, || std::fs::remove_file(&path))? {
+            deleted += 1;
+        } else {
+            skipped += 1;
+        }
+    }
+    if skipped > 0 {
+        return Ok(format!("{} file(s) deleted, {} locked file(s) skipped", deleted, skipped));
+    }
+    Ok(format!("{} file(s) deleted", deleted))
+}
+
+// Check every file listed in the install's `pkg_version` files
+fn reverify(dir: &Path, stop: &CancelToken, progress: &dyn Fn(String)) -> Result<String, String> {
+    let mut entries = Vec::new();
+    let lists = std::fs::read_dir(dir).map_err(|e| format!("Folder read error ({}): {}", dir.display(), e))?;
+    for list in lists.flatten() {
+        let name = list.file_name().to_string_lossy().to_string();
+        if !name.ends_with("pkg_version") {
+            continue;
+        }
+        let input = File::open(list.path()).map_err(|e| format!("File read error ({}): {}", name, e))?;
+        entries.extend(reslist::read_entries(std::io::BufReader::new(input), &name)?);
+    }
+    if entries.is_empty() {
+        return Err("No pkg_version file in the install.".to_string());
+    }
+
+    let mut bad = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        if index % 100 == 0 {
+            progress(format!("{} of {} files checked", index, entries.len()));
+        }
+        let path = install_path(dir, &entry.remote_name);
+        let size_ok = std::fs::metadata(&path).is_ok_and(|metadata| entry.file_size == 0 || metadata.len() == entry.file_size);
+        let ok = size_ok && verify::file_md5(&path, &|| stop.is_cancelled())?.eq_ignore_ascii_case(&entry.md5);
+        if !ok {
+            warn!("{} does not match pkg_version.", path.display());
+            bad.push(entry.remote_name.clone());
+        }
+    }
+    if !bad.is_empty() {
+        return Err(format!("{} of {} files don't match pkg_version, e.g. {}", bad.len(), entries.len(), bad[0]));
+    }
+    Ok(format!("{} files OK", entries.len()))
+}
+
+// Record the new version in `config.ini` so the launcher agrees
+fn set_installed_version(dir: &Path, version: &str) -> Result<(), String> {
+    let path = dir.join("config.ini");
+    let config = std::fs::read_to_string(&path).map_err(|e| format!("config.ini error: {}", e))?;
+    let updated: Vec<String> = config
+        .lines()
+        .map(|line| {
+            if line.trim().starts_with("game_version=") {
+                format!("game_version={}", version)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    std::fs::write(&path, updated.join("\n") + "\n").map_err(|e| format!("config.ini error: {}", e))
+}
+
+// ----------------------
+// Dry Run
+// ----------------------
+
+// Everything an update would do, worked out without changing anything.
+// Patch archives that aren't downloaded yet are read from the CDN with range
+// requests, fetching only their file list and the patch lists inside.
+pub struct DryRun {
+    pub plan: UpdatePlan,
+    pub downloads: Vec<(PackageFile, bool)>, // Patch files and whether they are already downloaded
+    pub patched: Vec<String>,                // Files hpatchz will rewrite
+    pub deleted: Vec<String>,                // Files in deletefiles.txt
+    pub replaced: Vec<(String, u64)>,        // Existing files overwritten by extraction, with their new size
+    pub added: Vec<(String, u64)>,           // New files
+    pub peak_disk: u64,                      // Most extra disk space needed at any point
+}
+
+// Dry run being worked out as a background job; holds the result once done
+pub struct DryRunJob {
+    pub result: Arc<Mutex<Option<Result<DryRun, String>>>>,
+    pub cancel: CancelToken,
+}
+
+// Size of the blocks read from a remote archive
+const REMOTE_BLOCK: u64 = 256 * 1024;
+
+// Work out a dry run of a plan on a worker thread
+pub fn start_dry_run(ctx: &egui::Context, plan: UpdatePlan, download_root: &Path) -> DryRunJob {
+    let job = DryRunJob {
+        result: Arc::default(),
+        cancel: CancelToken::default(),
+    };
+    let folder = download::version_dir(download_root, &plan.game, &plan.target);
+    let result = job.result.clone();
+    let cancel = job.cancel.clone();
+    let ctx = ctx.clone();
+    let label = format!("Dry run of {} {}", plan.game, plan.target);
+    jobs::spawn(JobKind::Update, label, Some(cancel.clone()), move || {
+        let dry_run = dry_run(plan, &folder, &cancel);
+        if let Err(e) = &dry_run {
+            error!("Dry run failed: {}", e);
+        }
+        *result.lock().unwrap_or_else(|e| e.into_inner()) = Some(dry_run);
+        ctx.request_repaint();
+    });
+    job
+}
+
+fn dry_run(plan: UpdatePlan, folder: &Path, cancel: &CancelToken) -> Result<DryRun, String> {
+    let mut lists = PatchLists::default();
+    let mut downloads = Vec::new();
+    let mut entries = Vec::new();
+    for file in &plan.files {
+        if cancel.is_cancelled() {
+            return Err("Dry run cancelled.".to_string());
+        }
+        let path = folder.join(file.file_name());
+        let downloaded = std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() == file.size);
+        info!("Listing {} ({}).", file.file_name(), if downloaded { "local" } else { "remote" });
+        if downloaded {
+            let input = File::open(&path).map_err(|e| format!("File read error ({}): {}", path.display(), e))?;
+            entries.extend(list_archive(input, &mut lists)?);
+        } else {
+            entries.extend(list_archive(RemoteFile::open(file, cancel)?, &mut lists)?);
+        }
+        downloads.push((file.clone(), downloaded));
+    }
+
+    // Peak: the new downloads, then everything extracted (the .hdiff files
+    // are only removed once applied), plus the patched copy of the largest
+    // file while hpatchz writes it
+    let mut replaced = Vec::new();
+    let mut added = Vec::new();
+    let mut peak_disk: u64 = downloads.iter().filter(|(_, downloaded)| !downloaded).map(|(file, _)| file.size).sum();
+    for (name, size) in entries {
+        match std::fs::metadata(install_path(&plan.install.dir, &name)) {
+            Ok(metadata) if metadata.is_file() => {
+                peak_disk += size.saturating_sub(metadata.len());
+                replaced.push((name, size));
+            }
+            _ => {
+                peak_disk += size;
+                added.push((name, size));
+            }
+        }
+    }
+    peak_disk += lists
+        .hdiff
+        .iter()
+        .filter_map(|name| std::fs::metadata(install_path(&plan.install.dir, name)).ok())
+        .map(|metadata| metadata.len())
+        .max()
+        .unwrap_or(0);
+
+    Ok(DryRun {
+        plan,
+        downloads,
+        patched: lists.hdiff,
+        deleted: lists.delete,
+        replaced,
+        added,
+        peak_disk,
+    })
+}
+
+// Files an archive extracts (name and size), reading its patch lists into `lists`
+fn list_archive<R: std::io::Read + std::io::Seek>(input: R, lists: &mut PatchLists) -> Result<Vec<(String, u64)>, String> {
+    let mut archive = zip::ZipArchive::new(input).map_err(|e| format!("Archive read error: {}", e))?;
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(|e| format!("Archive read error: {}", e))?;
+        let Some(name) = entry.enclosed_name().map(Path::to_path_buf) else {
+            continue;
+        };
+        if entry.is_dir() || lists.read(&name, &mut entry)? {
+            continue;
+        }
+        entries.push((name.to_string_lossy().replace('\\', "/"), entry.size()));
+    }
+    Ok(entries)
+}
+
+impl DryRun {
+    // The plan as plain text, for review before updating
+    pub fn to_text(&self) -> String {
+        let gb = |size: u64| format!("{:.2} GB", crate::bytes_to_gb(&size.to_string()));
+        let plan = &self.plan;
+        let mut text = format!(
+            "{} update plan: {} -> {}\nInstall: {}\nVoice-overs: {}\nCreated {}\n\n",
+            plan.game,
+            plan.install.version,
+            plan.target,
+            plan.install.dir.display(),
+            plan.install.languages.join(", "),
+            chrono::Utc::now().format("%Y-%m-%d %H:%M UTC")
+        );
+
+        let to_download: u64 = self.downloads.iter().filter(|(_, downloaded)| !downloaded).map(|(file, _)| file.size).sum();
+        text.push_str(&format!("Downloads ({} files, {} to download):\n", self.downloads.len(), gb(to_download)));
+        for (file, downloaded) in &self.downloads {
+            let note = if *downloaded { "  (already downloaded)" } else { "" };
+            text.push_str(&format!("  {}  {}{}\n", file.file_name(), gb(file.size), note));
+        }
+        let sections = [
+            ("Patched", self.patched.iter().map(|name| name.to_string()).collect::<Vec<_>>()),
+            ("Replaced", self.replaced.iter().map(|(name, size)| format!("{}  {}", name, gb(*size))).collect()),
+            ("Added", self.added.iter().map(|(name, size)| format!("{}  {}", name, gb(*size))).collect()),
+            ("Deleted", self.deleted.clone()),
+        ];
+        for (title, lines) in sections {
+            text.push_str(&format!("\n{} ({} files):\n", title, lines.len()));
+            for line in lines {
+                text.push_str(&format!("  {}\n", line));
+            }
+        }
+        text.push_str(&format!("\nPeak additional disk usage: {}\n", gb(self.peak_disk)));
+        text
+    }
+}
+
+// Export a dry run as a text file
+pub fn write_dry_run(dry_run: &DryRun) -> Result<PathBuf, String> {
+    let title = format!("{} Update Plan {}", dry_run.plan.game, dry_run.plan.target);
+    let path = crate::export::export_dir()?.join(crate::export::export_file_name(&title, "txt"));
+    std::fs::write(&path, dry_run.to_text()).map_err(|e| format!("Plan export error: {}", e))?;
+    info!("Exported update plan to {}", path.display());
+    Ok(path)
+}
+
+// Read-only view of a file on the CDN, fetched in blocks with range requests
+struct RemoteFile {
+    client: reqwest::blocking::Client,
+    url: String,
+    size: u64,
+    position: u64,
+    block_start: u64,
+    block: Vec<u8>,
+    cancel: CancelToken, // Fails the next read once cancelled
+}
+
+impl RemoteFile {
+    fn open(file: &PackageFile, cancel: &CancelToken) -> Result<Self, String> {
+        let client = network::client_for(&[&file.url]);
+        let check = linkcheck::check_link(&client, file);
+        match (&check.status, check.size) {
+            (LinkStatus::Ok, Some(size)) if check.resumable => Ok(Self {
+                client,
+                url: file.url.clone(),
+                size,
+                position: 0,
+                block_start: 0,
+                block: Vec::new(),
+                cancel: cancel.clone(),
+            }),
+            (LinkStatus::Broken(code), _) => Err(format!("{} returned HTTP {}", file.file_name(), code)),
+            (LinkStatus::Failed(e), _) => Err(format!("{}: {}", file.file_name(), e)),
+            _ => Err(format!(
+                "The server of {} doesn't support range requests; download it first for a full plan.",
+                file.file_name()
+            )),
+        }
+    }
+}
+
+impl std::io::Read for RemoteFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position >= self.size {
+            return Ok(0);
+        }
+        let block_end = self.block_start + self.block.len() as u64;
+        if self.position < self.block_start || self.position >= block_end {
+            if self.cancel.is_cancelled() {
+                return Err(std::io::Error::other("Dry run cancelled."));
+            }
+            let end = (self.position + REMOTE_BLOCK).min(self.size) - 1;
+            let response = self
+                .client
+                .get(&self.url)
+                .header(reqwest::header::RANGE, format!("bytes={}-{}", self.position, end))
+                .send()
+                .and_then(|response| response.error_for_status())
+                .and_then(|response| response.bytes())
+                .map_err(std::io::Error::other)?;
+            self.block = response.to_vec();
+            self.block_start = self.position;
+            if self.block.is_empty() {
+                return Ok(0);
+            }
+        }
+
+        let offset = (self.position - self.block_start) as usize;
+        let count = buf.len().min(self.block.len() - offset);
+        buf[..count].copy_from_slice(&self.block[offset..offset + count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl std::io::Seek for RemoteFile {
+    fn seek(&mut self, position: std::io::SeekFrom) -> std::io::Result<u64> {
+        let target = match position {
+            std::io::SeekFrom::Start(offset) => offset as i64,
+            std::io::SeekFrom::End(offset) => self.size as i64 + offset,
+            std::io::SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+        if target < 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Seek before the start of the file"));
+        }
+        self.position = target as u64;
+        Ok(self.position)
+    }
+}
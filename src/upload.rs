@@ -0,0 +1,257 @@
+// ----------------------
+// Export Uploads
+// ----------------------
+
+// Pushes exports to an S3 bucket (or any S3-compatible store) or a WebDAV
+// share, e.g. the one behind a community website. Each game is uploaded as a
+// JSON snapshot of its packages and an HTML report, under fixed names
+// (`<biz>.json`, `<biz>.html`) so pages can link to them. Uploads run after
+// auto-checks, from `watch --upload`, or for a single export with
+// `export --upload`.
+
+use crate::network;
+use crate::report::{self, ExportFormat, ReportTemplates};
+use crate::GameData;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum UploadBackend {
+    #[default]
+    Off,
+    S3,
+    WebDav,
+}
+
+#[cfg(feature = "gui")]
+impl UploadBackend {
+    pub const ALL: [UploadBackend; 3] = [UploadBackend::Off, UploadBackend::S3, UploadBackend::WebDav];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            UploadBackend::Off => "Off",
+            UploadBackend::S3 => "S3",
+            UploadBackend::WebDav => "WebDAV",
+        }
+    }
+}
+
+// Where exports are uploaded to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct UploadTarget {
+    pub backend: UploadBackend,
+    pub url: String,      // Bucket or folder URL; files are uploaded below it
+    pub region: String,   // S3 only, e.g. "eu-central-1"
+    pub username: String, // S3 access key id, or WebDAV user
+    pub password: String, // S3 secret access key, or WebDAV password
+    pub after_auto_check: bool, // Upload snapshots after each auto-check
+}
+
+impl Default for UploadTarget {
+    fn default() -> Self {
+        Self {
+            backend: UploadBackend::Off,
+            url: String::new(),
+            region: "us-east-1".to_string(),
+            username: String::new(),
+            password: String::new(),
+            after_auto_check: false,
+        }
+    }
+}
+
+impl UploadTarget {
+    pub fn is_configured(&self) -> bool {
+        self.backend != UploadBackend::Off && !self.url.trim().is_empty()
+    }
+
+    // Upload one file below the target URL and return where it went
+    pub fn upload(&self, name: &str, contents: &[u8]) -> Result<String, String> {
+        if !self.is_configured() {
+            return Err("Upload error: no upload target is set up (see Games > Upload)".to_string());
+        }
+        let url = format!("{}/{}", self.url.trim().trim_end_matches('/'), encode_path_segment(name));
+        let url = reqwest::Url::parse(&url).map_err(|e| format!("Upload error ({}): {}", url, e))?;
+        let client = network::builder_for(&[url.as_str()])
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(|e| format!("Upload error: {}", e))?;
+
+        let mut request = client
+            .put(url.clone())
+            .header(reqwest::header::CONTENT_TYPE, content_type(name))
+            .body(contents.to_vec());
+        match self.backend {
+            UploadBackend::S3 => {
+                let payload_hash = format!("{:x}", Sha256::digest(contents));
+                let now = Utc::now();
+                let headers = [
+                    ("host", host_header(&url)),
+                    ("x-amz-content-sha256", payload_hash.clone()),
+                    ("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string()),
+                ];
+                let authorization = s3_authorization(self, "PUT", &url, &headers, &payload_hash, now);
+                for (name, value) in &headers[1..] {
+                    request = request.header(*name, value);
+                }
+                request = request.header(reqwest::header::AUTHORIZATION, authorization);
+            }
+            UploadBackend::WebDav => {
+                if !self.username.is_empty() {
+                    request = request.basic_auth(&self.username, Some(&self.password));
+                }
+            }
+            UploadBackend::Off => {}
+        }
+
+        let response = request.send().map_err(|e| format!("Upload error ({}): {}", url, e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().unwrap_or_default();
+            // S3 and most WebDAV servers explain refusals in an XML body
+            let detail = xml_text(&body, "Message").unwrap_or_else(|| status.canonical_reason().unwrap_or("").to_string());
+            return Err(format!("Upload error ({}): HTTP {} {}", url, status.as_u16(), detail));
+        }
+        info!("Uploaded {} to {}", name, url);
+        Ok(url.to_string())
+    }
+}
+
+// The JSON snapshot and HTML report of each game with package data, named
+// after its biz code
+pub fn snapshot_files(games: &[GameData], templates: &ReportTemplates) -> Vec<(String, Vec<u8>)> {
+    let mut files = Vec::new();
+    for game in games {
+        let Some(package) = &game.package else {
+            continue;
+        };
+        let biz = &game.game.biz;
+        match serde_json::to_vec_pretty(package) {
+            Ok(json) => files.push((format!("{}.json", biz), json)),
+            Err(e) => error!("JSON error for {}: {}", biz, e),
+        }
+        let title = templates.language.report_title(&game.game.display_name());
+        let body = report::game_reports(std::slice::from_ref(game), templates, Some(ExportFormat::Html));
+        files.push((format!("{}.html", biz), html_page(&title, &body).into_bytes()));
+    }
+    files
+}
+
+// Upload snapshot files, stopping at the first failure. Returns how many were uploaded.
+pub fn upload_files(target: &UploadTarget, files: &[(String, Vec<u8>)]) -> Result<usize, String> {
+    for (name, contents) in files {
+        target.upload(name, contents).map_err(|e| {
+            error!("{}", e);
+            e
+        })?;
+    }
+    Ok(files.len())
+}
+
+// The report fragment as a page of its own, so it can also be opened directly
+fn html_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        handlebars::html_escape(title),
+        body
+    )
+}
+
+fn content_type(name: &str) -> &'static str {
+    match name.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase()).as_deref() {
+        Some("html") => "text/html; charset=utf-8",
+        Some("json") => "application/json",
+        Some("md5") | Some("txt") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+// Percent-encode everything but the characters S3 leaves as they are
+fn encode_path_segment(name: &str) -> String {
+    name.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn host_header(url: &reqwest::Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+// Text of the first `<tag>` element in an XML body
+fn xml_text(body: &str, tag: &str) -> Option<String> {
+    let start = body.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = body[start..].find(&format!("</{}>", tag))? + start;
+    Some(body[start..end].trim().to_string())
+}
+
+// ----------------------
+// S3 Request Signing
+// ----------------------
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// Authorization header of an AWS Signature Version 4 request. `headers` are
+// the signed headers with lowercase names, sorted by name; the request must
+// carry all of them. The URL has no query string.
+pub fn s3_authorization(
+    target: &UploadTarget,
+    method: &str,
+    url: &reqwest::Url,
+    headers: &[(&str, String)],
+    payload_hash: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let region = target.region.trim();
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        url.path(),
+        canonical_headers,
+        signed_headers,
+        payload_hash
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+        amz_date,
+        scope,
+        Sha256::digest(canonical_request.as_bytes())
+    );
+
+    let key = hmac_sha256(format!("AWS4{}", target.password.trim()).as_bytes(), &date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, "s3");
+    let key = hmac_sha256(&key, "aws4_request");
+    let signature: String = hmac_sha256(&key, &string_to_sign).iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        target.username.trim(),
+        scope,
+        signed_headers,
+        signature
+    )
+}
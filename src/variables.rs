@@ -0,0 +1,162 @@
+// ----------------------
+// Template Variables
+// ----------------------
+
+// One set of facts about a version (its size, file count, links and when it
+// was seen) shared by everything that announces it: hook commands get them
+// as `$VERSION` and so on, event messages are written with them as `{{version}}`,
+// and report templates can use them as `{{vars.total_size_gb}}`. Working them
+// out in one place keeps a size or a count the same in every output.
+
+use crate::history::{VersionKind, VersionRecord};
+use crate::report;
+use crate::{bytes_to_gb, package_files, GamePackage};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "gui")]
+pub const VARIABLES_HELP: &str = "\
+event: new_version, new_pre_download or fetch_error
+game, biz: the game's name and biz code, e.g. hk4e_global
+kind: \"live version\" or \"pre-download\"
+version: e.g. 5.1.0
+total_size, total_size_gb: the full game and audio packages, in bytes and as \"12.34 GB\"
+file_count, patch_count: files of the version (patches included) and patches to it
+links[]: the URL of every file of the version
+timestamp: when the version was first seen (or the report made), RFC 3339
+source, error: for fetch_error, what was fetched and why it failed
+message: the event's message, from the message templates
+Messages use them as {{version}}; hook commands get them as environment variables ($VERSION, $LINKS, $MESSAGE, ...) and $URLS_FILE";
+
+// The facts about one event
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Variables {
+    pub event: String,
+    pub game: String,
+    pub biz: String,
+    pub kind: String,
+    pub version: String,
+    pub total_size: u64, // Bytes
+    pub total_size_gb: String,
+    pub file_count: usize,
+    pub patch_count: usize,
+    pub links: Vec<String>,
+    pub timestamp: String,
+    pub source: String,
+    pub error: String,
+    pub message: String, // Rendered from the message template of the event
+}
+
+impl Variables {
+    // The live version or the pre-download of a package, None if it has none
+    pub fn version(package: &GamePackage, kind: VersionKind, time: DateTime<Utc>) -> Option<Self> {
+        let (major, patches) = match kind {
+            VersionKind::Live => (package.main.major.as_ref()?, &package.main.patches),
+            VersionKind::PreDownload => {
+                let pre_download = package.pre_download.as_ref()?;
+                (pre_download.major.as_ref()?, &pre_download.patches)
+            }
+        };
+        let sizes = major.game_pkgs.iter().map(|pkg| &pkg.size).chain(major.audio_pkgs.iter().map(|pkg| &pkg.size));
+        let total_size: u64 = sizes.map(|size| size.parse::<u64>().unwrap_or(0)).sum();
+        let links: Vec<String> = package_files(package)
+            .into_iter()
+            .filter(|file| file.version == major.version)
+            .map(|file| file.url)
+            .collect();
+        let (event, kind) = match kind {
+            VersionKind::Live => ("new_version", "live version"),
+            VersionKind::PreDownload => ("new_pre_download", "pre-download"),
+        };
+        Some(Variables {
+            event: event.to_string(),
+            game: package.game.display_name(),
+            biz: package.game.biz.clone(),
+            kind: kind.to_string(),
+            version: major.version.clone(),
+            total_size,
+            total_size_gb: format!("{:.2} GB", bytes_to_gb(&total_size.to_string())),
+            file_count: links.len(),
+            patch_count: patches.len(),
+            links,
+            timestamp: time.to_rfc3339(),
+            ..Default::default()
+        })
+    }
+
+    // A version seen for the first time, with its message
+    pub fn new_version(package: &GamePackage, record: &VersionRecord, messages: &MessageTemplates) -> Option<Self> {
+        let mut vars = Variables::version(package, record.kind, record.first_seen)?;
+        vars.message = vars.render(messages.get(&vars.event));
+        Some(vars)
+    }
+
+    // A failed fetch of `source`, with its message
+    pub fn fetch_error(source: &str, error: &str, messages: &MessageTemplates) -> Self {
+        let mut vars = Variables {
+            event: "fetch_error".to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            source: source.to_string(),
+            error: error.to_string(),
+            ..Default::default()
+        };
+        vars.message = vars.render(&messages.fetch_error);
+        vars
+    }
+
+    // Fill a template in with these variables
+    pub fn render(&self, template: &str) -> String {
+        report::render(template, self)
+    }
+
+    // The variables as environment variables: `VERSION`, `TOTAL_SIZE` and so
+    // on, with `LINKS` one URL per line
+    pub fn env(&self) -> Vec<(String, String)> {
+        let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(self) else {
+            return Vec::new();
+        };
+        fields
+            .into_iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    serde_json::Value::String(text) => text,
+                    serde_json::Value::Array(items) => {
+                        items.iter().filter_map(|item| item.as_str()).collect::<Vec<_>>().join("\n")
+                    }
+                    other => other.to_string(),
+                };
+                (name.to_uppercase(), value)
+            })
+            .collect()
+    }
+}
+
+// The one-line messages announcing each event, in the window, on the command
+// line and in hooks (as `{{message}}`)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MessageTemplates {
+    pub new_version: String,
+    pub new_pre_download: String,
+    pub fetch_error: String,
+}
+
+impl Default for MessageTemplates {
+    fn default() -> Self {
+        Self {
+            new_version: "New live version {{version}} for {{game}} ({{total_size_gb}}, {{file_count}} files)".to_string(),
+            new_pre_download: "New pre-download {{version}} for {{game}} ({{total_size_gb}}, {{file_count}} files)".to_string(),
+            fetch_error: "Fetch of {{source}} failed: {{error}}".to_string(),
+        }
+    }
+}
+
+impl MessageTemplates {
+    fn get(&self, event: &str) -> &str {
+        match event {
+            "new_version" => &self.new_version,
+            "new_pre_download" => &self.new_pre_download,
+            _ => &self.fetch_error,
+        }
+    }
+}
@@ -0,0 +1,346 @@
+// ----------------------
+// Local File Verification
+// ----------------------
+
+// Checks downloaded package files in a local folder against the sizes and
+// MD5s the API publishes, hashing several files in parallel, and produces a
+// pass/fail report. SHA-256 can be computed as well for archive manifests,
+// which are written next to the files as `manifest.json`.
+
+#[cfg(feature = "gui")]
+use crate::export;
+use crate::PackageFile;
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+// Most files hashed at once; beyond this the disk is the bottleneck
+const MAX_WORKERS: usize = 4;
+
+// Hashes computed for each local file. MD5 is always included since it is
+// what the API gives to check against.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum HashAlgorithm {
+    #[default]
+    Md5,
+    Md5AndSha256,
+}
+
+#[cfg(feature = "gui")]
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 2] = [HashAlgorithm::Md5, HashAlgorithm::Md5AndSha256];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "MD5",
+            HashAlgorithm::Md5AndSha256 => "MD5 + SHA-256",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FileStatus {
+    Ok,
+    WrongSize,      // Size differs from the API, so it wasn't hashed
+    Mismatch,       // MD5 differs from the API
+    Missing,        // Not in the folder
+    Failed(String), // Could not be read
+}
+
+// Result of checking one file
+#[derive(Clone, Debug)]
+pub struct FileCheck {
+    pub file: PackageFile,
+    pub status: FileStatus,
+    pub size: u64,              // Size on disk
+    pub md5: Option<String>,    // Lowercase hex
+    pub sha256: Option<String>, // Only when SHA-256 was selected
+}
+
+// Verification running on a worker thread; the UI reads it every frame
+#[derive(Default)]
+pub struct VerifyProgress {
+    pub total: usize,
+    pub checks: Vec<FileCheck>,
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub unknown: Vec<String>, // Files in the folder that aren't in the API
+    pub finished: bool,
+}
+
+impl VerifyProgress {
+    // Every checked file is present and intact
+    pub fn passed(&self) -> bool {
+        self.finished && self.checks.len() == self.total && self.checks.iter().all(|check| check.status == FileStatus::Ok)
+    }
+
+    pub fn count(&self, status: &FileStatus) -> usize {
+        self.checks.iter().filter(|check| check.status == *status).count()
+    }
+}
+
+// Archive manifest of a folder of package files, written by verification
+// and updated by the downloader as files finish
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    pub game: String,
+    pub created: DateTime<Utc>,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub file_name: String,
+    pub version: String,
+    pub size: u64,
+    pub md5: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloaded: Option<DateTime<Utc>>, // When the built-in downloader fetched it
+}
+
+// Match the files in a folder to the API's entries by name. Returns the
+// matched entries and the names of files that aren't in the API.
+pub fn match_folder(folder: &Path, files: &[PackageFile]) -> Result<(Vec<PackageFile>, Vec<String>), String> {
+    let entries = std::fs::read_dir(folder).map_err(|e| format!("Folder read error ({}): {}", folder.display(), e))?;
+    let mut matched = Vec::new();
+    let mut unknown = Vec::new();
+    for entry in entries.flatten() {
+        if !entry.path().is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        match files.iter().find(|file| file.file_name() == name) {
+            Some(file) => matched.push(file.clone()),
+            None => unknown.push(name),
+        }
+    }
+    matched.sort_by_key(|file| file.file_name());
+    unknown.sort();
+    Ok((matched, unknown))
+}
+
+// Check files on several worker threads, adding each result to `progress` as it completes
+pub fn verify_files(
+    folder: &Path,
+    files: &[PackageFile],
+    algorithm: HashAlgorithm,
+    progress: &Mutex<VerifyProgress>,
+    on_checked: &(dyn Fn() + Sync),
+    is_cancelled: &(dyn Fn() -> bool + Sync),
+) {
+    let next = AtomicUsize::new(0);
+    let workers = std::thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1)
+        .clamp(1, MAX_WORKERS);
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(file) = files.get(index) else {
+                    break;
+                };
+                if is_cancelled() {
+                    break;
+                }
+                let check = verify_file(folder, file, algorithm, is_cancelled);
+                if let Ok(mut progress) = progress.lock() {
+                    progress.checks.push(check);
+                }
+                on_checked();
+            });
+        }
+    });
+}
+
+// Check one file of a package against the copy in `folder`
+pub fn verify_file(folder: &Path, file: &PackageFile, algorithm: HashAlgorithm, is_cancelled: &dyn Fn() -> bool) -> FileCheck {
+    let path = folder.join(file.file_name());
+    let mut check = FileCheck {
+        file: file.clone(),
+        status: FileStatus::Missing,
+        size: 0,
+        md5: None,
+        sha256: None,
+    };
+    let Some(metadata) = std::fs::metadata(&path).ok().filter(|metadata| metadata.is_file()) else {
+        return check;
+    };
+    // A size mismatch is certain without reading the whole file
+    if file.size > 0 && metadata.len() != file.size {
+        check.status = FileStatus::WrongSize;
+        check.size = metadata.len();
+        info!("Verified {}: {:?}", path.display(), check.status);
+        return check;
+    }
+
+    match hash_file(&path, algorithm, is_cancelled) {
+        Ok((size, md5, sha256)) => {
+            check.status = if md5.eq_ignore_ascii_case(&file.md5) {
+                FileStatus::Ok
+            } else {
+                FileStatus::Mismatch
+            };
+            check.size = size;
+            check.md5 = Some(md5);
+            check.sha256 = sha256;
+        }
+        Err(e) => {
+            error!("{}", e);
+            check.status = FileStatus::Failed(e);
+        }
+    }
+    info!("Verified {}: {:?}", path.display(), check.status);
+    check
+}
+
+// MD5 of a file, lowercase hex
+pub fn file_md5(path: &Path, is_cancelled: &dyn Fn() -> bool) -> Result<String, String> {
+    hash_file(path, HashAlgorithm::Md5, is_cancelled).map(|(_, md5, _)| md5)
+}
+
+// Hash a file in one pass. Returns its size, MD5 and SHA-256 (if selected).
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm, is_cancelled: &dyn Fn() -> bool) -> Result<(u64, String, Option<String>), String> {
+    let mut file = File::open(path).map_err(|e| format!("File read error ({}): {}", path.display(), e))?;
+    let mut md5 = Md5::new();
+    let mut sha256 = (algorithm == HashAlgorithm::Md5AndSha256).then(Sha256::new);
+
+    let mut size = 0;
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        if is_cancelled() {
+            return Err("Verification cancelled.".to_string());
+        }
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("File read error ({}): {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        md5.update(&buffer[..read]);
+        if let Some(sha256) = &mut sha256 {
+            sha256.update(&buffer[..read]);
+        }
+        size += read as u64;
+    }
+
+    Ok((
+        size,
+        format!("{:x}", md5.finalize()),
+        sha256.map(|sha256| format!("{:x}", sha256.finalize())),
+    ))
+}
+
+// Write a pass/fail report of a verification to the export folder
+#[cfg(feature = "gui")]
+pub fn write_report(game: &str, folder: &Path, progress: &VerifyProgress) -> Result<PathBuf, String> {
+    let mut report = format!(
+        "{} - Verification of {}\n{}\nResult: {}\n\n",
+        game,
+        folder.display(),
+        Utc::now().format("%Y-%m-%d %H:%M UTC"),
+        if progress.passed() { "PASS" } else { "FAIL" }
+    );
+    for check in &progress.checks {
+        let result = match &check.status {
+            FileStatus::Ok => "PASS".to_string(),
+            FileStatus::WrongSize => format!("FAIL size {} expected {}", check.size, check.file.size),
+            FileStatus::Mismatch => format!("FAIL md5 {} expected {}", check.md5.as_deref().unwrap_or("-"), check.file.md5),
+            FileStatus::Missing => "FAIL missing".to_string(),
+            FileStatus::Failed(e) => format!("FAIL {}", e),
+        };
+        report.push_str(&format!("{}  {} ({})  {}\n", check.file.file_name(), check.file.version, check.file.size, result));
+    }
+    if !progress.unknown.is_empty() {
+        report.push_str("\nNot in the API:\n");
+        for name in &progress.unknown {
+            report.push_str(&format!("{}\n", name));
+        }
+    }
+
+    let path = export::export_dir()?.join(export::export_file_name(&format!("{} Verification", game), "txt"));
+    std::fs::write(&path, report).map_err(|e| {
+        error!("Failed to write report {}: {}", path.display(), e);
+        format!("Report export error: {}", e)
+    })?;
+    info!("Exported verification report to {}", path.display());
+    Ok(path)
+}
+
+// Write `manifest.json` into the verified folder, listing the files that passed
+pub fn write_manifest(folder: &Path, game: &str, checks: &[FileCheck]) -> Result<PathBuf, String> {
+    let files: Vec<ManifestEntry> = checks
+        .iter()
+        .filter(|check| check.status == FileStatus::Ok)
+        .map(|check| ManifestEntry {
+            file_name: check.file.file_name(),
+            version: check.file.version.clone(),
+            size: check.size,
+            md5: check.md5.clone().unwrap_or_default(),
+            sha256: check.sha256.clone(),
+            url: Some(check.file.url.clone()),
+            downloaded: None,
+        })
+        .collect();
+    if files.is_empty() {
+        return Err("No verified files to put in a manifest.".to_string());
+    }
+
+    let manifest = Manifest {
+        game: game.to_string(),
+        created: Utc::now(),
+        files,
+    };
+    save_manifest(&folder.join("manifest.json"), &manifest)
+}
+
+// Add a downloaded file to the `manifest.json` of its folder, replacing any
+// earlier entry for the same file
+pub fn add_to_manifest(folder: &Path, game: &str, file: &PackageFile, size: u64) -> Result<PathBuf, String> {
+    let path = folder.join("manifest.json");
+    let mut manifest = read_manifest(&path).unwrap_or_else(|_| Manifest {
+        game: game.to_string(),
+        created: Utc::now(),
+        files: Vec::new(),
+    });
+
+    let file_name = file.file_name();
+    manifest.files.retain(|entry| entry.file_name != file_name);
+    manifest.files.push(ManifestEntry {
+        file_name,
+        version: file.version.clone(),
+        size,
+        md5: file.md5.to_lowercase(),
+        sha256: None,
+        url: Some(file.url.clone()),
+        downloaded: Some(Utc::now()),
+    });
+    save_manifest(&path, &manifest)
+}
+
+// Read a `manifest.json`
+pub fn read_manifest(path: &Path) -> Result<Manifest, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Manifest error ({}): {}", path.display(), e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Manifest error ({}): {}", path.display(), e))
+}
+
+// Helper function to write a manifest as pretty-printed JSON
+fn save_manifest(path: &Path, manifest: &Manifest) -> Result<PathBuf, String> {
+    let contents = serde_json::to_string_pretty(manifest).map_err(|e| format!("Manifest error: {}", e))?;
+    std::fs::write(path, contents).map_err(|e| {
+        error!("Failed to write manifest {}: {}", path.display(), e);
+        format!("Manifest error: {}", e)
+    })?;
+    info!("Wrote manifest {}", path.display());
+    Ok(path.to_path_buf())
+}
@@ -0,0 +1,98 @@
+// ----------------------
+// Game Versions
+// ----------------------
+
+// Versions such as "5.0.0" compared number by number, so "5.10.0" comes after
+// "5.9.0", and shortened the way patch notes write them: "5.0.0" is "5.0",
+// never "5". A patch's target version is read from its file names, which
+// carry both ends (game_4.8.0_5.1.0_hdiff_....zip), so a patch to something
+// other than the pre-download is still labelled right.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Clone, Debug)]
+pub struct Version {
+    parts: Vec<u64>, // At least one
+}
+
+impl Version {
+    // None unless every dot-separated part is a number
+    pub fn parse(text: &str) -> Option<Self> {
+        let parts = text.trim().split('.').map(|part| part.parse().ok()).collect::<Option<Vec<u64>>>()?;
+        Some(Version { parts })
+    }
+
+    // Without trailing zero parts, keeping major.minor: "5.0.0" -> "5.0",
+    // "4.8.0" -> "4.8", "5.1.2" -> "5.1.2"
+    pub fn short(&self) -> String {
+        let mut length = self.parts.len();
+        while length > 2 && self.parts[length - 1] == 0 {
+            length -= 1;
+        }
+        join(&self.parts[..length])
+    }
+}
+
+impl Ord for Version {
+    // Missing parts count as zero, so "5.0" and "5.0.0" are equal
+    fn cmp(&self, other: &Self) -> Ordering {
+        let length = self.parts.len().max(other.parts.len());
+        let part = |parts: &[u64], index: usize| parts.get(index).copied().unwrap_or(0);
+        (0..length)
+            .map(|index| part(&self.parts, index).cmp(&part(&other.parts, index)))
+            .find(|order| order.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&join(&self.parts))
+    }
+}
+
+fn join(parts: &[u64]) -> String {
+    parts.iter().map(u64::to_string).collect::<Vec<_>>().join(".")
+}
+
+// Order two version strings, newest first. Ones that don't parse go last, by text.
+pub fn newest_first(a: &str, b: &str) -> Ordering {
+    match (Version::parse(a), Version::parse(b)) {
+        (Some(a), Some(b)) => b.cmp(&a),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.cmp(b),
+    }
+}
+
+// The short form of a version string, or the string itself if it doesn't parse
+pub fn short(text: &str) -> String {
+    Version::parse(text).map_or_else(|| text.to_string(), |version| version.short())
+}
+
+// The version a patch from `from` updates to, read from the name of one of
+// its files: the version right after `from` among the name's `_`-separated parts
+pub fn patch_target(file_name: &str, from: &str) -> Option<String> {
+    let from = Version::parse(from)?;
+    let parts: Vec<&str> = file_name.split('_').collect();
+    parts
+        .windows(2)
+        .find(|pair| Version::parse(pair[0]).as_ref() == Some(&from))
+        .and_then(|pair| Version::parse(pair[1]))
+        .map(|target| target.to_string())
+}
@@ -0,0 +1,1696 @@
+// ----------------------
+// Game Workspace
+// ----------------------
+
+// Everything that belongs to one configured game: its data source, fetch
+// status, formatted reports and export actions. With several games
+// configured, each workspace is shown in its own tab.
+
+use crate::audio::AudioListing;
+use crate::clipboard;
+use crate::compare;
+use crate::export::{self, UrlListFormat};
+use crate::find::FindBar;
+use crate::history::{self, History};
+use crate::jobs::{self, JobKind};
+use crate::linkcheck::{self, LinkCheckJob, LinkProgress, LinkStatus};
+use crate::polling;
+use crate::reslist::{self, ResourceListJob};
+use crate::qr::{self, QrMatrix};
+use crate::report::{self, ExportFormat, PackageCategory, ReportFilter, ReportLanguage, ReportSection, ReportTemplates};
+use crate::settings::{GameConfig, PackagePin, PinTarget};
+use crate::share::{self, ShareFormat, ShareTarget};
+use crate::theme::{status_label, Status};
+use crate::timing;
+use crate::torrent::{self, TorrentOptions, TorrentVersion};
+use crate::verify::{self, FileStatus, HashAlgorithm, VerifyProgress};
+use crate::{
+    bytes_to_gb, fetch_with_body, labelled, map_language_code, package_files, CancelToken, FetchEvent, FetchFailure,
+    FetchResult, GameData, GamePackage, PackageFile, PreDownload,
+};
+use chrono::{DateTime, Utc};
+use eframe::egui;
+use eframe::egui::{Align, Layout};
+use log::{error, info};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+// The fetch currently in flight. Events from any other fetch id are stale
+// (the fetch was cancelled) and are dropped.
+struct ActiveFetch {
+    id: u64,
+    cancel: CancelToken,
+}
+
+// A check of local files running (or finished) on a worker thread
+struct LocalVerify {
+    folder: PathBuf,
+    progress: Arc<Mutex<VerifyProgress>>,
+    cancel: CancelToken,
+}
+
+// Torrent being built from verified files; holds the result once done
+type TorrentJob = Arc<Mutex<Option<Result<PathBuf, String>>>>;
+
+// Upload to the paste service; holds the link once done
+type ShareJob = Arc<Mutex<Option<Result<String, String>>>>;
+
+// App-wide state a workspace needs while drawing
+pub struct WorkspaceContext<'a> {
+    pub templates: &'a ReportTemplates,
+    pub event_sender: &'a Sender<(u64, FetchEvent)>, // Fetch workers report back through this
+    pub next_fetch_id: &'a mut u64,
+    pub discord_chunks: &'a mut Vec<String>,         // Pending multi-part Discord messages
+    pub history: &'a History,
+    pub pins: &'a mut Vec<PackagePin>,               // Saved by the app when changed
+    pub toast: &'a mut Option<(String, DateTime<Utc>)>, // Short notification shown by the app
+    pub verify_hashes: &'a mut HashAlgorithm,        // Saved by the app when changed
+    pub strict_parsing: bool,                        // Reject sections that don't match the models
+    pub share: &'a ShareTarget,                      // Paste service for the Share menu
+    pub hidden_sections: &'a [String],               // Keys of the sections hidden from the View menu
+}
+
+// The result sections of a game: the key their state is saved under, their
+// title and whether they start out open
+pub const SECTIONS: [(&str, &str, bool); 16] = [
+    ("warnings", "Warnings", false),
+    ("pinned", "Pinned", true),
+    ("next_patch_summary", "Next Patch Summary", true),
+    ("patch_cadence", "Patch Cadence", false),
+    ("audio_packages", "Audio Packages", false),
+    ("checksums", "Checksums", false),
+    ("verify_local_files", "Verify Local Files", false),
+    ("create_torrent", "Create Torrent", false),
+    ("check_links", "Check Links", false),
+    ("file_list", "File List", false),
+    ("qr_codes", "QR Codes", false),
+    ("main", "Main Data", false),
+    ("pre_download_main", "Pre-download (Main)", false),
+    ("pre_download_patches", "Pre-download (Patches)", false),
+    ("raw_main", "Raw Main Data", false),
+    ("raw_pre_download", "Raw Pre-download Data", false),
+];
+
+// Save whether the section of `response` is open under `key`, so the game's
+// tab comes back with its sections as they were left
+fn remember_open<R>(
+    open_sections: &mut BTreeMap<String, bool>,
+    ctx: &egui::Context,
+    key: &str,
+    response: &egui::CollapsingResponse<R>,
+) {
+    let Some(state) = egui::collapsing_header::CollapsingState::load(ctx, response.header_response.id) else {
+        return;
+    };
+    if open_sections.get(key) != Some(&state.is_open()) {
+        open_sections.insert(key.to_string(), state.is_open());
+    }
+}
+
+// Folder that holds a set of exported files
+fn export_folder(paths: &[std::path::PathBuf]) -> std::path::PathBuf {
+    match paths {
+        [path] => path.clone(),
+        _ => export::export_dir().unwrap_or_default(),
+    }
+}
+
+// Draw a QR code as black modules on white, quiet zone included, at most `size` points square
+fn paint_qr(ui: &mut egui::Ui, matrix: &QrMatrix, size: f32) {
+    // Whole points per module keep anti-aliasing from blurring the edges
+    let module = (size / matrix.full_width() as f32).floor().max(1.0);
+    let side = module * matrix.full_width() as f32;
+    let (response, painter) = ui.allocate_painter(egui::vec2(side, side), egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+    for y in 0..matrix.width {
+        for x in 0..matrix.width {
+            if matrix.is_dark(x, y) {
+                let min = rect.min + egui::vec2((x + qr::QUIET_ZONE) as f32, (y + qr::QUIET_ZONE) as f32) * module;
+                painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(module, module)), 0.0, egui::Color32::BLACK);
+            }
+        }
+    }
+}
+
+// Tell the user where an export was written, or why it failed
+fn notify_export(app: &mut WorkspaceContext, result: Result<std::path::PathBuf, String>) {
+    let message = match result {
+        Ok(path) => format!("Exported to {}", path.display()),
+        Err(e) => e,
+    };
+    *app.toast = Some((message, Utc::now()));
+}
+
+pub struct GameWorkspace {
+    pub config: GameConfig,
+    formatted_message: String,
+    pre_download_main_message: String,    // For Pre-download (Main)
+    pre_download_patches_message: String, // For Pre-download (Patches)
+    next_patch_summary: String,           // Live vs pre-download overview
+    error_message: String,                // Error messages
+    failure: Option<FetchFailure>,        // The last fetch's error, with the start of its response
+    active_fetch: Option<ActiveFetch>,    // The running fetch worker, if any
+    games: Vec<GameData>,                 // Every game in the last response
+    selected_game: usize,                 // Game whose data is displayed
+    package: Option<GamePackage>,         // Parsed package of the displayed game
+    pre_download: Option<PreDownload>,    // Pre-download section of the displayed game
+    last_fetch: Option<(DateTime<Utc>, bool)>, // When the last fetch finished and whether it succeeded
+    retry_at: Option<DateTime<Utc>>,      // When a fetch the API turned away as busy is tried again
+    retries: usize,                       // Retries of the current fetch so far
+    verify_folder: String,                // Folder of downloaded files to check
+    verify_version: Option<String>,       // Version to check, None for the files found in the folder
+    local_verify: Option<LocalVerify>,    // The last check of local files
+    torrent_version: TorrentVersion,      // Format of torrents made from verified files
+    torrent_trackers: String,             // Announce URLs, one per line
+    torrent_web_seeds: bool,              // Add the CDN links to torrents as a web seed
+    torrent_job: Option<TorrentJob>,      // Torrent being built, if any
+    link_check: Option<LinkCheckJob>,     // The last check of the package links
+    resource_list: Option<ResourceListJob>, // The last scattered-file list loaded
+    qr_code: Option<(PackageFile, QrMatrix)>, // QR code shown in its own window
+    share_job: Option<ShareJob>,          // Report being shared, if any
+    open_all: Option<bool>,               // Expand All or Collapse All clicked this frame
+    raw_main_find: FindBar,               // Search in the Raw Main Data panel
+    raw_pre_download_find: FindBar,       // Search in the Raw Pre-download Data panel
+    url_filter: String,                   // Regex the URLs of exported lists must match
+    audio_listing: AudioListing,          // Sorting and grouping of the Audio Packages table
+}
+
+impl GameWorkspace {
+    pub fn new(config: GameConfig) -> Self {
+        Self {
+            config,
+            formatted_message: String::new(),
+            pre_download_main_message: String::new(),
+            pre_download_patches_message: String::new(),
+            next_patch_summary: String::new(),
+            error_message: String::new(),
+            failure: None,
+            active_fetch: None,
+            games: Vec::new(),
+            selected_game: 0,
+            package: None,
+            pre_download: None,
+            last_fetch: None,
+            retry_at: None,
+            retries: 0,
+            verify_folder: String::new(),
+            verify_version: None,
+            local_verify: None,
+            torrent_version: TorrentVersion::V1,
+            torrent_trackers: String::new(),
+            torrent_web_seeds: true,
+            torrent_job: None,
+            link_check: None,
+            resource_list: None,
+            qr_code: None,
+            share_job: None,
+            open_all: None,
+            raw_main_find: FindBar::default(),
+            raw_pre_download_find: FindBar::default(),
+            url_filter: String::new(),
+            audio_listing: AudioListing::default(),
+        }
+    }
+
+    pub fn is_fetching(&self) -> bool {
+        self.active_fetch.is_some()
+    }
+
+    // When the last fetch finished and whether it succeeded
+    pub fn last_fetch(&self) -> Option<(DateTime<Utc>, bool)> {
+        self.last_fetch
+    }
+
+    // When the fetch the API turned away is tried again, if it will be
+    pub fn retry_at(&self) -> Option<DateTime<Utc>> {
+        self.retry_at
+    }
+
+    // Whether a worker event with this fetch id belongs to this workspace
+    pub fn owns_fetch(&self, fetch_id: u64) -> bool {
+        self.active_fetch.as_ref().map(|fetch| fetch.id) == Some(fetch_id)
+    }
+
+    // ----------------------
+    // UI
+    // ----------------------
+
+    // The header of a result section, open as it was left in this game
+    fn section_header(&self, key: &str, title: impl Into<egui::WidgetText>) -> egui::CollapsingHeader {
+        let default_open = SECTIONS.iter().any(|(section, _, open)| *section == key && *open);
+        egui::CollapsingHeader::new(title)
+            .id_source((key, &self.config.name))
+            .default_open(self.config.open_sections.get(key).copied().unwrap_or(default_open))
+            .open(self.open_all)
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, app: &mut WorkspaceContext) {
+        let ctx = ui.ctx().clone();
+
+        // Hand over the link of a finished share
+        let shared = self
+            .share_job
+            .as_ref()
+            .and_then(|job| job.lock().unwrap_or_else(|e| e.into_inner()).take());
+        if let Some(result) = shared {
+            self.share_job = None;
+            let message = match result {
+                Ok(url) => {
+                    let message = format!("Shared to {} (link copied)", url);
+                    ctx.output_mut(|o| o.copied_text = url);
+                    message
+                }
+                Err(e) => e,
+            };
+            *app.toast = Some((message, Utc::now()));
+        }
+
+        // Data source: API URL or a local JSON file (e.g. an archived legacy payload)
+        ui.horizontal(|ui| {
+            labelled(ui, "Source:", egui::TextEdit::singleline(&mut self.config.source).desired_width(f32::INFINITY));
+        });
+
+        ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+            // Fetch Data Button
+            if ui.add_enabled(!self.is_fetching(), egui::Button::new("Fetch Data")).clicked() {
+                self.start_fetch(&ctx, app);
+            }
+
+            // Cancel Button, only while a fetch is in flight
+            if self.is_fetching() && ui.button("Cancel").clicked() {
+                self.cancel_fetch();
+            }
+
+            // Clear Button
+            if ui.button("Clear").clicked() {
+                self.clear();
+            }
+
+            // Copy All Button: every section in one report
+            if ui.button("Copy All").clicked() {
+                let combined = self.combined_report(app.templates.language);
+                ctx.output_mut(|o| o.copied_text = combined);
+            }
+            let rich = ui
+                .button("Copy All as Rich Text")
+                .on_hover_text("Headings, tables and links for Word, Google Docs or Outlook");
+            if rich.clicked() {
+                let html = self.combined_html(app.templates.language);
+                let text = self.combined_report(app.templates.language);
+                if let Err(e) = clipboard::copy_html(&html, &text) {
+                    *app.toast = Some((e, Utc::now()));
+                }
+            }
+            ui.menu_button("Export All", |ui| {
+                if ui.button("PNG Image").clicked() {
+                    let combined = self.combined_report(app.templates.language);
+                    // The combined report starts with its own title line
+                    let (title, body) = combined.split_once('\n').unwrap_or((&combined, ""));
+                    notify_export(app, export::export_png(title, body.trim_start()));
+                    ui.close_menu();
+                }
+                if ui.button("PDF Document").clicked() {
+                    let chapters = self.pdf_chapters(app.templates);
+                    notify_export(app, export::export_pdf(&self.report_title(app.templates.language), &chapters));
+                    ui.close_menu();
+                }
+                if ui.add_enabled(self.package.is_some(), egui::Button::new("MD5 Checksums")).clicked() {
+                    if let Some(package) = &self.package {
+                        let result = export::export_md5_checksums(&self.game_title(), package);
+                        notify_export(app, result.map(|paths| export_folder(&paths)));
+                    }
+                    ui.close_menu();
+                }
+
+                // The URL lists only hold the files matching the filter
+                ui.separator();
+                labelled(
+                    ui,
+                    "URL filter:",
+                    egui::TextEdit::singleline(&mut self.url_filter).hint_text("Regex, e.g. Audio_Japanese"),
+                );
+                let filter = export::url_filter(&self.url_filter);
+                match (&filter, &self.package) {
+                    (Err(e), _) => {
+                        status_label(ui, Status::Error, e);
+                    }
+                    (Ok(Some(filter)), Some(package)) => {
+                        let matching = export::filtered_files(package, Some(filter)).len();
+                        ui.label(format!("{} of {} files match", matching, package_files(package).len()));
+                    }
+                    _ => {}
+                }
+                let can_list = self.package.is_some() && filter.is_ok();
+                let copies = [
+                    ("Copy URLs Only", UrlListFormat::Plain),
+                    ("Copy as Bash Array", UrlListFormat::BashArray),
+                    ("Copy as PowerShell Array", UrlListFormat::PowerShellArray),
+                ];
+                for (label, format) in copies {
+                    if ui.add_enabled(can_list, egui::Button::new(label)).clicked() {
+                        if let (Some(package), Ok(filter)) = (&self.package, &filter) {
+                            let count = export::filtered_files(package, filter.as_ref()).len();
+                            let urls = export::url_list(package, format, filter.as_ref());
+                            ctx.output_mut(|o| o.copied_text = urls);
+                            *app.toast = Some((format!("Copied {} URLs", count), Utc::now()));
+                        }
+                        ui.close_menu();
+                    }
+                }
+                let url_lists = [
+                    ("URL List (urls.txt)", UrlListFormat::Plain),
+                    ("Free Download Manager List", UrlListFormat::FreeDownloadManager),
+                    ("URL List with Referer", UrlListFormat::WithReferer),
+                    ("aria2 Input File", UrlListFormat::Aria2),
+                ];
+                for (label, format) in url_lists {
+                    if ui.add_enabled(can_list, egui::Button::new(label)).clicked() {
+                        if let (Some(package), Ok(filter)) = (&self.package, &filter) {
+                            notify_export(app, export::export_url_list(&self.game_title(), package, format, filter.as_ref()));
+                        }
+                        ui.close_menu();
+                    }
+                }
+            });
+
+            ui.menu_button("Share", |ui| {
+                if !app.share.is_configured() {
+                    ui.label(format!("Set a {} key under Games > Sharing first.", app.share.service.label()));
+                    return;
+                }
+                let idle = self.share_job.is_none();
+                if ui.add_enabled(idle, egui::Button::new("Report")).clicked() {
+                    let report = self.combined_report(app.templates.language);
+                    self.start_share(&ctx, app.share, self.report_title(app.templates.language), report, ShareFormat::Text);
+                    ui.close_menu();
+                }
+                if ui.add_enabled(idle && self.package.is_some(), egui::Button::new("Package JSON")).clicked() {
+                    if let Some(json) = self.package.as_ref().and_then(|package| serde_json::to_string_pretty(package).ok()) {
+                        self.start_share(&ctx, app.share, self.game_title(), json, ShareFormat::Json);
+                    }
+                    ui.close_menu();
+                }
+            });
+
+            if self.is_fetching() {
+                ui.spinner();
+                ui.label("Fetching...");
+            }
+            if self.share_job.is_some() {
+                ui.spinner();
+                ui.label("Sharing...");
+            }
+        });
+
+        ui.separator();
+
+        // Which game the data below belongs to
+        if let Some(game) = self.games.get(self.selected_game) {
+            if self.games.len() > 1 {
+                let mut selected_game = self.selected_game;
+                egui::ComboBox::from_label("Game")
+                    .selected_text(game.game.label())
+                    .show_ui(ui, |ui| {
+                        for (index, game) in self.games.iter().enumerate() {
+                            ui.selectable_value(&mut selected_game, index, game.game.label());
+                        }
+                    });
+                if selected_game != self.selected_game {
+                    self.select_game(selected_game, app.templates);
+                }
+            }
+
+            let game = &self.games[self.selected_game].game;
+            ui.heading(game.display_name());
+            ui.horizontal(|ui| {
+                ui.label(format!("biz: {}    id: {}", game.biz, if game.id.is_empty() { "-" } else { &game.id }));
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    if ui.small_button("Collapse All").clicked() {
+                        self.open_all = Some(false);
+                    }
+                    if ui.small_button("Expand All").clicked() {
+                        self.open_all = Some(true);
+                    }
+                });
+            });
+            ui.separator();
+        }
+
+        // Display Error Messages. A failed fetch gets a card with its details.
+        if self.failure.is_some() {
+            self.show_failure(ui, app);
+            ui.separator();
+        } else if !self.error_message.is_empty() {
+            status_label(ui, Status::Error, &self.error_message);
+            ui.separator();
+        }
+        // Sections hidden from the View menu are skipped
+        let hidden = app.hidden_sections;
+        let shown = |key: &str| !hidden.iter().any(|section| section == key);
+
+        if shown("warnings") {
+            self.show_warnings(ui);
+        }
+        if shown("pinned") {
+            self.show_pinned(ui, app);
+        }
+
+        // What the pre-download changes compared to the live version
+        if !self.next_patch_summary.is_empty() && shown("next_patch_summary") {
+            let response = self
+                .section_header("next_patch_summary", "Next Patch Summary")
+                .show(ui, |ui| {
+                    if ui.button("Copy").clicked() {
+                        ctx.output_mut(|o| o.copied_text = self.next_patch_summary.clone());
+                    }
+                    ui.separator();
+                    ui.label(&self.next_patch_summary);
+                    self.show_version_notes(ui, app.history);
+                });
+            remember_open(&mut self.config.open_sections, ui.ctx(), "next_patch_summary", &response);
+        }
+
+        // Release cadence from the recorded version history
+        if let Some(game) = self.games.get(self.selected_game).filter(|_| shown("patch_cadence")) {
+            let response = self
+                .section_header("patch_cadence", "Patch Cadence")
+                .show(ui, |ui| match app.history.cadence(&game.game.biz) {
+                    Some(cadence) => {
+                        let now = Utc::now();
+                        ui.label(format!("Versions recorded: {}", cadence.releases));
+                        ui.label(format!(
+                            "Average time between versions: {}",
+                            history::format_duration(cadence.average_interval)
+                        ));
+                        if let Some(lead) = cadence.average_pre_download_lead {
+                            ui.label(format!("Average pre-download lead: {}", history::format_duration(lead)));
+                        }
+                        ui.label(format!("Last release seen: {}", cadence.last_release.format("%Y-%m-%d %H:%M UTC")));
+                        let remaining = cadence.next_release_estimate - now;
+                        ui.label(format!(
+                            "Estimated next release: {} ({})",
+                            cadence.next_release_estimate.format("%Y-%m-%d %H:%M UTC"),
+                            if remaining > chrono::Duration::zero() {
+                                format!("in {}", history::format_duration(remaining))
+                            } else {
+                                format!("overdue by {}", history::format_duration(-remaining))
+                            }
+                        ));
+                    }
+                    None => {
+                        ui.label("Not enough history yet. Statistics appear once two versions have been seen.");
+                    }
+                });
+            remember_open(&mut self.config.open_sections, ui.ctx(), "patch_cadence", &response);
+        }
+
+        if let Some(package) = self.package.as_ref().filter(|_| shown("audio_packages")) {
+            let response = self
+                .section_header("audio_packages", "Audio Packages")
+                .show(ui, |ui| self.audio_listing.show(ui, package));
+            remember_open(&mut self.config.open_sections, ui.ctx(), "audio_packages", &response);
+        }
+        if shown("checksums") {
+            self.show_checksums(ui);
+        }
+        if shown("verify_local_files") {
+            self.show_local_files(ui, app);
+        }
+        if shown("check_links") {
+            self.show_link_check(ui);
+        }
+        if shown("file_list") {
+            self.show_resource_list(ui);
+        }
+        if shown("qr_codes") {
+            self.show_qr_codes(ui, app);
+        }
+
+        // Display the formatted sections, each with its copy and export buttons
+        let sections = [
+            (ReportSection::Main, &self.formatted_message),
+            (ReportSection::PreDownloadMain, &self.pre_download_main_message),
+            (ReportSection::PreDownloadPatches, &self.pre_download_patches_message),
+        ];
+        for (section, message) in sections {
+            if message.is_empty() || !shown(section.key()) {
+                continue;
+            }
+            let response = self
+                .section_header(section.key(), section.title())
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("Copy").clicked() {
+                            ctx.output_mut(|o| o.copied_text = message.clone());
+                        }
+                        self.show_export_buttons(ui, section, app);
+                    });
+                    ui.separator();
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui.label(message);
+                    });
+                });
+            remember_open(&mut self.config.open_sections, ui.ctx(), section.key(), &response);
+        }
+
+        // Display Raw Main Data for Debugging. The JSON is only written out
+        // while a section is expanded, with a find bar above it.
+        if let Some(package) = self.package.as_ref().filter(|_| shown("raw_main")) {
+            let response = self
+                .section_header("raw_main", "Raw Main Data")
+                .show(ui, |ui| {
+                    let json = serde_json::to_string_pretty(package).unwrap_or_default();
+                    self.raw_main_find.show(ui, ("raw_main_json", &self.config.name), &json);
+                });
+            remember_open(&mut self.config.open_sections, ui.ctx(), "raw_main", &response);
+        }
+
+        // Display Raw Pre-download Data for Debugging
+        if let Some(pre_download) = self.pre_download.as_ref().filter(|_| shown("raw_pre_download")) {
+            let response = self
+                .section_header("raw_pre_download", "Raw Pre-download Data")
+                .show(ui, |ui| {
+                    let json = serde_json::to_string_pretty(pre_download).unwrap_or_default();
+                    self.raw_pre_download_find.show(ui, ("raw_pre_download_json", &self.config.name), &json);
+                });
+            remember_open(&mut self.config.open_sections, ui.ctx(), "raw_pre_download", &response);
+        }
+        self.open_all = None;
+    }
+
+    // The card of a failed fetch: what went wrong (or the countdown to the
+    // retry of a busy API), Retry and Copy Details buttons, and the whole
+    // error with the start of the response folded away below
+    fn show_failure(&mut self, ui: &mut egui::Ui, app: &mut WorkspaceContext) {
+        let Some(failure) = &self.failure else {
+            return;
+        };
+        let first_line = failure.message.lines().next().unwrap_or_default();
+        let (status, headline) = match self.retry_at {
+            Some(retry_at) => {
+                let wait = history::format_duration((retry_at - Utc::now()).max(chrono::Duration::zero()));
+                let retries = polling::RETRY_DELAYS.len();
+                (Status::Warning, format!("{}. Retrying in {} (retry {} of {}).", first_line, wait, self.retries, retries))
+            }
+            None => (Status::Error, first_line.to_string()),
+        };
+        let mut retry = false;
+        let mut stop_retrying = false;
+        let stroke = egui::Stroke::new(1.0, status.color(ui));
+        egui::Frame::group(ui.style()).stroke(stroke).show(ui, |ui| {
+            status_label(ui, status, headline);
+            ui.horizontal(|ui| {
+                retry = ui.add_enabled(!self.is_fetching(), egui::Button::new("Retry")).clicked();
+                if ui.button("Copy Details").on_hover_text("For a bug report").clicked() {
+                    let details = self.failure_details(failure);
+                    ui.ctx().output_mut(|o| o.copied_text = details);
+                }
+                if self.retry_at.is_some() {
+                    stop_retrying = ui.button("Don't Retry").clicked();
+                }
+            });
+            egui::CollapsingHeader::new("Details").id_source(("fetch_failure", &self.config.name)).show(ui, |ui| {
+                egui::Grid::new("fetch_failure_fields").num_columns(2).show(ui, |ui| {
+                    ui.label("Source:");
+                    ui.label(&self.config.source);
+                    ui.end_row();
+                    if let Some((failed_at, _)) = self.last_fetch {
+                        ui.label("Failed at:");
+                        ui.label(failed_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string());
+                        ui.end_row();
+                    }
+                    ui.label("Error:");
+                    ui.label(&failure.message);
+                    ui.end_row();
+                });
+                match &failure.body {
+                    Some(body) => {
+                        ui.label("Response:");
+                        egui::ScrollArea::vertical().id_source("fetch_failure_body").max_height(240.0).show(ui, |ui| {
+                            ui.add(egui::TextEdit::multiline(&mut body.as_str()).code_editor().desired_width(f32::INFINITY));
+                        });
+                    }
+                    None => {
+                        ui.label("No response was received.");
+                    }
+                }
+            });
+        });
+        if retry {
+            self.start_fetch(&ui.ctx().clone(), app);
+        }
+        if stop_retrying {
+            self.retry_at = None;
+        }
+    }
+
+    // A failed fetch as text: the source, the error, the timings of the
+    // request if it was the last one made, and the start of the response
+    fn failure_details(&self, failure: &FetchFailure) -> String {
+        let mut text = format!("Source: {}\n", self.config.source);
+        if let Some((failed_at, _)) = self.last_fetch {
+            text.push_str(&format!("Failed at: {}\n", failed_at.to_rfc3339()));
+        }
+        text.push_str(&format!("Error: {}\n", failure.message));
+        if let Some(timings) = timing::last().filter(|timings| timings.url == self.config.source.trim()) {
+            text.push_str(&format!("\nTimings of {}\n", timings.summary()));
+        }
+        match &failure.body {
+            Some(body) => text.push_str(&format!("\nResponse:\n{}\n", body)),
+            None => text.push_str("\nNo response was received.\n"),
+        }
+        text
+    }
+
+    // Notes from the History tab for the live and pre-download versions
+    fn show_version_notes(&self, ui: &mut egui::Ui, history: &History) {
+        let Some(package) = &self.package else {
+            return;
+        };
+        let live = package.main.major.as_ref().map(|major| &major.version);
+        let next = package
+            .pre_download
+            .as_ref()
+            .and_then(|pre| pre.major.as_ref())
+            .map(|major| &major.version);
+
+        for version in [live, next].into_iter().flatten() {
+            if let Some(note) = history.note(&package.game.biz, version) {
+                ui.label(format!("Note ({}): {}", version, note));
+            }
+        }
+    }
+
+    // Pinned packages of the displayed game, plus the menu to change the pins
+    fn show_pinned(&mut self, ui: &mut egui::Ui, app: &mut WorkspaceContext) {
+        let Some(package) = &self.package else {
+            return;
+        };
+        let Some(major) = &package.main.major else {
+            return;
+        };
+        let biz = &package.game.biz;
+
+        // Every package of the live version that can be pinned
+        let mut candidates: Vec<(PinTarget, String, &str, &str, &str)> = Vec::new();
+        for (index, pkg) in major.game_pkgs.iter().enumerate() {
+            let label = format!("Game Part {}", index + 1);
+            candidates.push((PinTarget::GamePart(index + 1), label, &pkg.url, &pkg.size, &pkg.md5));
+        }
+        for pkg in &major.audio_pkgs {
+            let label = format!("Audio: {}", map_language_code(&pkg.language));
+            candidates.push((PinTarget::Audio(pkg.language.clone()), label, &pkg.url, &pkg.size, &pkg.md5));
+        }
+
+        let is_pinned = |target: &PinTarget| app.pins.iter().any(|pin| pin.biz == *biz && pin.target == *target);
+        let pinned: Vec<_> = candidates.iter().filter(|(target, ..)| is_pinned(target)).collect();
+
+        let mut toggled = None;
+        let response = self
+            .section_header("pinned", format!("Pinned ({})", pinned.len()))
+            .show(ui, |ui| {
+                ui.menu_button("Pin Packages", |ui| {
+                    for (target, label, ..) in &candidates {
+                        let mut checked = is_pinned(target);
+                        if ui.checkbox(&mut checked, label).changed() {
+                            toggled = Some(target.clone());
+                        }
+                    }
+                });
+
+                if pinned.is_empty() {
+                    ui.label("Pin the packages you need often to keep them here across fetches.");
+                }
+                for (_, label, url, size, md5) in &pinned {
+                    ui.horizontal(|ui| {
+                        if ui.button("Copy").clicked() {
+                            ui.ctx().output_mut(|o| o.copied_text = url.to_string());
+                        }
+                        ui.strong(label);
+                        ui.label(format!("{:.2}GB", bytes_to_gb(size)));
+                        ui.monospace(*md5);
+                    });
+                    ui.label(*url);
+                }
+            });
+        remember_open(&mut self.config.open_sections, ui.ctx(), "pinned", &response);
+
+        if let Some(target) = toggled {
+            match app.pins.iter().position(|pin| pin.biz == *biz && pin.target == target) {
+                Some(index) => {
+                    app.pins.remove(index);
+                }
+                None => app.pins.push(PackagePin { biz: biz.clone(), target }),
+            }
+        }
+    }
+
+    // Check downloaded files in a local folder against the API's MD5s
+    fn show_local_files(&mut self, ui: &mut egui::Ui, app: &mut WorkspaceContext) {
+        let Some(package) = &self.package else {
+            return;
+        };
+        let files = package_files(package);
+        let mut versions: Vec<String> = Vec::new();
+        for file in &files {
+            if !versions.contains(&file.version) {
+                versions.push(file.version.clone());
+            }
+        }
+
+        let response = self
+            .section_header("verify_local_files", "Verify Local Files")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    labelled(
+                        ui,
+                        "Folder:",
+                        egui::TextEdit::singleline(&mut self.verify_folder)
+                            .hint_text("Folder with the downloaded .zip parts")
+                            .desired_width(360.0),
+                    );
+                    egui::ComboBox::from_id_source("verify_version")
+                        .selected_text(self.verify_version.as_deref().unwrap_or("Files in folder"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.verify_version, None, "Files in folder");
+                            for version in &versions {
+                                ui.selectable_value(&mut self.verify_version, Some(version.clone()), version);
+                            }
+                        });
+                    egui::ComboBox::from_id_source("verify_hashes")
+                        .selected_text(app.verify_hashes.label())
+                        .show_ui(ui, |ui| {
+                            for algorithm in HashAlgorithm::ALL {
+                                ui.selectable_value(app.verify_hashes, algorithm, algorithm.label());
+                            }
+                        });
+                });
+
+                let running = self
+                    .local_verify
+                    .as_ref()
+                    .is_some_and(|local| !local.progress.lock().map(|progress| progress.finished).unwrap_or(true));
+                ui.horizontal(|ui| {
+                    let can_start = !running && !self.verify_folder.trim().is_empty();
+                    if ui.add_enabled(can_start, egui::Button::new("Verify")).clicked() {
+                        if let Err(e) = self.start_verify(ui.ctx(), &files, *app.verify_hashes) {
+                            *app.toast = Some((e, Utc::now()));
+                        }
+                    }
+                    if running && ui.button("Cancel").clicked() {
+                        if let Some(local) = &self.local_verify {
+                            local.cancel.cancel();
+                        }
+                    }
+
+                    let finished = self.local_verify.as_ref().filter(|_| !running);
+                    if let Some(local) = finished {
+                        if ui.button("Export Report").clicked() {
+                            let progress = local.progress.lock().unwrap_or_else(|e| e.into_inner());
+                            let result = verify::write_report(&self.game_title(), &local.folder, &progress);
+                            drop(progress);
+                            notify_export(app, result);
+                        }
+                        if ui.button("Write Manifest").clicked() {
+                            let progress = local.progress.lock().unwrap_or_else(|e| e.into_inner());
+                            let result = verify::write_manifest(&local.folder, &self.game_title(), &progress.checks);
+                            drop(progress);
+                            notify_export(app, result);
+                        }
+                    }
+                });
+                if !running && !app.hidden_sections.iter().any(|section| section == "create_torrent") {
+                    self.show_torrent_options(ui, app);
+                }
+
+                let Some(local) = &self.local_verify else {
+                    return;
+                };
+                let progress = local.progress.lock().unwrap_or_else(|e| e.into_inner());
+                if running {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("Verified {} of {} files...", progress.checks.len(), progress.total));
+                    });
+                }
+                if progress.finished {
+                    if progress.passed() {
+                        status_label(ui, Status::Success, "PASS: every file is intact");
+                    } else {
+                        status_label(ui, Status::Error, "FAIL: some files are missing or damaged");
+                    }
+                }
+                ui.label(format!(
+                    "{} OK, {} wrong size, {} mismatched, {} missing",
+                    progress.count(&FileStatus::Ok),
+                    progress.count(&FileStatus::WrongSize),
+                    progress.count(&FileStatus::Mismatch),
+                    progress.count(&FileStatus::Missing)
+                ));
+                if !progress.unknown.is_empty() {
+                    ui.label(format!("Not in the API: {}", progress.unknown.join(", ")));
+                }
+
+                egui::Grid::new("verify_results").striped(true).show(ui, |ui| {
+                    ui.strong("File");
+                    ui.strong("Version");
+                    ui.strong("Result");
+                    ui.strong("MD5");
+                    if progress.checks.iter().any(|check| check.sha256.is_some()) {
+                        ui.strong("SHA-256");
+                    }
+                    ui.end_row();
+
+                    for check in &progress.checks {
+                        ui.label(check.file.file_name());
+                        ui.label(&check.file.version);
+                        match &check.status {
+                            FileStatus::Ok => status_label(ui, Status::Success, "OK"),
+                            FileStatus::WrongSize => status_label(ui, Status::Error, "Wrong size"),
+                            FileStatus::Mismatch => status_label(ui, Status::Error, "MD5 mismatch"),
+                            FileStatus::Missing => ui.label("Missing"),
+                            FileStatus::Failed(e) => status_label(ui, Status::Error, "Error").on_hover_text(e),
+                        };
+                        ui.monospace(check.md5.as_deref().unwrap_or("-"));
+                        if let Some(sha256) = &check.sha256 {
+                            ui.monospace(sha256);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        remember_open(&mut self.config.open_sections, ui.ctx(), "verify_local_files", &response);
+    }
+
+    // Check that every package URL still answers and whether its host supports resuming
+    // Every file with its MD5, for checking downloads made by hand. Copy All
+    // gives the lines `md5sum -c` reads.
+    fn show_checksums(&mut self, ui: &mut egui::Ui) {
+        let Some(package) = &self.package else {
+            return;
+        };
+        let files = package_files(package);
+        if files.is_empty() {
+            return;
+        }
+
+        let response = self
+            .section_header("checksums", "Checksums")
+            .show(ui, |ui| {
+                if ui.button("Copy All").on_hover_text("As \"md5  file name\" lines, for md5sum -c").clicked() {
+                    ui.ctx().output_mut(|o| o.copied_text = export::checksum_lines(files.iter()));
+                }
+                egui::Grid::new("checksums").striped(true).show(ui, |ui| {
+                    ui.strong("File");
+                    ui.strong("Version");
+                    ui.strong("Size");
+                    ui.strong("MD5");
+                    ui.end_row();
+
+                    for file in &files {
+                        ui.label(file.file_name()).on_hover_text(&file.url);
+                        ui.label(&file.version);
+                        ui.label(format!("{:.2} GB", bytes_to_gb(&file.size.to_string())));
+                        ui.horizontal(|ui| {
+                            ui.monospace(&file.md5);
+                            if ui.small_button("Copy").clicked() {
+                                ui.ctx().output_mut(|o| o.copied_text = file.md5.clone());
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+            });
+        remember_open(&mut self.config.open_sections, ui.ctx(), "checksums", &response);
+    }
+
+    fn show_link_check(&mut self, ui: &mut egui::Ui) {
+        let Some(package) = &self.package else {
+            return;
+        };
+        let files = package_files(package);
+
+        let response = self
+            .section_header("check_links", "Check Links")
+            .show(ui, |ui| {
+                let running = self
+                    .link_check
+                    .as_ref()
+                    .is_some_and(|job| !job.progress.lock().map(|progress| progress.finished).unwrap_or(true));
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!running, egui::Button::new("Check Links")).clicked() {
+                        self.start_link_check(ui.ctx(), files);
+                    }
+                    if running && ui.button("Cancel").clicked() {
+                        if let Some(job) = &self.link_check {
+                            job.cancel.cancel();
+                        }
+                    }
+                });
+
+                let Some(job) = &self.link_check else {
+                    return;
+                };
+                let progress = job.progress.lock().unwrap_or_else(|e| e.into_inner());
+                if running {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("Checked {} of {} links...", progress.checks.len(), progress.total));
+                    });
+                }
+                let broken = progress.checks.len() - progress.count(&LinkStatus::Ok);
+                ui.label(format!(
+                    "{} OK, {} broken, {} of {} support resuming",
+                    progress.count(&LinkStatus::Ok),
+                    broken,
+                    progress.resumable(),
+                    progress.checks.len()
+                ));
+
+                egui::Grid::new("link_results").striped(true).show(ui, |ui| {
+                    ui.strong("File");
+                    ui.strong("Version");
+                    ui.strong("Result");
+                    ui.strong("Size");
+                    ui.strong("Resumable");
+                    ui.end_row();
+
+                    for check in &progress.checks {
+                        ui.label(check.file.file_name());
+                        ui.label(&check.file.version);
+                        match &check.status {
+                            LinkStatus::Ok => status_label(ui, Status::Success, "OK"),
+                            LinkStatus::Broken(code) => status_label(ui, Status::Error, format!("HTTP {}", code)),
+                            LinkStatus::Failed(e) => status_label(ui, Status::Error, "Error").on_hover_text(e),
+                        };
+                        let gb = |size: u64| format!("{:.2} GB", bytes_to_gb(&size.to_string()));
+                        match check.size {
+                            Some(size) if check.size_differs() => status_label(ui, Status::Warning, gb(size))
+                                .on_hover_text(format!("The API lists {}", gb(check.file.size))),
+                            Some(size) => ui.label(gb(size)),
+                            None => ui.label("-"),
+                        };
+                        if check.status != LinkStatus::Ok {
+                            ui.label("-");
+                        } else if check.resumable {
+                            ui.label("Yes");
+                        } else {
+                            status_label(ui, Status::Warning, "No")
+                                .on_hover_text("Downloads from this host use one connection and restart from the beginning");
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        remember_open(&mut self.config.open_sections, ui.ctx(), "check_links", &response);
+    }
+
+    // A QR code per package URL, for downloading on another device
+    fn show_qr_codes(&mut self, ui: &mut egui::Ui, app: &mut WorkspaceContext) {
+        let Some(package) = &self.package else {
+            return;
+        };
+        let files = package_files(package);
+
+        let response = self
+            .section_header("qr_codes", "QR Codes")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Scan a package link with a phone or another device to download it there.");
+                    if ui.button("Save All as PNG").clicked() {
+                        notify_export(app, qr::export_qr_pngs(&self.game_title(), &files));
+                    }
+                });
+                egui::Grid::new("qr_files").striped(true).show(ui, |ui| {
+                    for file in &files {
+                        ui.label(file.file_name());
+                        ui.label(&file.version);
+                        if ui.button("Show").clicked() {
+                            match QrMatrix::new(&file.url) {
+                                Ok(matrix) => self.qr_code = Some((file.clone(), matrix)),
+                                Err(e) => *app.toast = Some((e, Utc::now())),
+                            }
+                        }
+                        if ui.button("Save PNG").clicked() {
+                            notify_export(app, qr::export_qr_png(&self.game_title(), file));
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        remember_open(&mut self.config.open_sections, ui.ctx(), "qr_codes", &response);
+
+        let Some((file, matrix)) = &self.qr_code else {
+            return;
+        };
+        let mut open = true;
+        let mut save = false;
+        egui::Window::new(file.file_name())
+            .id(egui::Id::new(("qr_code", &self.config.name)))
+            .open(&mut open)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                paint_qr(ui, matrix, 320.0);
+                ui.label(egui::RichText::new(&file.url).small());
+                ui.horizontal(|ui| {
+                    if ui.button("Copy URL").clicked() {
+                        ui.ctx().output_mut(|o| o.copied_text = file.url.clone());
+                    }
+                    save = ui.button("Save PNG").clicked();
+                });
+            });
+        if save {
+            notify_export(app, qr::export_qr_png(&self.game_title(), file));
+        }
+        if !open {
+            self.qr_code = None;
+        }
+    }
+
+    // The scattered-file lists (`pkg_version`) of the live version and the
+    // pre-download, loaded on request
+    fn show_resource_list(&mut self, ui: &mut egui::Ui) {
+        let non_empty = |url: &Option<String>| url.clone().filter(|url| !url.trim().is_empty());
+        let mut lists = Vec::new();
+        if let Some(package) = &self.package {
+            if let (Some(url), Some(major)) = (non_empty(&package.main.res_list_url), &package.main.major) {
+                lists.push((major.version.clone(), url));
+            }
+        }
+        if let Some(pre_download) = &self.pre_download {
+            if let (Some(url), Some(major)) = (non_empty(&pre_download.res_list_url), &pre_download.major) {
+                lists.push((major.version.clone(), url));
+            }
+        }
+        if lists.is_empty() {
+            return;
+        }
+        let biz = self.games.get(self.selected_game).map(|game| game.game.biz.clone()).unwrap_or_default();
+
+        let response = self
+            .section_header("file_list", "File List")
+            .show(ui, |ui| {
+                let job = self.resource_list.as_ref();
+                let running = job.is_some_and(|job| job.result.lock().map(|result| result.is_none()).unwrap_or(false));
+                ui.horizontal(|ui| {
+                    for (version, url) in &lists {
+                        let button = egui::Button::new(format!("Load {} File List", version));
+                        if ui.add_enabled(!running, button).on_hover_text(url).clicked() {
+                            self.resource_list = Some(reslist::start_load(ui.ctx(), &biz, version, url));
+                        }
+                    }
+                    if running && ui.button("Cancel").clicked() {
+                        if let Some(job) = &self.resource_list {
+                            job.cancel.cancel();
+                        }
+                    }
+                });
+
+                let Some(job) = &self.resource_list else {
+                    return;
+                };
+                let result = job.result.lock().unwrap_or_else(|e| e.into_inner());
+                let list = match result.as_ref() {
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(format!("Loading the {} file list...", job.version));
+                        });
+                        return;
+                    }
+                    Some(Err(e)) => {
+                        status_label(ui, Status::Error, e);
+                        return;
+                    }
+                    Some(Ok(list)) => list,
+                };
+                ui.label(format!(
+                    "{}: {} files, {:.2} GB",
+                    list.version,
+                    list.entries.len(),
+                    bytes_to_gb(&list.total_size().to_string())
+                ))
+                .on_hover_text(format!("Cached at {}", list.path.display()));
+
+                // Lists run to tens of thousands of files, so only the visible rows are laid out
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::vertical()
+                    .id_source("resource_list")
+                    .max_height(300.0)
+                    .show_rows(ui, row_height, list.entries.len(), |ui, rows| {
+                        for entry in &list.entries[rows] {
+                            ui.horizontal(|ui| {
+                                ui.label(&entry.remote_name);
+                                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                    ui.monospace(&entry.md5);
+                                    ui.label(format!("{} bytes", entry.file_size));
+                                });
+                            });
+                        }
+                    });
+            });
+        remember_open(&mut self.config.open_sections, ui.ctx(), "file_list", &response);
+    }
+
+    // Check the package links on worker threads
+    fn start_link_check(&mut self, ctx: &egui::Context, files: Vec<crate::PackageFile>) {
+        let progress = Arc::new(Mutex::new(LinkProgress {
+            total: files.len(),
+            ..Default::default()
+        }));
+        let cancel = CancelToken::default();
+        self.link_check = Some(LinkCheckJob {
+            progress: progress.clone(),
+            cancel: cancel.clone(),
+        });
+
+        let ctx = ctx.clone();
+        let label = format!("Check {} links of {}", files.len(), self.config.name);
+        jobs::spawn(JobKind::LinkCheck, label, Some(cancel.clone()), move || {
+            info!("Checking {} links.", files.len());
+            linkcheck::check_links(&files, &progress, &|| ctx.request_repaint(), &|| cancel.is_cancelled());
+            if let Ok(mut progress) = progress.lock() {
+                progress.finished = true;
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    // Options and button for building a .torrent from the files that passed verification
+    fn show_torrent_options(&mut self, ui: &mut egui::Ui, app: &mut WorkspaceContext) {
+        let Some(local) = &self.local_verify else {
+            return;
+        };
+        let verified: Vec<crate::PackageFile> = local
+            .progress
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .checks
+            .iter()
+            .filter(|check| check.status == FileStatus::Ok)
+            .map(|check| check.file.clone())
+            .collect();
+        if verified.is_empty() {
+            return;
+        }
+
+        // Report a finished build
+        let result = self
+            .torrent_job
+            .as_ref()
+            .and_then(|job| job.lock().unwrap_or_else(|e| e.into_inner()).take());
+        if let Some(result) = result {
+            self.torrent_job = None;
+            notify_export(app, result);
+        }
+
+        let response = self
+            .section_header("create_torrent", "Create Torrent")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("torrent_version")
+                        .selected_text(self.torrent_version.label())
+                        .show_ui(ui, |ui| {
+                            for version in TorrentVersion::ALL {
+                                ui.selectable_value(&mut self.torrent_version, version, version.label());
+                            }
+                        });
+                    ui.checkbox(&mut self.torrent_web_seeds, "Use CDN links as web seeds");
+                });
+                labelled(
+                    ui,
+                    "Trackers (one per line):",
+                    egui::TextEdit::multiline(&mut self.torrent_trackers)
+                        .hint_text("udp://tracker.example.org:1337/announce")
+                        .desired_rows(2)
+                        .desired_width(360.0),
+                );
+
+                ui.horizontal(|ui| {
+                    let building = self.torrent_job.is_some();
+                    let label = format!("Create Torrent ({} files)", verified.len());
+                    if ui.add_enabled(!building, egui::Button::new(label)).clicked() {
+                        let job = TorrentJob::default();
+                        self.torrent_job = Some(job.clone());
+                        let folder = local.folder.clone();
+                        // Its own token: the verification's may be cancelled already, and
+                        // cancelling this from the jobs list shouldn't stop a verification
+                        let cancel = CancelToken::default();
+                        let options = TorrentOptions {
+                            version: self.torrent_version,
+                            trackers: self.torrent_trackers.lines().map(str::to_string).collect(),
+                            web_seeds: self.torrent_web_seeds,
+                        };
+                        let ctx = ui.ctx().clone();
+                        let label = format!("Create torrent of {}", folder.display());
+                        jobs::spawn(JobKind::Hashing, label, Some(cancel.clone()), move || {
+                            let result = torrent::create_torrent(&folder, &verified, &options, &|| cancel.is_cancelled());
+                            if let Err(e) = &result {
+                                error!("{}", e);
+                            }
+                            *job.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+                            ctx.request_repaint();
+                        });
+                    }
+                    if building {
+                        ui.spinner();
+                        ui.label("Hashing files...");
+                    }
+                });
+            });
+        remember_open(&mut self.config.open_sections, ui.ctx(), "create_torrent", &response);
+    }
+
+    // Upload a report to the paste service on a worker thread
+    fn start_share(&mut self, ctx: &egui::Context, target: &ShareTarget, title: String, content: String, format: ShareFormat) {
+        let job = ShareJob::default();
+        self.share_job = Some(job.clone());
+        let target = target.clone();
+        let ctx = ctx.clone();
+        let label = format!("Share {} to {}", title, target.service.label());
+        jobs::spawn(JobKind::Upload, label, None, move || {
+            let result = share::share(&target, &title, &content, format);
+            *job.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+            ctx.request_repaint();
+        });
+    }
+
+    // Check files from the verify folder on worker threads: the files of the
+    // chosen version, or every file in the folder that the API knows
+    fn start_verify(&mut self, ctx: &egui::Context, files: &[crate::PackageFile], algorithm: HashAlgorithm) -> Result<(), String> {
+        let folder = PathBuf::from(self.verify_folder.trim());
+        let (files, unknown) = match &self.verify_version {
+            Some(version) => (files.iter().filter(|file| file.version == *version).cloned().collect(), Vec::new()),
+            None => verify::match_folder(&folder, files)?,
+        };
+        let progress = Arc::new(Mutex::new(VerifyProgress {
+            total: files.len(),
+            unknown,
+            ..Default::default()
+        }));
+        let cancel = CancelToken::default();
+        self.local_verify = Some(LocalVerify {
+            folder: folder.clone(),
+            progress: progress.clone(),
+            cancel: cancel.clone(),
+        });
+
+        let ctx = ctx.clone();
+        let label = format!("Verify {} files in {}", files.len(), folder.display());
+        jobs::spawn(JobKind::Hashing, label, Some(cancel.clone()), move || {
+            info!("Verifying {} files in {}.", files.len(), folder.display());
+            verify::verify_files(&folder, &files, algorithm, &progress, &|| ctx.request_repaint(), &|| cancel.is_cancelled());
+            if let Ok(mut progress) = progress.lock() {
+                progress.finished = true;
+            }
+            ctx.request_repaint();
+        });
+        Ok(())
+    }
+
+    // Copy buttons for the built-in BBCode, Discord and rich-text formats of a
+    // section, plus a menu for copying only part of it
+    fn show_export_buttons(&self, ui: &mut egui::Ui, section: ReportSection, app: &mut WorkspaceContext) {
+        ui.menu_button("Copy Part", |ui| {
+            let categories = [
+                ("Game Packages Only", PackageCategory::Game),
+                ("Audio Packages Only", PackageCategory::Audio),
+            ];
+            for (label, category) in categories {
+                if ui.button(label).clicked() {
+                    self.copy_filtered(ui, section, app.templates, &ReportFilter::category(category));
+                    ui.close_menu();
+                }
+            }
+
+            if section == ReportSection::PreDownloadPatches {
+                ui.separator();
+                for version in report::patch_versions(self.pre_download.as_ref()) {
+                    ui.menu_button(format!("Patch from {}", version), |ui| {
+                        let categories = [
+                            ("Whole Patch", PackageCategory::All),
+                            ("Game Patch Only", PackageCategory::Game),
+                            ("Audio Patches Only", PackageCategory::Audio),
+                        ];
+                        for (label, category) in categories {
+                            if ui.button(label).clicked() {
+                                let filter = ReportFilter {
+                                    category,
+                                    patch_version: Some(version.clone()),
+                                };
+                                self.copy_filtered(ui, section, app.templates, &filter);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                }
+            }
+        });
+
+        if ui.button("Copy BBCode").clicked() {
+            let bbcode = self.render_section(
+                section,
+                report::builtin_template(ExportFormat::BBCode, section),
+                app.templates.language,
+                &ReportFilter::default(),
+            );
+            ui.ctx().output_mut(|o| o.copied_text = bbcode);
+        }
+        ui.menu_button("Export", |ui| {
+            if ui.button("PNG Image").clicked() {
+                let message = self.render_section(
+                    section,
+                    app.templates.get(section),
+                    app.templates.language,
+                    &ReportFilter::default(),
+                );
+                let title = format!("{} - {}", self.game_title(), section.title());
+                notify_export(app, export::export_png(&title, &message));
+                ui.close_menu();
+            }
+        });
+        if ui.button("Copy for Discord").clicked() {
+            let markdown = self.render_section(
+                section,
+                report::builtin_template(ExportFormat::Discord, section),
+                app.templates.language,
+                &ReportFilter::default(),
+            );
+            let mut chunks = report::split_for_discord(&markdown);
+            if chunks.len() == 1 {
+                ui.ctx().output_mut(|o| o.copied_text = chunks.remove(0));
+            } else {
+                // Too long for one message; let the user copy each part in turn
+                *app.discord_chunks = chunks;
+            }
+        }
+        let rich = ui
+            .button("Copy Rich Text")
+            .on_hover_text("Headings, tables and links for Word, Google Docs or Outlook");
+        if rich.clicked() {
+            let language = app.templates.language;
+            let filter = ReportFilter::default();
+            let html = self.render_section(section, report::builtin_template(ExportFormat::Html, section), language, &filter);
+            let text = self.render_section(section, app.templates.get(section), language, &filter);
+            if let Err(e) = clipboard::copy_html(&html, &text) {
+                *app.toast = Some((e, Utc::now()));
+            }
+        }
+    }
+
+    // Copy part of a section, rendered with the user's template
+    fn copy_filtered(&self, ui: &egui::Ui, section: ReportSection, templates: &ReportTemplates, filter: &ReportFilter) {
+        let text = self.render_section(section, templates.get(section), templates.language, filter);
+        ui.ctx().output_mut(|o| o.copied_text = text);
+    }
+
+    // ----------------------
+    // Fetch Handling
+    // ----------------------
+
+    // Start a background fetch; the result comes back as a FetchEvent
+    pub fn start_fetch(&mut self, ctx: &egui::Context, app: &mut WorkspaceContext) {
+        let fetch_id = *app.next_fetch_id;
+        *app.next_fetch_id += 1;
+        let cancel = self.begin_fetch(fetch_id);
+
+        let sender = app.event_sender.clone();
+        let ctx = ctx.clone();
+        let source = self.config.source.trim().to_string();
+        let name = self.config.name.clone();
+        let strict = app.strict_parsing;
+
+        // Fetch on a background job; cancelling goes through the workspace, which shows the state
+        let label = format!("Fetch {}", name);
+        jobs::spawn(JobKind::Fetch, label, None, move || {
+            info!("Starting data fetch for {}.", name);
+            let event = match fetch_with_body(&source, strict, &|| cancel.is_cancelled()) {
+                Ok(result) => FetchEvent::Completed(result),
+                Err(err) => FetchEvent::Failed(err),
+            };
+            if cancel.is_cancelled() {
+                info!("Fetch {} cancelled.", fetch_id);
+                return;
+            }
+            // The receiver only disappears when the app is shutting down
+            let _ = sender.send((fetch_id, event));
+            // Wake the UI so the result shows up immediately
+            ctx.request_repaint();
+        });
+    }
+
+    // Mark a fetch with this id as running and return its cancel token. The
+    // caller is responsible for running the worker.
+    pub fn begin_fetch(&mut self, fetch_id: u64) -> CancelToken {
+        // Clear existing messages and data
+        self.clear();
+        self.retries = 0;
+
+        let cancel = CancelToken::default();
+        self.active_fetch = Some(ActiveFetch {
+            id: fetch_id,
+            cancel: cancel.clone(),
+        });
+        cancel
+    }
+
+    // Fetch again once a retry of a fetch the API turned away is due,
+    // counting it as a retry of the same fetch
+    pub fn retry_if_due(&mut self, ctx: &egui::Context, app: &mut WorkspaceContext) {
+        if self.is_fetching() || self.retry_at.is_none_or(|retry_at| retry_at > Utc::now()) {
+            return;
+        }
+        info!("Retrying the fetch of {} ({} of {}).", self.config.name, self.retries, polling::RETRY_DELAYS.len());
+        let retries = self.retries;
+        self.start_fetch(ctx, app);
+        self.retries = retries;
+    }
+
+    // Stop waiting for the running fetch. The worker aborts its download at the
+    // next chunk and anything it still sends is ignored.
+    pub fn cancel_fetch(&mut self) {
+        if let Some(fetch) = self.active_fetch.take() {
+            info!("Cancelling fetch {}.", fetch.id);
+            fetch.cancel.cancel();
+            self.error_message = "Fetch cancelled.".to_string();
+        }
+    }
+
+    pub fn handle_fetch_event(&mut self, event: FetchEvent, templates: &ReportTemplates) {
+        self.active_fetch = None;
+        self.last_fetch = Some((Utc::now(), matches!(event, FetchEvent::Completed(_))));
+
+        match event {
+            FetchEvent::Completed(FetchResult { games, parse_errors, .. }) => {
+                info!("Data fetch and processing successful for {}.", self.config.name);
+                self.retries = 0;
+
+                // Report fields that failed validation; the rest is still shown
+                if !parse_errors.is_empty() {
+                    self.error_message = format!("Some fields could not be parsed:\n{}", parse_errors.join("\n"));
+                }
+
+                info!("Response contains {} game(s).", games.len());
+                self.games = games;
+                self.select_game(0, templates);
+            }
+            FetchEvent::Failed(failure) => {
+                error!("Error during data fetch for {}: {}", self.config.name, failure.message);
+                if let Some(delay) = polling::retry_delay(&failure.message, self.retries) {
+                    self.retries += 1;
+                    self.retry_at = Some(Utc::now() + chrono::Duration::from_std(delay).unwrap_or_else(|_| chrono::Duration::zero()));
+                }
+                self.error_message = failure.message.clone();
+                self.failure = Some(failure);
+            }
+        }
+    }
+
+    // Fields of the displayed game that the lenient parser skipped or filled
+    // in, and audio language codes without a name
+    fn show_warnings(&mut self, ui: &mut egui::Ui) {
+        let Some(game) = self.games.get(self.selected_game) else {
+            return;
+        };
+        if game.warnings.is_empty() {
+            return;
+        }
+        let response = self
+            .section_header("warnings", format!("Warnings ({})", game.warnings.len()))
+            .show(ui, |ui| {
+                ui.label("Everything that could be parsed is shown. These fields were skipped or left at their defaults, or these audio languages have no name yet.");
+                if ui.button("Copy").clicked() {
+                    ui.ctx().output_mut(|o| o.copied_text = game.warnings.join("\n"));
+                }
+                for warning in &game.warnings {
+                    status_label(ui, Status::Warning, warning);
+                }
+            });
+        remember_open(&mut self.config.open_sections, ui.ctx(), "warnings", &response);
+        ui.separator();
+    }
+
+    // Show the data of one of the games in the last response
+    fn select_game(&mut self, index: usize, templates: &ReportTemplates) {
+        self.selected_game = index;
+        let Some(game) = self.games.get(index) else {
+            return;
+        };
+
+        self.package = game.package.clone();
+        self.pre_download = game.pre_download.clone();
+        if self.pre_download.is_some() {
+            info!("Pre-download data available for {}.", game.game.biz);
+        } else {
+            info!("No pre-download data found for {}.", game.game.biz);
+        }
+
+        self.refresh_reports(templates);
+    }
+
+    // The displayed game's package
+    pub fn game_package(&self) -> Option<&GamePackage> {
+        self.package.as_ref()
+    }
+
+    // Every game in the last response
+    pub fn games(&self) -> &[GameData] {
+        &self.games
+    }
+
+    // The packages of every game in the last response
+    pub fn game_packages(&self) -> Vec<GamePackage> {
+        self.games.iter().filter_map(|game| game.package.clone()).collect()
+    }
+
+    // Every section of the displayed game in one timestamped report
+    pub fn combined_report(&self, language: ReportLanguage) -> String {
+        let sections = [
+            (ReportSection::Main, self.formatted_message.clone()),
+            (ReportSection::PreDownloadMain, self.pre_download_main_message.clone()),
+            (ReportSection::PreDownloadPatches, self.pre_download_patches_message.clone()),
+        ];
+        report::combined_report(&self.report_title(language), &sections, language, Utc::now())
+    }
+
+    // Every section of the displayed game as HTML, for rich-text copies
+    fn combined_html(&self, language: ReportLanguage) -> String {
+        let sections: Vec<(ReportSection, String)> = ReportSection::ALL
+            .iter()
+            .map(|&section| {
+                let template = report::builtin_template(ExportFormat::Html, section);
+                (section, self.render_section(section, template, language, &ReportFilter::default()))
+            })
+            .collect();
+        report::combined_html(&self.report_title(language), &sections, language, Utc::now())
+    }
+
+    // One chapter per version for PDF exports: the live version, the
+    // pre-download and each pre-download patch
+    fn pdf_chapters(&self, templates: &ReportTemplates) -> Vec<(String, String)> {
+        let mut chapters = Vec::new();
+        let version = |major: Option<&crate::Major>| major.map(|major| format!(" (Version {})", major.version)).unwrap_or_default();
+
+        if !self.formatted_message.trim().is_empty() {
+            let live = self.package.as_ref().and_then(|package| package.main.major.as_ref());
+            chapters.push((format!("{}{}", ReportSection::Main.title(), version(live)), self.formatted_message.clone()));
+        }
+        if !self.pre_download_main_message.trim().is_empty() {
+            let next = self
+                .package
+                .as_ref()
+                .and_then(|package| package.pre_download.as_ref())
+                .and_then(|pre| pre.major.as_ref());
+            chapters.push((
+                format!("{}{}", ReportSection::PreDownloadMain.title(), version(next)),
+                self.pre_download_main_message.clone(),
+            ));
+        }
+        for patch_version in report::patch_versions(self.pre_download.as_ref()) {
+            let filter = ReportFilter {
+                category: PackageCategory::All,
+                patch_version: Some(patch_version.clone()),
+            };
+            let section = ReportSection::PreDownloadPatches;
+            let message = self.render_section(section, templates.get(section), templates.language, &filter);
+            if !message.trim().is_empty() {
+                chapters.push((format!("Pre-download Patch from {}", patch_version), message));
+            }
+        }
+        chapters
+    }
+
+    // Show the game with this biz code, if it is in the last response
+    pub fn select_biz(&mut self, biz: &str, templates: &ReportTemplates) {
+        if let Some(index) = self.games.iter().position(|game| game.game.biz == biz) {
+            if index != self.selected_game {
+                self.select_game(index, templates);
+            }
+        }
+    }
+
+    // Name of the displayed game, or of the workspace before anything is fetched
+    fn game_title(&self) -> String {
+        match self.games.get(self.selected_game) {
+            Some(game) => game.game.display_name(),
+            None => self.config.name.clone(),
+        }
+    }
+
+    // Title for combined reports, naming the displayed game
+    fn report_title(&self, language: ReportLanguage) -> String {
+        language.report_title(&self.game_title())
+    }
+
+    // Reset all displayed data and error messages
+    pub fn clear(&mut self) {
+        self.games.clear();
+        self.selected_game = 0;
+        self.package = None;
+        self.formatted_message.clear();
+        self.pre_download_main_message.clear();
+        self.pre_download_patches_message.clear();
+        self.next_patch_summary.clear();
+        self.pre_download = None;
+        self.error_message.clear();
+        self.failure = None;
+        self.retry_at = None;
+    }
+
+    // ----------------------
+    // Rendering
+    // ----------------------
+
+    // Render a section of the currently loaded data with the given template
+    pub fn render_section(
+        &self,
+        section: ReportSection,
+        template: &str,
+        language: ReportLanguage,
+        filter: &ReportFilter,
+    ) -> String {
+        report::render_section(section, template, language, self.package.as_ref(), self.pre_download.as_ref(), filter)
+    }
+
+    // Re-render the displayed reports with the given templates
+    pub fn refresh_reports(&mut self, templates: &ReportTemplates) {
+        let render = |section: ReportSection| {
+            self.render_section(section, templates.get(section), templates.language, &ReportFilter::default())
+        };
+        let main = render(ReportSection::Main);
+        let pre_download_main = render(ReportSection::PreDownloadMain);
+        let pre_download_patches = render(ReportSection::PreDownloadPatches);
+
+        self.formatted_message = main;
+        self.pre_download_main_message = pre_download_main;
+        self.pre_download_patches_message = pre_download_patches;
+        self.next_patch_summary = self
+            .package
+            .as_ref()
+            .and_then(compare::next_patch_summary)
+            .unwrap_or_default();
+    }
+}